@@ -0,0 +1,377 @@
+use std::{fmt, io};
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// Default maximum payload length accepted by [`ChecksumCodec`] (8 MiB).
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Length of the frame header.
+const HEADER_LEN: usize = 4;
+
+/// Checksum algorithm used by [`ChecksumCodec`].
+///
+/// Implement this trait to use an algorithm other than the built-in [`Crc32c`] (e.g. XXH3).
+pub trait Checksum {
+    /// Number of checksum bytes appended to each frame. Must be at most 8.
+    const SIZE: usize;
+
+    /// Compute checksum of `data`.
+    ///
+    /// Only the lower [`SIZE`](Self::SIZE) bytes of the result are written to the frame.
+    fn checksum(&self, data: &[u8]) -> u64;
+}
+
+/// CRC-32C (Castagnoli) checksum.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Crc32c;
+
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+impl Checksum for Crc32c {
+    const SIZE: usize = 4;
+
+    fn checksum(&self, data: &[u8]) -> u64 {
+        let crc = data.iter().fold(!0u32, |crc, byte| {
+            CRC32C_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+        });
+
+        u64::from(!crc)
+    }
+}
+
+/// Action taken by [`ChecksumCodec`] when a decoded frame fails checksum validation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CorruptFramePolicy {
+    /// Return an [`io::ErrorKind::InvalidData`] error from the decoder.
+    Error,
+
+    /// Drop the frame and continue decoding from the next one.
+    Skip,
+}
+
+/// Framing adapter that protects each frame of an inner codec with a checksum.
+///
+/// Every item encoded by the inner codec is written as a frame consisting of a big-endian `u32`
+/// payload length, the payload and a big-endian checksum of the payload. On decode the checksum is
+/// validated before the payload is handed to the inner decoder, which must produce exactly one
+/// item from it.
+///
+/// # Examples
+/// ```
+/// use actix_codec::{BytesCodec, ChecksumCodec, CorruptFramePolicy, Crc32c, Decoder, Encoder};
+/// use bytes::{Bytes, BytesMut};
+///
+/// let mut codec = ChecksumCodec::new(BytesCodec, Crc32c)
+///     .corrupt_frame_policy(CorruptFramePolicy::Skip);
+///
+/// let mut buf = BytesMut::new();
+/// codec.encode(Bytes::from_static(b"hello"), &mut buf).unwrap();
+///
+/// let item = codec.decode(&mut buf).unwrap().unwrap();
+/// assert_eq!(&item[..], b"hello");
+/// ```
+pub struct ChecksumCodec<C, K> {
+    inner: C,
+    checksum: K,
+    policy: CorruptFramePolicy,
+    max_frame_length: usize,
+    skipped: u64,
+}
+
+impl<C, K: Checksum> ChecksumCodec<C, K> {
+    /// Create new checksum adapter around `inner` codec.
+    ///
+    /// By default corrupted frames produce an error and payloads are limited to 8 MiB.
+    pub fn new(inner: C, checksum: K) -> Self {
+        assert!(K::SIZE <= 8, "checksum size can not exceed 8 bytes");
+
+        Self {
+            inner,
+            checksum,
+            policy: CorruptFramePolicy::Error,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            skipped: 0,
+        }
+    }
+
+    /// Set action to take on frames that fail checksum validation.
+    pub fn corrupt_frame_policy(mut self, policy: CorruptFramePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set maximum payload length for both encoding and decoding.
+    ///
+    /// Larger frames result in an [`io::ErrorKind::InvalidData`] error.
+    pub fn max_frame_length(mut self, len: usize) -> Self {
+        self.max_frame_length = len;
+        self
+    }
+
+    /// Returns number of corrupted frames dropped under [`CorruptFramePolicy::Skip`].
+    pub fn skipped_frames(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns a reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Consume adapter, returning inner codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn frame_too_large(&self, len: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "frame length {} exceeds maximum of {}",
+                len, self.max_frame_length
+            ),
+        )
+    }
+}
+
+impl<C, K, I> Encoder<I> for ChecksumCodec<C, K>
+where
+    C: Encoder<I>,
+    K: Checksum,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let start = dst.len();
+        dst.put_u32(0);
+
+        if let Err(err) = self.inner.encode(item, dst) {
+            dst.truncate(start);
+            return Err(err);
+        }
+
+        let len = dst.len() - start - HEADER_LEN;
+        if len > self.max_frame_length || len > u32::MAX as usize {
+            dst.truncate(start);
+            return Err(self.frame_too_large(len).into());
+        }
+
+        dst[start..start + HEADER_LEN].copy_from_slice(&(len as u32).to_be_bytes());
+
+        let sum = self.checksum.checksum(&dst[start + HEADER_LEN..]);
+        dst.put_uint(sum, K::SIZE);
+
+        Ok(())
+    }
+}
+
+impl<C, K> Decoder for ChecksumCodec<C, K>
+where
+    C: Decoder,
+    K: Checksum,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if src.len() < HEADER_LEN {
+                return Ok(None);
+            }
+
+            let mut header = [0; HEADER_LEN];
+            header.copy_from_slice(&src[..HEADER_LEN]);
+            let len = u32::from_be_bytes(header) as usize;
+
+            if len > self.max_frame_length {
+                return Err(self.frame_too_large(len).into());
+            }
+
+            let frame_len = HEADER_LEN + len + K::SIZE;
+            if src.len() < frame_len {
+                src.reserve(frame_len - src.len());
+                return Ok(None);
+            }
+
+            let mut frame = src.split_to(frame_len);
+            frame.advance(HEADER_LEN);
+            let mut payload = frame.split_to(len);
+            let expected = frame.get_uint(K::SIZE) & size_mask(K::SIZE);
+            let actual = self.checksum.checksum(&payload) & size_mask(K::SIZE);
+
+            if actual != expected {
+                match self.policy {
+                    CorruptFramePolicy::Error => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "frame checksum mismatch",
+                        )
+                        .into())
+                    }
+                    CorruptFramePolicy::Skip => {
+                        log::debug!("Dropping frame of {} bytes with bad checksum", len);
+                        self.skipped += 1;
+                        continue;
+                    }
+                }
+            }
+
+            return match self.inner.decode_eof(&mut payload)? {
+                Some(item) if payload.is_empty() => Ok(Some(item)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "inner codec did not decode exactly one item from frame",
+                )
+                .into()),
+            };
+        }
+    }
+}
+
+impl<C: fmt::Debug, K: fmt::Debug> fmt::Debug for ChecksumCodec<C, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChecksumCodec")
+            .field("inner", &self.inner)
+            .field("checksum", &self.checksum)
+            .field("policy", &self.policy)
+            .field("max_frame_length", &self.max_frame_length)
+            .finish()
+    }
+}
+
+fn size_mask(size: usize) -> u64 {
+    if size >= 8 {
+        u64::MAX
+    } else {
+        (1 << (size * 8)) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::BytesCodec;
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(Crc32c.checksum(b"123456789"), 0xE306_9283);
+        assert_eq!(Crc32c.checksum(b""), 0);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut codec = ChecksumCodec::new(BytesCodec, Crc32c);
+        let mut buf = BytesMut::new();
+
+        codec.encode(Bytes::from_static(b"one"), &mut buf).unwrap();
+        codec.encode(Bytes::from_static(b"two"), &mut buf).unwrap();
+        assert_eq!(buf.len(), 2 * (HEADER_LEN + 3 + Crc32c::SIZE));
+
+        // partial frames wait for more data
+        let mut partial = buf.split_to(5);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buf);
+        let mut buf = partial;
+
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"one");
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"two");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_frame() {
+        let mut codec = ChecksumCodec::new(BytesCodec, Crc32c);
+        let mut buf = BytesMut::new();
+        codec.encode(Bytes::from_static(b"bad"), &mut buf).unwrap();
+        codec.encode(Bytes::from_static(b"good"), &mut buf).unwrap();
+        buf[HEADER_LEN] ^= 0xFF;
+
+        let err = codec.decode(&mut buf.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut codec = codec.corrupt_frame_policy(CorruptFramePolicy::Skip);
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"good");
+        assert_eq!(codec.skipped_frames(), 1);
+    }
+
+    /// 16-bit checksum computed as a wider value, of which only the lower bytes are sent.
+    struct Sum16;
+
+    impl Checksum for Sum16 {
+        const SIZE: usize = 2;
+
+        fn checksum(&self, data: &[u8]) -> u64 {
+            data.iter()
+                .fold(0xABCD_0000, |sum, byte| sum + u64::from(*byte))
+        }
+    }
+
+    #[test]
+    fn narrow_checksum() {
+        let mut codec = ChecksumCodec::new(BytesCodec, Sum16);
+        let mut buf = BytesMut::new();
+
+        codec
+            .encode(Bytes::from_static(b"short"), &mut buf)
+            .unwrap();
+        assert_eq!(buf.len(), HEADER_LEN + 5 + Sum16::SIZE);
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"short");
+
+        codec
+            .encode(Bytes::from_static(b"short"), &mut buf)
+            .unwrap();
+        buf[HEADER_LEN] ^= 0x01;
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn max_frame_length() {
+        let mut codec = ChecksumCodec::new(BytesCodec, Crc32c).max_frame_length(2);
+        let mut buf = BytesMut::new();
+
+        let err = codec
+            .encode(Bytes::from_static(b"abc"), &mut buf)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(buf.is_empty());
+
+        buf.put_u32(3);
+        buf.extend_from_slice(b"abc");
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,495 @@
+use std::{fmt, io};
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// Default maximum (uncompressed) payload length accepted by [`CompressCodec`] (8 MiB).
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Default minimum payload length that will be compressed.
+const DEFAULT_MIN_COMPRESS_LENGTH: usize = 256;
+
+/// Length of the frame header; payload length followed by flags byte.
+const HEADER_LEN: usize = 5;
+
+/// Frame payload is compressed and prefixed with its uncompressed length.
+const FLAG_COMPRESSED: u8 = 0b0000_0001;
+
+/// Compression algorithm used by [`CompressCodec`].
+///
+/// Implementations are free to keep state (e.g. dictionaries) between calls.
+pub trait Compressor {
+    /// Compress `src`, appending output to `dst`.
+    fn compress(&mut self, src: &[u8], dst: &mut BytesMut) -> io::Result<()>;
+
+    /// Decompress `src`, appending output to `dst`.
+    ///
+    /// `len` is the exact uncompressed length recorded by the encoder. It has already been
+    /// checked against the configured maximum frame length.
+    fn decompress(&mut self, src: &[u8], len: usize, dst: &mut BytesMut) -> io::Result<()>;
+}
+
+/// Compression statistics collected by [`CompressCodec`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CompressStats {
+    /// Number of frames encoded.
+    pub frames: u64,
+
+    /// Number of encoded frames that were sent compressed.
+    pub compressed_frames: u64,
+
+    /// Total payload bytes produced by the inner encoder.
+    pub bytes_in: u64,
+
+    /// Total payload bytes written after compression.
+    pub bytes_out: u64,
+}
+
+impl CompressStats {
+    /// Returns ratio of written bytes to inner encoder bytes. Lower is better.
+    ///
+    /// Returns `1.0` if nothing has been encoded.
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_in == 0 {
+            1.0
+        } else {
+            self.bytes_out as f64 / self.bytes_in as f64
+        }
+    }
+}
+
+/// Framing adapter that compresses each frame of an inner codec.
+///
+/// Every item encoded by the inner codec is written as a frame consisting of a big-endian `u32`
+/// payload length, a flags byte and the payload. Payloads shorter than the configured threshold,
+/// or that do not shrink when compressed, are sent as-is. Compressed payloads are prefixed with
+/// their uncompressed length as a big-endian `u32`.
+///
+/// Compression algorithms are provided by implementing [`Compressor`]. LZ4 and Zstandard are
+/// available as `Lz4` and `Zstd` with the `compress-lz4` and `compress-zstd` features.
+pub struct CompressCodec<C, Z> {
+    inner: C,
+    compressor: Z,
+    min_compress_length: usize,
+    max_frame_length: usize,
+    stats: CompressStats,
+    scratch: BytesMut,
+}
+
+impl<C, Z: Compressor> CompressCodec<C, Z> {
+    /// Create new compression adapter around `inner` codec.
+    ///
+    /// By default payloads shorter than 256 bytes are not compressed and payloads are limited to
+    /// 8 MiB.
+    pub fn new(inner: C, compressor: Z) -> Self {
+        Self {
+            inner,
+            compressor,
+            min_compress_length: DEFAULT_MIN_COMPRESS_LENGTH,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            stats: CompressStats::default(),
+            scratch: BytesMut::new(),
+        }
+    }
+
+    /// Set minimum payload length that will be compressed.
+    pub fn min_compress_length(mut self, len: usize) -> Self {
+        self.min_compress_length = len;
+        self
+    }
+
+    /// Set maximum uncompressed payload length for both encoding and decoding.
+    ///
+    /// Larger frames result in an [`io::ErrorKind::InvalidData`] error.
+    pub fn max_frame_length(mut self, len: usize) -> Self {
+        self.max_frame_length = len;
+        self
+    }
+
+    /// Returns compression statistics for frames encoded so far.
+    pub fn stats(&self) -> CompressStats {
+        self.stats
+    }
+
+    /// Returns a reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Consume adapter, returning inner codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn frame_too_large(&self, len: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "frame length {} exceeds maximum of {}",
+                len, self.max_frame_length
+            ),
+        )
+    }
+}
+
+impl<C, Z, I> Encoder<I> for CompressCodec<C, Z>
+where
+    C: Encoder<I>,
+    Z: Compressor,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.scratch.clear();
+        self.inner.encode(item, &mut self.scratch)?;
+
+        let len = self.scratch.len();
+        if len > self.max_frame_length || len > u32::MAX as usize {
+            return Err(self.frame_too_large(len).into());
+        }
+
+        let start = dst.len();
+
+        if len >= self.min_compress_length {
+            dst.put_u32(0);
+            dst.put_u8(FLAG_COMPRESSED);
+            dst.put_u32(len as u32);
+
+            if let Err(err) = self.compressor.compress(&self.scratch, dst) {
+                dst.truncate(start);
+                return Err(err.into());
+            }
+
+            let written = dst.len() - start - HEADER_LEN;
+            if written < len {
+                dst[start..start + 4].copy_from_slice(&(written as u32).to_be_bytes());
+
+                self.stats.frames += 1;
+                self.stats.compressed_frames += 1;
+                self.stats.bytes_in += len as u64;
+                self.stats.bytes_out += written as u64;
+                return Ok(());
+            }
+
+            // compression did not help; fall back to raw frame
+            dst.truncate(start);
+        }
+
+        dst.reserve(HEADER_LEN + len);
+        dst.put_u32(len as u32);
+        dst.put_u8(0);
+        dst.extend_from_slice(&self.scratch);
+
+        self.stats.frames += 1;
+        self.stats.bytes_in += len as u64;
+        self.stats.bytes_out += len as u64;
+
+        Ok(())
+    }
+}
+
+impl<C, Z> Decoder for CompressCodec<C, Z>
+where
+    C: Decoder,
+    Z: Compressor,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let mut header = [0; 4];
+        header.copy_from_slice(&src[..4]);
+        let len = u32::from_be_bytes(header) as usize;
+        let flags = src[4];
+
+        if len > self.max_frame_length {
+            return Err(self.frame_too_large(len).into());
+        }
+
+        if src.len() < HEADER_LEN + len {
+            src.reserve(HEADER_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+        let mut payload = src.split_to(len);
+
+        if flags & FLAG_COMPRESSED != 0 {
+            if payload.len() < 4 {
+                return Err(invalid_data("compressed frame is missing length prefix").into());
+            }
+
+            let raw_len = payload.get_u32() as usize;
+            if raw_len > self.max_frame_length {
+                return Err(self.frame_too_large(raw_len).into());
+            }
+
+            let mut raw = BytesMut::with_capacity(raw_len);
+            self.compressor.decompress(&payload, raw_len, &mut raw)?;
+
+            if raw.len() != raw_len {
+                return Err(invalid_data("decompressed frame length mismatch").into());
+            }
+
+            payload = raw;
+        }
+
+        match self.inner.decode_eof(&mut payload)? {
+            Some(item) if payload.is_empty() => Ok(Some(item)),
+            _ => Err(
+                invalid_data("inner codec did not decode exactly one item from frame").into(),
+            ),
+        }
+    }
+}
+
+impl<C: fmt::Debug, Z: fmt::Debug> fmt::Debug for CompressCodec<C, Z> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressCodec")
+            .field("inner", &self.inner)
+            .field("compressor", &self.compressor)
+            .field("min_compress_length", &self.min_compress_length)
+            .field("max_frame_length", &self.max_frame_length)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+/// LZ4 block compression, enabled with the `compress-lz4` feature.
+#[cfg(feature = "compress-lz4")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Lz4;
+
+#[cfg(feature = "compress-lz4")]
+impl Compressor for Lz4 {
+    fn compress(&mut self, src: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        let start = dst.len();
+        dst.resize(
+            start + lz4_flex::block::get_maximum_output_size(src.len()),
+            0,
+        );
+
+        match lz4_flex::block::compress_into(src, &mut dst[start..]) {
+            Ok(written) => {
+                dst.truncate(start + written);
+                Ok(())
+            }
+            Err(err) => {
+                dst.truncate(start);
+                Err(io::Error::new(io::ErrorKind::Other, err))
+            }
+        }
+    }
+
+    fn decompress(&mut self, src: &[u8], len: usize, dst: &mut BytesMut) -> io::Result<()> {
+        let start = dst.len();
+        dst.resize(start + len, 0);
+
+        match lz4_flex::block::decompress_into(src, &mut dst[start..]) {
+            Ok(written) => {
+                dst.truncate(start + written);
+                Ok(())
+            }
+            Err(err) => {
+                dst.truncate(start);
+                Err(io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        }
+    }
+}
+
+/// Zstandard compression, enabled with the `compress-zstd` feature.
+///
+/// Compression and decompression contexts are kept and reused between frames; each frame is
+/// still compressed independently.
+#[cfg(feature = "compress-zstd")]
+pub struct Zstd {
+    level: i32,
+    compressor: zstd::block::Compressor,
+    decompressor: zstd::block::Decompressor,
+}
+
+#[cfg(feature = "compress-zstd")]
+impl Zstd {
+    /// Create Zstandard compressor using compression `level`.
+    ///
+    /// A level of `0` uses the library default.
+    pub fn new(level: i32) -> Self {
+        Self {
+            level,
+            compressor: zstd::block::Compressor::new(),
+            decompressor: zstd::block::Decompressor::new(),
+        }
+    }
+}
+
+#[cfg(feature = "compress-zstd")]
+impl Default for Zstd {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(feature = "compress-zstd")]
+impl Compressor for Zstd {
+    fn compress(&mut self, src: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        let start = dst.len();
+        dst.resize(start + zstd::zstd_safe::compress_bound(src.len()), 0);
+
+        let res = self
+            .compressor
+            .compress_to_buffer(src, &mut dst[start..], self.level);
+        dst.truncate(start + *res.as_ref().unwrap_or(&0));
+        res.map(|_| ())
+    }
+
+    fn decompress(&mut self, src: &[u8], len: usize, dst: &mut BytesMut) -> io::Result<()> {
+        let start = dst.len();
+        dst.resize(start + len, 0);
+
+        let res = self
+            .decompressor
+            .decompress_to_buffer(src, &mut dst[start..]);
+        dst.truncate(start + *res.as_ref().unwrap_or(&0));
+        res.map(|_| ())
+    }
+}
+
+#[cfg(feature = "compress-zstd")]
+impl fmt::Debug for Zstd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Zstd").field("level", &self.level).finish()
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::BytesCodec;
+
+    /// Run-length encoding as pairs of (count, byte).
+    struct Rle;
+
+    impl Compressor for Rle {
+        fn compress(&mut self, src: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+            let mut iter = src.iter().peekable();
+
+            while let Some(byte) = iter.next() {
+                let mut count = 1u8;
+                while count < u8::MAX && iter.peek() == Some(&byte) {
+                    iter.next();
+                    count += 1;
+                }
+                dst.put_u8(count);
+                dst.put_u8(*byte);
+            }
+
+            Ok(())
+        }
+
+        fn decompress(&mut self, src: &[u8], len: usize, dst: &mut BytesMut) -> io::Result<()> {
+            for pair in src.chunks(2) {
+                if pair.len() != 2 || dst.len() + pair[0] as usize > len {
+                    return Err(invalid_data("bad rle data"));
+                }
+                for _ in 0..pair[0] {
+                    dst.put_u8(pair[1]);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut codec = CompressCodec::new(BytesCodec, Rle).min_compress_length(8);
+        let mut buf = BytesMut::new();
+
+        let long = Bytes::from(vec![b'a'; 100]);
+        codec.encode(Bytes::from_static(b"tiny"), &mut buf).unwrap();
+        codec.encode(long.clone(), &mut buf).unwrap();
+        codec
+            .encode(Bytes::from_static(b"incompressible"), &mut buf)
+            .unwrap();
+
+        let stats = codec.stats();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.compressed_frames, 1);
+        assert_eq!(stats.bytes_in, 4 + 100 + 14);
+        assert_eq!(stats.bytes_out, 4 + 6 + 14);
+        assert!(stats.ratio() < 0.25);
+
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"tiny");
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], &long[..]);
+        assert_eq!(
+            &codec.decode(&mut buf).unwrap().unwrap()[..],
+            b"incompressible"
+        );
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[cfg(feature = "compress-lz4")]
+    #[test]
+    fn lz4_roundtrip() {
+        let mut codec = CompressCodec::new(BytesCodec, Lz4);
+        let mut buf = BytesMut::new();
+
+        let long = Bytes::from(b"compressible ".repeat(100));
+        codec.encode(long.clone(), &mut buf).unwrap();
+        assert_eq!(codec.stats().compressed_frames, 1);
+        assert!(codec.stats().ratio() < 0.5);
+
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], &long[..]);
+    }
+
+    #[cfg(feature = "compress-zstd")]
+    #[test]
+    fn zstd_roundtrip() {
+        let mut codec = CompressCodec::new(BytesCodec, Zstd::new(3));
+        let mut buf = BytesMut::new();
+
+        let long = Bytes::from(b"compressible ".repeat(100));
+        codec.encode(long.clone(), &mut buf).unwrap();
+        codec.encode(long.clone(), &mut buf).unwrap();
+        assert_eq!(codec.stats().compressed_frames, 2);
+        assert!(codec.stats().ratio() < 0.5);
+
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], &long[..]);
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], &long[..]);
+
+        // corrupted compressed payload is rejected
+        codec.encode(long, &mut buf).unwrap();
+        let last = buf.len() - 1;
+        buf[last] ^= 0xFF;
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn max_frame_length() {
+        let mut codec = CompressCodec::new(BytesCodec, Rle).min_compress_length(0);
+        let mut buf = BytesMut::new();
+        codec.encode(Bytes::from(vec![0; 64]), &mut buf).unwrap();
+
+        // compressed size is small but uncompressed size exceeds limit
+        let mut codec = codec.max_frame_length(32);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
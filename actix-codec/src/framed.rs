@@ -1,6 +1,8 @@
+use std::cell::RefCell;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::{fmt, io};
+use std::{error, fmt, io};
 
 use bytes::{Buf, BytesMut};
 use futures_core::{ready, Stream};
@@ -12,6 +14,11 @@ use crate::{AsyncRead, AsyncWrite, Decoder, Encoder};
 const LW: usize = 1024;
 /// High-water mark
 const HW: usize = 8 * 1024;
+/// Default upper limit of read sizes
+const READ_MAX: usize = 64 * 1024;
+/// Number of polls in a row finding no data, without a read filling the read size in between,
+/// after which the read size shrinks and an empty read buffer is released.
+const IDLE_POLLS: u8 = 4;
 
 bitflags::bitflags! {
     struct Flags: u8 {
@@ -35,6 +42,7 @@ pin_project_lite::pin_project! {
         codec: U,
         flags: Flags,
         read_buf: BytesMut,
+        read_size: ReadBufSize,
         write_buf: BytesMut,
     }
 }
@@ -53,7 +61,8 @@ where
             io,
             codec,
             flags: Flags::empty(),
-            read_buf: BytesMut::with_capacity(HW),
+            read_buf: BytesMut::with_capacity(LW),
+            read_size: ReadBufSize::default(),
             write_buf: BytesMut::with_capacity(HW),
         }
     }
@@ -99,6 +108,24 @@ impl<T, U> Framed<T, U> {
         self.read_buf.is_empty()
     }
 
+    /// Set lower and upper limit of the amount of data read from the I/O stream at once.
+    ///
+    /// The read size starts at `min` and doubles each time a read fills it, up to `max`. It
+    /// halves again after two consecutive reads smaller than half of it or after the stream had
+    /// no data to read four times in a row without a read filling it in between. In the latter
+    /// case the read buffer is also shrunk to `min` unless a frame is partially buffered, so idle
+    /// transports hold little memory while busy ones keep their buffer.
+    ///
+    /// The limits do not cap the read buffer itself, which still grows as needed to hold a
+    /// partially received frame. Defaults to 1KiB and 64KiB.
+    ///
+    /// # Panics
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn set_read_buf_size(&mut self, min: usize, max: usize) {
+        self.read_size = ReadBufSize::new(min, max);
+        self.read_size.release(&mut self.read_buf);
+    }
+
     /// Check if write buffer is empty.
     pub fn is_write_buf_empty(&self) -> bool {
         self.write_buf.is_empty()
@@ -123,6 +150,7 @@ impl<T, U> Framed<T, U> {
             io: self.io,
             flags: self.flags,
             read_buf: self.read_buf,
+            read_size: self.read_size,
             write_buf: self.write_buf,
         }
     }
@@ -137,6 +165,7 @@ impl<T, U> Framed<T, U> {
             codec: self.codec,
             flags: self.flags,
             read_buf: self.read_buf,
+            read_size: self.read_size,
             write_buf: self.write_buf,
         }
     }
@@ -151,6 +180,7 @@ impl<T, U> Framed<T, U> {
             codec: f(self.codec),
             flags: self.flags,
             read_buf: self.read_buf,
+            read_size: self.read_size,
             write_buf: self.write_buf,
         }
     }
@@ -216,17 +246,19 @@ impl<T, U> Framed<T, U> {
             debug_assert!(!this.flags.contains(Flags::EOF));
 
             // Otherwise, try to read more data and try again. Make sure we've got room
-            let remaining = this.read_buf.capacity() - this.read_buf.len();
-            if remaining < LW {
-                this.read_buf.reserve(HW - remaining)
-            }
+            this.read_size.reserve(this.read_buf);
 
             let cnt = match tokio_util::io::poll_read_buf(this.io, cx, this.read_buf) {
-                Poll::Pending => return Poll::Pending,
+                Poll::Pending => {
+                    this.read_size.idle(this.read_buf);
+                    return Poll::Pending;
+                }
                 Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
                 Poll::Ready(Ok(cnt)) => cnt,
             };
 
+            this.read_size.record(cnt);
+
             if cnt == 0 {
                 this.flags.insert(Flags::EOF);
             }
@@ -355,6 +387,7 @@ impl<T, U> Framed<T, U> {
             flags: parts.flags,
             write_buf: parts.write_buf,
             read_buf: parts.read_buf,
+            read_size: parts.read_size,
         }
     }
 
@@ -370,6 +403,7 @@ impl<T, U> Framed<T, U> {
             codec: self.codec,
             flags: self.flags,
             read_buf: self.read_buf,
+            read_size: self.read_size,
             write_buf: self.write_buf,
         }
     }
@@ -393,6 +427,7 @@ pub struct FramedParts<T, U> {
     pub write_buf: BytesMut,
 
     flags: Flags,
+    read_size: ReadBufSize,
 }
 
 impl<T, U> FramedParts<T, U> {
@@ -403,6 +438,7 @@ impl<T, U> FramedParts<T, U> {
             codec,
             flags: Flags::empty(),
             read_buf: BytesMut::new(),
+            read_size: ReadBufSize::default(),
             write_buf: BytesMut::new(),
         }
     }
@@ -414,7 +450,545 @@ impl<T, U> FramedParts<T, U> {
             codec,
             read_buf,
             flags: Flags::empty(),
+            read_size: ReadBufSize::default(),
             write_buf: BytesMut::new(),
         }
     }
 }
+
+impl<T, U> Framed<T, U>
+where
+    T: Unpin,
+{
+    /// Split transport into separate read and write halves.
+    ///
+    /// Halves can be used independently, for example from two tasks spawned on the same arbiter.
+    /// The I/O object and codec are shared between the halves through a non-thread-safe reference
+    /// counted cell; neither half holds a borrow across polls so they never contend.
+    ///
+    /// Closing the write half flushes its buffer and shuts down the write direction of the I/O
+    /// object while the read half continues to receive frames until EOF.
+    ///
+    /// Use [`FramedRead::reunite`] to recover the original `Framed`.
+    pub fn split(self) -> (FramedRead<T, U>, FramedWrite<T, U>) {
+        let shared = Rc::new(RefCell::new(SplitShared {
+            io: self.io,
+            codec: self.codec,
+        }));
+
+        let read = FramedRead {
+            shared: shared.clone(),
+            flags: self.flags,
+            read_buf: self.read_buf,
+            read_size: self.read_size,
+        };
+
+        let write = FramedWrite {
+            shared,
+            write_buf: self.write_buf,
+        };
+
+        (read, write)
+    }
+}
+
+struct SplitShared<T, U> {
+    io: T,
+    codec: U,
+}
+
+/// Read half of a [`Framed`] transport, created by [`Framed::split`].
+pub struct FramedRead<T, U> {
+    shared: Rc<RefCell<SplitShared<T, U>>>,
+    flags: Flags,
+    read_buf: BytesMut,
+    read_size: ReadBufSize,
+}
+
+impl<T, U> FramedRead<T, U> {
+    /// Check if read buffer is empty.
+    pub fn is_read_buf_empty(&self) -> bool {
+        self.read_buf.is_empty()
+    }
+
+    /// Set lower and upper limit of the amount of data read from the I/O stream at once.
+    ///
+    /// See [`Framed::set_read_buf_size`].
+    ///
+    /// # Panics
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn set_read_buf_size(&mut self, min: usize, max: usize) {
+        self.read_size = ReadBufSize::new(min, max);
+        self.read_size.release(&mut self.read_buf);
+    }
+
+    /// Check if this half and `other` were split from the same `Framed`.
+    pub fn is_pair_of(&self, other: &FramedWrite<T, U>) -> bool {
+        Rc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// Rejoin with write half, returning the original `Framed`.
+    ///
+    /// Buffered data in both halves is preserved. Returns both halves unchanged in the error if
+    /// they were not split from the same `Framed`.
+    pub fn reunite(self, other: FramedWrite<T, U>) -> Result<Framed<T, U>, ReuniteError<T, U>> {
+        if !self.is_pair_of(&other) {
+            return Err(ReuniteError(self, other));
+        }
+
+        let FramedRead {
+            shared,
+            flags,
+            read_buf,
+            read_size,
+        } = self;
+        let FramedWrite {
+            shared: other_shared,
+            write_buf,
+        } = other;
+
+        drop(other_shared);
+
+        let SplitShared { io, codec } = match Rc::try_unwrap(shared) {
+            Ok(shared) => shared.into_inner(),
+            Err(_) => unreachable!("both halves are consumed; no other references exist"),
+        };
+
+        Ok(Framed {
+            io,
+            codec,
+            flags,
+            read_buf,
+            read_size,
+            write_buf,
+        })
+    }
+
+    /// Try to read underlying I/O stream and decode item.
+    pub fn next_item(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<<U as Decoder>::Item, U::Error>>>
+    where
+        T: AsyncRead + Unpin,
+        U: Decoder,
+    {
+        let mut shared = self.shared.borrow_mut();
+        let SplitShared { io, codec } = &mut *shared;
+
+        loop {
+            if self.flags.contains(Flags::READABLE) {
+                if self.flags.contains(Flags::EOF) {
+                    match codec.decode_eof(&mut self.read_buf) {
+                        Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                        Ok(None) => return Poll::Ready(None),
+                        Err(e) => return Poll::Ready(Some(Err(e))),
+                    }
+                }
+
+                log::trace!("attempting to decode a frame");
+
+                match codec.decode(&mut self.read_buf) {
+                    Ok(Some(frame)) => {
+                        log::trace!("frame decoded from buffer");
+                        return Poll::Ready(Some(Ok(frame)));
+                    }
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                    _ => (), // Need more data
+                }
+
+                self.flags.remove(Flags::READABLE);
+            }
+
+            debug_assert!(!self.flags.contains(Flags::EOF));
+
+            // Otherwise, try to read more data and try again. Make sure we've got room
+            self.read_size.reserve(&mut self.read_buf);
+
+            let cnt =
+                match tokio_util::io::poll_read_buf(Pin::new(&mut *io), cx, &mut self.read_buf)
+                {
+                    Poll::Pending => {
+                        self.read_size.idle(&mut self.read_buf);
+                        return Poll::Pending;
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                    Poll::Ready(Ok(cnt)) => cnt,
+                };
+
+            self.read_size.record(cnt);
+
+            if cnt == 0 {
+                self.flags.insert(Flags::EOF);
+            }
+            self.flags.insert(Flags::READABLE);
+        }
+    }
+}
+
+impl<T, U> Stream for FramedRead<T, U>
+where
+    T: AsyncRead + Unpin,
+    U: Decoder,
+{
+    type Item = Result<U::Item, U::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().next_item(cx)
+    }
+}
+
+impl<T, U> fmt::Debug for FramedRead<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedRead")
+            .field("read_buf_len", &self.read_buf.len())
+            .finish()
+    }
+}
+
+/// Write half of a [`Framed`] transport, created by [`Framed::split`].
+pub struct FramedWrite<T, U> {
+    shared: Rc<RefCell<SplitShared<T, U>>>,
+    write_buf: BytesMut,
+}
+
+impl<T, U> FramedWrite<T, U> {
+    /// Check if write buffer is empty.
+    pub fn is_write_buf_empty(&self) -> bool {
+        self.write_buf.is_empty()
+    }
+
+    /// Check if write buffer is full.
+    pub fn is_write_buf_full(&self) -> bool {
+        self.write_buf.len() >= HW
+    }
+
+    /// Check if write half is able to write more data.
+    pub fn is_write_ready(&self) -> bool {
+        self.write_buf.len() < HW
+    }
+
+    /// Serialize item and Write to the inner buffer
+    pub fn write<I>(&mut self, item: I) -> Result<(), <U as Encoder<I>>::Error>
+    where
+        U: Encoder<I>,
+    {
+        let remaining = self.write_buf.capacity() - self.write_buf.len();
+        if remaining < LW {
+            self.write_buf.reserve(HW - remaining);
+        }
+
+        self.shared
+            .borrow_mut()
+            .codec
+            .encode(item, &mut self.write_buf)
+    }
+
+    /// Flush write buffer to underlying I/O stream.
+    pub fn flush<I>(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), U::Error>>
+    where
+        T: AsyncWrite + Unpin,
+        U: Encoder<I>,
+    {
+        let mut shared = self.shared.borrow_mut();
+        let io = &mut shared.io;
+        log::trace!("flushing framed write half");
+
+        while !self.write_buf.is_empty() {
+            log::trace!("writing; remaining={}", self.write_buf.len());
+
+            let n = ready!(Pin::new(&mut *io).poll_write(cx, &self.write_buf))?;
+
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write frame to transport",
+                )
+                .into()));
+            }
+
+            // remove written data
+            self.write_buf.advance(n);
+        }
+
+        // Try flushing the underlying IO
+        ready!(Pin::new(&mut *io).poll_flush(cx))?;
+
+        log::trace!("framed write half flushed");
+        Poll::Ready(Ok(()))
+    }
+
+    /// Flush write buffer and shutdown write direction of underlying I/O stream.
+    ///
+    /// The read half is unaffected and continues to receive frames.
+    pub fn close<I>(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), U::Error>>
+    where
+        T: AsyncWrite + Unpin,
+        U: Encoder<I>,
+    {
+        ready!(self.flush(cx))?;
+
+        let mut shared = self.shared.borrow_mut();
+        ready!(Pin::new(&mut shared.io).poll_shutdown(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T, U, I> Sink<I> for FramedWrite<T, U>
+where
+    T: AsyncWrite + Unpin,
+    U: Encoder<I>,
+    U::Error: From<io::Error>,
+{
+    type Error = U::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.is_write_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        self.get_mut().write(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().close(cx)
+    }
+}
+
+impl<T, U> fmt::Debug for FramedWrite<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedWrite")
+            .field("write_buf_len", &self.write_buf.len())
+            .finish()
+    }
+}
+
+/// Adaptive amount of data read from the I/O stream at once.
+#[derive(Debug, Clone, Copy)]
+struct ReadBufSize {
+    min: usize,
+    max: usize,
+    next: usize,
+    decrease_now: bool,
+    idle_polls: u8,
+}
+
+impl Default for ReadBufSize {
+    fn default() -> Self {
+        Self::new(LW, READ_MAX)
+    }
+}
+
+impl ReadBufSize {
+    fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0, "minimum read size must be greater than zero");
+        assert!(min <= max, "minimum read size must not exceed maximum");
+
+        Self {
+            min,
+            max,
+            next: min,
+            decrease_now: false,
+            idle_polls: 0,
+        }
+    }
+
+    /// Make sure buffer has room for the next read.
+    fn reserve(&self, buf: &mut BytesMut) {
+        // reserving guarantees room for the given number of bytes beyond the current length
+        buf.reserve(self.next);
+    }
+
+    /// Adjust read size to the number of bytes read.
+    fn record(&mut self, cnt: usize) {
+        if cnt >= self.next {
+            self.next = self.max.min(self.next.saturating_mul(2));
+            self.decrease_now = false;
+            self.idle_polls = 0;
+        } else if cnt < self.next / 2 {
+            // shrink only after two small reads in a row so a single short read at the end of
+            // a burst keeps the size
+            if self.decrease_now {
+                self.shrink();
+                self.decrease_now = false;
+            } else {
+                self.decrease_now = true;
+            }
+        } else {
+            self.decrease_now = false;
+        }
+    }
+
+    /// Count a poll finding no data. Once the stream stays idle, shrink read size and release
+    /// memory of an empty buffer.
+    fn idle(&mut self, buf: &mut BytesMut) {
+        self.idle_polls += 1;
+        if self.idle_polls < IDLE_POLLS {
+            return;
+        }
+
+        self.idle_polls = 0;
+        self.shrink();
+        self.decrease_now = false;
+        self.release(buf);
+    }
+
+    /// Shrink capacity of an empty buffer to the minimum read size.
+    fn release(&self, buf: &mut BytesMut) {
+        if buf.is_empty() && buf.capacity() > self.min {
+            *buf = BytesMut::with_capacity(self.min);
+        }
+    }
+
+    fn shrink(&mut self) {
+        self.next = self.min.max(self.next / 2);
+    }
+}
+
+/// Error indicating that a [`FramedRead`] and [`FramedWrite`] were not split from the same
+/// [`Framed`] and could not be reunited.
+pub struct ReuniteError<T, U>(pub FramedRead<T, U>, pub FramedWrite<T, U>);
+
+impl<T, U> fmt::Debug for ReuniteError<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish()
+    }
+}
+
+impl<T, U> fmt::Display for ReuniteError<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same framed transport")
+    }
+}
+
+impl<T, U> error::Error for ReuniteError<T, U> {}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::BytesCodec;
+
+    #[actix_rt::test]
+    async fn split_halves_in_separate_tasks() {
+        let (client, mut server) = duplex(64);
+        let (mut rx, mut tx) = Framed::new(client, BytesCodec).split();
+
+        let writer = tokio::task::spawn_local(async move {
+            tx.send(Bytes::from_static(b"ping")).await.unwrap();
+            SinkExt::close(&mut tx).await.unwrap();
+            tx
+        });
+
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"ping");
+
+        // read half still works after write half is shut down
+        server.write_all(b"pong").await.unwrap();
+        drop(server);
+
+        let item = rx.next().await.unwrap().unwrap();
+        assert_eq!(&item[..], b"pong");
+        assert!(rx.next().await.is_none());
+
+        let tx = writer.await.unwrap();
+        let framed = rx.reunite(tx).unwrap();
+        assert!(framed.is_read_buf_empty());
+        assert!(framed.is_write_buf_empty());
+    }
+
+    #[test]
+    fn read_buf_size_adapts() {
+        let mut size = ReadBufSize::new(16, 64);
+
+        size.record(16);
+        size.record(32);
+        assert_eq!(size.next, 64);
+        size.record(64);
+        assert_eq!(size.next, 64);
+
+        // single short read keeps the size
+        size.record(10);
+        size.record(40);
+        assert_eq!(size.next, 64);
+
+        size.record(10);
+        size.record(10);
+        assert_eq!(size.next, 32);
+
+        // polls without data in between full reads keep the size
+        let mut buf = BytesMut::with_capacity(64);
+        for _ in 0..IDLE_POLLS - 1 {
+            size.idle(&mut buf);
+        }
+        size.record(32);
+        size.idle(&mut buf);
+        assert_eq!(size.next, 64);
+        assert_eq!(buf.capacity(), 64);
+
+        for _ in 0..IDLE_POLLS {
+            size.idle(&mut buf);
+        }
+        assert_eq!(size.next, 32);
+        assert_eq!(buf.capacity(), 16);
+
+        // next read reserves room for the current read size again
+        size.reserve(&mut buf);
+        assert!(buf.capacity() >= 32);
+    }
+
+    #[actix_rt::test]
+    async fn read_buf_shrinks_when_idle() {
+        let (client, mut server) = duplex(1024);
+        let mut framed = Framed::new(client, BytesCodec);
+        framed.set_read_buf_size(16, 256);
+
+        server.write_all(&[b'x'; 512]).await.unwrap();
+
+        let mut received = 0;
+        while received < 512 {
+            received += framed.next().await.unwrap().unwrap().len();
+        }
+        assert_eq!(framed.read_size.next, 256);
+
+        // no more data; the empty buffer is released once the stream stays idle
+        for poll in 1..=IDLE_POLLS {
+            let res = futures_util::future::poll_fn(|cx| {
+                Poll::Ready(Pin::new(&mut framed).next_item(cx))
+            })
+            .await;
+            assert!(res.is_pending());
+
+            if poll < IDLE_POLLS {
+                assert_eq!(framed.read_size.next, 256);
+            }
+        }
+        assert_eq!(framed.read_size.next, 128);
+        assert_eq!(framed.read_buf.capacity(), 16);
+    }
+
+    #[actix_rt::test]
+    async fn reunite_mismatch() {
+        let (io1, _io2) = duplex(64);
+        let (io3, _io4) = duplex(64);
+
+        let (rx1, tx1) = Framed::new(io1, BytesCodec).split();
+        let (rx2, tx2) = Framed::new(io3, BytesCodec).split();
+
+        let ReuniteError(rx1, tx2) = rx1.reunite(tx2).unwrap_err();
+        assert!(rx1.reunite(tx1).is_ok());
+        assert!(rx2.reunite(tx2).is_ok());
+    }
+}
@@ -13,10 +13,18 @@
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
 
 mod bcodec;
+mod checksum;
+mod compress;
 mod framed;
 
 pub use self::bcodec::BytesCodec;
-pub use self::framed::{Framed, FramedParts};
+pub use self::checksum::{Checksum, ChecksumCodec, CorruptFramePolicy, Crc32c};
+#[cfg(feature = "compress-lz4")]
+pub use self::compress::Lz4;
+#[cfg(feature = "compress-zstd")]
+pub use self::compress::Zstd;
+pub use self::compress::{CompressCodec, CompressStats, Compressor};
+pub use self::framed::{Framed, FramedParts, FramedRead, FramedWrite, ReuniteError};
 
 pub use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 pub use tokio_util::codec::{Decoder, Encoder};
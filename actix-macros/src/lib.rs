@@ -7,6 +7,9 @@
 //!
 //! # Tests
 //! See docs for the [`#[test]`](macro@test) macro.
+//!
+//! # Benchmarks
+//! See docs for the [`#[bench]`](macro@bench) macro.
 
 #![deny(rust_2018_idioms, nonstandard_style)]
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
@@ -142,3 +145,124 @@ pub fn test(_: TokenStream, item: TokenStream) -> TokenStream {
     })
     .into()
 }
+
+/// Marks benchmark function to be executed in an Actix runtime.
+///
+/// An async function runs to completion in a new runtime, removing the runtime setup from
+/// benchmarks that measure time themselves.
+///
+/// A function that is not async receives the runtime through its last parameter, which is removed
+/// from the signature. This fits harnesses like criterion that call the benchmark with their own
+/// arguments and iterate a closure: the runtime is created once and each iteration runs an async
+/// body with `block_on`.
+///
+/// # Runtime flavor
+/// By default a new `actix_rt::System` is started and the runtime parameter is a
+/// `&actix_rt::SystemRunner`. With `flavor = "runtime"` a bare `actix_rt::Runtime` is used
+/// instead, without a system or its arbiter, and the runtime parameter is a
+/// `&actix_rt::Runtime`.
+///
+/// # Examples
+/// ```
+/// use actix_rt::SystemRunner;
+/// # struct Criterion;
+/// # struct Bencher;
+/// # impl Criterion {
+/// #     fn bench_function(&mut self, _: &str, mut f: impl FnMut(&mut Bencher)) { f(&mut Bencher) }
+/// # }
+/// # impl Bencher {
+/// #     fn iter<T>(&mut self, mut f: impl FnMut() -> T) { f(); }
+/// # }
+///
+/// #[actix_rt::bench]
+/// fn bench_echo(c: &mut Criterion, rt: &SystemRunner) {
+///     c.bench_function("echo", |b| {
+///         b.iter(|| rt.block_on(async { actix_rt::task::yield_now().await }))
+///     });
+/// }
+///
+/// #[actix_rt::bench(flavor = "runtime")]
+/// async fn bench_startup() {
+///     let start = std::time::Instant::now();
+///     actix_rt::task::yield_now().await;
+///     println!("took {:?}", start.elapsed());
+/// }
+/// # bench_echo(&mut Criterion);
+/// # bench_startup();
+/// ```
+#[proc_macro_attribute]
+pub fn bench(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = syn::parse_macro_input!(item as syn::ItemFn);
+    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
+
+    let mut runtime = quote!(::actix_rt::System::new());
+
+    for arg in &args {
+        match arg {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                path,
+                ..
+            })) if path.is_ident("flavor") => match lit.value().as_str() {
+                "system" => runtime = quote!(::actix_rt::System::new()),
+                "runtime" => {
+                    runtime = quote!(
+                        ::actix_rt::Runtime::new().expect("Can not create Actix runtime")
+                    )
+                }
+                _ => {
+                    return syn::Error::new_spanned(
+                        lit,
+                        "Unknown flavor, expected \"system\" or \"runtime\"",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+            },
+            _ => {
+                return syn::Error::new_spanned(arg, "Unknown attribute specified")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let sig = &mut input.sig;
+    let body = &input.block;
+
+    if sig.asyncness.is_some() {
+        sig.asyncness = None;
+
+        return (quote! {
+            #(#attrs)*
+            #vis #sig {
+                #runtime.block_on(async move { #body })
+            }
+        })
+        .into();
+    }
+
+    let (pat, ty) = match sig.inputs.pop().map(|pair| pair.into_value()) {
+        Some(syn::FnArg::Typed(arg)) => (arg.pat, arg.ty),
+        _ => {
+            return syn::Error::new_spanned(
+                &sig.ident,
+                "the runtime parameter is missing from the function declaration",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    (quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __actix_runtime = #runtime;
+            let #pat: #ty = &__actix_runtime;
+            #body
+        }
+    })
+    .into()
+}
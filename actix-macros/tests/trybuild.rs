@@ -11,4 +11,9 @@ fn compile_macros() {
     t.pass("tests/trybuild/test-01-basic.rs");
     t.pass("tests/trybuild/test-02-keep-attrs.rs");
     t.compile_fail("tests/trybuild/test-03-only-async.rs");
+
+    t.pass("tests/trybuild/bench-01-async.rs");
+    t.pass("tests/trybuild/bench-02-runner.rs");
+    t.compile_fail("tests/trybuild/bench-03-no-runner.rs");
+    t.compile_fail("tests/trybuild/bench-04-unknown-flavor.rs");
 }
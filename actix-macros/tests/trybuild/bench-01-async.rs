@@ -0,0 +1,14 @@
+#[actix_rt::bench]
+async fn bench_async() {
+    futures_util::future::ready(()).await
+}
+
+#[actix_rt::bench(flavor = "runtime")]
+async fn bench_async_runtime(_param: bool) {
+    futures_util::future::ready(()).await
+}
+
+fn main() {
+    bench_async();
+    bench_async_runtime(true);
+}
@@ -0,0 +1,18 @@
+use actix_rt::{Runtime, SystemRunner};
+
+#[actix_rt::bench]
+fn bench_system(iters: u32, rt: &SystemRunner) {
+    for _ in 0..iters {
+        rt.block_on(futures_util::future::ready(()));
+    }
+}
+
+#[actix_rt::bench(flavor = "runtime")]
+fn bench_runtime(rt: &Runtime) {
+    rt.block_on(futures_util::future::ready(()));
+}
+
+fn main() {
+    bench_system(3);
+    bench_runtime();
+}
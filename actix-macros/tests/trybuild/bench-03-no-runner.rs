@@ -0,0 +1,4 @@
+#[actix_rt::bench]
+fn bench_no_runner() {}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[actix_rt::bench(flavor = "multi_thread")]
+async fn bench_async() {}
+
+#[actix_rt::bench(foo = "bar")]
+async fn bench_async2() {}
+
+fn main() {}
@@ -1,15 +1,23 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt,
     future::Future,
+    panic::{self, AssertUnwindSafe},
     pin::Pin,
-    sync::atomic::{AtomicUsize, Ordering},
-    task::{Context, Poll},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
     thread,
+    time::Duration,
 };
 
-use futures_core::ready;
-use tokio::{sync::mpsc, task::LocalSet};
+use tokio::{
+    sync::mpsc,
+    task::LocalSet,
+    time::{sleep, Sleep},
+};
 
 use crate::{
     runtime::{default_tokio_runtime, Runtime},
@@ -20,10 +28,12 @@ pub(crate) static COUNT: AtomicUsize = AtomicUsize::new(0);
 
 thread_local!(
     static HANDLE: RefCell<Option<ArbiterHandle>> = RefCell::new(None);
+    static TASKS: LocalTasks = LocalTasks::default();
 );
 
 pub(crate) enum ArbiterCommand {
     Stop,
+    StopGracefully(Duration),
     Execute(Pin<Box<dyn Future<Output = ()> + Send>>),
 }
 
@@ -31,20 +41,98 @@ impl fmt::Debug for ArbiterCommand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ArbiterCommand::Stop => write!(f, "ArbiterCommand::Stop"),
+            ArbiterCommand::StopGracefully(timeout) => {
+                write!(f, "ArbiterCommand::StopGracefully({:?})", timeout)
+            }
             ArbiterCommand::Execute(_) => write!(f, "ArbiterCommand::Execute"),
         }
     }
 }
 
+/// Unfinished tasks of the Arbiter running on the current thread.
+#[derive(Default)]
+struct LocalTasks {
+    count: Cell<usize>,
+    /// Waker of an [ArbiterRunner] waiting for tasks to finish.
+    waker: RefCell<Option<Waker>>,
+}
+
+/// Counts a task of the current thread's Arbiter until it is finished or dropped.
+struct TaskGuard;
+
+impl TaskGuard {
+    fn new() -> Self {
+        let _ = TASKS.try_with(|tasks| tasks.count.set(tasks.count.get() + 1));
+        TaskGuard
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        // tasks may be dropped while thread locals are destroyed
+        let _ = TASKS.try_with(|tasks| {
+            tasks.count.set(tasks.count.get() - 1);
+
+            if tasks.count.get() == 0 {
+                if let Some(waker) = tasks.waker.borrow_mut().take() {
+                    waker.wake();
+                }
+            }
+        });
+    }
+}
+
+/// What an [Arbiter] does when one of its tasks panics.
+///
+/// Applies to futures and functions sent to the arbiter and to tasks spawned on its thread with
+/// [`spawn`](crate::spawn). The panic is reported by the panic hook in any case and counted
+/// unless the arbiter ignores panics without [tracking its tasks](Arbiter::with_task_tracking).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Drop the panicking task and keep running other tasks, as arbiters created with
+    /// [`Arbiter::new`] do. Tasks are spawned as they are, without catching their panics.
+    Ignore,
+
+    /// Stop the arbiter's event loop, dropping all its tasks.
+    StopArbiter,
+
+    /// Abort the process.
+    Abort,
+}
+
+/// State of an [Arbiter] shared by its handles.
+#[derive(Debug)]
+struct ArbiterState {
+    policy: PanicPolicy,
+    /// Whether tasks are wrapped for counting them and catching their panics. Unwrapped tasks
+    /// are neither waited for by a graceful stop nor counted when they panic.
+    tracked: bool,
+    panics: AtomicUsize,
+    /// Set once the arbiter was told to stop gracefully and takes no more work.
+    stopping: AtomicBool,
+}
+
+impl ArbiterState {
+    fn new(policy: PanicPolicy, track_tasks: bool) -> Arc<Self> {
+        Arc::new(Self {
+            policy,
+            tracked: track_tasks || policy != PanicPolicy::Ignore,
+            panics: AtomicUsize::new(0),
+            stopping: AtomicBool::new(false),
+        })
+    }
+}
+
 /// A handle for sending spawn and stop messages to an [Arbiter].
 #[derive(Debug, Clone)]
 pub struct ArbiterHandle {
     tx: mpsc::UnboundedSender<ArbiterCommand>,
+    state: Arc<ArbiterState>,
 }
 
 impl ArbiterHandle {
-    pub(crate) fn new(tx: mpsc::UnboundedSender<ArbiterCommand>) -> Self {
-        Self { tx }
+    fn new(tx: mpsc::UnboundedSender<ArbiterCommand>, state: Arc<ArbiterState>) -> Self {
+        Self { tx, state }
     }
 
     /// Send a future to the [Arbiter]'s thread and spawn it.
@@ -56,9 +144,11 @@ impl ArbiterHandle {
     where
         Fut: Future<Output = ()> + Send + 'static,
     {
-        self.tx
-            .send(ArbiterCommand::Execute(Box::pin(future)))
-            .is_ok()
+        !self.state.stopping.load(Ordering::Acquire)
+            && self
+                .tx
+                .send(ArbiterCommand::Execute(Box::pin(future)))
+                .is_ok()
     }
 
     /// Send a function to the [Arbiter]'s thread and execute it.
@@ -81,6 +171,47 @@ impl ArbiterHandle {
     pub fn stop(&self) -> bool {
         self.tx.send(ArbiterCommand::Stop).is_ok()
     }
+
+    /// Instruct [Arbiter] to stop once its tasks are finished, waiting for them up to `timeout`.
+    ///
+    /// The Arbiter stops accepting futures and functions right away; those sent before are still
+    /// run. Its event loop keeps running until all tasks spawned through the Arbiter or with
+    /// [`spawn`](crate::spawn) on its thread are finished, or until `timeout` elapsed. Tasks
+    /// still running then are dropped.
+    ///
+    /// Tasks are only waited for on arbiters [tracking them](Arbiter::with_task_tracking) or
+    /// with a [`PanicPolicy`] other than `Ignore`; other arbiters stop once the futures and
+    /// functions sent before were started.
+    ///
+    /// Returns true if stop message was sent successfully and false if the [Arbiter] has
+    /// been dropped.
+    pub fn stop_gracefully(&self, timeout: Duration) -> bool {
+        self.state.stopping.store(true, Ordering::Release);
+        self.tx
+            .send(ArbiterCommand::StopGracefully(timeout))
+            .is_ok()
+    }
+
+    /// Returns number of tasks of the [Arbiter] that panicked.
+    ///
+    /// Panics are only counted on arbiters [tracking their tasks](Arbiter::with_task_tracking)
+    /// or with a [`PanicPolicy`] other than `Ignore`.
+    pub fn panic_count(&self) -> usize {
+        self.state.panics.load(Ordering::Relaxed)
+    }
+
+    /// Count a panic of one of the arbiter's tasks and apply its panic policy.
+    fn panicked(&self) {
+        self.state.panics.fetch_add(1, Ordering::Relaxed);
+
+        match self.state.policy {
+            PanicPolicy::Ignore => {}
+            PanicPolicy::StopArbiter => {
+                self.stop();
+            }
+            PanicPolicy::Abort => std::process::abort(),
+        }
+    }
 }
 
 /// An Arbiter represents a thread that provides an asynchronous execution environment for futures
@@ -90,6 +221,7 @@ impl ArbiterHandle {
 #[derive(Debug)]
 pub struct Arbiter {
     tx: mpsc::UnboundedSender<ArbiterCommand>,
+    state: Arc<ArbiterState>,
     thread_handle: thread::JoinHandle<()>,
 }
 
@@ -110,6 +242,76 @@ impl Arbiter {
     /// [tokio-runtime]: tokio::runtime::Runtime
     #[doc(hidden)]
     pub fn with_tokio_rt<F>(runtime_factory: F) -> Arbiter
+    where
+        F: Fn() -> tokio::runtime::Runtime + Send + 'static,
+    {
+        Self::start(None, runtime_factory, PanicPolicy::Ignore, false)
+    }
+
+    /// Spawn a new Arbiter with the given thread name using the [Tokio Runtime](tokio-runtime)
+    /// returned from a closure.
+    ///
+    /// [tokio-runtime]: tokio::runtime::Runtime
+    #[doc(hidden)]
+    pub fn with_tokio_rt_and_name<F>(name: String, runtime_factory: F) -> Arbiter
+    where
+        F: Fn() -> tokio::runtime::Runtime + Send + 'static,
+    {
+        Self::start(Some(name), runtime_factory, PanicPolicy::Ignore, false)
+    }
+
+    /// Spawn a new Arbiter thread that handles panicking tasks according to `policy`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::{Arbiter, PanicPolicy, System};
+    ///
+    /// let _ = System::new();
+    ///
+    /// let arbiter = Arbiter::with_panic_policy(PanicPolicy::StopArbiter);
+    /// # std::panic::set_hook(Box::new(|_| {}));
+    /// arbiter.spawn_fn(|| panic!("corrupted state"));
+    ///
+    /// // arbiter stops on its own
+    /// let handle = arbiter.handle();
+    /// arbiter.join().unwrap();
+    /// assert_eq!(handle.panic_count(), 1);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if a [System] is not registered on the current thread.
+    pub fn with_panic_policy(policy: PanicPolicy) -> Arbiter {
+        Self::start(
+            None,
+            || default_tokio_runtime().expect("Cannot create new Arbiter's Runtime."),
+            policy,
+            false,
+        )
+    }
+
+    /// Spawn a new Arbiter thread that keeps track of its tasks, ignoring their panics.
+    ///
+    /// A [graceful stop](Self::stop_gracefully) waits for tasks sent to the arbiter or spawned on
+    /// its thread, and panics of those tasks are [counted](Self::panic_count). Arbiters created
+    /// with [`Arbiter::new`] spawn tasks as they are, which saves wrapping each of them.
+    ///
+    /// # Panics
+    /// Panics if a [System] is not registered on the current thread.
+    pub fn with_task_tracking() -> Arbiter {
+        Self::start(
+            None,
+            || default_tokio_runtime().expect("Cannot create new Arbiter's Runtime."),
+            PanicPolicy::Ignore,
+            true,
+        )
+    }
+
+    fn start<F>(
+        name: Option<String>,
+        runtime_factory: F,
+        policy: PanicPolicy,
+        track_tasks: bool,
+    ) -> Arbiter
     where
         F: Fn() -> tokio::runtime::Runtime + Send + 'static,
     {
@@ -117,8 +319,10 @@ impl Arbiter {
         let system_id = sys.id();
         let arb_id = COUNT.fetch_add(1, Ordering::Relaxed);
 
-        let name = format!("actix-rt|system:{}|arbiter:{}", system_id, arb_id);
+        let name =
+            name.unwrap_or_else(|| format!("actix-rt|system:{}|arbiter:{}", system_id, arb_id));
         let (tx, rx) = mpsc::unbounded_channel();
+        let state = ArbiterState::new(policy, track_tasks);
 
         let (ready_tx, ready_rx) = std::sync::mpsc::channel::<()>();
 
@@ -126,9 +330,10 @@ impl Arbiter {
             .name(name.clone())
             .spawn({
                 let tx = tx.clone();
+                let state = state.clone();
                 move || {
                     let rt = Runtime::from(runtime_factory());
-                    let hnd = ArbiterHandle::new(tx);
+                    let hnd = ArbiterHandle::new(tx, state);
 
                     System::set_current(sys);
 
@@ -137,12 +342,15 @@ impl Arbiter {
                     // register arbiter
                     let _ = System::current()
                         .tx()
-                        .send(SystemCommand::RegisterArbiter(arb_id, hnd));
+                        .send(SystemCommand::RegisterArbiter(arb_id, hnd.clone()));
 
                     ready_tx.send(()).unwrap();
 
                     // run arbiter event processing loop
-                    rt.block_on(ArbiterRunner { rx });
+                    rt.block_on(ArbiterRunner::new(rx, hnd));
+
+                    // drop remaining tasks before the arbiter is considered gone
+                    drop(rt);
 
                     // deregister arbiter
                     let _ = System::current()
@@ -156,25 +364,36 @@ impl Arbiter {
 
         ready_rx.recv().unwrap();
 
-        Arbiter { tx, thread_handle }
+        Arbiter {
+            tx,
+            state,
+            thread_handle,
+        }
     }
 
     /// Sets up an Arbiter runner in a new System using the provided runtime local task set.
     pub(crate) fn in_new_system(local: &LocalSet) -> ArbiterHandle {
         let (tx, rx) = mpsc::unbounded_channel();
 
-        let hnd = ArbiterHandle::new(tx);
+        let hnd = ArbiterHandle::new(tx, ArbiterState::new(PanicPolicy::Ignore, false));
 
         HANDLE.with(|cell| *cell.borrow_mut() = Some(hnd.clone()));
 
-        local.spawn_local(ArbiterRunner { rx });
+        local.spawn_local(ArbiterRunner::new(rx, hnd.clone()));
 
         hnd
     }
 
     /// Return a handle to the this Arbiter's message sender.
     pub fn handle(&self) -> ArbiterHandle {
-        ArbiterHandle::new(self.tx.clone())
+        ArbiterHandle::new(self.tx.clone(), self.state.clone())
+    }
+
+    /// Returns number of tasks of this Arbiter that panicked.
+    ///
+    /// See [`ArbiterHandle::panic_count`] for details.
+    pub fn panic_count(&self) -> usize {
+        self.state.panics.load(Ordering::Relaxed)
     }
 
     /// Return a handle to the current thread's Arbiter's message sender.
@@ -195,6 +414,38 @@ impl Arbiter {
         self.tx.send(ArbiterCommand::Stop).is_ok()
     }
 
+    /// Stop Arbiter once its tasks are finished, waiting for them up to `timeout`.
+    ///
+    /// See [`ArbiterHandle::stop_gracefully`] for details.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::{sync::mpsc, time::Duration};
+    /// use actix_rt::{Arbiter, System};
+    ///
+    /// let _ = System::new();
+    /// let (tx, rx) = mpsc::channel();
+    ///
+    /// let arbiter = Arbiter::with_task_tracking();
+    /// arbiter.spawn(async move {
+    ///     actix_rt::time::sleep(Duration::from_millis(100)).await;
+    ///     tx.send(42).unwrap();
+    /// });
+    ///
+    /// arbiter.stop_gracefully(Duration::from_secs(5));
+    /// // no new work is taken
+    /// assert!(!arbiter.spawn_fn(|| {}));
+    ///
+    /// arbiter.join().unwrap();
+    /// assert_eq!(rx.try_recv(), Ok(42));
+    /// ```
+    pub fn stop_gracefully(&self, timeout: Duration) -> bool {
+        self.state.stopping.store(true, Ordering::Release);
+        self.tx
+            .send(ArbiterCommand::StopGracefully(timeout))
+            .is_ok()
+    }
+
     /// Send a future to the Arbiter's thread and spawn it.
     ///
     /// If you require a result, include a response channel in the future.
@@ -204,9 +455,11 @@ impl Arbiter {
     where
         Fut: Future<Output = ()> + Send + 'static,
     {
-        self.tx
-            .send(ArbiterCommand::Execute(Box::pin(future)))
-            .is_ok()
+        !self.state.stopping.load(Ordering::Acquire)
+            && self
+                .tx
+                .send(ArbiterCommand::Execute(Box::pin(future)))
+                .is_ok()
     }
 
     /// Send a function to the Arbiter's thread and execute it.
@@ -230,9 +483,73 @@ impl Arbiter {
     }
 }
 
+/// Spawn a task on the current thread, tracked if its Arbiter tracks tasks.
+pub(crate) fn spawn_local<Fut>(fut: Fut) -> tokio::task::JoinHandle<()>
+where
+    Fut: Future<Output = ()> + 'static,
+{
+    let hnd = HANDLE.with(|cell| {
+        cell.borrow()
+            .as_ref()
+            .filter(|hnd| hnd.state.tracked)
+            .cloned()
+    });
+
+    match hnd {
+        Some(hnd) => tokio::task::spawn_local(catch_panic(fut, hnd)),
+        None => tokio::task::spawn_local(fut),
+    }
+}
+
+/// Wrap task of the current thread's Arbiter, counting it from now on until it is finished.
+fn catch_panic<Fut: Future>(fut: Fut, hnd: ArbiterHandle) -> impl Future<Output = Fut::Output> {
+    let guard = TaskGuard::new();
+
+    async move {
+        let _guard = guard;
+        tokio::pin!(fut);
+        CatchPanic { fut, hnd }.await
+    }
+}
+
+/// Future passing panics of the wrapped task to its Arbiter before resuming them.
+struct CatchPanic<'a, Fut> {
+    fut: Pin<&'a mut Fut>,
+    hnd: ArbiterHandle,
+}
+
+impl<Fut: Future> Future for CatchPanic<'_, Fut> {
+    type Output = Fut::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| this.fut.as_mut().poll(cx))) {
+            Ok(res) => res,
+            Err(payload) => {
+                this.hnd.panicked();
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
 /// A persistent future that processes [Arbiter] commands.
 struct ArbiterRunner {
     rx: mpsc::UnboundedReceiver<ArbiterCommand>,
+    hnd: ArbiterHandle,
+    /// Timeout of a graceful stop waiting for tasks to finish.
+    stopping: Option<Pin<Box<Sleep>>>,
+}
+
+impl ArbiterRunner {
+    fn new(rx: mpsc::UnboundedReceiver<ArbiterCommand>, hnd: ArbiterHandle) -> Self {
+        Self {
+            rx,
+            hnd,
+            stopping: None,
+        }
+    }
 }
 
 impl Future for ArbiterRunner {
@@ -241,20 +558,53 @@ impl Future for ArbiterRunner {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // process all items currently buffered in channel
         loop {
-            match ready!(Pin::new(&mut self.rx).poll_recv(cx)) {
+            match Pin::new(&mut self.rx).poll_recv(cx) {
                 // channel closed; no more messages can be received
-                None => return Poll::Ready(()),
+                Poll::Ready(None) if self.stopping.is_none() => return Poll::Ready(()),
+                Poll::Ready(None) | Poll::Pending => break,
 
                 // process arbiter command
-                Some(item) => match item {
+                Poll::Ready(Some(item)) => match item {
                     ArbiterCommand::Stop => {
                         return Poll::Ready(());
                     }
+                    ArbiterCommand::StopGracefully(timeout) => {
+                        // commands sent before are still received
+                        self.rx.close();
+
+                        if self.stopping.is_none() {
+                            self.stopping = Some(Box::pin(sleep(timeout)));
+                        }
+                    }
                     ArbiterCommand::Execute(task_fut) => {
-                        tokio::task::spawn_local(task_fut);
+                        if self.hnd.state.tracked {
+                            tokio::task::spawn_local(catch_panic(task_fut, self.hnd.clone()));
+                        } else {
+                            tokio::task::spawn_local(task_fut);
+                        }
                     }
                 },
             }
         }
+
+        match self.stopping {
+            Some(ref mut timeout) => {
+                let finished = TASKS.with(|tasks| {
+                    if tasks.count.get() == 0 {
+                        true
+                    } else {
+                        *tasks.waker.borrow_mut() = Some(cx.waker().clone());
+                        false
+                    }
+                });
+
+                if finished || timeout.as_mut().poll(cx).is_ready() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+            None => Poll::Pending,
+        }
     }
 }
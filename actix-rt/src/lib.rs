@@ -46,13 +46,15 @@ use tokio::task::JoinHandle;
 // Cannot define a main macro when compiled into test harness.
 // Workaround for https://github.com/rust-lang/rust/issues/62127.
 #[cfg(all(feature = "macros", not(test)))]
-pub use actix_macros::{main, test};
+pub use actix_macros::{bench, main, test};
 
 mod arbiter;
+mod pool;
 mod runtime;
 mod system;
 
-pub use self::arbiter::{Arbiter, ArbiterHandle};
+pub use self::arbiter::{Arbiter, ArbiterHandle, PanicPolicy};
+pub use self::pool::{ArbiterPool, ArbiterSelection};
 pub use self::runtime::Runtime;
 pub use self::system::{System, SystemRunner};
 
@@ -66,6 +68,12 @@ pub mod signal {
         //! Unix specific signals (Tokio re-exports).
         pub use tokio::signal::unix::*;
     }
+
+    #[cfg(windows)]
+    pub mod windows {
+        //! Windows specific console events (Tokio re-exports).
+        pub use tokio::signal::windows::*;
+    }
     pub use tokio::signal::ctrl_c;
 }
 
@@ -142,21 +150,72 @@ pub mod net {
 
 pub mod time {
     //! Utilities for tracking time (Tokio re-exports).
+    //!
+    //! # Missed Ticks
+    //! An [`Interval`] misses ticks when the thread running it is blocked for longer than its
+    //! period, e.g. by a slow synchronous operation in a worker. By default, missed ticks are all
+    //! fired immediately once the task is polled again ([`MissedTickBehavior::Burst`]), so a
+    //! periodic maintenance task runs several times in a row after a stall. Use
+    //! [`Interval::set_missed_tick_behavior`] to instead shift the schedule to start from the
+    //! delayed tick ([`Delay`](MissedTickBehavior::Delay)) or to skip to the next tick aligned with
+    //! the original schedule ([`Skip`](MissedTickBehavior::Skip)).
+    //!
+    //! ```
+    //! use std::time::Duration;
+    //! use actix_rt::time::{interval_at, Instant, MissedTickBehavior};
+    //!
+    //! # actix_rt::System::new().block_on(async {
+    //! let period = Duration::from_millis(10);
+    //! let mut interval = interval_at(Instant::now() + period, period);
+    //! interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    //!
+    //! interval.tick().await;
+    //! # });
+    //! ```
 
     pub use tokio::time::Instant;
-    pub use tokio::time::{interval, interval_at, Interval};
+    pub use tokio::time::{interval, interval_at, Interval, MissedTickBehavior};
     pub use tokio::time::{sleep, sleep_until, Sleep};
-    pub use tokio::time::{timeout, Timeout};
+    pub use tokio::time::{timeout, timeout_at, Timeout};
 }
 
 pub mod task {
     //! Task management (Tokio re-exports).
-
+    //!
+    //! # Cooperative Scheduling
+    //! Each Actix worker runs on a single-threaded runtime so a future that performs a long
+    //! synchronous loop without awaiting will block every other task on that thread. Tokio tracks a
+    //! per-task budget of operations that is reset each time the task is polled by the scheduler;
+    //! once it is spent, Tokio resources return `Pending` to force the task to yield.
+    //!
+    //! With the `coop` feature, which raises the minimum Tokio version to 1.44, loops that do not
+    //! touch Tokio resources can participate in this scheme by awaiting `consume_budget` on each
+    //! iteration, which only yields once the budget is exhausted. For an unconditional yield back
+    //! to the scheduler, use [`yield_now`].
+    //!
+    //! ```
+    //! # #[cfg(feature = "coop")]
+    //! # actix_rt::System::new().block_on(async {
+    //! let mut sum = 0u64;
+    //!
+    //! for i in 0..10_000 {
+    //!     sum += i;
+    //!     actix_rt::task::consume_budget().await;
+    //! }
+    //! # assert_eq!(sum, 49_995_000);
+    //! # });
+    //! ```
+
+    #[cfg(feature = "coop")]
+    pub use tokio::task::coop::{consume_budget, unconstrained, Unconstrained};
     pub use tokio::task::{spawn_blocking, yield_now, JoinError, JoinHandle};
 }
 
 /// Spawns a future on the current thread.
 ///
+/// A panic of the future is handled according to the [`PanicPolicy`] of the current thread's
+/// [`Arbiter`].
+///
 /// # Panics
 /// Panics if Actix system is not running.
 #[inline]
@@ -164,5 +223,5 @@ pub fn spawn<Fut>(f: Fut) -> JoinHandle<()>
 where
     Fut: Future<Output = ()> + 'static,
 {
-    tokio::task::spawn_local(f)
+    arbiter::spawn_local(f)
 }
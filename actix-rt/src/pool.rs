@@ -0,0 +1,200 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::arbiter::{Arbiter, ArbiterHandle};
+
+/// How an [ArbiterPool] picks the [Arbiter] that runs a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArbiterSelection {
+    /// Use the arbiters in turn.
+    RoundRobin,
+
+    /// Use the arbiter with the fewest unfinished tasks spawned through the pool, taking the
+    /// arbiters in turn when several have as few.
+    LeastBusy,
+}
+
+/// A fixed number of [Arbiter]s that tasks are spread over.
+///
+/// Futures and functions are sent to an arbiter picked according to the pool's
+/// [selection](Self::selection). Work that is not `Send` is created on the arbiter's thread with
+/// [`spawn_with`](Self::spawn_with). Only tasks spawned through the pool are counted for
+/// [`ArbiterSelection::LeastBusy`]; tasks those spawn in turn are not.
+///
+/// Like an [Arbiter], the pool keeps running when dropped until its arbiters are
+/// [stopped](Self::stop), e.g. when the [System](crate::System) stops.
+///
+/// # Examples
+/// ```
+/// use std::{rc::Rc, sync::mpsc};
+/// use actix_rt::{ArbiterPool, ArbiterSelection, System};
+///
+/// let _ = System::new();
+///
+/// let pool = ArbiterPool::new(2).selection(ArbiterSelection::LeastBusy);
+/// let (tx, rx) = mpsc::channel();
+///
+/// for num in 0..4 {
+///     let tx = tx.clone();
+///     pool.spawn_with(move || async move {
+///         // futures created on the arbiter's thread do not have to be `Send`
+///         let num = Rc::new(num);
+///         tx.send(*num).unwrap();
+///     });
+/// }
+///
+/// let mut nums = rx.iter().take(4).collect::<Vec<_>>();
+/// nums.sort_unstable();
+/// assert_eq!(nums, [0, 1, 2, 3]);
+///
+/// pool.stop();
+/// pool.join().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ArbiterPool {
+    arbiters: Vec<(Arbiter, Arc<AtomicUsize>)>,
+    selection: ArbiterSelection,
+    next: AtomicUsize,
+}
+
+impl ArbiterPool {
+    /// Spawn `n` new Arbiters, used in turn.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0 or a [System](crate::System) is not registered on the current thread.
+    pub fn new(n: usize) -> Self {
+        assert_ne!(n, 0, "ArbiterPool needs at least one Arbiter");
+
+        Self {
+            arbiters: (0..n)
+                .map(|_| (Arbiter::new(), Arc::new(AtomicUsize::new(0))))
+                .collect(),
+            selection: ArbiterSelection::RoundRobin,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Set how the arbiter running a task is picked.
+    pub fn selection(mut self, selection: ArbiterSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Send a future to an Arbiter of the pool and spawn it.
+    ///
+    /// Returns true if future was sent successfully and false if the picked Arbiter has died.
+    pub fn spawn<Fut>(&self, future: Fut) -> bool
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (arb, guard) = self.pick();
+        arb.spawn(async move {
+            let _guard = guard;
+            future.await
+        })
+    }
+
+    /// Send a function to an Arbiter of the pool and execute it.
+    ///
+    /// Returns true if function was sent successfully and false if the picked Arbiter has died.
+    pub fn spawn_fn<F>(&self, f: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.spawn(async { f() })
+    }
+
+    /// Send a function to an Arbiter of the pool and spawn the future it returns on the
+    /// Arbiter's thread.
+    ///
+    /// Unlike with [`spawn`](Self::spawn), the future does not have to be `Send`.
+    ///
+    /// Returns true if function was sent successfully and false if the picked Arbiter has died.
+    pub fn spawn_with<F, Fut>(&self, f: F) -> bool
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let (arb, guard) = self.pick();
+        arb.spawn_fn(move || {
+            crate::spawn(async move {
+                let _guard = guard;
+                f().await
+            });
+        })
+    }
+
+    /// Returns handle to the Arbiter with index `idx`.
+    pub fn handle(&self, idx: usize) -> Option<ArbiterHandle> {
+        self.arbiters.get(idx).map(|(arb, _)| arb.handle())
+    }
+
+    /// Returns handles to all Arbiters of the pool, ordered by index.
+    pub fn handles(&self) -> Vec<ArbiterHandle> {
+        self.arbiters.iter().map(|(arb, _)| arb.handle()).collect()
+    }
+
+    /// Stop all Arbiters of the pool.
+    ///
+    /// Returns true if stop message was sent successfully to all Arbiters.
+    pub fn stop(&self) -> bool {
+        self.arbiters
+            .iter()
+            .fold(true, |sent, (arb, _)| arb.stop() && sent)
+    }
+
+    /// Wait for the event loops of all Arbiters of the pool to complete.
+    ///
+    /// Returns the first error of joining their threads, after all of them were joined.
+    pub fn join(self) -> thread::Result<()> {
+        let mut res = Ok(());
+
+        for (arb, _) in self.arbiters {
+            let joined = arb.join();
+            if res.is_ok() {
+                res = joined;
+            }
+        }
+
+        res
+    }
+
+    /// Pick the Arbiter for a new task and count the task on it.
+    fn pick(&self) -> (&Arbiter, TaskGuard) {
+        let len = self.arbiters.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % len;
+
+        let idx = match self.selection {
+            ArbiterSelection::RoundRobin => start,
+            ArbiterSelection::LeastBusy => (0..len)
+                .map(|offset| (start + offset) % len)
+                .min_by_key(|idx| self.arbiters[*idx].1.load(Ordering::Relaxed))
+                .unwrap_or(start),
+        };
+
+        let (arb, tasks) = &self.arbiters[idx];
+        (arb, TaskGuard::new(tasks.clone()))
+    }
+}
+
+/// Counts a task spawned through an [ArbiterPool] until it is finished or dropped.
+struct TaskGuard(Arc<AtomicUsize>);
+
+impl TaskGuard {
+    fn new(tasks: Arc<AtomicUsize>) -> Self {
+        tasks.fetch_add(1, Ordering::Relaxed);
+        Self(tasks)
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -6,10 +6,13 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicUsize, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
-use futures_core::ready;
-use tokio::sync::{mpsc, oneshot};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{sleep, Instant, Sleep},
+};
 
 use crate::{arbiter::ArbiterHandle, runtime::default_tokio_runtime, Arbiter, Runtime};
 
@@ -144,6 +147,32 @@ impl System {
         let _ = self.sys_tx.send(SystemCommand::Exit(code));
     }
 
+    /// Stop the system (with code 0) once its [Arbiter]s are torn down.
+    ///
+    /// All arbiters are told to [stop gracefully](ArbiterHandle::stop_gracefully), including
+    /// those created afterwards. The system's event loop keeps running until the threads of all
+    /// of them finished their tasks and event loop, or until `timeout` elapsed, whichever comes
+    /// first. Arbiters still running then are abandoned, as with [`stop`](Self::stop). Only
+    /// arbiters [tracking their tasks](Arbiter::with_task_tracking) wait for spawned tasks.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_rt::{Arbiter, System};
+    ///
+    /// let sys = System::new();
+    ///
+    /// let arbiter = Arbiter::new();
+    /// arbiter.spawn_fn(|| System::current().stop_with_timeout(Duration::from_secs(5)));
+    ///
+    /// sys.run().unwrap();
+    /// // arbiter thread has finished already
+    /// arbiter.join().unwrap();
+    /// ```
+    pub fn stop_with_timeout(&self, timeout: Duration) {
+        let _ = self.sys_tx.send(SystemCommand::ExitAfter(0, timeout));
+    }
+
     pub(crate) fn tx(&self) -> &mpsc::UnboundedSender<SystemCommand> {
         &self.sys_tx
     }
@@ -190,6 +219,8 @@ impl SystemRunner {
 #[derive(Debug)]
 pub(crate) enum SystemCommand {
     Exit(i32),
+    /// Exit once arbiters are torn down or the timeout elapsed.
+    ExitAfter(i32, Duration),
     RegisterArbiter(usize, ArbiterHandle),
     DeregisterArbiter(usize),
 }
@@ -201,6 +232,8 @@ pub(crate) struct SystemController {
     stop_tx: Option<oneshot::Sender<i32>>,
     cmd_rx: mpsc::UnboundedReceiver<SystemCommand>,
     arbiters: HashMap<usize, ArbiterHandle>,
+    /// Exit code and timeout of a stop waiting for arbiters.
+    exit_after: Option<(i32, Pin<Box<Sleep>>)>,
 }
 
 impl SystemController {
@@ -212,6 +245,7 @@ impl SystemController {
             cmd_rx,
             stop_tx: Some(stop_tx),
             arbiters: HashMap::with_capacity(4),
+            exit_after: None,
         }
     }
 }
@@ -222,12 +256,12 @@ impl Future for SystemController {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // process all items currently buffered in channel
         loop {
-            match ready!(Pin::new(&mut self.cmd_rx).poll_recv(cx)) {
+            match Pin::new(&mut self.cmd_rx).poll_recv(cx) {
                 // channel closed; no more messages can be received
-                None => return Poll::Ready(()),
+                Poll::Ready(None) => return Poll::Ready(()),
 
                 // process system command
-                Some(cmd) => match cmd {
+                Poll::Ready(Some(cmd)) => match cmd {
                     SystemCommand::Exit(code) => {
                         // stop all arbiters
                         for arb in self.arbiters.values() {
@@ -241,7 +275,23 @@ impl Future for SystemController {
                         }
                     }
 
+                    SystemCommand::ExitAfter(code, timeout) => {
+                        for arb in self.arbiters.values() {
+                            arb.stop_gracefully(timeout);
+                        }
+
+                        if self.exit_after.is_none() {
+                            self.exit_after = Some((code, Box::pin(sleep(timeout))));
+                        }
+                    }
+
                     SystemCommand::RegisterArbiter(id, arb) => {
+                        // arbiters created while waiting for others are stopped right away
+                        if let Some((_, ref timeout)) = self.exit_after {
+                            let left =
+                                timeout.deadline().saturating_duration_since(Instant::now());
+                            arb.stop_gracefully(left);
+                        }
                         self.arbiters.insert(id, arb);
                     }
 
@@ -249,7 +299,26 @@ impl Future for SystemController {
                         self.arbiters.remove(&id);
                     }
                 },
+
+                Poll::Pending => break,
+            }
+        }
+
+        let this = &mut *self;
+
+        if let Some((code, ref mut timeout)) = this.exit_after {
+            // the system arbiter runs on the thread of this future and never deregisters
+            let torn_down = this.arbiters.keys().all(|id| *id == usize::MAX);
+
+            if torn_down || timeout.as_mut().poll(cx).is_ready() {
+                this.exit_after = None;
+
+                if let Some(stop_tx) = this.stop_tx.take() {
+                    let _ = stop_tx.send(code);
+                }
             }
         }
+
+        Poll::Pending
     }
 }
@@ -8,7 +8,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use actix_rt::{Arbiter, System};
+use actix_rt::{Arbiter, ArbiterPool, ArbiterSelection, PanicPolicy, System};
 use tokio::sync::oneshot;
 
 #[test]
@@ -155,6 +155,152 @@ fn arbiter_drop_no_panic_fn() {
     arbiter.join().unwrap();
 }
 
+#[test]
+fn arbiter_panic_policy_ignore() {
+    let _ = System::new();
+
+    let (tx, rx) = channel::<u32>();
+
+    let arbiter = Arbiter::with_task_tracking();
+    arbiter.spawn_fn(|| panic!("test"));
+    arbiter.spawn(async {
+        // tasks spawned on the arbiter thread are covered too
+        let _ = actix_rt::spawn(async { panic!("test") }).await;
+    });
+    arbiter.spawn_fn(move || tx.send(42).unwrap());
+
+    assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), 42);
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(arbiter.panic_count(), 2);
+
+    arbiter.stop();
+    arbiter.join().unwrap();
+}
+
+#[test]
+fn arbiter_panic_policy_stop() {
+    let _ = System::new();
+
+    let arbiter = Arbiter::with_panic_policy(PanicPolicy::StopArbiter);
+    let handle = arbiter.handle();
+    arbiter.spawn(async {
+        actix_rt::spawn(async { panic!("test") });
+    });
+
+    // arbiter stops without being told to
+    arbiter.join().unwrap();
+    assert_eq!(handle.panic_count(), 1);
+    assert!(!handle.spawn_fn(|| {}));
+}
+
+#[test]
+fn arbiter_pool_round_robin() {
+    let _ = System::new();
+
+    let (tx, rx) = channel();
+
+    let pool = ArbiterPool::new(3);
+    for _ in 0..6 {
+        let tx = tx.clone();
+        assert!(pool.spawn_fn(move || tx.send(thread::current().id()).unwrap()));
+    }
+
+    let ids = rx.iter().take(6).collect::<Vec<_>>();
+    for num in 0..3 {
+        assert_eq!(ids.iter().filter(|id| **id == ids[num]).count(), 2);
+    }
+
+    pool.stop();
+    pool.join().unwrap();
+}
+
+#[test]
+fn arbiter_pool_least_busy() {
+    use std::rc::Rc;
+
+    let _ = System::new();
+
+    let (tx, rx) = channel();
+    let (block_tx, block_rx) = oneshot::channel::<()>();
+
+    let pool = ArbiterPool::new(2).selection(ArbiterSelection::LeastBusy);
+    let busy = pool.handle(0).unwrap();
+
+    // occupies the first arbiter until the end of the test
+    pool.spawn(async move {
+        let _ = block_rx.await;
+    });
+
+    for _ in 0..3 {
+        let tx = tx.clone();
+        pool.spawn_with(move || async move {
+            // not `Send`
+            let id = Rc::new(thread::current().id());
+            tx.send(*id).unwrap();
+        });
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let (busy_tx, busy_rx) = channel();
+    busy.spawn_fn(move || busy_tx.send(thread::current().id()).unwrap());
+    let busy_id = busy_rx.recv().unwrap();
+
+    assert!(rx.iter().take(3).all(|id| id != busy_id));
+
+    let _ = block_tx.send(());
+    pool.stop();
+    pool.join().unwrap();
+}
+
+#[test]
+fn arbiter_stop_gracefully() {
+    let _ = System::new();
+
+    let (tx, rx) = channel();
+
+    let arbiter = Arbiter::with_task_tracking();
+    let tx2 = tx.clone();
+    arbiter.spawn(async move {
+        // tasks spawned on the arbiter thread are waited for too
+        actix_rt::spawn(async move {
+            actix_rt::time::sleep(Duration::from_millis(200)).await;
+            tx2.send("spawned").unwrap();
+        });
+    });
+    arbiter.spawn_fn(move || tx.send("sent").unwrap());
+
+    let start = Instant::now();
+    assert!(arbiter.stop_gracefully(Duration::from_secs(5)));
+    assert!(!arbiter.spawn_fn(|| {}));
+
+    arbiter.join().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(rx.iter().collect::<Vec<_>>(), ["sent", "spawned"]);
+}
+
+#[test]
+fn arbiter_stop_gracefully_timeout() {
+    let _ = System::new();
+
+    let (tx, rx) = channel::<()>();
+
+    let arbiter = Arbiter::with_task_tracking();
+    arbiter.spawn(async move {
+        actix_rt::time::sleep(Duration::from_secs(60)).await;
+        tx.send(()).unwrap();
+    });
+
+    let start = Instant::now();
+    arbiter.stop_gracefully(Duration::from_millis(200));
+    arbiter.join().unwrap();
+
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_secs(2));
+    // task was dropped
+    assert!(rx.recv().is_err());
+}
+
 #[test]
 fn arbiter_drop_no_panic_fut() {
     let _ = System::new();
@@ -230,6 +376,59 @@ fn system_stop_stops_arbiters() {
     arb.join().unwrap();
 }
 
+#[test]
+fn system_stop_with_timeout_waits_for_arbiters() {
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            thread::sleep(Duration::from_millis(200));
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let sys = System::new();
+    let arb = Arbiter::with_task_tracking();
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let dropped = Arc::new(AtomicBool::new(false));
+    let (finished2, guard) = (finished.clone(), SetOnDrop(dropped.clone()));
+    arb.spawn(async move {
+        let _guard = guard;
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+        finished2.store(true, Ordering::SeqCst);
+    });
+
+    let start = Instant::now();
+    System::current().stop_with_timeout(Duration::from_secs(5));
+    sys.run().unwrap();
+
+    // task of the arbiter finished and was dropped before the system stopped
+    assert!(finished.load(Ordering::SeqCst));
+    assert!(dropped.load(Ordering::SeqCst));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    arb.join().unwrap();
+}
+
+#[test]
+fn system_stop_with_timeout_abandons_arbiters() {
+    let sys = System::new();
+    let arb = Arbiter::new();
+
+    arb.spawn_fn(|| thread::sleep(Duration::from_secs(2)));
+    thread::sleep(Duration::from_millis(100));
+
+    let start = Instant::now();
+    System::current().stop_with_timeout(Duration::from_millis(200));
+    sys.run().unwrap();
+
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_secs(1));
+
+    arb.join().unwrap();
+}
+
 #[test]
 fn new_system_with_tokio() {
     let (tx, rx) = channel();
@@ -298,3 +497,48 @@ fn try_current_no_system() {
 fn try_current_with_system() {
     System::new().block_on(async { assert!(System::try_current().is_some()) });
 }
+
+#[cfg(feature = "coop")]
+#[test]
+fn consume_budget_yields_to_other_tasks() {
+    System::new().block_on(async {
+        let done = Arc::new(AtomicBool::new(false));
+        let done2 = done.clone();
+
+        actix_rt::spawn(async move {
+            done2.store(true, Ordering::SeqCst);
+        });
+
+        // without yielding, the spawned task would never run before this loop ends
+        let mut iters = 0;
+        while !done.load(Ordering::SeqCst) {
+            iters += 1;
+            assert!(iters < 1_000_000, "consume_budget never yielded");
+            actix_rt::task::consume_budget().await;
+        }
+    });
+}
+
+#[test]
+fn interval_skips_missed_ticks() {
+    use actix_rt::time::{interval_at, timeout_at, Instant, MissedTickBehavior};
+
+    System::new().block_on(async {
+        let period = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut interval = interval_at(start, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        interval.tick().await;
+
+        // stall the thread for several periods
+        std::thread::sleep(period * 5 + period / 5);
+
+        // one tick for the stall, then back on the original schedule
+        interval.tick().await;
+        let deadline = Instant::now() + period / 2;
+        assert!(timeout_at(deadline, interval.tick()).await.is_err());
+        interval.tick().await;
+        assert!(Instant::now() >= start + period * 6);
+    });
+}
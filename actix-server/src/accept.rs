@@ -1,82 +1,374 @@
 use std::time::Duration;
-use std::{io, thread};
+use std::{io, mem, thread};
 
 use actix_rt::{
     time::{sleep, Instant},
     System,
 };
-use log::{error, info};
-use mio::{Interest, Poll, Token as MioToken};
-
+use log::{error, info, warn};
+use tokio::sync::{mpsc::error::TrySendError, oneshot};
+
+use crate::accept_error::{AcceptError, AcceptErrorAction, AcceptErrorCallback};
+#[cfg(unix)]
+use crate::accept_mode::AcceptMode;
+use crate::conn_limit::ConnectionLimit;
+use crate::dispatch::{DispatchStrategy, Dispatcher};
+use crate::fd_limit::{fd_usage, FdPressure, FdPressureCallback, FD_CHECK_INTERVAL};
+use crate::health::{HealthCheck, HEALTH_REBIND_DELAY};
+use crate::heartbeat::{HeartbeatMonitor, WorkerHeartbeat};
+use crate::poller::{Poller, WAKER_TOKEN};
+use crate::pressure::AcceptPressure;
+use crate::rate_limit::TokenBucket;
 use crate::server::Server;
-use crate::socket::MioListener;
-use crate::waker_queue::{WakerInterest, WakerQueue, WAKER_TOKEN};
+use crate::socket::{MioListener, MioStream};
+use crate::tcp::TcpOptions;
+use crate::trace::ConnSpan;
+use crate::waker_queue::{HandOff, ReplaceWorker, WakerInterest, WakerQueue};
 use crate::worker::{Conn, WorkerHandleAccept};
+use crate::worker_status::WorkerStatus;
 
-struct ServerSocketInfo {
+pub(crate) struct ServerSocketInfo {
     token: usize,
 
+    /// Name of the service of this listener.
+    name: String,
+
     lst: MioListener,
 
     /// Timeout is used to mark the deadline when this socket's listener should be registered again
     /// after an error.
     timeout: Option<Instant>,
+
+    /// Per listener accept rate limit.
+    rate_limit: Option<AcceptRateLimit>,
+
+    /// Socket options set on every accepted stream.
+    tcp_options: Option<TcpOptions>,
+
+    /// Pausing of this listener while file descriptors run low.
+    fd_shedding: Option<FdShedding>,
+
+    /// Whether accepting on this listener was paused on request.
+    paused: bool,
+}
+
+struct FdShedding {
+    name: String,
+
+    /// Number of free file descriptors below which the listener is paused.
+    headroom: usize,
+
+    paused: bool,
+}
+
+impl ServerSocketInfo {
+    pub(crate) fn new(token: usize, name: &str, lst: MioListener) -> Self {
+        Self {
+            token,
+            name: name.to_owned(),
+            lst,
+            timeout: None,
+            rate_limit: None,
+            tcp_options: None,
+            fd_shedding: None,
+            paused: false,
+        }
+    }
+
+    /// Set socket options on the listener or on every stream accepted by it.
+    pub(crate) fn tcp_options(mut self, name: &str, opts: &TcpOptions) -> Self {
+        if let MioListener::Tcp(ref lst) = self.lst {
+            if let Err(e) = opts.apply_listener_only(lst) {
+                error!("Can not set socket options of \"{}\" service: {}", name, e);
+            }
+
+            if !opts.is_on_listener() {
+                self.tcp_options = Some(opts.clone());
+            } else if let Err(e) = opts.apply_listener(lst) {
+                error!("Can not set socket options of \"{}\" service: {}", name, e);
+            }
+        }
+        self
+    }
+
+    /// Limit accepts on this listener to `per_second` connections per second.
+    pub(crate) fn max_accept_rate(mut self, name: &str, per_second: u32) -> Self {
+        self.rate_limit = Some(AcceptRateLimit::new(
+            format!("\"{}\" service", name),
+            per_second,
+        ));
+        self
+    }
+
+    /// Pause this listener while fewer than `headroom` file descriptors are free.
+    pub(crate) fn fd_headroom(mut self, name: &str, headroom: usize) -> Self {
+        self.fd_shedding = Some(FdShedding {
+            name: name.to_owned(),
+            headroom,
+            paused: false,
+        });
+        self
+    }
+
+    /// Returns true while accepting on this listener is throttled by its own rate limit.
+    fn is_throttled(&self) -> bool {
+        matches!(self.rate_limit, Some(ref limit) if limit.throttled.is_some())
+    }
+
+    /// Returns true while this listener is paused because file descriptors run low.
+    fn is_shed(&self) -> bool {
+        matches!(self.fd_shedding, Some(ref shedding) if shedding.paused)
+    }
+
+    /// Returns true while this listener is paused on its own, on request or because file
+    /// descriptors run low.
+    fn is_held(&self) -> bool {
+        self.paused || self.is_shed()
+    }
+}
+
+/// Accept loop settings taken from `ServerBuilder`.
+pub(crate) struct AcceptConfig {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub(crate) mode: crate::AcceptMode,
+    pub(crate) strategy: DispatchStrategy,
+    pub(crate) affinity: Option<usize>,
+    /// Maximum number of readiness events handled per poll.
+    pub(crate) events: usize,
+    pub(crate) max_connection_rate: Option<u32>,
+    pub(crate) max_total_connections: Option<usize>,
+    /// Resource pressure holding back accepting while high.
+    pub(crate) pressure: Option<AcceptPressure>,
+    /// Maximum number of connections accepted from a listener per readiness event.
+    pub(crate) burst: usize,
+    /// Whether file descriptor usage is checked for listeners with a headroom.
+    pub(crate) fd_check: bool,
+    pub(crate) on_fd_pressure: Option<FdPressureCallback>,
+    pub(crate) on_accept_error: Option<AcceptErrorCallback>,
+    pub(crate) health: Option<HealthCheck>,
+    pub(crate) heartbeat: Option<WorkerHeartbeat>,
+    /// Notified once all listeners are registered.
+    pub(crate) ready: Option<oneshot::Sender<()>>,
+    /// Name of the accept thread, if not the default one.
+    pub(crate) thread_name: Option<String>,
+}
+
+impl AcceptConfig {
+    /// Settings for another accept loop of the same server, without health checks.
+    fn for_loop(&self) -> Self {
+        Self {
+            mode: self.mode,
+            strategy: self.strategy,
+            affinity: self.affinity,
+            events: self.events,
+            max_connection_rate: self.max_connection_rate,
+            max_total_connections: self.max_total_connections,
+            pressure: self.pressure.clone(),
+            burst: self.burst,
+            fd_check: self.fd_check,
+            on_fd_pressure: self.on_fd_pressure.clone(),
+            on_accept_error: self.on_accept_error.clone(),
+            health: None,
+            heartbeat: self.heartbeat.clone(),
+            ready: None,
+            thread_name: self.thread_name.clone(),
+        }
+    }
+}
+
+struct AcceptRateLimit {
+    /// Description of the limited listeners used in log messages.
+    target: String,
+    per_second: u32,
+    bucket: TokenBucket,
+
+    /// Start of the current throttling episode.
+    throttled: Option<Instant>,
+
+    /// Number of times accepting was deferred during the current throttling episode.
+    deferred: u64,
 }
 
 /// Accept loop would live with `ServerBuilder`.
 ///
-/// It's tasked with construct `Poll` instance and `WakerQueue` which would be distributed to
+/// It's tasked with construct `Poller` instance and `WakerQueue` which would be distributed to
 /// `Accept` and `Worker`.
 ///
 /// It would also listen to `ServerCommand` and push interests to `WakerQueue`.
+///
+/// A server can run several accept loops, each with its own `Poller` and `WakerQueue`. Workers
+/// are split between them; worker `n` is served by accept loop `n % loops`.
 pub(crate) struct AcceptLoop {
     srv: Option<Server>,
-    poll: Option<Poll>,
-    waker: WakerQueue,
+    loops: Vec<(Option<Poller>, WakerQueue)>,
+    coalesce: bool,
 }
 
 impl AcceptLoop {
     pub fn new(srv: Server) -> Self {
-        let poll = Poll::new().unwrap_or_else(|e| panic!("Can not create `mio::Poll`: {}", e));
-        let waker = WakerQueue::new(poll.registry())
-            .unwrap_or_else(|e| panic!("Can not create `mio::Waker`: {}", e));
+        let poll = new_poller();
+        let waker = new_waker(&poll);
 
         Self {
             srv: Some(srv),
-            poll: Some(poll),
-            waker,
+            loops: vec![(Some(poll), waker)],
+            coalesce: false,
+        }
+    }
+
+    /// Set number of accept loops. Must be called before workers are started.
+    pub(crate) fn set_loops(&mut self, num: usize) {
+        self.loops.truncate(num.max(1));
+
+        while self.loops.len() < num {
+            let poll = new_poller();
+            let waker = new_waker(&poll);
+            waker.set_coalescing(self.coalesce);
+            self.loops.push((Some(poll), waker));
         }
     }
 
+    /// Returns number of accept loops.
+    pub(crate) fn loops(&self) -> usize {
+        self.loops.len()
+    }
+
+    /// Returns waker of the first accept loop, which accepts on listeners not shared with the
+    /// other ones.
     pub(crate) fn waker_owned(&self) -> WakerQueue {
-        self.waker.clone()
+        self.loops[0].1.clone()
     }
 
+    /// Returns waker of the accept loop serving the worker with given index.
+    pub(crate) fn worker_waker(&self, idx: usize) -> WakerQueue {
+        self.loops[idx % self.loops.len()].1.clone()
+    }
+
+    /// Returns wakers of all accept loops.
+    pub(crate) fn wakers(&self) -> Vec<WakerQueue> {
+        self.loops.iter().map(|(_, waker)| waker.clone()).collect()
+    }
+
+    /// Wake the first accept loop.
     pub fn wake(&self, i: WakerInterest) {
-        self.waker.wake(i);
+        self.loops[0].1.wake(i);
+    }
+
+    /// Wake the accept loop serving the worker with given index.
+    pub(crate) fn wake_worker(&self, idx: usize, i: WakerInterest) {
+        self.loops[idx % self.loops.len()].1.wake(i);
     }
 
+    /// Wake all accept loops with an interest created for each of them.
+    pub(crate) fn wake_all(&self, interest: impl Fn() -> WakerInterest) {
+        self.loops
+            .iter()
+            .for_each(|(_, waker)| waker.wake(interest()));
+    }
+
+    pub(crate) fn coalesce_wakes(&mut self, enabled: bool) {
+        self.coalesce = enabled;
+        self.loops
+            .iter()
+            .for_each(|(_, waker)| waker.set_coalescing(enabled));
+    }
+
+    /// Start accept loops, each accepting on its list of `socks` and dispatching to the workers
+    /// it serves.
+    ///
+    /// Returns receivers notified once each accept loop registered its listeners.
     pub(crate) fn start(
         &mut self,
-        socks: Vec<(usize, MioListener)>,
+        socks: Vec<Vec<ServerSocketInfo>>,
         handles: Vec<WorkerHandleAccept>,
-    ) {
+        config: AcceptConfig,
+    ) -> Vec<oneshot::Receiver<()>> {
         let srv = self.srv.take().expect("Can not re-use AcceptInfo");
-        let poll = self.poll.take().unwrap();
-        let waker = self.waker.clone();
+        let num = self.loops.len();
+
+        // limit on open connections is shared by all accept loops
+        let conn_limit = config
+            .max_total_connections
+            .map(|max| ConnectionLimit::new(max, self.wakers()));
+
+        // server wide accept rate is split between accept loops
+        let rate = config
+            .max_connection_rate
+            .map(|rate| std::cmp::max(rate / num as u32, 1));
+
+        let mut handles = handles.into_iter().fold(
+            (0..num).map(|_| Vec::new()).collect::<Vec<_>>(),
+            |mut handles, handle| {
+                handles[handle.idx() % num].push(handle);
+                handles
+            },
+        );
+
+        // health checks are run by the first accept loop only
+        let mut configs = (1..num).map(|_| config.for_loop()).collect::<Vec<_>>();
+        configs.insert(0, config);
+
+        socks
+            .into_iter()
+            .chain(std::iter::repeat_with(Vec::new))
+            .zip(configs)
+            .zip(self.loops.iter_mut())
+            .enumerate()
+            .map(|(i, ((socks, mut config), (poll, waker)))| {
+                let (tx, rx) = oneshot::channel();
+                config.max_connection_rate = rate;
+                config.ready = Some(tx);
+                if i > 0 {
+                    config.thread_name = Some(match config.thread_name {
+                        Some(ref name) => format!("{}-{}", name, i),
+                        None => format!("actix-server accept loop {}", i),
+                    });
+                }
+
+                let poll = poll.take().unwrap();
+                let handles = mem::take(&mut handles[i]);
+                let srv = srv.clone();
+                let conn_limit = conn_limit.clone();
+                Accept::start(poll, waker.clone(), socks, srv, handles, conn_limit, config);
 
-        Accept::start(poll, waker, socks, srv, handles);
+                rx
+            })
+            .collect()
     }
 }
 
+fn new_poller() -> Poller {
+    Poller::new().unwrap_or_else(|e| panic!("Can not create `mio::Poll`: {}", e))
+}
+
+fn new_waker(poll: &Poller) -> WakerQueue {
+    WakerQueue::new(poll).unwrap_or_else(|e| panic!("Can not create accept loop waker: {}", e))
+}
+
 /// poll instance of the server.
 struct Accept {
-    poll: Poll,
+    poll: Poller,
     waker: WakerQueue,
     handles: Vec<WorkerHandleAccept>,
     srv: Server,
     next: usize,
     avail: Availability,
+    dispatcher: Dispatcher,
+    /// Accept rate limit shared by all listeners.
+    rate_limit: Option<AcceptRateLimit>,
+    /// Limit on connections open on all workers.
+    conn_limit: Option<ConnectionLimit>,
+    pressure: Option<AcceptPressure>,
+    /// Maximum number of connections accepted from a listener before they are dispatched.
+    burst: usize,
+    /// Connections accepted in the current burst; kept to reuse its allocation.
+    batch: Vec<Conn>,
+    on_fd_pressure: Option<FdPressureCallback>,
+    on_accept_error: Option<AcceptErrorCallback>,
+    health: Option<HealthCheck>,
+    heartbeat: Option<HeartbeatMonitor>,
+    /// Number of workers the server runs with.
+    workers: usize,
     paused: bool,
 }
 
@@ -147,7 +439,7 @@ impl Availability {
 /// All other errors will incur a timeout before next `accept()` is performed.
 /// The timeout is useful to handle resource exhaustion errors like ENFILE
 /// and EMFILE. Otherwise, could enter into tight loop.
-fn connection_error(e: &io::Error) -> bool {
+pub(crate) fn connection_error(e: &io::Error) -> bool {
     e.kind() == io::ErrorKind::ConnectionRefused
         || e.kind() == io::ErrorKind::ConnectionAborted
         || e.kind() == io::ErrorKind::ConnectionReset
@@ -155,47 +447,96 @@ fn connection_error(e: &io::Error) -> bool {
 
 impl Accept {
     pub(crate) fn start(
-        poll: Poll,
+        poll: Poller,
         waker: WakerQueue,
-        socks: Vec<(usize, MioListener)>,
+        socks: Vec<ServerSocketInfo>,
         srv: Server,
         handles: Vec<WorkerHandleAccept>,
+        conn_limit: Option<ConnectionLimit>,
+        mut config: AcceptConfig,
     ) {
+        #[cfg(unix)]
+        if config.mode == AcceptMode::Runtime {
+            let fd = poll.as_raw_fd();
+            let (accept, sockets) =
+                Accept::new_ready(poll, waker, socks, handles, srv, conn_limit, config);
+            actix_rt::spawn(accept.poll_async(sockets, fd));
+            return;
+        }
+
         // Accept runs in its own thread and would want to spawn additional futures to current
         // actix system.
         let sys = System::current();
+        let name = config
+            .thread_name
+            .take()
+            .unwrap_or_else(|| "actix-server accept loop".to_owned());
         thread::Builder::new()
-            .name("actix-server accept loop".to_owned())
+            .name(name)
             .spawn(move || {
                 System::set_current(sys);
-                let (mut accept, mut sockets) =
-                    Accept::new_with_sockets(poll, waker, socks, handles, srv);
 
+                if let Some(core) = config.affinity {
+                    crate::affinity::pin_current_thread(core);
+                }
+
+                let (mut accept, mut sockets) =
+                    Accept::new_ready(poll, waker, socks, handles, srv, conn_limit, config);
                 accept.poll_with(&mut sockets);
             })
             .unwrap();
     }
 
+    /// Construct `Accept` and report it ready once its listeners are registered.
+    fn new_ready(
+        poll: Poller,
+        waker: WakerQueue,
+        socks: Vec<ServerSocketInfo>,
+        handles: Vec<WorkerHandleAccept>,
+        srv: Server,
+        conn_limit: Option<ConnectionLimit>,
+        mut config: AcceptConfig,
+    ) -> (Accept, Vec<ServerSocketInfo>) {
+        let fd_check = config.fd_check;
+        let ready = config.ready.take();
+        let (accept, sockets) =
+            Accept::new_with_sockets(poll, waker, socks, handles, srv, conn_limit, config);
+
+        if let Some(tx) = ready {
+            let _ = tx.send(());
+        }
+
+        if fd_check {
+            accept.wake_after(FD_CHECK_INTERVAL, WakerInterest::FdCheck);
+        }
+
+        if let Some(ref heartbeat) = accept.heartbeat {
+            let interval = heartbeat.policy().interval();
+            accept.wake_after(interval, WakerInterest::HeartbeatCheck);
+        }
+
+        (accept, sockets)
+    }
+
     fn new_with_sockets(
-        poll: Poll,
+        mut poll: Poller,
         waker: WakerQueue,
-        socks: Vec<(usize, MioListener)>,
+        socks: Vec<ServerSocketInfo>,
         handles: Vec<WorkerHandleAccept>,
         srv: Server,
+        conn_limit: Option<ConnectionLimit>,
+        config: AcceptConfig,
     ) -> (Accept, Vec<ServerSocketInfo>) {
+        poll.set_capacity(config.events);
+
         let sockets = socks
             .into_iter()
-            .map(|(token, mut lst)| {
+            .map(|mut info| {
                 // Start listening for incoming connections
-                poll.registry()
-                    .register(&mut lst, MioToken(token), Interest::READABLE)
+                poll.register(&mut info.lst, info.token)
                     .unwrap_or_else(|e| panic!("Can not register io: {}", e));
 
-                ServerSocketInfo {
-                    token,
-                    lst,
-                    timeout: None,
-                }
+                info
             })
             .collect();
 
@@ -204,6 +545,21 @@ impl Accept {
         // Assume all handles are avail at construct time.
         avail.set_available_all(&handles);
 
+        let mut health = config.health;
+        if let Some(ref mut health) = health {
+            health.register(&poll);
+        }
+        let workers = handles.len();
+
+        let heartbeat = config.heartbeat.map(|policy| {
+            let mut monitor = HeartbeatMonitor::new(policy);
+            let now = Instant::now();
+            handles
+                .iter()
+                .for_each(|handle| monitor.started(handle.idx(), now));
+            monitor
+        });
+
         let accept = Accept {
             poll,
             waker,
@@ -211,43 +567,124 @@ impl Accept {
             srv,
             next: 0,
             avail,
+            dispatcher: Dispatcher::new(config.strategy),
+            rate_limit: config
+                .max_connection_rate
+                .map(|per_second| AcceptRateLimit::new("server".to_owned(), per_second)),
+            conn_limit,
+            pressure: config.pressure,
+            burst: config.burst,
+            batch: Vec::with_capacity(config.burst),
+            on_fd_pressure: config.on_fd_pressure,
+            on_accept_error: config.on_accept_error,
+            health,
+            heartbeat,
+            workers,
             paused: false,
         };
 
         (accept, sockets)
     }
 
-    fn poll_with(&mut self, sockets: &mut [ServerSocketInfo]) {
-        let mut events = mio::Events::with_capacity(128);
+    fn poll_with(&mut self, sockets: &mut Vec<ServerSocketInfo>) {
+        let mut tokens = Vec::with_capacity(128);
 
         loop {
-            if let Err(e) = self.poll.poll(&mut events, None) {
+            if let Err(e) = self.poll.poll(&mut tokens, None) {
                 match e.kind() {
                     io::ErrorKind::Interrupted => {}
                     _ => panic!("Poll error: {}", e),
                 }
             }
 
-            for event in events.iter() {
-                let token = event.token();
-                match token {
-                    WAKER_TOKEN => {
-                        let exit = self.handle_waker(sockets);
-                        if exit {
-                            info!("Accept is stopped.");
-                            return;
-                        }
-                    }
+            if self.handle_tokens(sockets, &tokens) {
+                info!("Accept is stopped.");
+                return;
+            }
+        }
+    }
+
+    /// Run accept loop on the current runtime, polling without blocking whenever `fd` of the
+    /// poller is readable.
+    #[cfg(unix)]
+    async fn poll_async(
+        mut self,
+        mut sockets: Vec<ServerSocketInfo>,
+        fd: std::os::unix::io::RawFd,
+    ) {
+        use tokio::io::{unix::AsyncFd, Interest};
+
+        let fd = match AsyncFd::with_interest(fd, Interest::READABLE) {
+            Ok(fd) => fd,
+            Err(e) => {
+                error!("Can not register accept loop with runtime: {}", e);
+                return self.stop_server();
+            }
+        };
+        let mut tokens = Vec::with_capacity(128);
+
+        loop {
+            let mut guard = match fd.readable().await {
+                Ok(guard) => guard,
+                Err(e) => {
+                    error!("Poll error: {}", e);
+                    return self.stop_server();
+                }
+            };
+
+            if let Err(e) = self.poll.poll(&mut tokens, Some(Duration::from_millis(0))) {
+                match e.kind() {
+                    io::ErrorKind::Interrupted => {}
                     _ => {
-                        let token = usize::from(token);
-                        self.accept(sockets, token);
+                        error!("Poll error: {}", e);
+                        return self.stop_server();
                     }
                 }
             }
+
+            // poller is drained; wait for the next readiness of its fd
+            if tokens.is_empty() {
+                guard.clear_ready();
+                continue;
+            }
+            drop(guard);
+
+            if self.handle_tokens(&mut sockets, &tokens) {
+                info!("Accept is stopped.");
+                return;
+            }
         }
     }
 
-    fn handle_waker(&mut self, sockets: &mut [ServerSocketInfo]) -> bool {
+    /// Stop the server once the accept loop running on the runtime failed, as it can not accept
+    /// connections anymore.
+    #[cfg(unix)]
+    fn stop_server(&self) {
+        error!("Accept loop failed, stopping server");
+        actix_rt::spawn(self.srv.stop(true));
+    }
+
+    /// Handle ready tokens reported by the poller. Returns true once the accept loop is stopped.
+    fn handle_tokens(&mut self, sockets: &mut Vec<ServerSocketInfo>, tokens: &[usize]) -> bool {
+        for &token in tokens {
+            match token {
+                WAKER_TOKEN => {
+                    if self.handle_waker(sockets) {
+                        return true;
+                    }
+                }
+                _ => match self.health {
+                    Some(ref mut health) if health.owns(token) => health.accept(token),
+                    _ => self.accept(sockets, token),
+                },
+            }
+        }
+
+        self.update_health();
+        false
+    }
+
+    fn handle_waker(&mut self, sockets: &mut Vec<ServerSocketInfo>) -> bool {
         // This is a loop because interests for command from previous version was
         // a loop that would try to drain the command channel. It's yet unknown
         // if it's necessary/good practice to actively drain the waker queue.
@@ -266,10 +703,21 @@ impl Accept {
                         self.accept_all(sockets);
                     }
                 }
+                Some(WakerInterest::ConnectionAvailable) => {
+                    drop(guard);
+
+                    if !self.paused {
+                        self.accept_all(sockets);
+                    }
+                }
                 // a new worker thread is made and it's handle would be added to Accept
                 Some(WakerInterest::Worker(handle)) => {
                     drop(guard);
 
+                    if let Some(ref mut heartbeat) = self.heartbeat {
+                        heartbeat.started(handle.idx(), Instant::now());
+                    }
+
                     self.avail.set_available(handle.idx(), true);
                     self.handles.push(handle);
 
@@ -277,12 +725,107 @@ impl Accept {
                         self.accept_all(sockets);
                     }
                 }
+                // a worker is restarted on request and its replacement takes over
+                Some(WakerInterest::ReplaceWorker(replace)) => {
+                    drop(guard);
+
+                    let ReplaceWorker { handle, old, stop } = *replace;
+
+                    let idx = handle.idx();
+                    let prev = match self.handles.iter().position(|h| h.idx() == idx) {
+                        Some(pos) => Some(mem::replace(&mut self.handles[pos], handle)),
+                        // old worker faulted in the meantime
+                        None => {
+                            self.handles.push(handle);
+                            None
+                        }
+                    };
+
+                    // stop old worker before its connection channel is closed
+                    old.send_stop(stop);
+                    drop(prev);
+
+                    if let Some(ref mut heartbeat) = self.heartbeat {
+                        heartbeat.started(idx, Instant::now());
+                    }
+
+                    self.avail.set_available(idx, true);
+
+                    if !self.paused {
+                        self.accept_all(sockets);
+                    }
+                }
+                // a worker started under load is no longer needed
+                Some(WakerInterest::RetireWorker { old, stop }) => {
+                    drop(guard);
+
+                    let idx = old.idx();
+                    let prev = self
+                        .handles
+                        .iter()
+                        .position(|h| h.idx() == idx)
+                        .map(|pos| self.handles.swap_remove(pos));
+                    self.avail.set_available(idx, false);
+                    if self.next >= self.handles.len() {
+                        self.next = 0;
+                    }
+
+                    if let Some(ref mut heartbeat) = self.heartbeat {
+                        heartbeat.stopped(idx);
+                    }
+
+                    // stop worker before its connection channel is closed
+                    old.send_stop(stop);
+                    drop(prev);
+                }
+                // connections queued for a stopped worker go to the others
+                Some(WakerInterest::Redispatch(conns)) => {
+                    drop(guard);
+
+                    if self.handles.is_empty() {
+                        error!("No workers");
+                        continue;
+                    }
+
+                    for conn in conns {
+                        self.accept_one(conn);
+                    }
+                }
                 // got timer interest and it's time to try register socket(s) again
                 Some(WakerInterest::Timer) => {
                     drop(guard);
 
                     self.process_timer(sockets)
                 }
+                // time to check file descriptor usage again
+                Some(WakerInterest::FdCheck) => {
+                    drop(guard);
+
+                    self.check_fd_pressure(sockets);
+                    self.wake_after(FD_CHECK_INTERVAL, WakerInterest::FdCheck);
+                }
+                Some(WakerInterest::Heartbeat(idx)) => {
+                    drop(guard);
+
+                    let recovered = match self.heartbeat {
+                        Some(ref mut heartbeat) => heartbeat.beat(idx, Instant::now()),
+                        None => false,
+                    };
+
+                    if recovered && self.handles.iter().any(|h| h.idx() == idx) {
+                        info!("Worker {} is responsive again", idx);
+                        self.avail.set_available(idx, true);
+
+                        if !self.paused {
+                            self.accept_all(sockets);
+                        }
+                    }
+                }
+                Some(WakerInterest::HeartbeatCheck) => {
+                    drop(guard);
+
+                    self.check_heartbeat();
+                }
                 Some(WakerInterest::Pause) => {
                     drop(guard);
 
@@ -298,13 +841,117 @@ impl Accept {
                     if self.paused {
                         self.paused = false;
 
-                        sockets.iter_mut().for_each(|info| {
-                            self.register_logged(info);
-                        });
+                        sockets
+                            .iter_mut()
+                            .filter(|info| !info.is_held())
+                            .for_each(|info| self.register_logged(info));
 
                         self.accept_all(sockets);
                     }
                 }
+                Some(WakerInterest::AddListener(info)) => {
+                    drop(guard);
+
+                    let mut info = *info;
+
+                    info!("Starting service on {}", info.lst);
+                    let token = info.token;
+
+                    // a paused server registers all listeners on resume
+                    if !self.paused {
+                        if let Err(e) = self.register(&mut info) {
+                            error!("Can not register server socket {}", e);
+                        }
+                    }
+
+                    sockets.push(info);
+
+                    if !self.paused {
+                        self.accept(sockets, token);
+                    }
+                }
+                Some(WakerInterest::RemoveListener(token)) => {
+                    drop(guard);
+
+                    if let Some(pos) = sockets.iter().position(|info| info.token == token) {
+                        let mut info = sockets.swap_remove(pos);
+
+                        // listeners with a timeout, paused ones or those of a paused server are
+                        // not registered
+                        if !self.paused && info.timeout.is_none() && !info.is_held() {
+                            self.deregister_logged(&mut info);
+                        }
+
+                        info!("Stopped service on {}", info.lst);
+                    }
+                }
+                Some(WakerInterest::PauseListener(token)) => {
+                    drop(guard);
+
+                    if let Some(info) = sockets.iter_mut().find(|info| info.token == token) {
+                        if !info.paused {
+                            // listeners with a timeout, shed ones or those of a paused server
+                            // are not registered
+                            if !self.paused && !info.is_shed() && info.timeout.take().is_none()
+                            {
+                                self.deregister_logged(info);
+                            }
+
+                            info.paused = true;
+                        }
+                    }
+                }
+                Some(WakerInterest::ResumeListener(token)) => {
+                    drop(guard);
+
+                    if let Some(info) = sockets.iter_mut().find(|info| info.token == token) {
+                        if info.paused {
+                            info.paused = false;
+
+                            if !self.paused && !info.is_shed() {
+                                self.register_logged(info);
+                                self.accept(sockets, token);
+                            }
+                        }
+                    }
+                }
+                // listener not driven by the poller has connections to accept
+                Some(WakerInterest::Accept(token)) => {
+                    drop(guard);
+
+                    if !self.paused {
+                        self.accept(sockets, token);
+                    }
+                }
+                Some(WakerInterest::HandOff(hand_off)) => {
+                    drop(guard);
+
+                    let HandOff {
+                        io,
+                        tokens,
+                        worker,
+                        tx,
+                    } = *hand_off;
+
+                    let res = self.hand_off(sockets, io, tokens, worker);
+                    let _ = tx.send(res);
+                }
+                Some(WakerInterest::Workers(tx)) => {
+                    drop(guard);
+
+                    let mut workers = self
+                        .handles
+                        .iter()
+                        .map(|handle| {
+                            let idx = handle.idx();
+                            let available = self.avail.get_available(idx);
+                            WorkerStatus::new(idx, available, handle.connections())
+                        })
+                        .collect::<Vec<_>>();
+                    workers.sort_unstable_by_key(WorkerStatus::idx);
+
+                    let _ = tx.send(workers);
+                }
                 Some(WakerInterest::Stop) => {
                     if !self.paused {
                         self.deregister_all(sockets);
@@ -335,7 +982,14 @@ impl Accept {
                 if now < inst {
                     info.timeout = Some(inst);
                 } else if !self.paused {
-                    self.register_logged(info);
+                    if info.is_throttled() || self.is_throttled() {
+                        // throttling episodes are logged by the rate limiter
+                        if let Err(e) = self.register(info) {
+                            error!("Can not register server socket {}", e);
+                        }
+                    } else {
+                        self.register_logged(info);
+                    }
                 }
 
                 // Drop the timeout if server is paused and socket timeout is expired.
@@ -344,28 +998,8 @@ impl Accept {
             });
     }
 
-    #[cfg(not(target_os = "windows"))]
-    fn register(&self, info: &mut ServerSocketInfo) -> io::Result<()> {
-        let token = MioToken(info.token);
-        self.poll
-            .registry()
-            .register(&mut info.lst, token, Interest::READABLE)
-    }
-
-    #[cfg(target_os = "windows")]
     fn register(&self, info: &mut ServerSocketInfo) -> io::Result<()> {
-        // On windows, calling register without deregister cause an error.
-        // See https://github.com/actix/actix-web/issues/905
-        // Calling reregister seems to fix the issue.
-        let token = MioToken(info.token);
-        self.poll
-            .registry()
-            .register(&mut info.lst, token, Interest::READABLE)
-            .or_else(|_| {
-                self.poll
-                    .registry()
-                    .reregister(&mut info.lst, token, Interest::READABLE)
-            })
+        self.poll.register(&mut info.lst, info.token)
     }
 
     fn register_logged(&self, info: &mut ServerSocketInfo) {
@@ -376,7 +1010,7 @@ impl Accept {
     }
 
     fn deregister_logged(&self, info: &mut ServerSocketInfo) {
-        match self.poll.registry().deregister(&mut info.lst) {
+        match self.poll.deregister(&mut info.lst) {
             Ok(_) => info!("Paused accepting connections on {}", info.lst.local_addr()),
             Err(e) => {
                 error!("Can not deregister server socket {}", e)
@@ -398,8 +1032,8 @@ impl Accept {
             // Take all timeout.
             // This is to prevent Accept::process_timer method re-register a socket afterwards.
             .map(|info| (info.timeout.take(), info))
-            // Socket info with a timeout is already deregistered so skip them.
-            .filter(|(timeout, _)| timeout.is_none())
+            // Socket info with a timeout or paused on its own is already deregistered so skip them.
+            .filter(|(timeout, info)| timeout.is_none() && !info.is_held())
             .for_each(|(_, info)| self.deregister_logged(info));
     }
 
@@ -417,7 +1051,15 @@ impl Accept {
                 self.set_next();
                 Ok(())
             }
-            Err(conn) => {
+            Err(TrySendError::Full(conn)) => {
+                // Worker is not keeping up. Treat it as unavailable until it has taken
+                // connections off its channel.
+                let idx = next.idx();
+                self.avail.set_available(idx, false);
+                self.set_next();
+                Err(conn)
+            }
+            Err(TrySendError::Closed(conn)) => {
                 // Worker thread is error and could be gone.
                 // Remove worker handle and notify `ServerBuilder`.
                 self.remove_next();
@@ -437,6 +1079,8 @@ impl Accept {
     }
 
     fn accept_one(&mut self, mut conn: Conn) {
+        self.select_next();
+
         loop {
             let next = self.next();
             let idx = next.idx();
@@ -451,47 +1095,365 @@ impl Accept {
                 self.set_next();
 
                 if !self.avail.available() {
-                    while let Err(c) = self.send_connection(conn) {
-                        conn = c;
+                    // all workers are at their connection limit; go over it rather than keep
+                    // the connection waiting
+                    for _ in 0..self.handles.len() {
+                        match self.send_connection(conn) {
+                            Ok(_) => return,
+                            Err(c) => conn = c,
+                        }
                     }
+
+                    // channels of all workers are full. listeners are not accepted from in this
+                    // state, so only connections handed back or handed off end up here
+                    warn!("Channels of all workers are full, closing connection");
+                    drop(conn);
                     return;
                 }
             }
         }
     }
 
+    /// Dispatch a connection released by a service to the first of `tokens` with a listener of
+    /// matching kind, preferring the given worker.
+    fn hand_off(
+        &mut self,
+        sockets: &[ServerSocketInfo],
+        io: MioStream,
+        tokens: Vec<usize>,
+        worker: Option<usize>,
+    ) -> io::Result<()> {
+        let token = tokens
+            .into_iter()
+            .find(|token| {
+                sockets
+                    .iter()
+                    .any(|info| info.token == *token && info.lst.is_stream_kind(&io))
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No listener accepting this kind of stream",
+                )
+            })?;
+
+        if self.handles.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "No workers"));
+        }
+
+        // listener name is only recorded in the span
+        #[cfg(feature = "tracing")]
+        let span = ConnSpan::accepted(
+            sockets
+                .iter()
+                .find(|info| info.token == token)
+                .map_or("", |info| info.name.as_str()),
+        );
+        #[cfg(not(feature = "tracing"))]
+        let span = ConnSpan::accepted("");
+
+        let permit = self.conn_limit.as_ref().map(ConnectionLimit::acquire);
+        let mut conn = Conn {
+            io,
+            token,
+            permit,
+            span,
+            accepted: Instant::now(),
+        };
+
+        if let Some(idx) = worker {
+            if let Some(handle) = self.handles.iter().find(|handle| handle.idx() == idx) {
+                match handle.send(conn) {
+                    Ok(_) => {
+                        if !handle.inc_counter() {
+                            self.avail.set_available(idx, false);
+                        }
+                        return Ok(());
+                    }
+                    Err(TrySendError::Full(c)) => {
+                        self.avail.set_available(idx, false);
+                        conn = c;
+                    }
+                    // worker is gone; it gets removed the next time a connection is sent to it
+                    Err(TrySendError::Closed(c)) => conn = c,
+                }
+            }
+        }
+
+        self.accept_one(conn);
+        Ok(())
+    }
+
     fn accept(&mut self, sockets: &mut [ServerSocketInfo], token: usize) {
+        let info = match sockets.iter_mut().find(|info| info.token == token) {
+            Some(info) => info,
+            // listener was removed
+            None => return,
+        };
+
+        // readiness may have been reported before the listener was paused
+        if info.is_held() {
+            return;
+        }
+
+        // connections stay in the backlog of the listener while channels of all workers are
+        // full; a worker taking connections off a full channel wakes up accepting again
+        let capacity = self
+            .handles
+            .iter()
+            .map(WorkerHandleAccept::capacity)
+            .sum::<usize>();
+        if capacity == 0 {
+            return;
+        }
+
+        // accept a burst of connections before dispatching them to workers, no larger than
+        // available workers can take
+        let remaining = self
+            .handles
+            .iter()
+            .filter(|handle| self.avail.get_available(handle.idx()))
+            .map(WorkerHandleAccept::remaining)
+            .sum::<usize>();
+        let burst = self.burst.min(remaining.max(1)).min(capacity);
+
+        let mut batch = mem::take(&mut self.batch);
+        let full = self.accept_batch(info, &mut batch, burst);
+        for conn in batch.drain(..) {
+            self.accept_one(conn);
+        }
+        self.batch = batch;
+
+        // let commands and other listeners go first before accepting more from this one
+        if full {
+            self.waker.wake(WakerInterest::Accept(token));
+        }
+    }
+
+    /// Accept connections on `info` into `batch` until accepting would block, a limit is reached
+    /// or `burst` connections were accepted. Returns true in the latter case.
+    fn accept_batch(
+        &mut self,
+        info: &mut ServerSocketInfo,
+        batch: &mut Vec<Conn>,
+        burst: usize,
+    ) -> bool {
         while self.avail.available() {
-            let info = &mut sockets[token];
+            if batch.len() >= burst {
+                return true;
+            }
+
+            // accepting resumes once a connection was closed
+            if let Some(ref limit) = self.conn_limit {
+                if !limit.available() {
+                    return false;
+                }
+            }
+
+            // accepting resumes once the pressure cleared
+            if matches!(self.pressure, Some(ref pressure) if pressure.is_high()) {
+                return false;
+            }
+
+            // server wide limit is checked first so a listener is not throttled on its own
+            // while the server is
+            if let Some(limit) = self.rate_limit.as_mut() {
+                if let Err(wait) = limit.check() {
+                    self.throttle(info, wait);
+                    return false;
+                }
+            }
+
+            if let Some(limit) = info.rate_limit.as_mut() {
+                if let Err(wait) = limit.check() {
+                    self.throttle(info, wait);
+                    return false;
+                }
+            }
 
             match info.lst.accept() {
                 Ok(io) => {
-                    let conn = Conn { io, token };
-                    self.accept_one(conn);
+                    if let (Some(opts), MioStream::Tcp(ref stream)) = (&info.tcp_options, &io) {
+                        if let Err(e) = opts.apply_stream(stream) {
+                            warn!("Can not set socket options of accepted stream: {}", e);
+                        }
+                    }
+
+                    if let Some(limit) = self.rate_limit.as_mut() {
+                        limit.bucket.take();
+                    }
+                    if let Some(limit) = info.rate_limit.as_mut() {
+                        limit.bucket.take();
+                    }
+
+                    let permit = self.conn_limit.as_ref().map(ConnectionLimit::acquire);
+                    batch.push(Conn {
+                        io,
+                        token: info.token,
+                        permit,
+                        span: ConnSpan::accepted(&info.name),
+                        accepted: Instant::now(),
+                    });
                 }
-                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return,
-                Err(ref e) if connection_error(e) => continue,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return false,
                 Err(e) => {
-                    error!("Error accepting connection: {}", e);
+                    let err = AcceptError::new(info.name.clone(), e);
+                    let action = match self.on_accept_error {
+                        Some(ref callback) => callback(&err),
+                        None => err.default_action(),
+                    };
+
+                    match action {
+                        AcceptErrorAction::Retry => continue,
+                        AcceptErrorAction::Backoff(wait) => {
+                            error!("{}", err);
+
+                            // deregister listener temporary
+                            self.deregister_logged(info);
+
+                            // sleep after error. write the timeout to socket info as later
+                            // the poll would need it mark which socket and when it's
+                            // listener should be registered
+                            info.timeout = Some(Instant::now() + wait);
+
+                            // after the sleep a Timer interest is sent to Accept Poll
+                            self.wake_after(
+                                wait + Duration::from_millis(10),
+                                WakerInterest::Timer,
+                            );
+                        }
+                        AcceptErrorAction::Pause => {
+                            error!("{}, pausing listener", err);
+                            self.deregister_logged(info);
+                            info.paused = true;
+                        }
+                        AcceptErrorAction::Stop => {
+                            error!("{}, stopping server", err);
+                            self.deregister_logged(info);
+                            info.paused = true;
+                            System::current().arbiter().spawn(self.srv.stop(true));
+                        }
+                    }
 
-                    // deregister listener temporary
-                    self.deregister_logged(info);
+                    return false;
+                }
+            };
+        }
 
-                    // sleep after error. write the timeout to socket info as later
-                    // the poll would need it mark which socket and when it's
-                    // listener should be registered
-                    info.timeout = Some(Instant::now() + Duration::from_millis(500));
+        false
+    }
 
-                    // after the sleep a Timer interest is sent to Accept Poll
-                    let waker = self.waker.clone();
-                    System::current().arbiter().spawn(async move {
-                        sleep(Duration::from_millis(510)).await;
-                        waker.wake(WakerInterest::Timer);
-                    });
+    /// Open health check listeners while the server is running with all of its workers available
+    /// and close them otherwise.
+    fn update_health(&mut self) {
+        let healthy = !self.paused
+            && self.handles.len() == self.workers
+            && self
+                .handles
+                .iter()
+                .all(|handle| self.avail.get_available(handle.idx()));
+
+        if let Some(ref mut health) = self.health {
+            if let Err(e) = health.set_healthy(healthy, &self.poll) {
+                error!("Can not bind health check socket {}", e);
+                self.wake_after(HEALTH_REBIND_DELAY, WakerInterest::Timer);
+            }
+        }
+    }
 
-                    return;
-                }
+    /// Returns true while the server wide accept rate is exceeded.
+    fn is_throttled(&self) -> bool {
+        matches!(self.rate_limit, Some(ref limit) if limit.throttled.is_some())
+    }
+
+    /// Stop accepting on a listener that exceeded its accept rate until a connection may be
+    /// accepted again.
+    fn throttle(&self, info: &mut ServerSocketInfo, wait: Duration) {
+        // avoid rapid deregister/register cycles for high rates
+        let wait = wait.max(Duration::from_millis(10));
+
+        if let Err(e) = self.poll.deregister(&mut info.lst) {
+            error!("Can not deregister server socket {}", e);
+        }
+        info.timeout = Some(Instant::now() + wait);
+        self.wake_after(wait + Duration::from_millis(1), WakerInterest::Timer);
+    }
+
+    fn wake_after(&self, dur: Duration, interest: WakerInterest) {
+        let waker = self.waker.clone();
+        System::current().arbiter().spawn(async move {
+            sleep(dur).await;
+            waker.wake(interest);
+        });
+    }
+
+    /// Pause listeners whose file descriptor headroom is exceeded and resume those back within
+    /// it.
+    fn check_fd_pressure(&mut self, sockets: &mut [ServerSocketInfo]) {
+        let (open, limit) = match fd_usage() {
+            Ok(usage) => usage,
+            Err(e) => {
+                error!("Can not check file descriptor usage: {}", e);
+                return;
+            }
+        };
+        let free = limit.saturating_sub(open);
+
+        let mut resumed = Vec::new();
+
+        for info in sockets.iter_mut() {
+            let shedding = match info.fd_shedding {
+                Some(ref mut shedding) => shedding,
+                None => continue,
             };
+
+            let paused = free < shedding.headroom;
+            if paused == shedding.paused {
+                continue;
+            }
+            shedding.paused = paused;
+
+            let event = FdPressure::new(shedding.name.clone(), paused, open, limit);
+
+            if paused {
+                warn!(
+                    "Pausing \"{}\" service on {}, {} of {} file descriptors in use",
+                    event.name(),
+                    info.lst,
+                    open,
+                    limit
+                );
+
+                // listeners with a timeout are not registered; they stay so until resumed
+                if !self.paused && !info.paused && info.timeout.take().is_none() {
+                    if let Err(e) = self.poll.deregister(&mut info.lst) {
+                        error!("Can not deregister server socket {}", e);
+                    }
+                }
+            } else {
+                info!(
+                    "Resuming \"{}\" service on {}, {} of {} file descriptors in use",
+                    event.name(),
+                    info.lst,
+                    open,
+                    limit
+                );
+
+                if !self.paused && !info.paused {
+                    if let Err(e) = self.register(info) {
+                        error!("Can not register server socket {}", e);
+                    }
+                    resumed.push(info.token);
+                }
+            }
+
+            if let Some(ref callback) = self.on_fd_pressure {
+                callback(&event);
+            }
+        }
+
+        for token in resumed {
+            self.accept(sockets, token);
         }
     }
 
@@ -509,16 +1471,57 @@ impl Accept {
         &self.handles[self.next]
     }
 
+    /// Point next worker handle at the one picked by the dispatch strategy.
+    fn select_next(&mut self) {
+        let handles = &self.handles;
+        let avail = &self.avail;
+
+        self.next = self.dispatcher.select(self.next, handles.len(), |i| {
+            let handle = &handles[i];
+            if avail.get_available(handle.idx()) {
+                Some(handle.connections())
+            } else {
+                None
+            }
+        });
+    }
+
     /// Set next worker handle that would accept connection.
     #[inline(always)]
     fn set_next(&mut self) {
         self.next = (self.next + 1) % self.handles.len();
     }
 
+    /// Stop sending connections to workers that did not report within the heartbeat timeout and
+    /// schedule the next check.
+    fn check_heartbeat(&mut self) {
+        let heartbeat = match self.heartbeat {
+            Some(ref mut heartbeat) => heartbeat,
+            None => return,
+        };
+
+        let policy = heartbeat.policy().clone();
+        for idx in heartbeat.check(Instant::now()) {
+            self.avail.set_available(idx, false);
+
+            if policy.restarts() {
+                error!("Worker {} is unresponsive, restarting", idx);
+                self.srv.worker_unresponsive(idx);
+            } else {
+                error!("Worker {} is unresponsive, marking it unavailable", idx);
+            }
+        }
+
+        self.wake_after(policy.interval(), WakerInterest::HeartbeatCheck);
+    }
+
     /// Remove next worker handle that fail to accept connection.
     fn remove_next(&mut self) {
         let handle = self.handles.swap_remove(self.next);
         let idx = handle.idx();
+        if let Some(ref mut heartbeat) = self.heartbeat {
+            heartbeat.stopped(idx);
+        }
         // A message is sent to `ServerBuilder` future to notify it a new worker
         // should be made.
         self.srv.worker_faulted(idx);
@@ -526,6 +1529,53 @@ impl Accept {
     }
 }
 
+impl AcceptRateLimit {
+    fn new(target: String, per_second: u32) -> Self {
+        Self {
+            target,
+            per_second,
+            bucket: TokenBucket::new(per_second, per_second),
+            throttled: None,
+            deferred: 0,
+        }
+    }
+
+    /// Check if a connection may be accepted, logging start and end of throttling episodes.
+    fn check(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+
+        if let Some(since) = self.throttled {
+            // demand dropped below the limit for long enough to refill the bucket
+            if self.bucket.is_full(now) {
+                info!(
+                    "Accept rate of {} back below {}/s after {:?}; accepting was deferred {} times",
+                    self.target,
+                    self.per_second,
+                    now - since,
+                    self.deferred
+                );
+                self.throttled = None;
+                self.deferred = 0;
+            }
+        }
+
+        let res = self.bucket.check(now);
+
+        if res.is_err() {
+            if self.throttled.is_none() {
+                warn!(
+                    "Accept rate of {} exceeded {}/s; throttling accepts",
+                    self.target, self.per_second
+                );
+                self.throttled = Some(now);
+            }
+            self.deferred += 1;
+        }
+
+        res
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Availability;
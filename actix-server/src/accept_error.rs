@@ -0,0 +1,94 @@
+use std::{fmt, io, sync::Arc, time::Duration};
+
+use crate::accept::connection_error;
+
+/// Callback set with [`ServerBuilder::on_accept_error`](crate::ServerBuilder::on_accept_error).
+pub(crate) type AcceptErrorCallback =
+    Arc<dyn Fn(&AcceptError) -> AcceptErrorAction + Send + Sync>;
+
+/// Time a listener stops accepting after errors that are not per connection.
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Error accepting a connection on a listener.
+///
+/// Passed to the callback set with
+/// [`ServerBuilder::on_accept_error`](crate::ServerBuilder::on_accept_error).
+#[derive(Debug)]
+pub struct AcceptError {
+    name: String,
+    error: io::Error,
+}
+
+impl AcceptError {
+    pub(crate) fn new(name: String, error: io::Error) -> Self {
+        Self { name, error }
+    }
+
+    /// Returns name of the listener.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns error returned by `accept()`.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns action the accept loop takes on this error when no callback is set.
+    ///
+    /// Per connection errors, e.g. `ECONNABORTED`, are skipped. On other errors, e.g. `EMFILE`
+    /// when the process runs out of file descriptors, the listener backs off for 500ms.
+    pub fn default_action(&self) -> AcceptErrorAction {
+        if connection_error(&self.error) {
+            AcceptErrorAction::Retry
+        } else {
+            AcceptErrorAction::Backoff(DEFAULT_BACKOFF)
+        }
+    }
+}
+
+impl fmt::Display for AcceptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error accepting connection of \"{}\" service: {}",
+            self.name, self.error
+        )
+    }
+}
+
+/// Action taken by the accept loop after an [`AcceptError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptErrorAction {
+    /// Keep accepting on the listener.
+    ///
+    /// On errors that persist, e.g. `EMFILE`, this keeps the accept thread busy retrying.
+    Retry,
+
+    /// Stop accepting on the listener for the given time.
+    Backoff(Duration),
+
+    /// Pause the listener until it is resumed with
+    /// [`Server::resume_listener`](crate::Server::resume_listener).
+    Pause,
+
+    /// Stop accepting on the listener and stop the server gracefully.
+    Stop,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_action() {
+        let err = AcceptError::new("test".to_owned(), io::ErrorKind::ConnectionAborted.into());
+        assert_eq!(err.default_action(), AcceptErrorAction::Retry);
+
+        let err = AcceptError::new("test".to_owned(), io::Error::from_raw_os_error(24));
+        assert_eq!(
+            err.default_action(),
+            AcceptErrorAction::Backoff(DEFAULT_BACKOFF)
+        );
+    }
+}
@@ -0,0 +1,30 @@
+/// Where the accept loop of the server runs, set with
+/// [`ServerBuilder::accept_mode`](crate::ServerBuilder::accept_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptMode {
+    /// Run the accept loop on a dedicated thread blocking on readiness of the listeners.
+    Thread,
+
+    /// Run the accept loop as a task on the runtime of the system the server is started on.
+    ///
+    /// Saves a thread, e.g. in containers with tight thread limits, and keeps the accept loop
+    /// visible to runtime instrumentation. Connections are accepted between other tasks of that
+    /// thread, so long running tasks there delay accepting.
+    ///
+    /// Listeners are still polled with `mio` rather than accepted with tokio's listeners: the
+    /// task waits for the file descriptor of the `mio` poll, e.g. its epoll instance, to become
+    /// readable through tokio's `AsyncFd` and then polls it without blocking. If the runtime
+    /// fails to report readiness of that file descriptor, the error is logged and the server is
+    /// stopped gracefully.
+    ///
+    /// Only supported on unix; [`Thread`](Self::Thread) is used on other platforms.
+    Runtime,
+}
+
+// deriving needs `#[default]` on the variant, only stable since Rust 1.62 (MSRV is 1.46)
+#[allow(clippy::derivable_impls)]
+impl Default for AcceptMode {
+    fn default() -> Self {
+        AcceptMode::Thread
+    }
+}
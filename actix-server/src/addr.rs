@@ -0,0 +1,101 @@
+use std::{fmt, net::SocketAddr, rc::Rc};
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use crate::socket;
+
+tokio::task_local! {
+    static LISTENER_ADDR: Rc<ListenerAddr>;
+}
+
+/// Address a listener is bound to.
+///
+/// Services get the address of the listener that accepted the connection they handle with
+/// [`current`](Self::current), from their `call` or the future it returns. Log messages about
+/// services show it as well.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListenerAddr {
+    /// TCP listener bound to a socket address.
+    Tcp(SocketAddr),
+
+    /// UDP socket bound to a socket address.
+    Udp(SocketAddr),
+
+    /// Unix domain socket bound to a path.
+    #[cfg(unix)]
+    Uds(PathBuf),
+
+    /// Unix domain socket bound to a name in the abstract namespace, without the leading NUL
+    /// byte.
+    #[cfg(unix)]
+    UdsAbstract(Vec<u8>),
+
+    /// In-memory listener.
+    Memory,
+
+    /// SCTP listener bound to a socket address.
+    #[cfg(all(target_os = "linux", feature = "sctp"))]
+    Sctp(SocketAddr),
+
+    /// Listener whose address can not be represented, e.g. an unnamed unix domain socket.
+    Other,
+}
+
+impl ListenerAddr {
+    /// Returns address of the listener that accepted the connection handled by the current task.
+    ///
+    /// Returns `None` outside of connection tasks.
+    pub fn current() -> Option<Self> {
+        LISTENER_ADDR.try_with(|addr| (**addr).clone()).ok()
+    }
+
+    /// Run `f` with `addr` set as listener address of the current connection.
+    pub(crate) fn sync_scope<R>(addr: Rc<Self>, f: impl FnOnce() -> R) -> R {
+        LISTENER_ADDR.sync_scope(addr, f)
+    }
+
+    /// Set `addr` as listener address of the connection handled by `fut`.
+    pub(crate) async fn scope<F: std::future::Future>(addr: Rc<Self>, fut: F) -> F::Output {
+        LISTENER_ADDR.scope(addr, fut).await
+    }
+
+    /// Convert local address of a listener.
+    pub(crate) fn from_socket_addr(addr: socket::SocketAddr) -> Self {
+        match addr {
+            socket::SocketAddr::Tcp(addr) => Self::Tcp(addr),
+            socket::SocketAddr::Udp(addr) => Self::Udp(addr),
+            #[cfg(unix)]
+            socket::SocketAddr::Uds(addr) => {
+                if let Some(path) = addr.as_pathname() {
+                    Self::Uds(path.to_owned())
+                } else if let Some(name) = addr.as_abstract_namespace() {
+                    Self::UdsAbstract(name.to_owned())
+                } else {
+                    Self::Other
+                }
+            }
+            socket::SocketAddr::Memory => Self::Memory,
+            socket::SocketAddr::Custom(addr) => addr,
+            socket::SocketAddr::Unknown => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for ListenerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Udp(addr) => write!(f, "{} (udp)", addr),
+            #[cfg(unix)]
+            Self::Uds(path) => write!(f, "{}", path.display()),
+            #[cfg(unix)]
+            Self::UdsAbstract(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+            Self::Memory => write!(f, "memory"),
+            #[cfg(all(target_os = "linux", feature = "sctp"))]
+            Self::Sctp(addr) => write!(f, "{} (sctp)", addr),
+            Self::Other => write!(f, "unknown"),
+        }
+    }
+}
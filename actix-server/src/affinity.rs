@@ -0,0 +1,43 @@
+/// Pin the current thread to the given CPU core.
+///
+/// Failure is logged and otherwise ignored; the thread keeps running on any core.
+#[cfg(target_os = "linux")]
+pub(crate) fn pin_current_thread(core: usize) {
+    use std::{io, mem};
+
+    use log::{error, trace};
+
+    // CPU_SET does not check bounds
+    if core >= libc::CPU_SETSIZE as usize {
+        error!(
+            "Can not pin thread to CPU core {}: core index out of range",
+            core
+        );
+        return;
+    }
+
+    // SAFETY: cpu_set_t is a plain bit mask and core index was checked above.
+    let res = unsafe {
+        let mut set = mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+
+    if res == 0 {
+        trace!(
+            "Pinned thread {:?} to CPU core {}",
+            std::thread::current().name(),
+            core
+        );
+    } else {
+        error!(
+            "Can not pin thread to CPU core {}: {}",
+            core,
+            io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn pin_current_thread(_: usize) {}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use actix_rt::time::Instant;
+
+/// Policy for starting additional workers under load, set with
+/// [`ServerBuilder::worker_autoscale`](crate::ServerBuilder::worker_autoscale).
+///
+/// Load is the number of open connections relative to what all workers can handle under their
+/// [connection limit](crate::ServerBuilder::maxconn). Once load stayed above the
+/// [upper threshold](Self::thresholds) for the [sustain](Self::sustain) time, another worker is
+/// started, up to the maximum number of workers. Once load stayed below the lower threshold for
+/// the [cool-down](Self::cool_down) time, the last worker started this way is stopped
+/// gracefully. The server never runs fewer than [`workers`](crate::ServerBuilder::workers).
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_server::WorkerAutoscale;
+///
+/// // grow to 16 workers once connections stayed above 80% of capacity for 10 seconds
+/// let policy = WorkerAutoscale::new(16)
+///     .thresholds(0.8, 0.2)
+///     .sustain(Duration::from_secs(10));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkerAutoscale {
+    max_workers: usize,
+    scale_up: f64,
+    scale_down: f64,
+    interval: Duration,
+    sustain: Duration,
+    cool_down: Duration,
+}
+
+impl WorkerAutoscale {
+    /// Create policy starting up to `max_workers` workers in total.
+    pub fn new(max_workers: usize) -> Self {
+        Self {
+            max_workers,
+            scale_up: 0.75,
+            scale_down: 0.25,
+            interval: Duration::from_secs(1),
+            sustain: Duration::from_secs(5),
+            cool_down: Duration::from_secs(60),
+        }
+    }
+
+    /// Set load above which workers are added and below which they are retired, as fraction of
+    /// the capacity of all workers. Defaults to 0.75 and 0.25.
+    pub fn thresholds(mut self, scale_up: f64, scale_down: f64) -> Self {
+        assert!(
+            scale_down < scale_up,
+            "scale down threshold must be below scale up threshold"
+        );
+        self.scale_up = scale_up;
+        self.scale_down = scale_down;
+        self
+    }
+
+    /// Set interval at which load is sampled. Defaults to 1 second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set time load has to stay above the upper threshold before a worker is added. Defaults to
+    /// 5 seconds.
+    pub fn sustain(mut self, sustain: Duration) -> Self {
+        self.sustain = sustain;
+        self
+    }
+
+    /// Set time load has to stay below the lower threshold before a worker is retired. Defaults
+    /// to 60 seconds.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+
+    pub(crate) fn max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    pub(crate) fn sample_interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Change of the number of workers decided by an [`Autoscaler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scale {
+    Up,
+    Down,
+}
+
+/// Load samples of a running server, deciding when to add or retire workers.
+pub(crate) struct Autoscaler {
+    policy: WorkerAutoscale,
+    min_workers: usize,
+    /// Since when load is above or below a threshold, and which of them.
+    since: Option<(Scale, Instant)>,
+}
+
+impl Autoscaler {
+    pub(crate) fn new(policy: WorkerAutoscale, min_workers: usize) -> Self {
+        Self {
+            policy,
+            min_workers,
+            since: None,
+        }
+    }
+
+    /// Add a sample of `conns` connections open on `workers` workers with a limit of `maxconn`
+    /// connections each.
+    pub(crate) fn sample(
+        &mut self,
+        now: Instant,
+        conns: usize,
+        workers: usize,
+        maxconn: usize,
+    ) -> Option<Scale> {
+        let load = conns as f64 / (workers.max(1) * maxconn.max(1)) as f64;
+
+        let (pressure, wait) =
+            if load > self.policy.scale_up && workers < self.policy.max_workers {
+                (Scale::Up, self.policy.sustain)
+            } else if load < self.policy.scale_down && workers > self.min_workers {
+                (Scale::Down, self.policy.cool_down)
+            } else {
+                self.since = None;
+                return None;
+            };
+
+        match self.since {
+            Some((scale, since)) if scale == pressure => {
+                if now.duration_since(since) >= wait {
+                    // next change needs the load to stay high or low for the whole time again
+                    self.since = None;
+                    Some(scale)
+                } else {
+                    None
+                }
+            }
+            _ => {
+                self.since = Some((pressure, now));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_up_and_down() {
+        let policy = WorkerAutoscale::new(3)
+            .sustain(Duration::from_secs(5))
+            .cool_down(Duration::from_secs(10));
+        let mut scaler = Autoscaler::new(policy, 1);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // load has to stay high for the sustain time
+        assert_eq!(scaler.sample(at(0), 90, 1, 100), None);
+        assert_eq!(scaler.sample(at(3), 50, 1, 100), None);
+        assert_eq!(scaler.sample(at(4), 90, 1, 100), None);
+        assert_eq!(scaler.sample(at(8), 90, 1, 100), None);
+        assert_eq!(scaler.sample(at(9), 90, 1, 100), Some(Scale::Up));
+
+        // not above maximum number of workers
+        assert_eq!(scaler.sample(at(10), 290, 3, 100), None);
+        assert_eq!(scaler.sample(at(20), 290, 3, 100), None);
+
+        // load has to stay low for the cool-down time
+        assert_eq!(scaler.sample(at(30), 10, 2, 100), None);
+        assert_eq!(scaler.sample(at(35), 10, 2, 100), None);
+        assert_eq!(scaler.sample(at(40), 10, 2, 100), Some(Scale::Down));
+
+        // not below minimum number of workers
+        assert_eq!(scaler.sample(at(50), 0, 1, 100), None);
+        assert_eq!(scaler.sample(at(70), 0, 1, 100), None);
+    }
+}
@@ -0,0 +1,35 @@
+/// Which of the addresses a host name resolves to are bound, set with
+/// [`ServerBuilder::bind_policy`](crate::ServerBuilder::bind_policy).
+///
+/// Applies to TCP listeners created by `bind()` and its variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindPolicy {
+    /// Bind a listener to every resolved address.
+    ///
+    /// A host name like `localhost` usually resolves to an IPv4 and an IPv6 address and gets one
+    /// listener for each.
+    All,
+
+    /// Bind only the resolved IPv6 addresses, with `IPV6_V6ONLY` set.
+    ///
+    /// Binding fails if the address resolves to no IPv6 address.
+    Ipv6Only,
+
+    /// Bind IPv6 addresses with `IPV6_V6ONLY` disabled, so their listeners also accept IPv4
+    /// connections.
+    ///
+    /// IPv4 clients reach a dual-stack listener bound to `[::]` or to an IPv4-mapped address
+    /// only, so only IPv4 addresses covered by such a listener are skipped: all of them if `[::]`
+    /// was resolved as well and those whose IPv4-mapped address was resolved. Others are
+    /// bound as usual; e.g. `localhost` gets a listener for `127.0.0.1` next to the one for
+    /// `[::1]`, which does not accept connections to `127.0.0.1`.
+    DualStack,
+}
+
+// deriving needs `#[default]` on the variant, only stable since Rust 1.62 (MSRV is 1.46)
+#[allow(clippy::derivable_impls)]
+impl Default for BindPolicy {
+    fn default() -> Self {
+        BindPolicy::All
+    }
+}
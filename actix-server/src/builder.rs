@@ -1,39 +1,124 @@
 use std::{
+    cmp,
+    collections::HashMap,
+    convert::TryFrom,
     future::Future,
     io, mem,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 
-use actix_rt::{self as rt, net::TcpStream, time::sleep, System};
-use log::{error, info};
+use actix_rt::{
+    self as rt,
+    net::TcpStream,
+    time::{sleep, Instant},
+    System,
+};
+use actix_service::ServiceFactory as BaseServiceFactory;
+use log::{error, info, warn};
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedReceiver},
     oneshot,
 };
 
-use crate::accept::AcceptLoop;
+use crate::accept::{AcceptConfig, AcceptLoop, ServerSocketInfo};
+use crate::accept_error::{AcceptError, AcceptErrorAction, AcceptErrorCallback};
+use crate::accept_mode::AcceptMode;
+use crate::addr::ListenerAddr;
+use crate::autoscale::{Autoscaler, Scale, WorkerAutoscale};
+use crate::bind_policy::BindPolicy;
+use crate::close::{CloseReason, CloseTracker};
+use crate::config::ServerConfig;
+use crate::dispatch::DispatchStrategy;
+use crate::drain::{DrainReport, DrainReporter};
+use crate::fd_limit::{FdPressure, FdPressureCallback};
+use crate::health::HealthCheck;
+use crate::heartbeat::WorkerHeartbeat;
 use crate::join_all;
+use crate::labels::ServiceLabels;
+use crate::memory::MemoryListener;
+use crate::metrics::WorkerMetricsHandler;
+use crate::mux::Mux;
+use crate::pressure::{AcceptPressure, PressureCheck};
+use crate::proxy::ProxyStream;
+use crate::restart::{FaultCallback, WorkerFault, WorkerRestart};
 use crate::server::{Server, ServerCommand};
-use crate::service::{InternalServiceFactory, ServiceFactory, StreamNewService};
+use crate::service::{InternalServiceFactory, OnConnect, ServiceFactory, StreamNewService};
+#[cfg(unix)]
+use crate::signals::SignalHandler;
 use crate::signals::{Signal, Signals};
-use crate::socket::{MioListener, StdSocketAddr, StdTcpListener, ToSocketAddrs};
-use crate::socket::{MioTcpListener, MioTcpSocket};
-use crate::waker_queue::{WakerInterest, WakerQueue};
-use crate::worker::{ServerWorker, ServerWorkerConfig, WorkerHandleAccept, WorkerHandleServer};
+use crate::socket::{FromStream, Listener, MioTcpListener, MioTcpSocket};
+use crate::socket::{MioListener, StdSocketAddr, StdTcpListener, StdUdpSocket, ToSocketAddrs};
+use crate::tcp::TcpOptions;
+use crate::udp::Datagram;
+#[cfg(unix)]
+use crate::uds::UdsOptions;
+use crate::waker_queue::{HandOff, ReplaceWorker, WakerInterest};
+use crate::worker::{
+    ServerWorker, ServerWorkerConfig, Stop, WorkerHandleAccept, WorkerHandleServer,
+};
+use crate::worker_status::{ServiceStatus, WorkerStatus};
 
 /// Server builder
 pub struct ServerBuilder {
     threads: usize,
     token: usize,
-    backlog: u32,
+    bind_opts: BindOptions,
+    listener_opts: HashMap<String, ListenerOptions>,
+    idle_timeout: Option<Duration>,
+    on_fd_pressure: Option<FdPressureCallback>,
+    on_accept_error: Option<AcceptErrorCallback>,
+    max_connection_rate: Option<u32>,
+    max_total_connections: Option<usize>,
+    accept_pressure: Option<(Duration, PressureCheck)>,
+    accept_burst: usize,
+    accept_events: usize,
+    dispatch: DispatchStrategy,
+    worker_affinity: Vec<usize>,
+    accept_affinity: Option<usize>,
+    accept_loops: Option<usize>,
+    worker_thread_name: Option<String>,
+    accept_thread_name: Option<String>,
+    accept_mode: AcceptMode,
+    close: Arc<CloseTracker>,
+    /// Whether open connections are published periodically, started with the first receiver.
+    conns_published: bool,
+    drain: Option<Arc<DrainReporter>>,
+    restart: WorkerRestart,
+    on_worker_faulted: Option<FaultCallback>,
+    heartbeat: Option<WorkerHeartbeat>,
+    faults: HashMap<usize, Vec<Instant>>,
+    /// Number of times the worker with each index was restarted.
+    restarts: HashMap<usize, usize>,
+    autoscale: Option<WorkerAutoscale>,
+    autoscaler: Option<Autoscaler>,
+    /// Number of workers started by the autoscaler, indexed after the configured workers.
+    scaled: usize,
+    #[cfg(unix)]
+    inherited_fds: Option<Vec<(std::os::unix::io::RawFd, Option<String>)>>,
+    /// Socket files of unix domain listeners removed when the server stops.
+    #[cfg(unix)]
+    uds_paths: Vec<(usize, PathBuf)>,
     handles: Vec<(usize, WorkerHandleServer)>,
     services: Vec<Box<dyn InternalServiceFactory>>,
     sockets: Vec<(usize, String, MioListener)>,
+    health: Vec<(usize, MioTcpListener)>,
+    health_payload: Vec<u8>,
+    /// Listeners bound from a `ServerConfig` that have no service yet.
+    configured: Vec<(String, MioTcpListener)>,
     accept: AcceptLoop,
     exit: bool,
+    stopping: bool,
+    /// Whether all workers and the accept thread started; `None` while starting.
+    started: Option<bool>,
+    ready: Vec<oneshot::Sender<io::Result<()>>>,
     no_signals: bool,
+    #[cfg(unix)]
+    signal_hooks: Vec<(actix_rt::signal::unix::SignalKind, SignalHandler)>,
     cmd: UnboundedReceiver<ServerCommand>,
     server: Server,
     notify: Vec<oneshot::Sender<()>>,
@@ -58,10 +143,58 @@ impl ServerBuilder {
             handles: Vec::new(),
             services: Vec::new(),
             sockets: Vec::new(),
+            health: Vec::new(),
+            health_payload: Vec::new(),
+            configured: Vec::new(),
             accept: AcceptLoop::new(server.clone()),
-            backlog: 2048,
+            bind_opts: BindOptions {
+                backlog: 2048,
+                reuse_port: false,
+                ipv6_only: None,
+                incoming_cpu: None,
+                mptcp: false,
+                retry: None,
+                socket_options: None,
+                policy: BindPolicy::All,
+            },
+            listener_opts: HashMap::new(),
+            idle_timeout: None,
+            on_fd_pressure: None,
+            on_accept_error: None,
+            max_connection_rate: None,
+            max_total_connections: None,
+            accept_pressure: None,
+            accept_burst: 128,
+            accept_events: 128,
+            dispatch: DispatchStrategy::default(),
+            worker_affinity: Vec::new(),
+            accept_affinity: None,
+            accept_loops: None,
+            worker_thread_name: None,
+            accept_thread_name: None,
+            accept_mode: AcceptMode::default(),
+            close: Arc::new(CloseTracker::default()),
+            conns_published: false,
+            drain: None,
+            restart: WorkerRestart::default(),
+            on_worker_faulted: None,
+            heartbeat: None,
+            faults: HashMap::new(),
+            restarts: HashMap::new(),
+            autoscale: None,
+            autoscaler: None,
+            scaled: 0,
+            #[cfg(unix)]
+            inherited_fds: None,
+            #[cfg(unix)]
+            uds_paths: Vec::new(),
             exit: false,
+            stopping: false,
+            started: None,
+            ready: Vec::new(),
             no_signals: false,
+            #[cfg(unix)]
+            signal_hooks: Vec::new(),
             cmd: rx,
             notify: Vec::new(),
             server,
@@ -69,6 +202,44 @@ impl ServerBuilder {
         }
     }
 
+    /// Create server builder with the settings of `config`, binding its listeners.
+    ///
+    /// Services of the listeners are added with [`bind_configured`](Self::bind_configured).
+    /// Other builder methods can be used as usual to change settings the configuration does not
+    /// cover.
+    pub fn from_config(config: ServerConfig) -> io::Result<ServerBuilder> {
+        let mut builder = ServerBuilder::new();
+
+        if let Some(num) = config.workers {
+            builder = builder.workers(num);
+        }
+        if let Some(num) = config.backlog {
+            builder = builder.backlog(num);
+        }
+        if let Some(num) = config.maxconn {
+            builder = builder.maxconn(num);
+        }
+        if let Some(sec) = config.shutdown_timeout {
+            builder = builder.shutdown_timeout(sec);
+        }
+
+        for lst in config.listeners {
+            if let Some(nodelay) = lst.nodelay {
+                let opts = builder
+                    .listener_opts(&lst.name)
+                    .tcp
+                    .get_or_insert_with(Default::default);
+                *opts = mem::take(opts).nodelay(nodelay);
+            }
+
+            for sock in bind_addr(lst.addr.as_str(), builder.bind_opts)? {
+                builder.configured.push((lst.name.clone(), sock));
+            }
+        }
+
+        Ok(builder)
+    }
+
     /// Set number of workers to start.
     ///
     /// By default server uses number of available logical cpu as workers
@@ -97,6 +268,109 @@ impl ServerBuilder {
         self
     }
 
+    /// Pin worker threads to the given CPU cores.
+    ///
+    /// Worker `n` is pinned to the `n`-th core of the list, wrapping around if there are more
+    /// workers than cores. A restarted worker is pinned to the same core as the one it replaces.
+    /// Cores that can not be used (e.g. offline or out of the process' CPU set) are logged and
+    /// the worker runs unpinned.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use actix_server::ServerBuilder;
+    /// let builder = ServerBuilder::new()
+    ///     .workers(2)
+    ///     .worker_affinity(vec![2, 3]); // first worker on core 2, second on core 3.
+    /// ```
+    ///
+    /// By default worker threads are not pinned.
+    #[cfg(target_os = "linux")]
+    pub fn worker_affinity<I: IntoIterator<Item = usize>>(mut self, cores: I) -> Self {
+        self.worker_affinity = cores.into_iter().collect();
+        self
+    }
+
+    /// Set number of accept loops.
+    ///
+    /// Every accept loop runs on a thread of its own and dispatches connections to its share of
+    /// the workers; worker `n` is served by accept loop `n % num`. TCP listeners with
+    /// `SO_REUSEPORT` set, e.g. those bound with [`reuse_port()`](Self::reuse_port) enabled, get
+    /// a socket of their own on every accept loop and the kernel distributes connections between
+    /// them. Other TCP and unix domain listeners are shared by all accept loops. UDP, SCTP and
+    /// in-memory listeners are only accepted on by the first accept loop and its workers.
+    ///
+    /// The number of accept loops is capped at the number of workers. By default a server with
+    /// `SO_REUSEPORT` listeners runs one accept loop per worker and other servers run a single
+    /// one.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use actix_server::ServerBuilder;
+    /// let builder = ServerBuilder::new()
+    ///     .workers(8)
+    ///     .accept_loops(2); // workers 0, 2, 4, 6 on the first loop, 1, 3, 5, 7 on the second.
+    /// ```
+    pub fn accept_loops(mut self, num: usize) -> Self {
+        assert_ne!(num, 0, "accept loops must be greater than 0");
+        self.accept_loops = Some(num);
+        self
+    }
+
+    /// Pin the accept thread to the given CPU core; with several accept loops, all of them.
+    ///
+    /// By default the accept thread is not pinned.
+    #[cfg(target_os = "linux")]
+    pub fn accept_affinity(mut self, core: usize) -> Self {
+        self.accept_affinity = Some(core);
+        self
+    }
+
+    /// Set prefix of the names of worker threads.
+    ///
+    /// Worker `n` runs on a thread named `{prefix}-{n}`, e.g. `srv-worker-3`, which shows up in
+    /// profilers, `top -H` and panic messages. A restarted worker gets the name of the worker it
+    /// replaces.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use actix_server::ServerBuilder;
+    /// let builder = ServerBuilder::new()
+    ///     .worker_thread_name("srv-worker")
+    ///     .accept_thread_name("srv-accept");
+    /// ```
+    ///
+    /// By default worker threads are named like other arbiter threads.
+    pub fn worker_thread_name<N: Into<String>>(mut self, prefix: N) -> Self {
+        self.worker_thread_name = Some(prefix.into());
+        self
+    }
+
+    /// Set name of the accept thread.
+    ///
+    /// By default the accept thread is named `actix-server accept loop`.
+    pub fn accept_thread_name<N: Into<String>>(mut self, name: N) -> Self {
+        self.accept_thread_name = Some(name.into());
+        self
+    }
+
+    /// Set where the accept loop runs.
+    ///
+    /// With [`AcceptMode::Runtime`] connections are accepted by a task on the runtime of the
+    /// system the server is started on instead of a dedicated thread, in which case
+    /// [`accept_thread_name()`](Self::accept_thread_name) and `accept_affinity()` have no effect.
+    ///
+    /// By default [`AcceptMode::Thread`] is used.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use actix_server::{AcceptMode, ServerBuilder};
+    /// let builder = ServerBuilder::new().accept_mode(AcceptMode::Runtime);
+    /// ```
+    pub fn accept_mode(mut self, mode: AcceptMode) -> Self {
+        self.accept_mode = mode;
+        self
+    }
+
     /// Set the maximum number of pending connections.
     ///
     /// This refers to the number of clients that can be waiting to be served.
@@ -108,174 +382,1497 @@ impl ServerBuilder {
     ///
     /// This method should be called before `bind()` method call.
     pub fn backlog(mut self, num: u32) -> Self {
-        self.backlog = num;
+        self.bind_opts.backlog = num;
         self
     }
 
-    /// Sets the maximum per-worker number of concurrent connections.
+    /// Enable or disable the `SO_REUSEPORT` option on TCP listeners created by `bind()`.
     ///
-    /// All socket listeners will stop accepting connections when this limit is
-    /// reached for each worker.
+    /// With this option set, several listeners (in this or other processes) can bind the same
+    /// address and the kernel distributes incoming connections between them. The server binds
+    /// one listener per [accept loop](Self::accept_loops) and runs one accept loop per worker
+    /// unless set otherwise, so connections are accepted on as many threads as there are
+    /// workers.
     ///
-    /// By default max connections is set to a 25k per worker.
-    pub fn maxconn(mut self, num: usize) -> Self {
-        self.worker_config.max_concurrent_connections(num);
+    /// Disabled by default.
+    ///
+    /// This method should be called before `bind()` method call.
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.bind_opts.reuse_port = enabled;
         self
     }
 
-    /// Stop Actix system.
-    pub fn system_exit(mut self) -> Self {
-        self.exit = true;
+    /// Set the `SO_INCOMING_CPU` option on TCP listeners created by `bind()`.
+    ///
+    /// Together with [`reuse_port()`](Self::reuse_port), the kernel prefers the listener whose
+    /// CPU matches the one that handled the incoming packets of a connection. Running one
+    /// server process per core, each with its accept thread [pinned](Self::accept_affinity) to
+    /// that core, keeps connections on the cores their packets arrive on and reduces cache
+    /// misses.
+    ///
+    /// Not set by default.
+    ///
+    /// This method should be called before `bind()` method call.
+    #[cfg(target_os = "linux")]
+    pub fn incoming_cpu(mut self, cpu: usize) -> Self {
+        self.bind_opts.incoming_cpu = Some(cpu);
         self
     }
 
-    /// Disable signal handling.
-    pub fn disable_signals(mut self) -> Self {
-        self.no_signals = true;
+    /// Create TCP listeners with Multipath TCP (MPTCP) enabled.
+    ///
+    /// MPTCP lets clients use several network paths for one connection, e.g. Wi-Fi and cellular
+    /// on mobile devices, and move between them without reconnecting. Clients that do not
+    /// support it fall back to plain TCP. Services still receive a `TcpStream` either way.
+    ///
+    /// Requires Linux 5.6 or later with `net.mptcp.enabled` set. Where MPTCP is not available
+    /// a warning is logged and plain TCP listeners are created instead.
+    ///
+    /// Disabled by default.
+    ///
+    /// This method should be called before `bind()` method call.
+    #[cfg(target_os = "linux")]
+    pub fn mptcp(mut self, enabled: bool) -> Self {
+        self.bind_opts.mptcp = enabled;
         self
     }
 
-    /// Timeout for graceful workers shutdown in seconds.
+    /// Retry binding TCP listeners when their address is in use.
     ///
-    /// After receiving a stop signal, workers have this much time to finish serving requests.
-    /// Workers still alive after the timeout are force dropped.
+    /// `bind()` fails with `AddrInUse` while another process still listens on the address, e.g.
+    /// a predecessor that is shutting down during a rolling restart. With this option set,
+    /// binding is retried up to `attempts` times, sleeping `delay` before each retry.
     ///
-    /// By default shutdown timeout sets to 30 seconds.
-    pub fn shutdown_timeout(mut self, sec: u64) -> Self {
-        self.worker_config
-            .shutdown_timeout(Duration::from_secs(sec));
+    /// Retries block the thread `bind()` is called on. Listeners added to a running server with
+    /// [`Server::bind`](crate::Server::bind) are not retried.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use actix_server::ServerBuilder;
+    /// let builder = ServerBuilder::new().bind_retry(10, Duration::from_millis(500));
+    /// ```
+    ///
+    /// By default binding is not retried.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn bind_retry(mut self, attempts: u32, delay: Duration) -> Self {
+        self.bind_opts.retry = Some((attempts, delay));
         self
     }
 
-    /// Add new service to the server.
-    pub fn bind<F, U, N: AsRef<str>>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
-    where
-        F: ServiceFactory<TcpStream>,
-        U: ToSocketAddrs,
-    {
-        let sockets = bind_addr(addr, self.backlog)?;
+    /// Set the `IPV6_V6ONLY` option on IPv6 TCP listeners created by `bind()`.
+    ///
+    /// When enabled, a listener bound to an IPv6 address only accepts IPv6 connections. When
+    /// disabled, a listener bound to the unspecified address `[::]` also accepts IPv4
+    /// connections as IPv4-mapped IPv6 addresses.
+    ///
+    /// If not set, the operating system default applies. It differs between platforms (and on
+    /// Linux, between system configurations), so set it explicitly when binding `[::]`. See
+    /// [`bind_dual_stack()`](Self::bind_dual_stack) for listening on both address families.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn ipv6_only(mut self, enabled: bool) -> Self {
+        self.bind_opts.ipv6_only = Some(enabled);
+        self
+    }
 
-        for lst in sockets {
-            let token = self.next_token();
-            self.services.push(StreamNewService::create(
-                name.as_ref().to_string(),
-                token,
-                factory.clone(),
-                lst.local_addr()?,
-            ));
-            self.sockets
-                .push((token, name.as_ref().to_string(), MioListener::Tcp(lst)));
-        }
-        Ok(self)
+    /// Set a function called on each TCP socket created by `bind()` before it is bound.
+    ///
+    /// Allows setting socket options the builder has no method for, e.g. `IP_FREEBIND` or
+    /// `SO_MARK`, without creating the listeners manually and passing them to `listen()`. The
+    /// function runs after the options set on the builder have been applied; an error it returns
+    /// fails the bind.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::ServerBuilder;
+    /// let builder = ServerBuilder::new().socket_options(|socket| {
+    ///     socket.set_recv_buffer_size(1 << 20)?;
+    ///     socket.set_keepalive(true)
+    /// });
+    /// ```
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn socket_options(mut self, hook: fn(&socket2::Socket) -> io::Result<()>) -> Self {
+        self.bind_opts.socket_options = Some(hook);
+        self
     }
 
-    /// Add new unix domain service to the server.
-    #[cfg(unix)]
-    pub fn bind_uds<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
-    where
-        F: ServiceFactory<actix_rt::net::UnixStream>,
-        N: AsRef<str>,
-        U: AsRef<std::path::Path>,
-    {
-        // The path must not exist when we try to bind.
-        // Try to remove it to avoid bind error.
-        if let Err(e) = std::fs::remove_file(addr.as_ref()) {
-            // NotFound is expected and not an issue. Anything else is.
-            if e.kind() != std::io::ErrorKind::NotFound {
-                return Err(e);
-            }
-        }
+    /// Choose which of the addresses passed to `bind()` are bound when a host name resolves to
+    /// both IPv4 and IPv6 addresses.
+    ///
+    /// By default every resolved address gets its own listener. See [`BindPolicy`] for binding
+    /// IPv6 only or a dual-stack listener instead. The policy takes precedence over
+    /// [`ipv6_only()`](Self::ipv6_only) and does not affect
+    /// [`bind_dual_stack()`](Self::bind_dual_stack).
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::{BindPolicy, ServerBuilder};
+    /// let builder = ServerBuilder::new().bind_policy(BindPolicy::Ipv6Only);
+    /// ```
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn bind_policy(mut self, policy: BindPolicy) -> Self {
+        self.bind_opts.policy = policy;
+        self
+    }
 
-        let lst = crate::socket::StdUnixListener::bind(addr)?;
-        self.listen_uds(name, lst, factory)
+    /// Limit the rate at which connections are accepted on listeners with the given name.
+    ///
+    /// Once more than `per_second` connections arrive within a second, the listener stops
+    /// accepting until the rate drops, leaving further connections in the kernel backlog. Other
+    /// listeners are unaffected, so a flood on one port does not starve the others. A warning is
+    /// logged when throttling starts and a summary when the rate is back below the limit.
+    ///
+    /// Applies to listeners bound with this name before or after this call, including those
+    /// added with [`Server::bind`].
+    pub fn max_accept_rate<N: AsRef<str>>(mut self, name: N, per_second: u32) -> Self {
+        assert_ne!(per_second, 0, "accept rate must be greater than 0");
+        self.listener_opts(name.as_ref()).accept_rate = Some(per_second);
+        self
     }
 
-    /// Add new unix domain service to the server.
-    /// Useful when running as a systemd service and
-    /// a socket FD can be acquired using the systemd crate.
-    #[cfg(unix)]
-    pub fn listen_uds<F, N: AsRef<str>>(
-        mut self,
-        name: N,
-        lst: crate::socket::StdUnixListener,
-        factory: F,
-    ) -> io::Result<Self>
+    /// Set socket options of TCP listeners with the given name.
+    ///
+    /// The options are set on every stream accepted by the listeners before it is passed to the
+    /// service, or once on the listeners if [`TcpOptions::on_listener`] is enabled. Failing to
+    /// set an option is logged and does not prevent the connection from being served.
+    ///
+    /// Applies to listeners bound with this name before or after this call, including those
+    /// added with [`Server::bind`].
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::net::TcpStream;
+    /// use actix_server::{Server, TcpOptions};
+    /// use actix_service::fn_service;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let builder = Server::build()
+    ///     .tcp_options("echo", TcpOptions::new().nodelay(true))
+    ///     .bind("echo", "127.0.0.1:0", || {
+    ///         fn_service(|_io: TcpStream| async { Ok::<_, ()>(()) })
+    ///     })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tcp_options<N: AsRef<str>>(mut self, name: N, opts: TcpOptions) -> Self {
+        self.listener_opts(name.as_ref()).tcp = Some(opts);
+        self
+    }
+
+    /// Set age after which services of listeners with the given name are signalled to close
+    /// their connections gracefully.
+    ///
+    /// Once a connection reached its maximum age, its [`CloseToken`](crate::CloseToken) is
+    /// closing. The service finishes the connection at the next point its protocol allows,
+    /// letting the client re-dial so long-lived connections are rotated and rebalanced over
+    /// workers. Connections are not closed by the server. Applies to listeners bound with this
+    /// name before or after this call, including those added with [`Server::bind`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use actix_server::Server;
+    /// # use actix_service::fn_service;
+    /// # fn run() -> std::io::Result<()> {
+    /// let server = Server::build()
+    ///     .max_connection_age("grpc", Duration::from_secs(30 * 60))
+    ///     .bind("grpc", "0.0.0.0:50051", || fn_service(|_| async { Ok::<_, ()>(()) }))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_connection_age<N: AsRef<str>>(mut self, name: N, age: Duration) -> Self {
+        self.listener_opts(name.as_ref()).max_connection_age = Some(age);
+        self
+    }
+
+    /// Set labels of listeners with the given name.
+    ///
+    /// Labels are passed with [`WorkerEvent`](crate::WorkerEvent)s to the
+    /// [metrics handler](Self::worker_metrics), returned with the status of each service by
+    /// [`Server::workers`] and shown in log messages about the service, so connections of
+    /// multi-tenant servers can be attributed without parsing names. Replaces labels previously
+    /// set for the name. Applies to listeners bound with this name before or after this call,
+    /// including those added with [`Server::bind`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::{Server, ServiceLabels};
+    /// # use actix_service::fn_service;
+    /// # fn run() -> std::io::Result<()> {
+    /// let server = Server::build()
+    ///     .labels("acme", ServiceLabels::new().label("tenant", "acme").label("env", "prod"))
+    ///     .bind("acme", "0.0.0.0:8080", || fn_service(|_| async { Ok::<_, ()>(()) }))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn labels<N: AsRef<str>>(mut self, name: N, labels: ServiceLabels) -> Self {
+        self.listener_opts(name.as_ref()).labels = Some(labels);
+        self
+    }
+
+    /// Set time without activity after which connections are closed.
+    ///
+    /// Applies to connections of all listeners. A connection is active while its service future
+    /// is woken, e.g. because reads, writes or timers it waits for became ready. Once a
+    /// connection was idle for `timeout`, its service future is dropped, closing the connection
+    /// and freeing its slot in the [`maxconn`](Self::maxconn) limit, e.g. for peers that went
+    /// away without closing it.
+    ///
+    /// By default connections are not closed for being idle.
+    pub fn client_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Pause accepting on listeners with the given name while the process is close to its limit
+    /// on open file descriptors.
+    ///
+    /// The listeners stop accepting once fewer than `headroom` file descriptors are left below
+    /// the soft `RLIMIT_NOFILE` limit and resume when usage drops again, leaving connections in
+    /// the kernel backlog meanwhile. Give less important listeners a larger headroom so they
+    /// are paused first and the most important service degrades last. Listeners without a
+    /// headroom accept until the limit is hit. Usage is checked every 250ms.
+    ///
+    /// Only supported on unix; ignored elsewhere. See [`on_fd_pressure()`](Self::on_fd_pressure)
+    /// for being notified of pauses.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::Server;
+    /// # use actix_service::fn_service;
+    /// # fn run() -> std::io::Result<()> {
+    /// // metrics go first, then the public API; admin keeps accepting
+    /// let server = Server::build()
+    ///     .fd_headroom("metrics", 2048)
+    ///     .fd_headroom("api", 512)
+    ///     .bind("metrics", "127.0.0.1:9090", || fn_service(|_| async { Ok::<_, ()>(()) }))?
+    ///     .bind("api", "0.0.0.0:8080", || fn_service(|_| async { Ok::<_, ()>(()) }))?
+    ///     .bind("admin", "127.0.0.1:8081", || fn_service(|_| async { Ok::<_, ()>(()) }))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fd_headroom<N: AsRef<str>>(mut self, name: N, headroom: usize) -> Self {
+        self.listener_opts(name.as_ref()).fd_headroom = Some(headroom);
+        self
+    }
+
+    /// Set a callback invoked with an [`FdPressure`] event each time a listener is paused or
+    /// resumed because of its [file descriptor headroom](Self::fd_headroom).
+    ///
+    /// The callback runs on the accept thread and should return quickly.
+    pub fn on_fd_pressure<F>(mut self, callback: F) -> Self
     where
-        F: ServiceFactory<actix_rt::net::UnixStream>,
+        F: Fn(&FdPressure) + Send + Sync + 'static,
     {
-        use std::net::{IpAddr, Ipv4Addr};
-        lst.set_nonblocking(true)?;
-        let token = self.next_token();
-        let addr = StdSocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-        self.services.push(StreamNewService::create(
-            name.as_ref().to_string(),
-            token,
-            factory,
-            addr,
-        ));
-        self.sockets
-            .push((token, name.as_ref().to_string(), MioListener::from(lst)));
-        Ok(self)
+        self.on_fd_pressure = Some(Arc::new(callback));
+        self
     }
 
-    /// Add new service to the server.
-    pub fn listen<F, N: AsRef<str>>(
-        mut self,
-        name: N,
-        lst: StdTcpListener,
-        factory: F,
-    ) -> io::Result<Self>
+    /// Set a callback deciding how the accept loop handles an [`AcceptError`].
+    ///
+    /// The returned [`AcceptErrorAction`] lets the listener keep accepting, back off, pause until
+    /// it is resumed, or stops the server. Without a callback
+    /// [`AcceptError::default_action`] is taken: per connection errors are skipped and the
+    /// listener backs off for 500ms on others, e.g. when file descriptors are exhausted.
+    ///
+    /// The callback runs on the accept thread and should return quickly.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_server::{AcceptErrorAction, Server};
+    ///
+    /// let builder = Server::build().on_accept_error(|err| {
+    ///     // EMFILE
+    ///     if err.error().raw_os_error() == Some(24) {
+    ///         AcceptErrorAction::Backoff(Duration::from_secs(1))
+    ///     } else {
+    ///         err.default_action()
+    ///     }
+    /// });
+    /// ```
+    pub fn on_accept_error<F>(mut self, callback: F) -> Self
     where
-        F: ServiceFactory<TcpStream>,
+        F: Fn(&AcceptError) -> AcceptErrorAction + Send + Sync + 'static,
     {
-        lst.set_nonblocking(true)?;
-        let addr = lst.local_addr()?;
+        self.on_accept_error = Some(Arc::new(callback));
+        self
+    }
 
-        let token = self.next_token();
-        self.services.push(StreamNewService::create(
-            name.as_ref().to_string(),
-            token,
-            factory,
-            addr,
-        ));
+    /// Limit the rate at which connections are accepted by the server as a whole.
+    ///
+    /// Once more than `per_second` connections arrive within a second on any of the listeners,
+    /// the accept loop stops accepting until the rate drops, leaving further connections in the
+    /// kernel backlogs. This protects services from connection floods and reconnect storms
+    /// without changes to application code. Applies in addition to per listener limits set with
+    /// [`max_accept_rate()`](Self::max_accept_rate).
+    pub fn max_connection_rate(mut self, per_second: u32) -> Self {
+        assert_ne!(per_second, 0, "connection rate must be greater than 0");
+        self.max_connection_rate = Some(per_second);
+        self
+    }
 
-        self.sockets
-            .push((token, name.as_ref().to_string(), MioListener::from(lst)));
+    /// Set strategy used to pick the worker that handles each new connection.
+    ///
+    /// By default connections are distributed with [`DispatchStrategy::RoundRobin`].
+    pub fn dispatch_strategy(mut self, strategy: DispatchStrategy) -> Self {
+        self.dispatch = strategy;
+        self
+    }
 
-        Ok(self)
+    /// Sets the maximum per-worker number of concurrent connections.
+    ///
+    /// All socket listeners will stop accepting connections when this limit is
+    /// reached for each worker.
+    ///
+    /// By default max connections is set to a 25k per worker.
+    pub fn maxconn(mut self, num: usize) -> Self {
+        self.worker_config.max_concurrent_connections(num);
+        self
     }
 
-    /// Starts processing incoming connections and return server controller.
+    /// Sets the maximum number of concurrent connections of all workers together.
+    ///
+    /// All listeners stop accepting connections while this many connections are open, leaving
+    /// further connections in the kernel backlogs, and resume once one of them is closed. Unlike
+    /// [`maxconn()`](Self::maxconn) the limit does not depend on the number of workers, so it
+    /// can be used to bound the file descriptors the server uses. Connections passed with
+    /// [`Server::hand_off`](crate::Server::hand_off) are always dispatched but count against
+    /// the limit.
+    ///
+    /// By default the number of connections of all workers is not limited.
+    pub fn max_total_connections(mut self, num: usize) -> Self {
+        self.max_total_connections = Some(num);
+        self
+    }
+
+    /// Hold back accepting connections while the process is under resource pressure.
+    ///
+    /// `check` is called every `interval` on the thread the server runs on and resolves to true
+    /// while the process is under pressure, e.g. because its memory use or the number of open
+    /// file descriptors is too high or an external limiter asks to shed load. All listeners stop
+    /// accepting connections until a later check resolves to false, leaving further connections
+    /// in the kernel backlogs. Connections passed with
+    /// [`Server::hand_off`](crate::Server::hand_off) are still dispatched.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// use actix_server::Server;
+    /// use actix_service::fn_service;
+    ///
+    /// async fn memory_exceeded() -> bool {
+    ///     // e.g. read resident memory from /proc/self/statm
+    /// #   false
+    /// }
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let server = Server::build()
+    ///     .accept_pressure(Duration::from_secs(1), memory_exceeded)
+    ///     .bind("app", "0.0.0.0:8080", || fn_service(|_| async { Ok::<_, ()>(()) }))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// By default accepting is not held back.
+    pub fn accept_pressure<F, Fut>(mut self, interval: Duration, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = bool> + 'static,
+    {
+        self.accept_pressure = Some((interval, Box::new(move || Box::pin(check()))));
+        self
+    }
+
+    /// Sets the maximum number of connections accepted from a listener at once.
+    ///
+    /// When a listener becomes ready, the accept loop accepts up to this many connections before
+    /// dispatching them to workers, reducing wakeups under high connection churn. Once the burst
+    /// is full, pending commands and other ready listeners are handled before accepting from the
+    /// listener again, so a busy listener does not starve the others. A burst is never larger than
+    /// the number of connections workers can still take under their [`maxconn`](Self::maxconn)
+    /// limit.
+    ///
+    /// By default up to 128 connections are accepted at once.
+    pub fn accept_burst(mut self, num: usize) -> Self {
+        assert_ne!(num, 0, "accept burst must be greater than 0");
+        self.accept_burst = num;
+        self
+    }
+
+    /// Sets the maximum number of readiness events the accept loop handles per poll.
+    ///
+    /// Events of listeners and wakes of the accept loop beyond this capacity are returned by the
+    /// next poll. A larger capacity saves system calls when many listeners are ready at once.
+    ///
+    /// By default up to 128 events are handled per poll.
+    pub fn accept_events(mut self, capacity: usize) -> Self {
+        assert_ne!(capacity, 0, "accept events capacity must be greater than 0");
+        self.accept_events = capacity;
+        self
+    }
+
+    /// Coalesce wakes of the accept loop.
+    ///
+    /// Workers, timers and server commands wake the accept loop with a system call each time
+    /// they queue a message for it, e.g. each time a worker drops back below its
+    /// [`maxconn`](Self::maxconn) limit. When enabled, the accept loop is only woken if it has no
+    /// messages queued yet, and repeated notifications of a worker becoming available are queued
+    /// once. Reduces system calls when workers flip between full and available thousands of
+    /// times per second.
+    ///
+    /// Disabled by default.
+    pub fn accept_wake_coalescing(mut self, enabled: bool) -> Self {
+        self.accept.coalesce_wakes(enabled);
+        self
+    }
+
+    /// Sets the capacity of the channel each worker receives accepted connections on.
+    ///
+    /// A worker whose channel is full is not sent new connections until it has caught up, so
+    /// that connections wait in the kernel backlog instead of in memory. If all workers are
+    /// behind, the accept loop stops accepting connections.
+    ///
+    /// By default the channel capacity is 1024.
+    pub fn worker_channel_capacity(mut self, num: usize) -> Self {
+        assert_ne!(num, 0, "channel capacity must be greater than 0");
+        self.worker_config.channel_capacity(num);
+        self
+    }
+
+    /// Set a callback invoked with the [`CloseReason`] of every connection that ends.
+    ///
+    /// The callback runs on the worker thread that served the connection and should return
+    /// quickly. Aggregate counts are available regardless through [`Server::close_stats`].
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn on_connection_close<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(CloseReason) + Send + Sync + 'static,
+    {
+        self.close = Arc::new(CloseTracker::with_callback(callback));
+        self
+    }
+
+    /// Set a callback invoked with a [`DrainReport`] after each graceful shutdown.
+    ///
+    /// The report lists for each listener name how many connections were being served when the
+    /// shutdown started and how many of them completed or had to be dropped at the
+    /// [shutdown timeout](Self::shutdown_timeout). Replaces a report path set with
+    /// [`drain_report_path()`](Self::drain_report_path).
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn on_drain<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&DrainReport) + Send + Sync + 'static,
+    {
+        self.drain = Some(Arc::new(DrainReporter::Callback(Box::new(callback))));
+        self
+    }
+
+    /// Write a [`DrainReport`] to the given file after each graceful shutdown.
+    ///
+    /// The file is overwritten with the report as a single line of JSON as returned by
+    /// [`DrainReport::to_json`], allowing deploy tooling to check that connections were drained.
+    /// Replaces a callback set with [`on_drain()`](Self::on_drain).
+    ///
+    /// Requires the `serde` and `serde_json` features.
+    ///
+    /// This method should be called before `run()` method call.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn drain_report_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.drain = Some(Arc::new(DrainReporter::File(path.into())));
+        self
+    }
+
+    /// Set policy for restarting workers that died.
+    ///
+    /// By default a dead worker is replaced immediately, however often it dies. See
+    /// [`WorkerRestart`] for delaying restarts and stopping the server when a worker keeps dying.
+    ///
+    /// The new worker reuses the index of the dead one; [`on_worker_start`](Self::on_worker_start)
+    /// hooks and metrics see it under the same index and it keeps the worker's CPU affinity.
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn worker_restart(mut self, policy: WorkerRestart) -> Self {
+        self.restart = policy;
+        self
+    }
+
+    /// Set a callback invoked with a [`WorkerFault`] each time a worker died.
+    ///
+    /// The callback runs before the worker is restarted or the server is stopped according to
+    /// the [restart policy](Self::worker_restart), e.g. to alert operators or abort the process.
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn on_worker_faulted<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&WorkerFault) + Send + 'static,
+    {
+        self.on_worker_faulted = Some(Box::new(callback));
+        self
+    }
+
+    /// Set policy for detecting workers that stopped responding.
+    ///
+    /// Workers report to the accept loop periodically. A worker whose thread is blocked, e.g. by
+    /// a deadlocked service, stops reporting and is logged and sent no further connections, or
+    /// is replaced if the policy restarts unresponsive workers. See [`WorkerHeartbeat`].
+    ///
+    /// Disabled by default.
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn worker_heartbeat(mut self, policy: WorkerHeartbeat) -> Self {
+        self.worker_config.heartbeat(policy.interval());
+        self.heartbeat = Some(policy);
+        self
+    }
+
+    /// Set policy for starting additional workers under load and retiring them once load is low
+    /// again.
+    ///
+    /// Workers started this way are indexed after the [configured](Self::workers) ones; the
+    /// configured workers are never retired. See [`WorkerAutoscale`] for thresholds and timing.
+    ///
+    /// Disabled by default.
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn worker_autoscale(mut self, policy: WorkerAutoscale) -> Self {
+        assert!(
+            policy.max_workers() <= 512,
+            "autoscale allows at most 512 workers"
+        );
+        self.autoscale = Some(policy);
+        self
+    }
+
+    /// Stop Actix system.
+    pub fn system_exit(mut self) -> Self {
+        self.exit = true;
+        self
+    }
+
+    /// Disable signal handling.
+    ///
+    /// By default `SIGTERM` stops the server gracefully and `SIGINT` and `SIGQUIT` stop it
+    /// immediately. On Windows Ctrl+C stops the server immediately while Ctrl+Break and closing
+    /// the console window stop it gracefully; after the console is closed Windows only waits a
+    /// few seconds before killing the process.
+    ///
+    /// Hooks registered with [`on_signal()`](Self::on_signal) still run.
+    pub fn disable_signals(mut self) -> Self {
+        self.no_signals = true;
+        self
+    }
+
+    /// Run an async callback each time the process receives the given signal.
+    ///
+    /// Built-in handling of stop signals is kept; a hook registered for one of them is started
+    /// before the server is stopped. Useful for reloading configuration or certificates on
+    /// `SIGHUP`, which is otherwise ignored. The callback is passed a handle to the running
+    /// server and the returned future is spawned on the thread `run()` was called on.
+    ///
+    /// This method should be called before `run()` method call.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::signal::unix::SignalKind;
+    /// use actix_server::Server;
+    ///
+    /// let builder = Server::build().on_signal(SignalKind::hangup(), |_srv| async {
+    ///     println!("reloading configuration");
+    /// });
+    /// ```
+    #[cfg(unix)]
+    pub fn on_signal<F, Fut>(
+        mut self,
+        kind: actix_rt::signal::unix::SignalKind,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Server) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.signal_hooks
+            .push((kind, Box::new(move |srv| Box::pin(handler(srv)))));
+        self
+    }
+
+    /// Timeout for graceful workers shutdown in seconds.
+    ///
+    /// After receiving a stop signal, workers have this much time to finish serving requests.
+    /// Workers still alive after the timeout are force dropped.
+    ///
+    /// By default shutdown timeout sets to 30 seconds.
+    pub fn shutdown_timeout(mut self, sec: u64) -> Self {
+        self.worker_config
+            .shutdown_timeout(Duration::from_secs(sec));
+        self
+    }
+
+    /// Close connections that waited for `timeout` for the services of their worker to be ready.
+    ///
+    /// Connections are sent to workers before their services are ready to serve them, e.g.
+    /// while a service is restarted after it failed or while it reports it is busy. Without a
+    /// timeout they wait for as long as that takes, and their clients hang. With a timeout,
+    /// connections queued for a worker are closed once `timeout` passed since they were
+    /// accepted while its services are still not ready. Closed connections are logged and
+    /// counted as [rejected](crate::CloseReason::Rejected).
+    ///
+    /// By default connections wait until services are ready.
+    ///
+    /// This method should be called before `run()` method call.
+    pub fn unavailable_timeout(mut self, timeout: Duration) -> Self {
+        self.worker_config.unavailable_timeout(timeout);
+        self
+    }
+
+    /// Run an async callback on each worker thread before its services are created.
+    ///
+    /// The callback is passed the index of the worker and the returned future is awaited on the
+    /// worker thread. Useful for per worker setup, e.g. seeding thread local RNGs or opening per
+    /// thread database connections. It also runs for restarted workers.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_server::Server;
+    ///
+    /// let builder = Server::build().on_worker_start(|idx| async move {
+    ///     println!("worker {} starting", idx);
+    /// });
+    /// ```
+    pub fn on_worker_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.worker_config
+            .on_start(Arc::new(move |idx| Box::pin(hook(idx))));
+        self
+    }
+
+    /// Run an async callback on each worker thread after it stopped.
+    ///
+    /// The callback is passed the index of the worker and the returned future is awaited on the
+    /// worker thread once its services are dropped, before the thread exits. Stopping the server
+    /// waits for it to finish.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_server::Server;
+    ///
+    /// let builder = Server::build().on_worker_stop(|idx| async move {
+    ///     println!("worker {} stopped", idx);
+    /// });
+    /// ```
+    pub fn on_worker_stop<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.worker_config
+            .on_stop(Arc::new(move |idx| Box::pin(hook(idx))));
+        self
+    }
+
+    /// Set a handler called by workers with [`WorkerEvent`](crate::WorkerEvent)s, e.g. accepted
+    /// connections, service restarts, readiness changes and the start of shutdown.
+    ///
+    /// Lets embedders push structured metrics to their own monitoring system. The handler is
+    /// shared by all workers and called on their threads, so it should return quickly.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_server::{Server, WorkerEvent};
+    ///
+    /// let builder = Server::build().worker_metrics(|idx, event: &WorkerEvent<'_>| {
+    ///     if let WorkerEvent::ConnectionAccepted { name, .. } = event {
+    ///         println!("worker {} accepted connection on {}", idx, name);
+    ///     }
+    /// });
+    /// ```
+    pub fn worker_metrics<H: WorkerMetricsHandler>(mut self, handler: H) -> Self {
+        self.worker_config.metrics(Arc::new(handler));
+        self
+    }
+
+    /// Add new service to the server.
+    pub fn bind<F, U, N: AsRef<str>>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: ToSocketAddrs,
+    {
+        let sockets = bind_addr(addr, self.bind_opts)?;
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server on the listeners configured with this name in the
+    /// [`ServerConfig`] passed to [`from_config`](Self::from_config).
+    ///
+    /// Returns a `NotFound` error if no listener with this name is configured.
+    pub fn bind_configured<F, N: AsRef<str>>(mut self, name: N, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        let name = name.as_ref();
+
+        let (sockets, rest) = mem::take(&mut self.configured)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(lst_name, _)| lst_name == name);
+        self.configured = rest;
+
+        if sockets.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No listener \"{}\" in server configuration", name),
+            ));
+        }
+
+        for (_, lst) in sockets {
+            self = self.add_tcp_listener(name, lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server, passing data produced by `on_connect` alongside each stream.
+    ///
+    /// `on_connect` is called on the worker for every accepted stream before it is passed to the
+    /// service. It can extract per connection information, such as peer and local address or
+    /// socket options, that the service then receives together with the stream as a
+    /// `(TcpStream, T)` tuple.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::net::SocketAddr;
+    /// use actix_rt::net::TcpStream;
+    /// use actix_server::Server;
+    /// use actix_service::fn_service;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let builder = Server::build().bind_on_connect(
+    ///     "peer",
+    ///     "127.0.0.1:0",
+    ///     |io: &TcpStream| io.peer_addr().ok(),
+    ///     || {
+    ///         fn_service(|(_io, peer): (TcpStream, Option<SocketAddr>)| async move {
+    ///             println!("connection from {:?}", peer);
+    ///             Ok::<_, ()>(())
+    ///         })
+    ///     },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_on_connect<F, C, S, T, U, N>(
+        mut self,
+        name: N,
+        addr: U,
+        on_connect: C,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: Fn() -> S + Send + Clone + 'static,
+        S: BaseServiceFactory<(TcpStream, T), Config = ()>,
+        S::Future: 'static,
+        C: Fn(&TcpStream) -> T + Send + Clone + 'static,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.bind_opts)?;
+        let factory = OnConnect::new(factory, on_connect);
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new listener serving connections with one of several services, picked by the first
+    /// bytes the client sends.
+    ///
+    /// See [`Mux`] for how connections are matched to services.
+    pub fn bind_multi<E, U, N>(mut self, name: N, addr: U, mux: Mux<E>) -> io::Result<Self>
+    where
+        E: From<io::Error> + 'static,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.bind_opts)?;
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, mux.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server that expects a PROXY protocol header on each connection.
+    ///
+    /// Use on listeners placed behind a proxy or load balancer that sends the header, e.g. HAProxy
+    /// with `send-proxy` or `send-proxy-v2`. The service receives a [`ProxyStream`] with the
+    /// client address reported by the proxy. Connections without a valid header are closed
+    /// before reaching the service.
+    pub fn bind_proxied<F, U, N: AsRef<str>>(
+        mut self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<ProxyStream<TcpStream>>,
+        U: ToSocketAddrs,
+    {
+        let sockets = bind_addr(addr, self.bind_opts)?;
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server that receives streams of a custom transport.
+    ///
+    /// Accepted TCP streams are converted to `Io` with its [`FromStream`] implementation,
+    /// including its handshake, before they are passed to the service. See [`FromStream`] for an
+    /// example.
+    pub fn bind_stream<F, Io, U, N: AsRef<str>>(
+        mut self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+        U: ToSocketAddrs,
+    {
+        let sockets = bind_addr(addr, self.bind_opts)?;
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    fn add_tcp_listener<F, Io>(
+        mut self,
+        name: &str,
+        lst: MioTcpListener,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+    {
+        let token = self.next_token();
+        self.services.push(StreamNewService::create(
+            name.to_string(),
+            token,
+            factory,
+            ListenerAddr::Tcp(lst.local_addr()?),
+        ));
+        self.sockets
+            .push((token, name.to_string(), MioListener::Tcp(lst)));
+        Ok(self)
+    }
+
+    /// Add new service listening on `port` of all IPv4 and IPv6 interfaces.
+    ///
+    /// Binds separate listeners to `0.0.0.0` and `[::]`, the latter with `IPV6_V6ONLY` set, so
+    /// behavior does not depend on platform defaults. Fails only if neither address family
+    /// could be bound; e.g. on hosts without IPv6 support only the IPv4 listener is added.
+    pub fn bind_dual_stack<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        port: u16,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        let addrs = [
+            StdSocketAddr::from((Ipv4Addr::UNSPECIFIED, port)),
+            StdSocketAddr::from((Ipv6Addr::UNSPECIFIED, port)),
+        ];
+
+        let opts = BindOptions {
+            ipv6_only: Some(true),
+            ..self.bind_opts
+        };
+
+        // With port 0 the first listener picks a free port; use the same for the second one.
+        let mut port = port;
+        let sockets = bind_each(&addrs[..], |mut addr| {
+            addr.set_port(port);
+            let lst = create_tcp_listener(addr, opts)?;
+            port = lst.local_addr()?.port();
+            Ok(lst)
+        })?;
+
+        for lst in sockets {
+            self = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new unix domain service to the server.
+    ///
+    /// A stale socket file at `addr` is removed before binding and the socket file is removed
+    /// again when the server stops. On Linux and Android, an address starting with `@` or a NUL
+    /// byte, e.g. `"@my-app"`, binds a socket in the abstract namespace instead; it is not visible
+    /// in the filesystem and vanishes when the listener is closed.
+    ///
+    /// See [`bind_uds_with_options()`](Self::bind_uds_with_options) for setting permissions of the
+    /// socket file.
+    #[cfg(unix)]
+    pub fn bind_uds<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+        N: AsRef<str>,
+        U: AsRef<std::path::Path>,
+    {
+        self.bind_uds_with_options(name, addr, UdsOptions::default(), factory)
+    }
+
+    /// Add new unix domain service to the server, setting mode and ownership of the socket file
+    /// and whether it is removed when the server stops.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::net::UnixStream;
+    /// use actix_server::{Server, UdsOptions};
+    /// use actix_service::fn_service;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let path = std::env::temp_dir().join(format!("actix-doc-{}.sock", std::process::id()));
+    /// let builder = Server::build().bind_uds_with_options(
+    ///     "app",
+    ///     &path,
+    ///     UdsOptions::new().mode(0o660),
+    ///     || fn_service(|_io: UnixStream| async { Ok::<_, std::io::Error>(()) }),
+    /// )?;
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn bind_uds_with_options<F, U, N>(
+        self,
+        name: N,
+        addr: U,
+        opts: UdsOptions,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+        N: AsRef<str>,
+        U: AsRef<std::path::Path>,
+    {
+        let lst = crate::uds::bind_uds_listener(addr.as_ref(), &opts)?;
+        let unlink = unlink_path(addr.as_ref(), &opts);
+        self.add_uds_listener(name.as_ref(), lst, unlink, factory)
+    }
+
+    /// Add new unix domain service to the server that expects a PROXY protocol header on each
+    /// connection.
+    ///
+    /// See [`bind_proxied()`](Self::bind_proxied) for details.
+    #[cfg(unix)]
+    pub fn bind_uds_proxied<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<ProxyStream<actix_rt::net::UnixStream>>,
+        N: AsRef<str>,
+        U: AsRef<std::path::Path>,
+    {
+        let opts = UdsOptions::default();
+        let lst = crate::uds::bind_uds_listener(addr.as_ref(), &opts)?;
+        let unlink = unlink_path(addr.as_ref(), &opts);
+        self.add_uds_listener(name.as_ref(), lst, unlink, factory)
+    }
+
+    /// Add new unix domain service to the server.
+    /// Useful when running as a systemd service and
+    /// a socket FD can be acquired using the systemd crate.
+    #[cfg(unix)]
+    pub fn listen_uds<F, N: AsRef<str>>(
+        self,
+        name: N,
+        lst: crate::socket::StdUnixListener,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+    {
+        self.add_uds_listener(name.as_ref(), lst, None, factory)
+    }
+
+    #[cfg(unix)]
+    fn add_uds_listener<F, Io>(
+        mut self,
+        name: &str,
+        lst: crate::socket::StdUnixListener,
+        unlink: Option<PathBuf>,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+    {
+        lst.set_nonblocking(true)?;
+        let token = self.next_token();
+        if let Some(path) = unlink {
+            self.uds_paths.push((token, path));
+        }
+        let lst = MioListener::from(lst);
+        let addr = ListenerAddr::from_socket_addr(lst.local_addr());
+        self.services.push(StreamNewService::create(
+            name.to_string(),
+            token,
+            factory,
+            addr,
+        ));
+        self.sockets.push((token, name.to_string(), lst));
+        Ok(self)
+    }
+
+    /// Add new service to the server using TCP sockets passed by a service manager.
+    ///
+    /// Supports socket activation as implemented by systemd: sockets are inherited as file
+    /// descriptors described by the `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` environment
+    /// variables. All sockets named `name` (set with `FileDescriptorName=` in the socket unit) are
+    /// added. If the service manager did not pass names, all passed sockets are added.
+    ///
+    /// Returns an error of kind [`NotFound`](io::ErrorKind::NotFound) if no socket with this name
+    /// was passed and of kind [`InvalidInput`](io::ErrorKind::InvalidInput) if one of them is not
+    /// a TCP listener.
+    #[cfg(unix)]
+    pub fn bind_from_env<F, N: AsRef<str>>(mut self, name: N, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        for sock in self.take_inherited(name.as_ref())? {
+            if sock.local_addr()?.as_socket().is_none() {
+                return Err(not_a_listener(name.as_ref(), "TCP"));
+            }
+
+            self = self.listen(name.as_ref(), sock.into(), factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new unix domain service to the server using sockets passed by a service manager.
+    ///
+    /// See [`bind_from_env()`](Self::bind_from_env) for details.
+    #[cfg(unix)]
+    pub fn bind_uds_from_env<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+    {
+        for sock in self.take_inherited(name.as_ref())? {
+            let lst = crate::socket::StdUnixListener::from(sock);
+
+            // fails if socket is not a unix domain socket
+            if lst.local_addr().is_err() {
+                return Err(not_a_listener(name.as_ref(), "unix domain"));
+            }
+
+            self = self.listen_uds(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Take ownership of inherited listening sockets with the given name.
+    #[cfg(unix)]
+    fn take_inherited(&mut self, name: &str) -> io::Result<Vec<socket2::Socket>> {
+        use std::os::unix::io::FromRawFd;
+
+        let fds = match self.inherited_fds {
+            Some(ref mut fds) => fds,
+            None => self
+                .inherited_fds
+                .get_or_insert(crate::systemd::listen_fds()?),
+        };
+
+        let (taken, rest) = fds.drain(..).partition::<Vec<_>, _>(|(_, fd_name)| {
+            fd_name.is_none() || fd_name.as_deref() == Some(name)
+        });
+        *fds = rest;
+
+        if taken.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No socket named \"{}\" passed by service manager", name),
+            ));
+        }
+
+        taken
+            .into_iter()
+            .map(|(fd, _)| {
+                // SAFETY: fds passed by the service manager are owned by this process and each
+                // is taken only once.
+                let sock = unsafe { socket2::Socket::from_raw_fd(fd) };
+                sock.set_cloexec(true)?;
+
+                if sock.r#type()? != socket2::Type::STREAM {
+                    return Err(not_a_listener(name, "stream"));
+                }
+
+                Ok(sock)
+            })
+            .collect()
+    }
+
+    /// Add new service to the server.
+    pub fn listen<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        lst: StdTcpListener,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        lst.set_nonblocking(true)?;
+        let addr = ListenerAddr::Tcp(lst.local_addr()?);
+
+        let token = self.next_token();
+        self.services.push(StreamNewService::create(
+            name.as_ref().to_string(),
+            token,
+            factory,
+            addr,
+        ));
+
+        self.sockets
+            .push((token, name.as_ref().to_string(), MioListener::from(lst)));
+
+        Ok(self)
+    }
+
+    /// Add new service to the server, accepting on a tokio TCP listener.
+    ///
+    /// The listener is deregistered from the runtime it was created on and accepted on by the
+    /// server's accept thread, so listeners created with tokio APIs, e.g. by other libraries, can
+    /// be passed as they are.
+    pub fn listen_tokio<F, N: AsRef<str>>(
+        self,
+        name: N,
+        lst: actix_rt::net::TcpListener,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        self.listen(name, lst.into_std()?, factory)
+    }
+
+    /// Add new UDP service to the server.
+    ///
+    /// Every datagram received on the bound socket(s) is dispatched to a worker and passed to a
+    /// new call of the service as a [`Datagram`].
+    pub fn bind_udp<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<Datagram>,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_each(addr, StdUdpSocket::bind)?;
+
+        for sock in sockets {
+            self = self.listen_udp(name.as_ref(), sock, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Add new SCTP service to the server.
+    ///
+    /// Binds one-to-one style SCTP sockets, whose associations are accepted and dispatched to
+    /// workers like TCP connections and passed to the service as an [`SctpStream`]. The
+    /// [backlog](Self::backlog) applies to them as well; other TCP options do not.
+    ///
+    /// Requires the `sctp` feature and a kernel with SCTP support.
+    ///
+    /// [`SctpStream`]: crate::SctpStream
+    #[cfg(all(target_os = "linux", feature = "sctp"))]
+    pub fn bind_sctp<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<crate::SctpStream>,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let backlog = self.bind_opts.backlog;
+        let listeners = bind_each(addr, |addr| crate::sctp::SctpListener::bind(addr, backlog))?;
+
+        for lst in listeners {
+            self = self.listen_custom(name.as_ref(), lst, factory.clone());
+        }
+        Ok(self)
+    }
+
+    /// Add new UDP service to the server using an already bound socket.
+    pub fn listen_udp<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        sock: StdUdpSocket,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<Datagram>,
+    {
+        sock.set_nonblocking(true)?;
+        let addr = ListenerAddr::Udp(sock.local_addr()?);
+        let lst = MioListener::try_from(sock)?;
+
+        let token = self.next_token();
+        self.services.push(StreamNewService::create(
+            name.as_ref().to_string(),
+            token,
+            factory,
+            addr,
+        ));
+
+        self.sockets.push((token, name.as_ref().to_string(), lst));
+
+        Ok(self)
+    }
+
+    /// Add new service to the server accepting in-memory connections opened with the
+    /// [`MemoryConnector`](crate::MemoryConnector) of `lst`.
+    ///
+    /// See [`MemoryListener`] for an example.
+    pub fn listen_memory<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        lst: MemoryListener,
+        factory: F,
+    ) -> Self
+    where
+        F: ServiceFactory<tokio::io::DuplexStream>,
+    {
+        lst.attach(self.accept.waker_owned());
+
+        let token = self.next_token();
+        self.services.push(StreamNewService::create(
+            name.as_ref().to_string(),
+            token,
+            factory,
+            ListenerAddr::Memory,
+        ));
+
+        self.sockets
+            .push((token, name.as_ref().to_string(), MioListener::Memory(lst)));
+
+        self
+    }
+
+    /// Add new service to the server accepting streams of a custom transport from `lst`.
+    ///
+    /// Accepted streams are converted to `Io` with its [`FromStream`] implementation. See
+    /// [`Listener`] for an example.
+    pub fn listen_custom<F, L, Io, N>(mut self, name: N, lst: L, factory: F) -> Self
+    where
+        F: ServiceFactory<Io>,
+        L: Listener,
+        Io: FromStream + Send + 'static,
+        N: AsRef<str>,
+    {
+        let addr = lst.local_addr();
+
+        let token = self.next_token();
+        self.services.push(StreamNewService::create(
+            name.as_ref().to_string(),
+            token,
+            factory,
+            addr,
+        ));
+
+        self.sockets.push((
+            token,
+            name.as_ref().to_string(),
+            MioListener::Custom(Box::new(lst)),
+        ));
+
+        self
+    }
+
+    /// Add listener for TCP health probes, such as Kubernetes liveness and readiness probes.
+    ///
+    /// Connections are accepted and closed right away by the accept thread, after writing the
+    /// [payload](Self::health_check_payload) if one is set. The listener is only open while all
+    /// workers are running and available to take connections. It is closed while a worker died
+    /// or reached its connection limit and while the server is paused or stopping, so probes are
+    /// refused.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::Server;
+    /// # use actix_service::fn_service;
+    /// # fn run() -> std::io::Result<()> {
+    /// Server::build()
+    ///     .bind("app", "0.0.0.0:8080", || fn_service(|_| async { Ok::<_, ()>(()) }))?
+    ///     .health_check("0.0.0.0:8081")?
+    ///     .health_check_payload("ok\n")
+    ///     .run();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn health_check<U: ToSocketAddrs>(mut self, addr: U) -> io::Result<Self> {
+        for lst in bind_addr(addr, self.bind_opts)? {
+            let token = self.next_token();
+            self.health.push((token, lst));
+        }
+        Ok(self)
+    }
+
+    /// Set data written to health probes before their connection is closed.
+    ///
+    /// Nothing is written by default.
+    pub fn health_check_payload<B: Into<Vec<u8>>>(mut self, payload: B) -> Self {
+        self.health_payload = payload.into();
+        self
+    }
+
+    /// Starts processing incoming connections and return server controller.
     pub fn run(mut self) -> Server {
         if self.sockets.is_empty() {
             panic!("Server should have at least one bound socket");
         } else {
+            for (name, lst) in mem::take(&mut self.configured) {
+                warn!(
+                    "Listener \"{}\" on {:?} in server configuration has no service",
+                    name, lst
+                );
+            }
+
             info!("Starting {} workers", self.threads);
 
+            let loops = match self.accept_loops {
+                Some(num) => num,
+                None if self.sockets.iter().any(|(_, _, lst)| reuses_port(lst)) => self.threads,
+                None => 1,
+            };
+            self.accept.set_loops(loops.min(self.threads));
+
+            let mut services = mem::take(&mut self.services);
+            for service in services.iter_mut() {
+                self.configure_service(service);
+            }
+            self.services = services;
+
+            let mut ready = Vec::new();
+
             // start workers
             let handles = (0..self.threads)
                 .map(|idx| {
-                    let (handle_accept, handle_server) =
-                        self.start_worker(idx, self.accept.waker_owned());
+                    let (tx, rx) = oneshot::channel();
+                    ready.push(rx);
+
+                    let (handle_accept, handle_server) = self.start_worker(idx, Some(tx));
                     self.handles.push((idx, handle_server));
 
-                    handle_accept
-                })
-                .collect();
+                    handle_accept
+                })
+                .collect();
+
+            // start accept thread
+            for sock in &self.sockets {
+                info!("Starting \"{}\" service on {}", sock.1, sock.2);
+            }
+            let mut sockets = (0..self.accept.loops())
+                .map(|_| Vec::new())
+                .collect::<Vec<_>>();
+            for (token, name, lst) in mem::take(&mut self.sockets) {
+                let lsts = self.loop_listeners(&name, lst);
+                let num = lsts.len();
+                for (i, lst) in lsts.into_iter().enumerate() {
+                    sockets[i].push(self.socket_info(token, &name, lst, num));
+                }
+            }
+
+            let mut health = HealthCheck::new(
+                self.bind_opts.without_retry(),
+                mem::take(&mut self.health_payload),
+            );
+            for (token, lst) in mem::take(&mut self.health) {
+                if let Err(e) = health.add(token, lst) {
+                    error!("Can not start health check: {}", e);
+                }
+            }
+
+            let mut config = AcceptConfig {
+                mode: self.accept_mode,
+                strategy: self.dispatch,
+                affinity: self.accept_affinity,
+                events: self.accept_events,
+                max_connection_rate: self.max_connection_rate,
+                max_total_connections: self.max_total_connections,
+                pressure: None,
+                burst: self.accept_burst,
+                fd_check: cfg!(unix)
+                    && self
+                        .listener_opts
+                        .values()
+                        .any(|opts| opts.fd_headroom.is_some()),
+                on_fd_pressure: self.on_fd_pressure.clone(),
+                on_accept_error: self.on_accept_error.clone(),
+                health: Some(health).filter(|health| !health.is_empty()),
+                heartbeat: self.heartbeat.clone(),
+                ready: None,
+                thread_name: self.accept_thread_name.clone(),
+            };
+
+            // hold back accepting under resource pressure
+            if let Some((interval, check)) = self.accept_pressure.take() {
+                let pressure = AcceptPressure::default();
+                config.pressure = Some(pressure.clone());
+                rt::spawn(pressure.watch(interval, check, self.accept.wakers()));
+            }
+
+            ready.extend(self.accept.start(sockets, handles, config));
 
-            // start accept thread
-            for sock in &self.sockets {
-                info!("Starting \"{}\" service on {}", sock.1, sock.2);
+            // workers and accept thread fail to report readiness only if they did not start
+            let server = self.server.clone();
+            rt::spawn(async move {
+                let started = join_all(ready).await.iter().all(Result::is_ok);
+                server.started(started);
+            });
+
+            // sample load for autoscaling
+            if let Some(policy) = self.autoscale.take() {
+                let interval = policy.sample_interval();
+                self.autoscaler = Some(Autoscaler::new(policy, self.threads));
+
+                let server = self.server.clone();
+                rt::spawn(async move {
+                    loop {
+                        sleep(interval).await;
+                        if !server.autoscale() {
+                            break;
+                        }
+                    }
+                });
             }
-            self.accept.start(
-                mem::take(&mut self.sockets)
-                    .into_iter()
-                    .map(|t| (t.0, t.2))
-                    .collect(),
-                handles,
-            );
 
             // handle signals
+            #[cfg(unix)]
+            Signals::start(
+                self.server.clone(),
+                !self.no_signals,
+                mem::take(&mut self.signal_hooks),
+            );
+            #[cfg(not(unix))]
             if !self.no_signals {
                 Signals::start(self.server.clone());
             }
@@ -290,32 +1887,75 @@ impl ServerBuilder {
     fn start_worker(
         &self,
         idx: usize,
-        waker_queue: WakerQueue,
+        ready: Option<oneshot::Sender<()>>,
     ) -> (WorkerHandleAccept, WorkerHandleServer) {
         let services = self.services.iter().map(|v| v.clone_factory()).collect();
 
-        ServerWorker::start(idx, services, waker_queue, self.worker_config)
+        let mut config = self.worker_config.clone();
+        if !self.worker_affinity.is_empty() {
+            config.affinity(Some(self.worker_affinity[idx % self.worker_affinity.len()]));
+        }
+
+        let name = self
+            .worker_thread_name
+            .as_ref()
+            .map(|prefix| format!("{}-{}", prefix, idx));
+
+        ServerWorker::start(
+            idx,
+            name,
+            services,
+            self.accept.worker_waker(idx),
+            config,
+            self.close.clone(),
+            ready,
+        )
+    }
+
+    /// Start a worker in place of the one at `pos` of the worker handles and stop the old one.
+    ///
+    /// Returns receiver of the stop outcome of the old worker.
+    fn replace_worker(&mut self, pos: usize, graceful: bool) -> oneshot::Receiver<bool> {
+        let idx = self.handles[pos].0;
+        *self.restarts.entry(idx).or_default() += 1;
+        let (handle_accept, handle_server) = self.start_worker(idx, None);
+        let old = mem::replace(&mut self.handles[pos].1, handle_server);
+
+        let (stop, rx) = Stop::new(graceful, None);
+        let stop = stop.redispatch();
+        self.accept.wake_worker(
+            idx,
+            WakerInterest::ReplaceWorker(Box::new(ReplaceWorker {
+                handle: handle_accept,
+                old,
+                stop,
+            })),
+        );
+
+        rx
     }
 
     fn handle_cmd(&mut self, item: ServerCommand) {
         match item {
             ServerCommand::Pause(tx) => {
-                self.accept.wake(WakerInterest::Pause);
+                self.accept.wake_all(|| WakerInterest::Pause);
                 let _ = tx.send(());
             }
             ServerCommand::Resume(tx) => {
-                self.accept.wake(WakerInterest::Resume);
+                self.accept.wake_all(|| WakerInterest::Resume);
                 let _ = tx.send(());
             }
             ServerCommand::Signal(sig) => {
                 // Signals support
-                // Handle `SIGINT`, `SIGTERM`, `SIGQUIT` signals and stop actix system
+                // Handle `SIGINT`, `SIGTERM`, `SIGQUIT` signals and Windows console events and stop
+                // actix system
                 match sig {
                     Signal::Int => {
                         info!("SIGINT received, exiting");
                         self.exit = true;
                         self.handle_cmd(ServerCommand::Stop {
                             graceful: false,
+                            timeout: None,
                             completion: None,
                         })
                     }
@@ -324,6 +1964,7 @@ impl ServerBuilder {
                         self.exit = true;
                         self.handle_cmd(ServerCommand::Stop {
                             graceful: true,
+                            timeout: None,
                             completion: None,
                         })
                     }
@@ -332,6 +1973,20 @@ impl ServerBuilder {
                         self.exit = true;
                         self.handle_cmd(ServerCommand::Stop {
                             graceful: false,
+                            timeout: None,
+                            completion: None,
+                        })
+                    }
+                    Signal::Break | Signal::Close => {
+                        if sig == Signal::Break {
+                            info!("CTRL_BREAK received, stopping");
+                        } else {
+                            info!("CTRL_CLOSE received, stopping");
+                        }
+                        self.exit = true;
+                        self.handle_cmd(ServerCommand::Stop {
+                            graceful: true,
+                            timeout: None,
                             completion: None,
                         })
                     }
@@ -341,26 +1996,103 @@ impl ServerBuilder {
             ServerCommand::Notify(tx) => {
                 self.notify.push(tx);
             }
+            ServerCommand::CloseStats(tx) => {
+                let _ = tx.send(self.close.stats());
+            }
+            ServerCommand::Connections(tx) => {
+                if !self.conns_published {
+                    self.conns_published = true;
+                    rt::spawn(CloseTracker::publish_periodically(Arc::downgrade(
+                        &self.close,
+                    )));
+                }
+                let _ = tx.send(self.close.connections());
+            }
+            ServerCommand::ServiceLatency(tx) => {
+                let _ = tx.send(self.close.service_latency(&self.service_names()));
+            }
+            ServerCommand::Workers(tx) => {
+                // availability and connections are tracked by the accept loops
+                let accept_rx = self
+                    .accept
+                    .wakers()
+                    .into_iter()
+                    .map(|waker| {
+                        let (tx, rx) = oneshot::channel();
+                        waker.wake(WakerInterest::Workers(tx));
+                        rx
+                    })
+                    .collect::<Vec<_>>();
+
+                let restarts = self.restarts.clone();
+                let services = self.service_status();
+                let close = self.close.clone();
+                rt::spawn(async move {
+                    let res = join_all(accept_rx)
+                        .await
+                        .into_iter()
+                        .collect::<Result<Vec<_>, _>>();
+                    if let Ok(workers) = res {
+                        let mut workers = workers.into_iter().flatten().collect::<Vec<_>>();
+                        workers.sort_unstable_by_key(WorkerStatus::idx);
+
+                        for worker in workers.iter_mut() {
+                            let num = restarts.get(&worker.idx()).copied().unwrap_or(0);
+                            worker.set_restarts(num);
+                            worker.set_services(close.service_status(worker.idx(), &services));
+                        }
+                        let _ = tx.send(workers);
+                    }
+                });
+            }
             ServerCommand::Stop {
                 graceful,
+                timeout,
                 completion,
             } => {
                 let exit = self.exit;
+                self.stopping = true;
 
-                // stop accept thread
-                self.accept.wake(WakerInterest::Stop);
-                let notify = std::mem::take(&mut self.notify);
+                // server stopped before it was ready
+                self.ready.clear();
+
+                let drain = match self.drain {
+                    Some(ref reporter) if graceful => {
+                        // discard counts of workers restarted before
+                        self.close.take_drain();
+
+                        let names = self.service_names();
+                        Some((reporter.clone(), names, self.close.clone(), Instant::now()))
+                    }
+                    _ => None,
+                };
 
-                // stop workers
+                // stop workers before their connection channels are closed by the accept thread;
+                // a worker whose channel is closed first exits without a graceful shutdown
                 let stop = self
                     .handles
                     .iter()
-                    .map(move |worker| worker.1.stop(graceful))
-                    .collect();
+                    .map(move |worker| worker.1.stop(graceful, timeout))
+                    .collect::<Vec<_>>();
+
+                // stop accept threads
+                self.accept.wake_all(|| WakerInterest::Stop);
+                #[cfg(unix)]
+                self.unlink_uds(|_| true);
+                let notify = std::mem::take(&mut self.notify);
 
                 rt::spawn(async move {
                     if graceful {
                         let _ = join_all(stop).await;
+
+                        if let Some((reporter, names, close, start)) = drain {
+                            let report = DrainReport::collect(
+                                start.elapsed(),
+                                &names,
+                                close.take_drain(),
+                            );
+                            reporter.report(report).await;
+                        }
                     }
 
                     if let Some(tx) = completion {
@@ -376,6 +2108,272 @@ impl ServerBuilder {
                     }
                 });
             }
+            ServerCommand::Bind {
+                name,
+                addrs,
+                factory,
+                tx,
+            } => {
+                if self
+                    .services
+                    .iter()
+                    .any(|srv| srv.name(srv.token()) == name)
+                {
+                    let _ = tx.send(Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("Service \"{}\" already exists", name),
+                    )));
+                    return;
+                }
+
+                // retrying would block the server
+                let sockets = match bind_addr(&addrs[..], self.bind_opts.without_retry()) {
+                    Ok(sockets) => sockets,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                };
+
+                // resolve all addresses before services are created, so nothing has to be
+                // rolled back on failure
+                let local_addrs = sockets
+                    .iter()
+                    .map(|lst| lst.local_addr())
+                    .collect::<io::Result<Vec<_>>>();
+                let local_addrs = match local_addrs {
+                    Ok(addrs) => addrs,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                };
+
+                let mut listeners = Vec::new();
+                let mut created = Vec::new();
+
+                for (lst, addr) in sockets.into_iter().zip(local_addrs) {
+                    let token = self.next_token();
+                    let mut service =
+                        factory.create(name.clone(), token, ListenerAddr::Tcp(addr));
+                    self.configure_service(&mut service);
+
+                    created.extend(
+                        self.handles
+                            .iter()
+                            .map(|(_, handle)| handle.add_service(service.clone_factory())),
+                    );
+
+                    self.services.push(service);
+
+                    let lsts = self.loop_listeners(&name, MioListener::Tcp(lst));
+                    let num = lsts.len();
+                    for (i, lst) in lsts.into_iter().enumerate() {
+                        listeners.push((i, self.socket_info(token, &name, lst, num)));
+                    }
+                }
+
+                let wakers = self.accept.wakers();
+                let server = self.server.clone();
+
+                rt::spawn(async move {
+                    // a worker that went away has its services created on restart
+                    let ok = join_all(created)
+                        .await
+                        .into_iter()
+                        .all(|res| res.unwrap_or(true));
+
+                    if ok {
+                        for (i, info) in listeners {
+                            wakers[i].wake(WakerInterest::AddListener(Box::new(info)));
+                        }
+                        let _ = tx.send(Ok(()));
+                    } else {
+                        // roll back services created on other workers
+                        let _ = server.unbind(&name).await;
+                        // `io::Error::other` is only stable since Rust 1.74 (MSRV is 1.46)
+                        #[allow(clippy::io_other_error)]
+                        let _ = tx.send(Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("Can not start \"{}\" service on all workers", name),
+                        )));
+                    }
+                });
+            }
+            ServerCommand::Unbind { name, tx } => {
+                let mut tokens = Vec::new();
+
+                self.services.retain(|srv| {
+                    let token = srv.token();
+                    if srv.name(token) == name {
+                        tokens.push(token);
+                        false
+                    } else {
+                        true
+                    }
+                });
+
+                if tokens.is_empty() {
+                    let _ = tx.send(Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Service \"{}\" not found", name),
+                    )));
+                    return;
+                }
+
+                for token in tokens {
+                    self.accept
+                        .wake_all(|| WakerInterest::RemoveListener(token));
+                    self.handles
+                        .iter()
+                        .for_each(|(_, handle)| handle.remove_service(token));
+                    self.close.remove_counters(|_, t| t == token);
+                    #[cfg(unix)]
+                    self.unlink_uds(|t| t == token);
+                }
+
+                info!("Removed \"{}\" service", name);
+                let _ = tx.send(Ok(()));
+            }
+            ServerCommand::Ready(tx) => match self.started {
+                Some(started) => {
+                    let _ = tx.send(startup_result(started));
+                }
+                None if self.stopping => {}
+                None => self.ready.push(tx),
+            },
+            ServerCommand::Started(started) => {
+                if started {
+                    info!("Server is ready");
+                } else {
+                    error!("Server failed to start");
+                }
+
+                self.started = Some(started);
+                for tx in self.ready.drain(..) {
+                    let _ = tx.send(startup_result(started));
+                }
+            }
+            ServerCommand::PauseListener { name, paused, tx } => {
+                let tokens = self
+                    .services
+                    .iter()
+                    .filter(|srv| srv.name(srv.token()) == name)
+                    .map(|srv| srv.token())
+                    .collect::<Vec<_>>();
+
+                if tokens.is_empty() {
+                    let _ = tx.send(Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Service \"{}\" not found", name),
+                    )));
+                    return;
+                }
+
+                for token in tokens {
+                    self.accept.wake_all(|| {
+                        if paused {
+                            WakerInterest::PauseListener(token)
+                        } else {
+                            WakerInterest::ResumeListener(token)
+                        }
+                    });
+                }
+
+                if paused {
+                    info!("Paused \"{}\" service", name);
+                } else {
+                    info!("Resumed \"{}\" service", name);
+                }
+                let _ = tx.send(Ok(()));
+            }
+            ServerCommand::MaxConn(num, tx) => {
+                info!("Setting max connections per worker to {}", num);
+                self.worker_config.max_concurrent_connections(num);
+                self.handles
+                    .iter()
+                    .for_each(|(_, handle)| handle.max_concurrent_connections(num));
+                let _ = tx.send(());
+            }
+            ServerCommand::ShutdownTimeout(dur, tx) => {
+                self.worker_config.shutdown_timeout(dur);
+                self.handles
+                    .iter()
+                    .for_each(|(_, handle)| handle.shutdown_timeout(dur));
+                let _ = tx.send(());
+            }
+            ServerCommand::RestartWorker { idx, graceful, tx } => {
+                let pos = match self.handles.iter().position(|(i, _)| *i == idx) {
+                    Some(pos) => pos,
+                    None => {
+                        let _ = tx.send(Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("Worker {} not found", idx),
+                        )));
+                        return;
+                    }
+                };
+
+                info!("Restarting worker {}", idx);
+                let rx = self.replace_worker(pos, graceful);
+
+                rt::spawn(async move {
+                    let _ = rx.await;
+                    let _ = tx.send(Ok(()));
+                });
+            }
+            ServerCommand::WorkerUnresponsive(idx) => {
+                if self.stopping {
+                    return;
+                }
+
+                // the unresponsive worker is not waited for; its thread may never finish
+                if let Some(pos) = self.handles.iter().position(|(i, _)| *i == idx) {
+                    drop(self.replace_worker(pos, false));
+                }
+            }
+            ServerCommand::HandOff {
+                name,
+                io,
+                worker,
+                tx,
+            } => {
+                let tokens = self
+                    .services
+                    .iter()
+                    .filter(|srv| srv.name(srv.token()) == name)
+                    .map(|srv| srv.token())
+                    .collect::<Vec<_>>();
+
+                if tokens.is_empty() {
+                    let _ = tx.send(Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Service \"{}\" not found", name),
+                    )));
+                    return;
+                }
+
+                if let Some(idx) = worker {
+                    if !self.handles.iter().any(|(i, _)| *i == idx) {
+                        let _ = tx.send(Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("Worker {} not found", idx),
+                        )));
+                        return;
+                    }
+                }
+
+                let interest = WakerInterest::HandOff(Box::new(HandOff {
+                    io,
+                    tokens,
+                    worker,
+                    tx,
+                }));
+                match worker {
+                    Some(idx) => self.accept.wake_worker(idx, interest),
+                    None => self.accept.wake(interest),
+                }
+            }
             ServerCommand::WorkerFaulted(idx) => {
                 let mut found = false;
                 for i in 0..self.handles.len() {
@@ -386,27 +2384,190 @@ impl ServerBuilder {
                     }
                 }
 
-                if found {
-                    error!("Worker has died {:?}, restarting", idx);
+                if !found || self.stopping {
+                    return;
+                }
+
+                let history = self.faults.entry(idx).or_default();
+                let fault = self.restart.fault(idx, history);
+
+                if let Some(ref callback) = self.on_worker_faulted {
+                    callback(&fault);
+                }
+
+                match fault.restart_delay() {
+                    None => {
+                        error!(
+                            "Worker {} has died {} times, stopping server",
+                            idx,
+                            fault.faults()
+                        );
+                        self.handle_cmd(ServerCommand::Stop {
+                            graceful: true,
+                            timeout: None,
+                            completion: None,
+                        });
+                    }
+                    Some(delay) if delay == Duration::from_secs(0) => {
+                        error!("Worker has died {:?}, restarting", idx);
+                        self.handle_cmd(ServerCommand::StartWorker(idx));
+                    }
+                    Some(delay) => {
+                        error!("Worker has died {:?}, restarting in {:?}", idx, delay);
+                        let server = self.server.clone();
+                        rt::spawn(async move {
+                            sleep(delay).await;
+                            server.start_worker(idx);
+                        });
+                    }
+                }
+            }
+            ServerCommand::StartWorker(idx) => {
+                // worker may have been retired by the autoscaler while waiting for restart
+                if self.stopping || idx >= self.threads + self.scaled {
+                    return;
+                }
+
+                // replacement takes over index (and CPU core) of the dead worker
+                *self.restarts.entry(idx).or_default() += 1;
+                let (handle_accept, handle_server) = self.start_worker(idx, None);
+                self.handles.push((idx, handle_server));
+                self.accept
+                    .wake_worker(idx, WakerInterest::Worker(handle_accept));
+            }
+            ServerCommand::Autoscale => {
+                if self.stopping {
+                    return;
+                }
+
+                let autoscaler = match self.autoscaler {
+                    Some(ref mut autoscaler) => autoscaler,
+                    None => return,
+                };
+
+                let conns = self.close.open_connections();
+                let workers = self.threads + self.scaled;
+                let maxconn = self.worker_config.max_concurrent_connections_limit();
+
+                match autoscaler.sample(Instant::now(), conns, workers, maxconn) {
+                    Some(Scale::Up) => {
+                        let idx = workers;
+                        info!("Load is high, starting worker {}", idx);
 
-                    let mut new_idx = self.handles.len();
-                    'found: loop {
-                        for i in 0..self.handles.len() {
-                            if self.handles[i].0 == new_idx {
-                                new_idx += 1;
-                                continue 'found;
-                            }
+                        self.scaled += 1;
+                        let (handle_accept, handle_server) = self.start_worker(idx, None);
+                        self.handles.push((idx, handle_server));
+                        self.accept
+                            .wake_worker(idx, WakerInterest::Worker(handle_accept));
+                    }
+                    Some(Scale::Down) => {
+                        let idx = workers - 1;
+                        info!("Load is low, stopping worker {}", idx);
+
+                        self.scaled -= 1;
+                        self.faults.remove(&idx);
+                        self.restarts.remove(&idx);
+                        self.close.remove_counters(|worker, _| worker == idx);
+
+                        // worker that faulted is not restarted
+                        if let Some(pos) = self.handles.iter().position(|(i, _)| *i == idx) {
+                            let (_, old) = self.handles.swap_remove(pos);
+                            let (stop, _) = Stop::new(true, None);
+                            let stop = stop.redispatch();
+                            self.accept
+                                .wake_worker(idx, WakerInterest::RetireWorker { old, stop });
                         }
-                        break;
                     }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// Listener of one of `num` accept loops accepting on the same address.
+    fn socket_info(
+        &self,
+        token: usize,
+        name: &str,
+        lst: MioListener,
+        num: usize,
+    ) -> ServerSocketInfo {
+        let mut info = ServerSocketInfo::new(token, name, lst);
+        let opts = match self.listener_opts.get(name) {
+            Some(opts) => opts,
+            None => return info,
+        };
+
+        if let Some(ref tcp) = opts.tcp {
+            info = info.tcp_options(name, tcp);
+        }
+
+        if let Some(headroom) = opts.fd_headroom {
+            info = info.fd_headroom(name, headroom);
+        }
+
+        // accept rate is split between accept loops
+        match opts.accept_rate {
+            Some(rate) => info.max_accept_rate(name, cmp::max(rate / num as u32, 1)),
+            None => info,
+        }
+    }
+
+    /// Apply options of the listener the service was created for and of all connections.
+    fn configure_service(&self, service: &mut Box<dyn InternalServiceFactory>) {
+        if let Some(opts) = self.listener_opts.get(service.name(service.token())) {
+            if let Some(age) = opts.max_connection_age {
+                service.set_max_connection_age(age);
+            }
+            if let Some(ref labels) = opts.labels {
+                service.set_labels(labels.clone());
+            }
+        }
+        if let Some(timeout) = self.idle_timeout {
+            service.set_idle_timeout(timeout);
+        }
+    }
+
+    /// Options of listeners with the given name, created if none were set yet.
+    fn listener_opts(&mut self, name: &str) -> &mut ListenerOptions {
+        self.listener_opts.entry(name.to_string()).or_default()
+    }
+
+    /// Listeners of the accept loops for `lst`, the first one being `lst` itself.
+    ///
+    /// A TCP listener with `SO_REUSEPORT` set gets another socket bound to its address for every
+    /// other accept loop. Other TCP and unix domain listeners are shared by all accept loops and
+    /// the remaining ones are only accepted on by the first accept loop.
+    fn loop_listeners(&self, name: &str, lst: MioListener) -> Vec<MioListener> {
+        let mut lsts = Vec::new();
+
+        for _ in 1..self.accept.loops() {
+            let res = match lst {
+                #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+                MioListener::Tcp(ref tcp) if reuses_port(&lst) => {
+                    Some(bind_reuse_port(tcp, self.bind_opts).map(MioListener::Tcp))
+                }
+                _ => lst.try_clone(),
+            };
 
-                    let (handle_accept, handle_server) =
-                        self.start_worker(new_idx, self.accept.waker_owned());
-                    self.handles.push((new_idx, handle_server));
-                    self.accept.wake(WakerInterest::Worker(handle_accept));
+            match res {
+                Some(Ok(lst)) => lsts.push(lst),
+                Some(Err(e)) => {
+                    error!(
+                        "Can not add \"{}\" service on {} to accept loop {}: {}",
+                        name,
+                        lst,
+                        lsts.len() + 1,
+                        e
+                    );
+                    break;
                 }
+                None => break,
             }
         }
+
+        lsts.insert(0, lst);
+        lsts
     }
 
     fn next_token(&mut self) -> usize {
@@ -414,6 +2575,45 @@ impl ServerBuilder {
         self.token += 1;
         token
     }
+
+    /// Remove socket files of unix domain listeners with matching tokens.
+    #[cfg(unix)]
+    fn unlink_uds(&mut self, matches: impl Fn(usize) -> bool) {
+        self.uds_paths.retain(|(token, path)| {
+            if !matches(*token) {
+                return true;
+            }
+
+            if let Err(e) = crate::uds::remove_uds_path(path) {
+                error!("Can not remove socket file {:?}: {}", path, e);
+            }
+            false
+        });
+    }
+
+    /// Listener tokens of all services with their names.
+    fn service_names(&self) -> Vec<(usize, String)> {
+        self.services
+            .iter()
+            .map(|srv| (srv.token(), srv.name(srv.token()).to_owned()))
+            .collect()
+    }
+
+    /// Status of each service without connections, with their listener tokens.
+    fn service_status(&self) -> Vec<(usize, ServiceStatus)> {
+        self.services
+            .iter()
+            .map(|srv| {
+                let token = srv.token();
+                let status = ServiceStatus::new(
+                    srv.name(token).to_owned(),
+                    srv.addr().clone(),
+                    srv.labels(token).clone(),
+                );
+                (token, status)
+            })
+            .collect()
+    }
 }
 
 impl Future for ServerBuilder {
@@ -429,15 +2629,154 @@ impl Future for ServerBuilder {
     }
 }
 
+fn startup_result(started: bool) -> io::Result<()> {
+    if started {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "Server failed to start all workers",
+        ))
+    }
+}
+
+/// Socket file of a listener bound at `path` that is removed when the server stops.
+#[cfg(unix)]
+fn unlink_path(path: &std::path::Path, opts: &UdsOptions) -> Option<PathBuf> {
+    if opts.unlinks_on_stop() && !crate::uds::is_abstract(path) {
+        Some(path.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Returns true for TCP listeners with `SO_REUSEPORT` set.
+fn reuses_port(lst: &MioListener) -> bool {
+    match *lst {
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        MioListener::Tcp(ref lst) => socket2::SockRef::from(lst).reuse_port().unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Bind another listener with `SO_REUSEPORT` set to the address of `lst`.
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+fn bind_reuse_port(lst: &MioTcpListener, opts: BindOptions) -> io::Result<MioTcpListener> {
+    let addr = lst.local_addr()?;
+    let ipv6_only = match addr {
+        StdSocketAddr::V4(_) => None,
+        StdSocketAddr::V6(_) => Some(socket2::SockRef::from(lst).only_v6()?),
+    };
+
+    try_create_tcp_listener(
+        addr,
+        BindOptions {
+            reuse_port: true,
+            ipv6_only,
+            ..opts
+        },
+    )
+}
+
+/// Options set for listeners with the same name, applied to those bound before and after.
+#[derive(Default)]
+struct ListenerOptions {
+    accept_rate: Option<u32>,
+    tcp: Option<TcpOptions>,
+    fd_headroom: Option<usize>,
+    max_connection_age: Option<Duration>,
+    labels: Option<ServiceLabels>,
+}
+
+/// Options applied to TCP listeners created by [`ServerBuilder`].
+#[derive(Clone, Copy)]
+pub(crate) struct BindOptions {
+    backlog: u32,
+    reuse_port: bool,
+    ipv6_only: Option<bool>,
+    incoming_cpu: Option<usize>,
+    mptcp: bool,
+    /// Number of retries and delay between them when the address is in use.
+    retry: Option<(u32, Duration)>,
+    /// Hook setting socket options not covered by the fields above.
+    socket_options: Option<fn(&socket2::Socket) -> io::Result<()>>,
+    policy: BindPolicy,
+}
+
+impl BindOptions {
+    /// Options for binding without blocking the current thread to retry.
+    pub(crate) fn without_retry(self) -> Self {
+        Self {
+            retry: None,
+            ..self
+        }
+    }
+}
+
 pub(super) fn bind_addr<S: ToSocketAddrs>(
     addr: S,
-    backlog: u32,
+    opts: BindOptions,
 ) -> io::Result<Vec<MioTcpListener>> {
+    let mut addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
+    let has_v6 = addrs.iter().any(StdSocketAddr::is_ipv6);
+
+    let opts = match opts.policy {
+        BindPolicy::All => opts,
+        BindPolicy::Ipv6Only if !has_v6 => {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "Address does not resolve to an IPv6 address.",
+            ))
+        }
+        BindPolicy::Ipv6Only => {
+            addrs.retain(StdSocketAddr::is_ipv6);
+
+            BindOptions {
+                ipv6_only: Some(true),
+                ..opts
+            }
+        }
+        BindPolicy::DualStack => {
+            let resolved = addrs.clone();
+            addrs.retain(|addr| !dual_stack_covers(&resolved, addr));
+
+            BindOptions {
+                ipv6_only: Some(false),
+                ..opts
+            }
+        }
+    };
+
+    bind_each(&addrs[..], |addr| create_tcp_listener(addr, opts))
+}
+
+/// Returns true if `addr` is an IPv4 address whose connections are accepted by a dual-stack
+/// listener bound to one of `addrs` with the same port, i.e. `[::]` or its IPv4-mapped address.
+fn dual_stack_covers(addrs: &[StdSocketAddr], addr: &StdSocketAddr) -> bool {
+    let v4 = match *addr {
+        StdSocketAddr::V4(ref v4) => v4,
+        StdSocketAddr::V6(_) => return false,
+    };
+
+    addrs.iter().any(|addr| match *addr {
+        StdSocketAddr::V6(ref v6) if v6.port() == v4.port() => {
+            v6.ip().is_unspecified() || *v6.ip() == v4.ip().to_ipv6_mapped()
+        }
+        _ => false,
+    })
+}
+
+/// Bind every address `addr` resolves to, succeeding if at least one of them could be bound.
+fn bind_each<S, T, F>(addr: S, mut bind: F) -> io::Result<Vec<T>>
+where
+    S: ToSocketAddrs,
+    F: FnMut(StdSocketAddr) -> io::Result<T>,
+{
     let mut err = None;
     let mut succ = false;
     let mut sockets = Vec::new();
     for addr in addr.to_socket_addrs()? {
-        match create_tcp_listener(addr, backlog) {
+        match bind(addr) {
             Ok(lst) => {
                 succ = true;
                 sockets.push(lst);
@@ -460,13 +2799,109 @@ pub(super) fn bind_addr<S: ToSocketAddrs>(
     }
 }
 
-fn create_tcp_listener(addr: StdSocketAddr, backlog: u32) -> io::Result<MioTcpListener> {
-    let socket = match addr {
-        StdSocketAddr::V4(_) => MioTcpSocket::new_v4()?,
-        StdSocketAddr::V6(_) => MioTcpSocket::new_v6()?,
+pub(crate) fn create_tcp_listener(
+    addr: StdSocketAddr,
+    opts: BindOptions,
+) -> io::Result<MioTcpListener> {
+    let (mut retries, delay) = opts.retry.unwrap_or((0, Duration::from_secs(0)));
+
+    loop {
+        match try_create_tcp_listener(addr, opts) {
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse && retries > 0 => {
+                warn!("Address {} is in use, retrying in {:?}", addr, delay);
+                retries -= 1;
+                std::thread::sleep(delay);
+            }
+            res => return res,
+        }
+    }
+}
+
+fn try_create_tcp_listener(
+    addr: StdSocketAddr,
+    opts: BindOptions,
+) -> io::Result<MioTcpListener> {
+    #[cfg(target_os = "linux")]
+    let socket = if opts.mptcp {
+        new_mptcp_socket(addr)?
+    } else {
+        new_tcp_socket(addr)?
     };
+    #[cfg(not(target_os = "linux"))]
+    let socket = new_tcp_socket(addr)?;
 
     socket.set_reuseaddr(true)?;
+
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    if opts.reuse_port {
+        socket.set_reuseport(true)?;
+    }
+
+    if let (StdSocketAddr::V6(_), Some(only_v6)) = (addr, opts.ipv6_only) {
+        socket2::SockRef::from(&socket).set_only_v6(only_v6)?;
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(cpu) = opts.incoming_cpu {
+        socket2::SockRef::from(&socket).set_cpu_affinity(cpu)?;
+    }
+
+    if let Some(hook) = opts.socket_options {
+        hook(&socket2::SockRef::from(&socket))?;
+    }
+
     socket.bind(addr)?;
-    socket.listen(backlog)
+    socket.listen(opts.backlog)
+}
+
+fn new_tcp_socket(addr: StdSocketAddr) -> io::Result<MioTcpSocket> {
+    match addr {
+        StdSocketAddr::V4(_) => MioTcpSocket::new_v4(),
+        StdSocketAddr::V6(_) => MioTcpSocket::new_v6(),
+    }
+}
+
+/// Create MPTCP socket, falling back to TCP where the kernel does not support MPTCP or has it
+/// disabled.
+#[cfg(target_os = "linux")]
+fn new_mptcp_socket(addr: StdSocketAddr) -> io::Result<MioTcpSocket> {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    // not defined by all supported versions of libc
+    const IPPROTO_MPTCP: libc::c_int = 262;
+
+    let socket = match Socket::new(
+        Domain::for_address(addr),
+        Type::STREAM,
+        Some(Protocol::from(IPPROTO_MPTCP)),
+    ) {
+        Ok(socket) => socket,
+        Err(ref e)
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::EPROTONOSUPPORT) | Some(libc::ENOPROTOOPT) | Some(libc::EINVAL)
+            ) =>
+        {
+            warn!("MPTCP is not available, binding {} with TCP: {}", addr, e);
+            return new_tcp_socket(addr);
+        }
+        Err(e) => return Err(e),
+    };
+    socket.set_nonblocking(true)?;
+
+    // MPTCP sockets are used like TCP sockets; accepted streams are `TcpStream`s
+    Ok(unsafe { MioTcpSocket::from_raw_fd(socket.into_raw_fd()) })
+}
+
+#[cfg(unix)]
+fn not_a_listener(name: &str, kind: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "Socket \"{}\" passed by service manager is not a {} listener",
+            name, kind
+        ),
+    )
 }
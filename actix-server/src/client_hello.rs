@@ -0,0 +1,313 @@
+use std::{io, time::Duration};
+
+use actix_rt::{net::TcpStream, time::sleep};
+
+/// Number of bytes peeked to read a ClientHello; a TLS record header and the largest record.
+pub(crate) const MAX_PEEK_LEN: usize = 5 + 16 * 1024;
+
+/// Time between peeks while waiting for the rest of a ClientHello.
+const PEEK_INTERVAL: Duration = Duration::from_millis(5);
+
+const CONTENT_HANDSHAKE: u8 = 0x16;
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+const EXT_SERVER_NAME: u16 = 0;
+const EXT_ALPN: u16 = 16;
+
+/// Server name and ALPN protocols of a TLS ClientHello, read without consuming the stream.
+///
+/// Allows routing TLS connections to different services before (or without) terminating TLS.
+/// Use [`Mux::tls_service`](crate::Mux::tls_service) to pick a service on a shared listener or
+/// [`ClientHello::peek`] in a service of its own. The stream is left untouched, so the service
+/// handling it performs the handshake as usual or forwards the encrypted bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHello {
+    server_name: Option<String>,
+    alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl ClientHello {
+    /// Parse a ClientHello from the start of a TLS connection.
+    ///
+    /// Returns `None` if `buf` does not start with a complete ClientHello.
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        parse(buf).ok()
+    }
+
+    /// Wait for the ClientHello of a TLS connection and parse it, leaving the data in the stream.
+    ///
+    /// Returns an `InvalidData` error if the client sent something else, and an
+    /// `UnexpectedEof` error if it closed the connection before completing the ClientHello.
+    /// Apply a timeout as needed; clients may never send data.
+    pub async fn peek(io: &TcpStream) -> io::Result<Self> {
+        let mut buf = vec![0; MAX_PEEK_LEN];
+        let len = peek(io, &mut buf).await?;
+
+        match parse(&buf[..len]) {
+            Ok(hello) => Ok(hello),
+            Err(ParseError::Incomplete) if len < buf.len() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before receiving complete ClientHello",
+            )),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid TLS ClientHello",
+            )),
+        }
+    }
+
+    /// Returns host name sent with the server name indication (SNI) extension.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Returns protocols offered with the ALPN extension, in client preference order.
+    pub fn alpn_protocols(&self) -> impl Iterator<Item = &[u8]> {
+        self.alpn_protocols.iter().map(|proto| proto.as_slice())
+    }
+
+    /// Check if `proto` is one of the protocols offered with the ALPN extension.
+    pub fn offers_alpn(&self, proto: &[u8]) -> bool {
+        self.alpn_protocols().any(|p| p == proto)
+    }
+}
+
+/// Peek into `buf` until it holds a complete ClientHello, or until it is known that it will not.
+///
+/// Returns number of bytes peeked; 0 if the client closed the connection without sending data.
+pub(crate) async fn peek(io: &TcpStream, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let len = io.peek(buf).await?;
+
+        // data stays readable until consumed, so wait a little before peeking again
+        match parse(&buf[..len]) {
+            Err(ParseError::Incomplete) if len > 0 && len < buf.len() => {
+                sleep(PEEK_INTERVAL).await
+            }
+            _ => return Ok(len),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    /// More data is needed.
+    Incomplete,
+    /// Data is not a TLS ClientHello.
+    Invalid,
+}
+
+/// Cursor over a complete handshake message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        if self.0.len() < len {
+            return Err(ParseError::Invalid);
+        }
+
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ParseError> {
+        let buf = self.take(2)?;
+        Ok(u16::from_be_bytes([buf[0], buf[1]]))
+    }
+
+    /// Take bytes prefixed with their 1 or 2 byte length.
+    fn vec8(&mut self) -> Result<&'a [u8], ParseError> {
+        let len = self.u8()?;
+        self.take(usize::from(len))
+    }
+
+    fn vec16(&mut self) -> Result<&'a [u8], ParseError> {
+        let len = self.u16()?;
+        self.take(usize::from(len))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn parse(buf: &[u8]) -> Result<ClientHello, ParseError> {
+    let msg = handshake_message(buf)?;
+    let mut msg = Reader(&msg);
+
+    // legacy version, random, session id, cipher suites, compression methods
+    msg.take(2 + 32)?;
+    msg.vec8()?;
+    msg.vec16()?;
+    msg.vec8()?;
+
+    let mut hello = ClientHello::default();
+
+    // extensions are optional
+    if msg.is_empty() {
+        return Ok(hello);
+    }
+
+    let mut exts = Reader(msg.vec16()?);
+    while !exts.is_empty() {
+        let typ = exts.u16()?;
+        let mut data = Reader(exts.vec16()?);
+
+        match typ {
+            EXT_SERVER_NAME => {
+                let mut names = Reader(data.vec16()?);
+                while !names.is_empty() {
+                    let name_type = names.u8()?;
+                    let name = names.vec16()?;
+
+                    // host_name
+                    if name_type == 0 {
+                        let name =
+                            std::str::from_utf8(name).map_err(|_| ParseError::Invalid)?;
+                        hello.server_name = Some(name.to_owned());
+                    }
+                }
+            }
+            EXT_ALPN => {
+                let mut protos = Reader(data.vec16()?);
+                while !protos.is_empty() {
+                    hello.alpn_protocols.push(protos.vec8()?.to_vec());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(hello)
+}
+
+/// Collect ClientHello handshake message, without its header, from one or more TLS records.
+fn handshake_message(mut buf: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let mut msg = Vec::new();
+
+    loop {
+        // content type, legacy version and length
+        if buf.len() < 5 {
+            return Err(match buf.first() {
+                Some(typ) if *typ != CONTENT_HANDSHAKE => ParseError::Invalid,
+                _ => ParseError::Incomplete,
+            });
+        }
+        if buf[0] != CONTENT_HANDSHAKE || buf[1] != 0x03 {
+            return Err(ParseError::Invalid);
+        }
+
+        let len = usize::from(u16::from_be_bytes([buf[3], buf[4]]));
+        if buf.len() < 5 + len {
+            return Err(ParseError::Incomplete);
+        }
+
+        msg.extend_from_slice(&buf[5..5 + len]);
+        buf = &buf[5 + len..];
+
+        if msg.len() >= 4 {
+            if msg[0] != HANDSHAKE_CLIENT_HELLO {
+                return Err(ParseError::Invalid);
+            }
+
+            let msg_len =
+                usize::from(msg[1]) << 16 | usize::from(msg[2]) << 8 | usize::from(msg[3]);
+            if msg.len() >= 4 + msg_len {
+                msg.truncate(4 + msg_len);
+                msg.drain(..4);
+                return Ok(msg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a ClientHello record with the given server name and ALPN protocols.
+    fn client_hello(server_name: Option<&str>, alpn: &[&[u8]]) -> Vec<u8> {
+        fn vec16(data: &[u8]) -> Vec<u8> {
+            let mut buf = (data.len() as u16).to_be_bytes().to_vec();
+            buf.extend_from_slice(data);
+            buf
+        }
+
+        let mut exts = Vec::new();
+        if let Some(name) = server_name {
+            let mut entry = vec![0];
+            entry.extend(vec16(name.as_bytes()));
+            exts.extend(&EXT_SERVER_NAME.to_be_bytes());
+            exts.extend(vec16(&vec16(&entry)));
+        }
+        if !alpn.is_empty() {
+            let mut list = Vec::new();
+            for proto in alpn {
+                list.push(proto.len() as u8);
+                list.extend_from_slice(proto);
+            }
+            exts.extend(&EXT_ALPN.to_be_bytes());
+            exts.extend(vec16(&vec16(&list)));
+        }
+
+        let mut body = vec![0x03, 0x03];
+        body.extend(&[0; 32]);
+        body.push(0);
+        body.extend(vec16(&[0x13, 0x01]));
+        body.extend(&[1, 0]);
+        body.extend(vec16(&exts));
+
+        let mut msg = vec![HANDSHAKE_CLIENT_HELLO, 0];
+        msg.extend(vec16(&body));
+
+        let mut record = vec![CONTENT_HANDSHAKE, 0x03, 0x01];
+        record.extend(vec16(&msg));
+        record
+    }
+
+    #[test]
+    fn parse_client_hello() {
+        let buf = client_hello(Some("example.com"), &[b"h2", b"http/1.1"]);
+        let hello = ClientHello::parse(&buf).unwrap();
+        assert_eq!(hello.server_name(), Some("example.com"));
+        assert_eq!(
+            hello.alpn_protocols().collect::<Vec<_>>(),
+            vec![&b"h2"[..], &b"http/1.1"[..]]
+        );
+        assert!(hello.offers_alpn(b"h2"));
+        assert!(!hello.offers_alpn(b"h3"));
+
+        let hello = ClientHello::parse(&client_hello(None, &[])).unwrap();
+        assert_eq!(hello, ClientHello::default());
+
+        // every truncation is incomplete, not invalid
+        for len in 0..buf.len() {
+            assert_eq!(parse(&buf[..len]).unwrap_err(), ParseError::Incomplete);
+        }
+
+        assert_eq!(parse(b"GET / HTTP/1.1").unwrap_err(), ParseError::Invalid);
+        assert_eq!(parse(b"G").unwrap_err(), ParseError::Invalid);
+    }
+
+    #[test]
+    fn parse_fragmented_client_hello() {
+        let record = client_hello(Some("example.com"), &[]);
+
+        // split handshake message over two records
+        let (msg1, msg2) = record[5..].split_at(10);
+        let mut buf = Vec::new();
+        for frag in &[msg1, msg2] {
+            buf.extend(&[CONTENT_HANDSHAKE, 0x03, 0x01]);
+            buf.extend(&(frag.len() as u16).to_be_bytes());
+            buf.extend_from_slice(frag);
+        }
+
+        let hello = ClientHello::parse(&buf).unwrap();
+        assert_eq!(hello.server_name(), Some("example.com"));
+        assert_eq!(parse(&buf[..20]).unwrap_err(), ParseError::Incomplete);
+    }
+}
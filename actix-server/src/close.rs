@@ -0,0 +1,398 @@
+use std::{
+    any::Any,
+    fmt, io, mem,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use actix_rt::time::sleep;
+
+use tokio::sync::watch;
+
+use crate::drain::DrainCounts;
+use crate::latency::{LatencyCounters, ServiceLatency};
+use crate::worker_status::ServiceStatus;
+
+/// Reason a connection handled by the server ended.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CloseReason {
+    /// Service future completed successfully.
+    Completed,
+
+    /// Service future returned an error.
+    ServiceError,
+
+    /// Service future returned an I/O error signaling that the peer reset or aborted the
+    /// connection.
+    PeerReset,
+
+    /// Connection was still being served when its worker shut down; either graceful shutdown
+    /// timed out or the server was stopped forcefully.
+    Shutdown,
+
+    /// Connection was dropped before reaching the service, e.g. because its listener was removed
+    /// after the connection was accepted or it did not start with a valid PROXY protocol header.
+    Rejected,
+
+    /// Connection had no activity for the time set with
+    /// [`ServerBuilder::client_idle_timeout`](crate::ServerBuilder::client_idle_timeout).
+    IdleTimeout,
+}
+
+impl CloseReason {
+    const ALL: [CloseReason; 6] = [
+        CloseReason::Completed,
+        CloseReason::ServiceError,
+        CloseReason::PeerReset,
+        CloseReason::Shutdown,
+        CloseReason::Rejected,
+        CloseReason::IdleTimeout,
+    ];
+
+    /// Classify outcome of a service future.
+    pub(crate) fn from_result<T, E: 'static>(res: &Result<T, E>) -> Self {
+        match res {
+            Ok(_) => CloseReason::Completed,
+            Err(err) => match (err as &dyn Any).downcast_ref::<io::Error>() {
+                Some(err) if is_peer_reset(err) => CloseReason::PeerReset,
+                _ => CloseReason::ServiceError,
+            },
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            CloseReason::Completed => 0,
+            CloseReason::ServiceError => 1,
+            CloseReason::PeerReset => 2,
+            CloseReason::Shutdown => 3,
+            CloseReason::Rejected => 4,
+            CloseReason::IdleTimeout => 5,
+        }
+    }
+}
+
+fn is_peer_reset(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// Number of closed connections by [`CloseReason`], aggregated over all workers.
+///
+/// Returned by [`Server::close_stats`](crate::Server::close_stats).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CloseStats {
+    counts: [u64; 6],
+}
+
+impl CloseStats {
+    /// Returns number of connections closed for the given reason.
+    pub fn get(&self, reason: CloseReason) -> u64 {
+        self.counts[reason.index()]
+    }
+
+    /// Returns total number of closed connections.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns iterator over reasons and their number of closed connections.
+    pub fn iter(&self) -> impl Iterator<Item = (CloseReason, u64)> + '_ {
+        CloseReason::ALL
+            .iter()
+            .map(move |reason| (*reason, self.get(*reason)))
+    }
+}
+
+/// Interval at which the number of open connections is published once it has receivers.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+type CloseCallback = Box<dyn Fn(CloseReason) + Send + Sync>;
+
+/// Counters of a service on a worker, updated by the worker and read by the server.
+#[derive(Debug, Default)]
+pub(crate) struct ServiceCounters {
+    pub(crate) latency: LatencyCounters,
+    /// Number of open connections.
+    pub(crate) open: AtomicUsize,
+}
+
+/// Close reason counters shared by all workers.
+pub(crate) struct CloseTracker {
+    counts: [AtomicU64; 6],
+    callback: Option<CloseCallback>,
+    /// Number of open connections by worker index, counted by the worker.
+    conns: Mutex<Vec<(usize, Arc<AtomicUsize>)>>,
+    /// Sum of `conns`, published by the server thread with
+    /// [`publish_connections`](Self::publish_connections).
+    conns_tx: watch::Sender<usize>,
+    conns_rx: watch::Receiver<usize>,
+    /// Per listener token counts reported by workers that finished a graceful shutdown.
+    drain: Mutex<Vec<(usize, DrainCounts)>>,
+    /// Service counters by worker index and listener token.
+    services: Mutex<Vec<(usize, usize, Arc<ServiceCounters>)>>,
+}
+
+impl Default for CloseTracker {
+    fn default() -> Self {
+        let (conns_tx, conns_rx) = watch::channel(0);
+
+        Self {
+            counts: Default::default(),
+            callback: None,
+            conns: Default::default(),
+            conns_tx,
+            conns_rx,
+            drain: Default::default(),
+            services: Default::default(),
+        }
+    }
+}
+
+impl CloseTracker {
+    pub(crate) fn with_callback<F>(callback: F) -> Self
+    where
+        F: Fn(CloseReason) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Some(Box::new(callback)),
+            ..Default::default()
+        }
+    }
+
+    /// Get counter of open connections of a worker, keeping it across worker restarts so
+    /// connections still draining on a replaced worker stay counted.
+    pub(crate) fn worker_connections(&self, worker: usize) -> Arc<AtomicUsize> {
+        let mut conns = self.conns.lock().unwrap();
+
+        match conns.iter().find(|(w, _)| *w == worker) {
+            Some((_, open)) => open.clone(),
+            None => {
+                let open = Arc::new(AtomicUsize::new(0));
+                conns.push((worker, open.clone()));
+                open
+            }
+        }
+    }
+
+    /// Returns number of connections open on all workers.
+    pub(crate) fn open_connections(&self) -> usize {
+        self.conns
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, open)| open.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Publish number of open connections to receivers, if it changed since it was published
+    /// last.
+    pub(crate) fn publish_connections(&self) {
+        let conns = self.open_connections();
+
+        if *self.conns_tx.borrow() != conns {
+            let _ = self.conns_tx.send(conns);
+        }
+    }
+
+    /// Returns receiver of the number of open connections, published now.
+    pub(crate) fn connections(&self) -> watch::Receiver<usize> {
+        self.publish_connections();
+        self.conns_rx.clone()
+    }
+
+    /// Publish number of open connections periodically until the tracker is dropped.
+    pub(crate) async fn publish_periodically(tracker: Weak<Self>) {
+        loop {
+            sleep(PUBLISH_INTERVAL).await;
+
+            match tracker.upgrade() {
+                Some(tracker) => tracker.publish_connections(),
+                None => return,
+            }
+        }
+    }
+
+    pub(crate) fn record(&self, reason: CloseReason) {
+        self.counts[reason.index()].fetch_add(1, Ordering::Relaxed);
+
+        if let Some(ref callback) = self.callback {
+            callback(reason);
+        }
+    }
+
+    pub(crate) fn record_drain(&self, token: usize, counts: DrainCounts) {
+        self.drain.lock().unwrap().push((token, counts));
+    }
+
+    pub(crate) fn take_drain(&self) -> Vec<(usize, DrainCounts)> {
+        mem::take(&mut *self.drain.lock().unwrap())
+    }
+
+    /// Get counters of a service on a worker, keeping them across service and worker restarts.
+    pub(crate) fn counters(&self, worker: usize, token: usize) -> Arc<ServiceCounters> {
+        let mut services = self.services.lock().unwrap();
+
+        match services
+            .iter()
+            .find(|(w, t, _)| *w == worker && *t == token)
+        {
+            Some((_, _, counters)) => counters.clone(),
+            None => {
+                let counters = Arc::new(ServiceCounters::default());
+                services.push((worker, token, counters.clone()));
+                counters
+            }
+        }
+    }
+
+    /// Drop counters of services for which `remove` returns true given the worker index and
+    /// listener token, once their listener was removed or their worker retired.
+    pub(crate) fn remove_counters<F>(&self, remove: F)
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        self.services
+            .lock()
+            .unwrap()
+            .retain(|(worker, token, _)| !remove(*worker, *token));
+    }
+
+    /// Status of the given services on a worker, with their listener tokens.
+    pub(crate) fn service_status(
+        &self,
+        worker: usize,
+        services: &[(usize, ServiceStatus)],
+    ) -> Vec<ServiceStatus> {
+        let counters = self.services.lock().unwrap();
+
+        services
+            .iter()
+            .map(|(token, status)| {
+                let open = counters
+                    .iter()
+                    .find(|(w, t, _)| *w == worker && t == token)
+                    .map_or(0, |(_, _, counters)| counters.open.load(Ordering::Relaxed));
+                status.clone().with_connections(open)
+            })
+            .collect()
+    }
+
+    /// Snapshot latency of services whose listener token has a name, ordered by worker.
+    pub(crate) fn service_latency(&self, names: &[(usize, String)]) -> Vec<ServiceLatency> {
+        let mut latency = self
+            .services
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(worker, token, counters)| {
+                let (_, name) = names.iter().find(|(t, _)| t == token)?;
+                Some((
+                    *worker,
+                    *token,
+                    ServiceLatency::new(name.clone(), *worker, counters.latency.snapshot()),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        latency.sort_by_key(|(worker, token, _)| (*worker, *token));
+        latency.into_iter().map(|(_, _, latency)| latency).collect()
+    }
+
+    pub(crate) fn stats(&self) -> CloseStats {
+        let mut stats = CloseStats::default();
+
+        for (count, counter) in stats.counts.iter_mut().zip(self.counts.iter()) {
+            *count = counter.load(Ordering::Relaxed);
+        }
+
+        stats
+    }
+}
+
+impl fmt::Debug for CloseTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CloseTracker")
+            .field("stats", &self.stats())
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        assert_eq!(
+            CloseReason::from_result::<_, ()>(&Ok(())),
+            CloseReason::Completed
+        );
+        assert_eq!(
+            CloseReason::from_result::<(), _>(&Err("error")),
+            CloseReason::ServiceError
+        );
+
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert_eq!(
+            CloseReason::from_result::<(), _>(&Err(reset)),
+            CloseReason::PeerReset
+        );
+
+        let other = io::Error::from(io::ErrorKind::InvalidData);
+        assert_eq!(
+            CloseReason::from_result::<(), _>(&Err(other)),
+            CloseReason::ServiceError
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let tracker = CloseTracker::default();
+        tracker.record(CloseReason::Completed);
+        tracker.record(CloseReason::Completed);
+        tracker.record(CloseReason::Shutdown);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.get(CloseReason::Completed), 2);
+        assert_eq!(stats.get(CloseReason::Shutdown), 1);
+        assert_eq!(stats.get(CloseReason::Rejected), 0);
+        assert_eq!(stats.total(), 3);
+        assert_eq!(stats.iter().count(), 6);
+    }
+
+    #[test]
+    fn connections() {
+        let tracker = CloseTracker::default();
+        let worker0 = tracker.worker_connections(0);
+        let worker1 = tracker.worker_connections(1);
+        worker0.fetch_add(2, Ordering::Relaxed);
+        worker1.fetch_add(1, Ordering::Relaxed);
+
+        // counted right away, published lazily
+        assert_eq!(tracker.open_connections(), 3);
+        assert_eq!(*tracker.conns_rx.borrow(), 0);
+
+        let mut conns = tracker.connections();
+        assert_eq!(*conns.borrow_and_update(), 3);
+
+        tracker
+            .worker_connections(0)
+            .fetch_sub(1, Ordering::Relaxed);
+        tracker.publish_connections();
+        assert!(conns.has_changed().unwrap());
+        assert_eq!(*conns.borrow_and_update(), 2);
+
+        tracker.publish_connections();
+        assert!(!conns.has_changed().unwrap());
+    }
+}
@@ -0,0 +1,176 @@
+use std::{cell::RefCell, time::Duration};
+
+use actix_rt::time::{timeout_at, Instant};
+use tokio::sync::watch;
+
+tokio::task_local! {
+    static CLOSE_TOKEN: CloseToken;
+}
+
+thread_local! {
+    // `const` initializers are only stable since Rust 1.59 (MSRV is 1.46)
+    #[allow(clippy::missing_const_for_thread_local)]
+    static WORKER_TOKEN: RefCell<Option<CloseToken>> = RefCell::new(None);
+}
+
+/// Signal to close a connection gracefully.
+///
+/// The token is closing once the worker serving the connection started shutting down, before
+/// the [shutdown timeout](crate::ServerBuilder::shutdown_timeout) starts counting, or once the
+/// connection reached the maximum age set with
+/// [`ServerBuilder::max_connection_age`](crate::ServerBuilder::max_connection_age). The worker
+/// also shuts down if it stopped for other reasons, e.g. its services could not be created.
+///
+/// Services get the token of the connection they handle with [`current`](Self::current), from
+/// their `call` or the future it returns, and move clones of it into any tasks they spawn.
+/// Service factories get the token of their worker, which only closes on shutdown. Once the
+/// token is closing, the service should finish the connection at the next point its protocol
+/// allows, such as between requests, so clients re-dial while there is still time to do it
+/// cleanly and get balanced over all workers again. The server does not close the connection
+/// itself.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{CloseToken, Server};
+/// use actix_service::fn_service;
+///
+/// async fn serve(io: TcpStream) -> Result<(), ()> {
+///     let token = CloseToken::current().unwrap();
+///
+///     loop {
+///         // serve one request at a time from `io`
+/// #       break;
+///
+///         if token.is_closing() {
+///             break;
+///         }
+///     }
+///
+///     Ok(())
+/// }
+///
+/// # fn run() -> std::io::Result<()> {
+/// let server = Server::build()
+///     .max_connection_age("app", Duration::from_secs(600))
+///     .bind("app", "0.0.0.0:8080", || fn_service(serve))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CloseToken {
+    shutdown: watch::Receiver<bool>,
+    deadline: Option<Instant>,
+}
+
+impl CloseToken {
+    /// Create token of a worker and the sender triggering its shutdown.
+    pub(crate) fn new() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        let token = Self {
+            shutdown: rx,
+            deadline: None,
+        };
+        (tx, token)
+    }
+
+    /// Returns token of a connection accepted now, which also closes after `max_age`.
+    ///
+    /// Returns `None` outside of worker threads.
+    pub(crate) fn connection(max_age: Option<Duration>) -> Option<Self> {
+        let mut token = WORKER_TOKEN.with(|token| token.borrow().clone())?;
+        token.deadline = max_age.map(|age| Instant::now() + age);
+        Some(token)
+    }
+
+    /// Returns token of the connection handled by the current task or, outside of connection
+    /// tasks, of the worker running on the current thread.
+    ///
+    /// Returns `None` outside of worker threads.
+    pub fn current() -> Option<Self> {
+        CLOSE_TOKEN
+            .try_with(Clone::clone)
+            .ok()
+            .or_else(|| WORKER_TOKEN.with(|token| token.borrow().clone()))
+    }
+
+    /// Set token of the worker running on the current thread.
+    pub(crate) fn set_worker(self) {
+        WORKER_TOKEN.with(|token| *token.borrow_mut() = Some(self));
+    }
+
+    /// Returns true once the connection should be closed.
+    pub fn is_closing(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+            || *self.shutdown.borrow()
+            || self.shutdown.has_changed().is_err()
+    }
+
+    /// Wait until the connection should be closed.
+    pub async fn closing(&self) {
+        match self.deadline {
+            Some(deadline) => {
+                let _ = timeout_at(deadline, self.shutting_down()).await;
+            }
+            None => self.shutting_down().await,
+        }
+    }
+
+    async fn shutting_down(&self) {
+        let mut rx = self.shutdown.clone();
+        while !*rx.borrow_and_update() {
+            // worker is gone
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Run `f` with this token set as token of the current connection.
+    pub(crate) fn sync_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        CLOSE_TOKEN.sync_scope(self.clone(), f)
+    }
+
+    /// Set this token as token of the connection handled by `fut`.
+    pub(crate) async fn scope<F: std::future::Future>(self, fut: F) -> F::Output {
+        CLOSE_TOKEN.scope(self, fut).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn closing() {
+        let (tx, token) = CloseToken::new();
+        assert!(!token.is_closing());
+
+        let clone = token.clone();
+        let handle = actix_rt::spawn(async move { clone.closing().await });
+
+        tx.send(true).unwrap();
+        handle.await.unwrap();
+        assert!(token.is_closing());
+
+        let (tx, token) = CloseToken::new();
+        drop(tx);
+        assert!(token.is_closing());
+        token.closing().await;
+    }
+
+    #[actix_rt::test]
+    async fn closing_after_max_age() {
+        let (_tx, token) = CloseToken::new();
+        assert!(CloseToken::connection(None).is_none());
+
+        token.set_worker();
+        let token = CloseToken::connection(Some(Duration::from_millis(50))).unwrap();
+        assert!(!token.is_closing());
+
+        token.closing().await;
+        assert!(token.is_closing());
+        assert!(!CloseToken::current().unwrap().is_closing());
+    }
+}
@@ -0,0 +1,128 @@
+/// Declarative server configuration, applied with
+/// [`ServerBuilder::from_config`](crate::ServerBuilder::from_config).
+///
+/// Settings left as `None` keep the builder defaults. Listeners only name addresses; their
+/// services are added with
+/// [`ServerBuilder::bind_configured`](crate::ServerBuilder::bind_configured).
+///
+/// With the `serde` feature enabled the configuration can be deserialized, e.g. from a file or
+/// environment variables. All fields are optional there and unknown fields are rejected.
+///
+/// # Examples
+/// ```
+/// use actix_server::{ListenerConfig, ServerBuilder, ServerConfig};
+/// use actix_service::fn_service;
+///
+/// # fn run() -> std::io::Result<()> {
+/// let config = ServerConfig {
+///     workers: Some(4),
+///     shutdown_timeout: Some(10),
+///     listeners: vec![ListenerConfig::new("api", "127.0.0.1:8080")],
+///     ..Default::default()
+/// };
+///
+/// let server = ServerBuilder::from_config(config)?
+///     .bind_configured("api", || fn_service(|_| async { Ok::<_, ()>(()) }))?
+///     .run();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct ServerConfig {
+    /// Number of workers, see [`ServerBuilder::workers`](crate::ServerBuilder::workers).
+    pub workers: Option<usize>,
+
+    /// Maximum number of pending connections of listeners, see
+    /// [`ServerBuilder::backlog`](crate::ServerBuilder::backlog).
+    pub backlog: Option<u32>,
+
+    /// Maximum number of concurrent connections per worker, see
+    /// [`ServerBuilder::maxconn`](crate::ServerBuilder::maxconn).
+    pub maxconn: Option<usize>,
+
+    /// Timeout for graceful shutdown in seconds, see
+    /// [`ServerBuilder::shutdown_timeout`](crate::ServerBuilder::shutdown_timeout).
+    pub shutdown_timeout: Option<u64>,
+
+    /// Named addresses to bind.
+    pub listeners: Vec<ListenerConfig>,
+}
+
+/// Named address to bind, part of a [`ServerConfig`].
+///
+/// Several listeners may share a name; all of them are served by the same service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct ListenerConfig {
+    /// Name of the service handling connections.
+    pub name: String,
+
+    /// Address to bind, e.g. `0.0.0.0:8080` or `localhost:8080`.
+    pub addr: String,
+
+    /// Set `TCP_NODELAY` on accepted streams, see [`ListenerConfig::nodelay`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nodelay: Option<bool>,
+}
+
+impl ListenerConfig {
+    /// Create listener configuration with given name and address.
+    pub fn new<N: Into<String>, A: Into<String>>(name: N, addr: A) -> Self {
+        Self {
+            name: name.into(),
+            addr: addr.into(),
+            nodelay: None,
+        }
+    }
+
+    /// Set `TCP_NODELAY` on streams accepted by the listener, disabling Nagle's algorithm when
+    /// enabled.
+    ///
+    /// Set before the stream is passed to the service, so latency sensitive services do not
+    /// need to do it themselves. Equivalent to setting
+    /// [`TcpOptions::nodelay`](crate::TcpOptions::nodelay) for the listener's name with
+    /// [`ServerBuilder::tcp_options`](crate::ServerBuilder::tcp_options), which replaces it when
+    /// called afterwards.
+    pub fn nodelay(mut self, enabled: bool) -> Self {
+        self.nodelay = Some(enabled);
+        self
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let config: ServerConfig = serde_json::from_str(
+            r#"{
+                "workers": 2,
+                "shutdown_timeout": 5,
+                "listeners": [{ "name": "api", "addr": "127.0.0.1:8080", "nodelay": true }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            ServerConfig {
+                workers: Some(2),
+                shutdown_timeout: Some(5),
+                listeners: vec![ListenerConfig::new("api", "127.0.0.1:8080").nodelay(true)],
+                ..Default::default()
+            }
+        );
+
+        assert!(serde_json::from_str::<ServerConfig>(r#"{ "worker": 2 }"#).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::waker_queue::{WakerInterest, WakerQueue};
+
+/// Limit on connections open on all workers, set with
+/// [`ServerBuilder::max_total_connections`](crate::ServerBuilder::max_total_connections).
+///
+/// `Accept` takes a [`ConnectionPermit`] for every connection it accepts. The permit travels with
+/// the connection and is released when it is dropped, wherever that happens: in the service, in
+/// a worker channel or while the connection waits in `Accept` for a worker.
+#[derive(Clone)]
+pub(crate) struct ConnectionLimit(Arc<Inner>);
+
+struct Inner {
+    open: AtomicUsize,
+    max: usize,
+    /// Wakers of all accept loops.
+    wakers: Vec<WakerQueue>,
+}
+
+impl ConnectionLimit {
+    pub(crate) fn new(max: usize, wakers: Vec<WakerQueue>) -> Self {
+        Self(Arc::new(Inner {
+            open: AtomicUsize::new(0),
+            max,
+            wakers,
+        }))
+    }
+
+    /// Returns true if another connection can be accepted.
+    pub(crate) fn available(&self) -> bool {
+        self.0.open.load(Ordering::Acquire) < self.0.max
+    }
+
+    /// Count a new connection, regardless of the limit.
+    pub(crate) fn acquire(&self) -> ConnectionPermit {
+        self.0.open.fetch_add(1, Ordering::AcqRel);
+        ConnectionPermit(self.0.clone())
+    }
+}
+
+/// Connection counted by a [`ConnectionLimit`].
+pub(crate) struct ConnectionPermit(Arc<Inner>);
+
+impl fmt::Debug for ConnectionPermit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionPermit")
+            .field("open", &self.0.open.load(Ordering::Relaxed))
+            .field("max", &self.0.max)
+            .finish()
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        // `Accept` stops accepting at the limit and is woken once a connection drops below it
+        if self.0.open.fetch_sub(1, Ordering::AcqRel) == self.0.max {
+            for waker in &self.0.wakers {
+                waker.wake(WakerInterest::ConnectionAvailable);
+            }
+        }
+    }
+}
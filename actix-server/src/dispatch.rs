@@ -0,0 +1,127 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Strategy used by the accept loop to pick the worker that handles a new connection.
+///
+/// Workers that reached their [`maxconn`](crate::ServerBuilder::maxconn) limit are never
+/// picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchStrategy {
+    /// Hand connections to workers in turn, regardless of their load.
+    RoundRobin,
+
+    /// Hand each connection to the worker with the fewest active connections.
+    ///
+    /// Suits long-lived connections of varying cost, where round-robin leaves some workers
+    /// busier than others.
+    LeastConnections,
+
+    /// Pick two workers at random and hand the connection to the one with fewer active
+    /// connections.
+    ///
+    /// Balances nearly as well as [`LeastConnections`](Self::LeastConnections) without scanning
+    /// every worker on each accept.
+    RandomTwoChoices,
+}
+
+// deriving needs `#[default]` on the variant, only stable since Rust 1.62 (MSRV is 1.46)
+#[allow(clippy::derivable_impls)]
+impl Default for DispatchStrategy {
+    fn default() -> Self {
+        DispatchStrategy::RoundRobin
+    }
+}
+
+/// Worker selection state of the accept loop.
+pub(crate) struct Dispatcher {
+    strategy: DispatchStrategy,
+    rng: u64,
+}
+
+impl Dispatcher {
+    pub(crate) fn new(strategy: DispatchStrategy) -> Self {
+        // xorshift state must be non-zero
+        let seed = RandomState::new().build_hasher().finish() | 1;
+        Self {
+            strategy,
+            rng: seed,
+        }
+    }
+
+    /// Select index of the worker to send the next connection to.
+    ///
+    /// `next` is the round-robin position and `len` the number of workers. `load` returns the
+    /// number of active connections of an available worker and `None` for an unavailable one.
+    /// Falls back to `next` if no worker is available.
+    pub(crate) fn select<F>(&mut self, next: usize, len: usize, load: F) -> usize
+    where
+        F: Fn(usize) -> Option<usize>,
+    {
+        match self.strategy {
+            DispatchStrategy::RoundRobin => next,
+
+            // scan from round-robin position so ties are spread between workers
+            DispatchStrategy::LeastConnections => (0..len)
+                .map(|i| (next + i) % len)
+                .filter_map(|idx| load(idx).map(|conns| (idx, conns)))
+                .min_by_key(|(_, conns)| *conns)
+                .map(|(idx, _)| idx)
+                .unwrap_or(next),
+
+            DispatchStrategy::RandomTwoChoices if len > 1 => {
+                let a = self.random() % len;
+                let b = (a + 1 + self.random() % (len - 1)) % len;
+
+                match (load(a), load(b)) {
+                    (Some(la), Some(lb)) if lb < la => b,
+                    (Some(_), _) => a,
+                    (None, Some(_)) => b,
+                    (None, None) => next,
+                }
+            }
+
+            DispatchStrategy::RandomTwoChoices => next,
+        }
+    }
+
+    fn random(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_connections() {
+        let mut dispatcher = Dispatcher::new(DispatchStrategy::LeastConnections);
+        let loads = [Some(3), Some(1), None, Some(1)];
+
+        assert_eq!(dispatcher.select(0, 4, |idx| loads[idx]), 1);
+        assert_eq!(dispatcher.select(2, 4, |idx| loads[idx]), 3);
+        assert_eq!(dispatcher.select(2, 4, |_| None), 2);
+    }
+
+    #[test]
+    fn random_two_choices() {
+        let mut dispatcher = Dispatcher::new(DispatchStrategy::RandomTwoChoices);
+
+        // the most loaded worker can never win a comparison
+        let loads = [Some(0), Some(5), Some(9)];
+        for _ in 0..100 {
+            assert_ne!(dispatcher.select(0, 3, |idx| loads[idx]), 2);
+        }
+
+        // unavailable workers are never picked while another one is available
+        let loads = [None, Some(5)];
+        for _ in 0..100 {
+            assert_eq!(dispatcher.select(0, 2, |idx| loads[idx]), 1);
+        }
+
+        assert_eq!(dispatcher.select(0, 1, |_| Some(0)), 0);
+    }
+}
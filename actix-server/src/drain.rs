@@ -0,0 +1,196 @@
+use std::time::Duration;
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+use std::{io, path::PathBuf};
+
+/// Outcome of a graceful shutdown, for deploy tooling to check that connections were drained.
+///
+/// Passed to the callback set with [`ServerBuilder::on_drain`](crate::ServerBuilder::on_drain).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrainReport {
+    duration: Duration,
+    listeners: Vec<ListenerDrain>,
+}
+
+impl DrainReport {
+    /// Sum up counts reported by workers for each listener name.
+    pub(crate) fn collect(
+        duration: Duration,
+        names: &[(usize, String)],
+        counts: Vec<(usize, DrainCounts)>,
+    ) -> Self {
+        let mut listeners = Vec::<ListenerDrain>::new();
+
+        for (_, name) in names {
+            if !listeners.iter().any(|lst| lst.name == *name) {
+                listeners.push(ListenerDrain::new(name.clone()));
+            }
+        }
+
+        // counts of listeners removed during shutdown are skipped
+        for (token, counts) in counts {
+            if let Some((_, name)) = names.iter().find(|(t, _)| *t == token) {
+                if let Some(lst) = listeners.iter_mut().find(|lst| lst.name == *name) {
+                    lst.add(&counts);
+                }
+            }
+        }
+
+        Self {
+            duration,
+            listeners,
+        }
+    }
+
+    /// Returns time from the start of the shutdown until all workers stopped.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns connection counts of each listener name, in the order listeners were added.
+    pub fn listeners(&self) -> &[ListenerDrain] {
+        &self.listeners
+    }
+
+    /// Returns true if no connection had to be dropped when the shutdown timeout expired.
+    pub fn is_clean(&self) -> bool {
+        self.listeners.iter().all(|lst| lst.abandoned == 0)
+    }
+
+    /// Returns the report as a single line JSON object, e.g. (wrapped here):
+    ///
+    /// ```json
+    /// {"duration_ms":1520,"clean":true,"listeners":[
+    ///     {"name":"http","in_flight":3,"completed":3,"abandoned":0}]}
+    /// ```
+    ///
+    /// Requires the `serde` and `serde_json` features.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("DrainReport is always serializable")
+    }
+}
+
+/// Serialized with the duration in milliseconds, whether the report [is clean](Self::is_clean)
+/// and the listeners.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DrainReport {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct as _;
+
+        let mut report = serializer.serialize_struct("DrainReport", 3)?;
+        report.serialize_field("duration_ms", &(self.duration.as_millis() as u64))?;
+        report.serialize_field("clean", &self.is_clean())?;
+        report.serialize_field("listeners", &self.listeners)?;
+        report.end()
+    }
+}
+
+/// Connection counts of listeners with the same name during a graceful shutdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ListenerDrain {
+    name: String,
+    in_flight: usize,
+    completed: usize,
+    abandoned: usize,
+}
+
+impl ListenerDrain {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            in_flight: 0,
+            completed: 0,
+            abandoned: 0,
+        }
+    }
+
+    fn add(&mut self, counts: &DrainCounts) {
+        self.in_flight += counts.in_flight;
+        self.completed += counts.completed;
+        self.abandoned += counts.abandoned;
+    }
+
+    /// Returns name of the listeners.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns number of connections being served when the shutdown started.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Returns number of connections that ended before the shutdown timeout expired.
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Returns number of connections dropped when the shutdown timeout expired.
+    pub fn abandoned(&self) -> usize {
+        self.abandoned
+    }
+}
+
+/// Connection counts of one listener on one worker.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DrainCounts {
+    pub(crate) in_flight: usize,
+    pub(crate) completed: usize,
+    pub(crate) abandoned: usize,
+}
+
+/// Destination of drain reports.
+pub(crate) enum DrainReporter {
+    Callback(Box<dyn Fn(&DrainReport) + Send + Sync>),
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    File(PathBuf),
+}
+
+impl DrainReporter {
+    pub(crate) async fn report(&self, report: DrainReport) {
+        match self {
+            DrainReporter::Callback(callback) => callback(&report),
+            #[cfg(all(feature = "serde", feature = "serde_json"))]
+            DrainReporter::File(path) => {
+                let path = path.clone();
+                let json = report.to_json() + "\n";
+
+                // writing may block; keep it off the runtime driving the server
+                let res = actix_rt::task::spawn_blocking(move || std::fs::write(path, json))
+                    .await
+                    .unwrap_or_else(|e| Err(io::Error::new(io::ErrorKind::Other, e)));
+                if let Err(e) = res {
+                    log::error!("Can not write drain report: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde", feature = "serde_json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let names = [(0, "http".to_owned()), (1, "a\"b\\c\n".to_owned())];
+        let counts = DrainCounts {
+            in_flight: 3,
+            completed: 2,
+            abandoned: 1,
+        };
+
+        let report =
+            DrainReport::collect(Duration::from_millis(1520), &names, vec![(0, counts)]);
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                r#"{"duration_ms":1520,"clean":false,"listeners":["#,
+                r#"{"name":"http","in_flight":3,"completed":2,"abandoned":1},"#,
+                r#"{"name":"a\"b\\c\n","in_flight":0,"completed":0,"abandoned":0}]}"#,
+            )
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::{io, sync::Arc, time::Duration};
+
+/// Interval between checks of file descriptor usage while any listener has a headroom set.
+pub(crate) const FD_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Callback set with [`ServerBuilder::on_fd_pressure`](crate::ServerBuilder::on_fd_pressure).
+pub(crate) type FdPressureCallback = Arc<dyn Fn(&FdPressure) + Send + Sync>;
+
+/// Listener paused or resumed because of file descriptor usage.
+///
+/// Passed to the callback set with
+/// [`ServerBuilder::on_fd_pressure`](crate::ServerBuilder::on_fd_pressure).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdPressure {
+    name: String,
+    paused: bool,
+    open: usize,
+    limit: usize,
+}
+
+impl FdPressure {
+    pub(crate) fn new(name: String, paused: bool, open: usize, limit: usize) -> Self {
+        Self {
+            name,
+            paused,
+            open,
+            limit,
+        }
+    }
+
+    /// Returns name of the listener.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if the listener was paused, false if it resumed accepting.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns number of file descriptors open in the process at the time of the check.
+    pub fn open_fds(&self) -> usize {
+        self.open
+    }
+
+    /// Returns the soft limit on open file descriptors of the process.
+    pub fn fd_limit(&self) -> usize {
+        self.limit
+    }
+}
+
+/// Returns number of open file descriptors of the process and the soft limit on them.
+#[cfg(unix)]
+pub(crate) fn fd_usage() -> io::Result<(usize, usize)> {
+    use std::convert::TryFrom as _;
+
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let limit = if limit.rlim_cur == libc::RLIM_INFINITY {
+        usize::MAX
+    } else {
+        usize::try_from(limit.rlim_cur).unwrap_or(usize::MAX)
+    };
+
+    let dir = if cfg!(any(target_os = "linux", target_os = "android")) {
+        "/proc/self/fd"
+    } else {
+        "/dev/fd"
+    };
+
+    // the descriptor used to read the directory is listed as well
+    let open = std::fs::read_dir(dir)?.count().saturating_sub(1);
+
+    Ok((open, limit))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn fd_usage() -> io::Result<(usize, usize)> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "file descriptor usage is only available on unix",
+    ))
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Write as _};
+use std::time::Duration;
+
+use log::{error, info, warn};
+
+use crate::accept::connection_error;
+use crate::builder::{create_tcp_listener, BindOptions};
+use crate::poller::Poller;
+use crate::socket::{MioListener, MioStream, MioTcpListener, StdSocketAddr};
+
+/// Delay before binding health check listeners again after it failed.
+pub(crate) const HEALTH_REBIND_DELAY: Duration = Duration::from_secs(1);
+
+/// Listeners set with [`ServerBuilder::health_check`](crate::ServerBuilder::health_check).
+///
+/// Connections are accepted and closed by the accept loop itself. The listeners are closed while
+/// the server is unhealthy, so probes are refused instead of waiting in the kernel backlog, and
+/// bound again to the same address once it recovers.
+pub(crate) struct HealthCheck {
+    listeners: Vec<HealthListener>,
+    payload: Vec<u8>,
+    opts: BindOptions,
+    healthy: bool,
+}
+
+struct HealthListener {
+    token: usize,
+    addr: StdSocketAddr,
+    lst: Option<MioListener>,
+}
+
+impl HealthCheck {
+    pub(crate) fn new(opts: BindOptions, payload: Vec<u8>) -> Self {
+        Self {
+            listeners: Vec::new(),
+            payload,
+            opts,
+            healthy: true,
+        }
+    }
+
+    /// Add a bound listener. Its local address is used when binding it again.
+    pub(crate) fn add(&mut self, token: usize, lst: MioTcpListener) -> io::Result<()> {
+        self.listeners.push(HealthListener {
+            token,
+            addr: lst.local_addr()?,
+            lst: Some(MioListener::Tcp(lst)),
+        });
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    pub(crate) fn owns(&self, token: usize) -> bool {
+        self.listeners.iter().any(|hl| hl.token == token)
+    }
+
+    /// Start listening for probes on all open listeners.
+    pub(crate) fn register(&mut self, poll: &Poller) {
+        for hl in self.listeners.iter_mut() {
+            if let Some(ref mut lst) = hl.lst {
+                info!("Starting health check on {}", lst);
+
+                if let Err(e) = poll.register(lst, hl.token) {
+                    error!("Can not register health check socket {}", e);
+                }
+            }
+        }
+    }
+
+    /// Open or close listeners if the health of the server changed.
+    ///
+    /// Returns an error if a listener could not be bound again; it is retried on the next call.
+    pub(crate) fn set_healthy(&mut self, healthy: bool, poll: &Poller) -> io::Result<()> {
+        if healthy == self.healthy {
+            return Ok(());
+        }
+
+        if healthy {
+            for hl in self.listeners.iter_mut().filter(|hl| hl.lst.is_none()) {
+                let mut lst = MioListener::Tcp(create_tcp_listener(hl.addr, self.opts)?);
+                poll.register(&mut lst, hl.token)?;
+                hl.lst = Some(lst);
+            }
+
+            info!("Health check is passing");
+        } else {
+            for hl in self.listeners.iter_mut() {
+                if let Some(mut lst) = hl.lst.take() {
+                    if let Err(e) = poll.deregister(&mut lst) {
+                        error!("Can not deregister health check socket {}", e);
+                    }
+                }
+            }
+
+            warn!("Health check is failing; not all workers are available");
+        }
+
+        self.healthy = healthy;
+        Ok(())
+    }
+
+    /// Accept and close pending probes of listener with given token.
+    pub(crate) fn accept(&mut self, token: usize) {
+        let lst = match self.listeners.iter_mut().find(|hl| hl.token == token) {
+            Some(HealthListener { lst: Some(lst), .. }) => lst,
+            // readiness may have been reported before the listener was closed
+            _ => return,
+        };
+
+        loop {
+            match lst.accept() {
+                Ok(MioStream::Tcp(mut stream)) => {
+                    // the payload is expected to fit the send buffer of a new connection; a
+                    // probe that went away in the meantime does not matter
+                    if !self.payload.is_empty() {
+                        let _ = stream.write_all(&self.payload);
+                    }
+                }
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(ref e) if connection_error(e) => continue,
+                Err(e) => {
+                    error!("Error accepting health check connection: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+}
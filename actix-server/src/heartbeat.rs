@@ -0,0 +1,164 @@
+use std::{collections::HashMap, time::Duration};
+
+use actix_rt::time::Instant;
+
+/// Policy for detecting unresponsive workers, set with
+/// [`ServerBuilder::worker_heartbeat`](crate::ServerBuilder::worker_heartbeat).
+///
+/// Each worker reports to the accept loop every [interval](Self::new) from a task on its thread.
+/// A worker that did not report within the [timeout](Self::timeout), e.g. because a service
+/// blocks its thread or deadlocked, is logged and no longer sent connections until it reports
+/// again. With [`restart`](Self::restart) enabled it is replaced by a new worker instead; the
+/// thread of the unresponsive worker is left to finish on its own.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_server::WorkerHeartbeat;
+///
+/// let policy = WorkerHeartbeat::new(Duration::from_secs(1))
+///     .timeout(Duration::from_secs(10))
+///     .restart(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkerHeartbeat {
+    interval: Duration,
+    timeout: Duration,
+    restart: bool,
+}
+
+impl WorkerHeartbeat {
+    /// Create policy with workers reporting every `interval`.
+    ///
+    /// The timeout defaults to three intervals and unresponsive workers are not restarted.
+    pub fn new(interval: Duration) -> Self {
+        assert!(
+            interval > Duration::from_secs(0),
+            "heartbeat interval must be greater than 0"
+        );
+
+        Self {
+            interval,
+            timeout: interval * 3,
+            restart: false,
+        }
+    }
+
+    /// Set time without a report after which a worker is considered unresponsive.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Replace unresponsive workers with new ones.
+    pub fn restart(mut self, enabled: bool) -> Self {
+        self.restart = enabled;
+        self
+    }
+
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub(crate) fn restarts(&self) -> bool {
+        self.restart
+    }
+}
+
+/// Last report of each worker, tracked by the accept loop.
+pub(crate) struct HeartbeatMonitor {
+    policy: WorkerHeartbeat,
+    seen: HashMap<usize, Instant>,
+    /// Workers considered unresponsive and not reported again since.
+    unresponsive: Vec<usize>,
+}
+
+impl HeartbeatMonitor {
+    pub(crate) fn new(policy: WorkerHeartbeat) -> Self {
+        Self {
+            policy,
+            seen: HashMap::new(),
+            unresponsive: Vec::new(),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> &WorkerHeartbeat {
+        &self.policy
+    }
+
+    /// Start tracking a worker started with the given index, replacing a previous one.
+    pub(crate) fn started(&mut self, idx: usize, now: Instant) {
+        self.seen.insert(idx, now);
+        self.unresponsive.retain(|i| *i != idx);
+    }
+
+    /// Stop tracking a worker that was stopped or died.
+    pub(crate) fn stopped(&mut self, idx: usize) {
+        self.seen.remove(&idx);
+        self.unresponsive.retain(|i| *i != idx);
+    }
+
+    /// Record report of a worker. Returns true if it was considered unresponsive.
+    pub(crate) fn beat(&mut self, idx: usize, now: Instant) -> bool {
+        if let Some(seen) = self.seen.get_mut(&idx) {
+            *seen = now;
+        }
+
+        let len = self.unresponsive.len();
+        self.unresponsive.retain(|i| *i != idx);
+        self.unresponsive.len() != len
+    }
+
+    /// Returns workers that became unresponsive since the last check.
+    pub(crate) fn check(&mut self, now: Instant) -> Vec<usize> {
+        let timeout = self.policy.timeout;
+        let unresponsive = &mut self.unresponsive;
+
+        let mut idx = self
+            .seen
+            .iter()
+            .filter(|(idx, seen)| {
+                now.duration_since(**seen) > timeout && !unresponsive.contains(idx)
+            })
+            .map(|(idx, _)| *idx)
+            .collect::<Vec<_>>();
+        idx.sort_unstable();
+
+        unresponsive.extend_from_slice(&idx);
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unresponsive() {
+        let policy = WorkerHeartbeat::new(Duration::from_millis(100));
+        let mut monitor = HeartbeatMonitor::new(policy);
+
+        let start = Instant::now();
+        monitor.started(0, start);
+        monitor.started(1, start);
+
+        let now = start + Duration::from_millis(200);
+        monitor.beat(1, now);
+        assert!(monitor.check(now).is_empty());
+
+        let now = start + Duration::from_millis(400);
+        assert_eq!(monitor.check(now), [0]);
+        // reported once
+        assert!(monitor.check(now).is_empty());
+
+        assert!(monitor.beat(0, now));
+        assert!(!monitor.beat(0, now));
+
+        let now = start + Duration::from_millis(800);
+        assert_eq!(monitor.check(now), [0, 1]);
+
+        monitor.stopped(0);
+        monitor.started(1, now);
+        assert!(monitor.check(now + Duration::from_millis(200)).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    future::Future,
+    mem::ManuallyDrop,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep_until, Instant, Sleep};
+
+/// Future of a connection task closed once the connection was idle for a timeout.
+///
+/// A connection is idle while its future is not woken, i.e. none of the reads, writes or other
+/// events it waits for became ready. The connection future is polled with its own waker, so
+/// wakes of the idle timer are not taken for activity.
+pub(crate) struct IdleTimeout<F> {
+    fut: Pin<Box<F>>,
+    activity: Arc<Activity>,
+    waker: Waker,
+    last: Instant,
+    timeout: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+/// Run `fut` until it completes or the connection was idle for `timeout`.
+///
+/// Resolves to `None` if the connection timed out; `fut` is dropped then.
+pub(crate) fn timeout<F: Future>(timeout: Duration, fut: F) -> IdleTimeout<F> {
+    let activity = Arc::new(Activity {
+        // connection future was not polled yet
+        woken: AtomicBool::new(true),
+        task: Mutex::new(None),
+    });
+    let waker = activity.clone().into_waker();
+    let last = Instant::now();
+
+    IdleTimeout {
+        fut: Box::pin(fut),
+        activity,
+        waker,
+        last,
+        timeout,
+        sleep: Box::pin(sleep_until(last + timeout)),
+    }
+}
+
+impl<F: Future> Future for IdleTimeout<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.activity.register(cx.waker());
+
+        if this.activity.woken.swap(false, Ordering::AcqRel) {
+            let mut fut_cx = Context::from_waker(&this.waker);
+            if let Poll::Ready(res) = this.fut.as_mut().poll(&mut fut_cx) {
+                return Poll::Ready(Some(res));
+            }
+            this.last = Instant::now();
+        }
+
+        // the deadline moves with each activity; sleep until it stops moving
+        loop {
+            if this.sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            let deadline = this.last + this.timeout;
+            if Instant::now() >= deadline {
+                return Poll::Ready(None);
+            }
+            this.sleep.as_mut().reset(deadline);
+        }
+    }
+}
+
+/// Wake state of a connection future, shared with its waker.
+struct Activity {
+    woken: AtomicBool,
+    /// Waker of the task running the connection.
+    task: Mutex<Option<Waker>>,
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop_waker);
+
+impl Activity {
+    fn register(&self, waker: &Waker) {
+        let mut task = self.task.lock().unwrap();
+        match *task {
+            Some(ref task) if task.will_wake(waker) => {}
+            _ => *task = Some(waker.clone()),
+        }
+    }
+
+    fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+        if let Some(ref task) = *self.task.lock().unwrap() {
+            task.wake_by_ref();
+        }
+    }
+
+    fn into_waker(self: Arc<Self>) -> Waker {
+        let raw = RawWaker::new(Arc::into_raw(self) as *const (), &VTABLE);
+        // SAFETY: vtable functions treat the pointer as the `Arc<Activity>` it was created from
+        unsafe { Waker::from_raw(raw) }
+    }
+}
+
+unsafe fn clone(ptr: *const ()) -> RawWaker {
+    let activity = ManuallyDrop::new(Arc::from_raw(ptr as *const Activity));
+    let activity = Arc::clone(&activity);
+    RawWaker::new(Arc::into_raw(activity) as *const (), &VTABLE)
+}
+
+unsafe fn wake(ptr: *const ()) {
+    Arc::from_raw(ptr as *const Activity).wake();
+}
+
+unsafe fn wake_by_ref(ptr: *const ()) {
+    (*(ptr as *const Activity)).wake();
+}
+
+unsafe fn drop_waker(ptr: *const ()) {
+    drop(Arc::from_raw(ptr as *const Activity));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::sleep;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn activity_resets_timeout() {
+        // time only advances, to the next timer, while all tasks wait
+        tokio::time::pause();
+        let timeout = Duration::from_millis(100);
+
+        let start = Instant::now();
+        let res = super::timeout(timeout, async {
+            for _ in 0..4 {
+                sleep(Duration::from_millis(60)).await;
+            }
+            1
+        })
+        .await;
+        assert_eq!(res, Some(1));
+        assert!(start.elapsed() >= Duration::from_millis(240));
+
+        let start = Instant::now();
+        let res = super::timeout(timeout, sleep(Duration::from_millis(200))).await;
+        assert_eq!(res, None);
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, fmt, iter::FromIterator, sync::Arc};
+
+/// Key-value labels of the listeners with a given name, set with
+/// [`ServerBuilder::labels`](crate::ServerBuilder::labels).
+///
+/// Labels are passed to [`WorkerMetricsHandler`](crate::WorkerMetricsHandler)s with each
+/// [`WorkerEvent`](crate::WorkerEvent), returned with [`ServiceStatus`](crate::ServiceStatus)es
+/// and shown in log messages about services, so connections can be attributed to e.g. tenants or
+/// environments without encoding them in the name. Labels are ordered by key; cloning them is
+/// cheap.
+///
+/// # Examples
+/// ```
+/// use actix_server::ServiceLabels;
+///
+/// let labels = ServiceLabels::new()
+///     .label("tenant", "acme")
+///     .label("env", "prod");
+/// assert_eq!(labels.get("tenant"), Some("acme"));
+/// assert_eq!(labels.to_string(), "env=prod,tenant=acme");
+///
+/// let labels: ServiceLabels = vec![("tenant", "acme")].into_iter().collect();
+/// assert_eq!(labels.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ServiceLabels(Arc<BTreeMap<String, String>>);
+
+impl ServiceLabels {
+    /// Create empty labels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add label `key` with `value`, replacing a previous value of `key`.
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        Arc::make_mut(&mut self.0).insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns value of label `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Returns iterator over labels, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns number of labels.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if there are no labels.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for ServiceLabels {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let labels = iter
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        Self(Arc::new(labels))
+    }
+}
+
+/// Formats labels as comma separated `key=value` pairs.
+impl fmt::Display for ServiceLabels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (key, value)) in self.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name of a service with its labels, as shown in log messages.
+pub(crate) struct LabeledName<'a>(pub(crate) &'a str, pub(crate) &'a ServiceLabels);
+
+impl fmt::Display for LabeledName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.1.is_empty() {
+            write!(f, "{:?}", self.0)
+        } else {
+            write!(f, "{:?} {{{}}}", self.0, self.1)
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Upper bounds of histogram buckets in milliseconds; the last bucket is unbounded.
+const BOUNDS_MS: [u64; 13] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Latency histogram of one service on one worker.
+///
+/// Returned by [`Server::service_latency`](crate::Server::service_latency).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceLatency {
+    name: String,
+    worker: usize,
+    histogram: LatencyHistogram,
+}
+
+impl ServiceLatency {
+    pub(crate) fn new(name: String, worker: usize, histogram: LatencyHistogram) -> Self {
+        Self {
+            name,
+            worker,
+            histogram,
+        }
+    }
+
+    /// Returns name of the service's listener.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns index of the worker running the service.
+    pub fn worker(&self) -> usize {
+        self.worker
+    }
+
+    /// Returns distribution of durations from calling the service with a connection until the
+    /// returned future completed.
+    pub fn histogram(&self) -> &LatencyHistogram {
+        &self.histogram
+    }
+}
+
+/// Distribution of durations over fixed buckets ranging from 1ms to over 10s.
+///
+/// Only connections whose service future completed are counted; connections dropped at shutdown
+/// or rejected before reaching the service are not.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; BOUNDS_MS.len() + 1],
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Returns number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns sum of recorded durations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Returns mean of recorded durations, or `None` if nothing was recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(Duration::from_nanos(
+                (self.sum.as_nanos() / u128::from(count)) as u64,
+            )),
+        }
+    }
+
+    /// Returns iterator over upper bounds of buckets and number of durations in each bucket.
+    ///
+    /// The bound of the last bucket is `None`; it holds durations exceeding all other bounds.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        BOUNDS_MS
+            .iter()
+            .map(|ms| Some(Duration::from_millis(*ms)))
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Returns upper bound of the bucket containing the given quantile, e.g. `0.99` for the 99th
+    /// percentile.
+    ///
+    /// Returns `None` if nothing was recorded or the quantile falls into the unbounded bucket.
+    ///
+    /// # Panics
+    /// Panics if `quantile` is not between 0 and 1.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "quantile must be between 0 and 1"
+        );
+
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (bound, num) in self.buckets() {
+            seen += num;
+            if seen >= rank {
+                return bound;
+            }
+        }
+
+        None
+    }
+}
+
+/// Latency counters updated by a worker and read by the server.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyCounters {
+    counts: [AtomicU64; BOUNDS_MS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl LatencyCounters {
+    pub(crate) fn record(&self, dur: Duration) {
+        let ms = dur.as_millis();
+        let idx = BOUNDS_MS
+            .iter()
+            .position(|bound| ms < u128::from(*bound))
+            .unwrap_or(BOUNDS_MS.len());
+
+        self.counts[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(dur.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+
+        for (count, counter) in histogram.counts.iter_mut().zip(self.counts.iter()) {
+            *count = counter.load(Ordering::Relaxed);
+        }
+        histogram.sum = Duration::from_micros(self.sum_micros.load(Ordering::Relaxed));
+
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let counters = LatencyCounters::default();
+        assert_eq!(counters.snapshot().quantile(0.5), None);
+        assert_eq!(counters.snapshot().mean(), None);
+
+        counters.record(Duration::from_micros(500));
+        counters.record(Duration::from_millis(1));
+        counters.record(Duration::from_millis(30));
+        counters.record(Duration::from_secs(20));
+
+        let histogram = counters.snapshot();
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), Duration::from_micros(20_031_500));
+
+        let buckets = histogram.buckets().collect::<Vec<_>>();
+        assert_eq!(buckets.len(), 14);
+        assert_eq!(buckets[0], (Some(Duration::from_millis(1)), 1));
+        assert_eq!(buckets[1], (Some(Duration::from_millis(2)), 1));
+        assert_eq!(buckets[5], (Some(Duration::from_millis(50)), 1));
+        assert_eq!(buckets[13], (None, 1));
+
+        assert_eq!(histogram.quantile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(2)));
+        assert_eq!(histogram.quantile(0.75), Some(Duration::from_millis(50)));
+        assert_eq!(histogram.quantile(1.0), None);
+    }
+}
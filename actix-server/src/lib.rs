@@ -5,22 +5,84 @@
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
 
 mod accept;
+mod accept_error;
+mod accept_mode;
+mod addr;
+mod affinity;
+mod autoscale;
+mod bind_policy;
 mod builder;
+mod client_hello;
+mod close;
+mod close_token;
+mod config;
+mod conn_limit;
+mod dispatch;
+mod drain;
+mod fd_limit;
+mod health;
+mod heartbeat;
+mod idle;
+mod labels;
+mod latency;
+mod memory;
+mod metrics;
+mod mux;
+mod poller;
+mod pressure;
+mod proxy;
+mod rate_limit;
+mod restart;
+#[cfg(all(target_os = "linux", feature = "sctp"))]
+mod sctp;
 mod server;
 mod service;
 mod signals;
 mod socket;
+#[cfg(unix)]
+mod systemd;
+mod tcp;
 mod test_server;
+mod trace;
+mod udp;
+#[cfg(unix)]
+mod uds;
 mod waker_queue;
 mod worker;
+mod worker_status;
 
+pub use self::accept_error::{AcceptError, AcceptErrorAction};
+pub use self::accept_mode::AcceptMode;
+pub use self::addr::ListenerAddr;
+pub use self::autoscale::WorkerAutoscale;
+pub use self::bind_policy::BindPolicy;
 pub use self::builder::ServerBuilder;
+pub use self::client_hello::ClientHello;
+pub use self::close::{CloseReason, CloseStats};
+pub use self::close_token::CloseToken;
+pub use self::config::{ListenerConfig, ServerConfig};
+pub use self::dispatch::DispatchStrategy;
+pub use self::drain::{DrainReport, ListenerDrain};
+pub use self::fd_limit::FdPressure;
+pub use self::heartbeat::WorkerHeartbeat;
+pub use self::labels::ServiceLabels;
+pub use self::latency::{LatencyHistogram, ServiceLatency};
+pub use self::memory::{MemoryConnector, MemoryListener};
+pub use self::metrics::{WorkerEvent, WorkerMetricsHandler};
+pub use self::mux::Mux;
+pub use self::proxy::{ProxyHeader, ProxyStream};
+pub use self::restart::{WorkerFault, WorkerRestart};
+#[cfg(all(target_os = "linux", feature = "sctp"))]
+pub use self::sctp::SctpStream;
 pub use self::server::Server;
 pub use self::service::ServiceFactory;
+pub use self::socket::{FromStream, IntoStream, Listener, MioStream};
+pub use self::tcp::TcpOptions;
 pub use self::test_server::TestServer;
-
-#[doc(hidden)]
-pub use self::socket::FromStream;
+pub use self::udp::Datagram;
+#[cfg(unix)]
+pub use self::uds::UdsOptions;
+pub use self::worker_status::{ServiceStatus, WorkerStatus};
 
 use std::future::Future;
 use std::pin::Pin;
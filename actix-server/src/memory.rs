@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::{fmt, io};
+
+use tokio::io::{duplex, DuplexStream};
+
+use crate::socket::{FromStream, IntoStream, MioStream};
+use crate::waker_queue::{WakerInterest, WakerQueue};
+
+/// Default capacity of the buffers of in-memory connections, in each direction.
+const DEFAULT_MAX_BUF_SIZE: usize = 64 * 1024;
+
+/// Listener for in-memory connections, added with
+/// [`ServerBuilder::listen_memory`](crate::ServerBuilder::listen_memory).
+///
+/// In-memory connections are [`DuplexStream`]s opened with the [`MemoryConnector`] created
+/// together with the listener. They go through the accept loop and workers like connections of
+/// socket listeners, so services can be exercised with the full server machinery in tests
+/// without binding sockets.
+///
+/// # Examples
+/// ```
+/// use actix_server::{MemoryListener, Server};
+/// use actix_service::fn_service;
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let (lst, connector) = MemoryListener::new();
+///
+/// let srv = Server::build()
+///     .workers(1)
+///     .disable_signals()
+///     .listen_memory("echo", lst, || {
+///         fn_service(|mut io: DuplexStream| async move {
+///             let mut buf = [0; 5];
+///             io.read_exact(&mut buf).await?;
+///             io.write_all(&buf).await
+///         })
+///     })
+///     .run();
+///
+/// let mut conn = connector.connect().unwrap();
+/// conn.write_all(b"hello").await.unwrap();
+///
+/// let mut buf = Vec::new();
+/// conn.read_to_end(&mut buf).await.unwrap();
+/// assert_eq!(buf, b"hello");
+///
+/// srv.stop(true).await;
+/// # });
+/// ```
+pub struct MemoryListener {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    /// Server halves of connections not accepted yet.
+    pending: VecDeque<DuplexStream>,
+    waker: Option<WakerQueue>,
+    /// Token of the listener while it is registered with the accept loop.
+    token: Option<usize>,
+}
+
+impl MemoryListener {
+    /// Create listener and the connector opening connections to it.
+    pub fn new() -> (MemoryListener, MemoryConnector) {
+        let inner = Arc::new(Mutex::new(Inner {
+            pending: VecDeque::new(),
+            waker: None,
+            token: None,
+        }));
+
+        let connector = MemoryConnector {
+            inner: Arc::downgrade(&inner),
+            max_buf_size: DEFAULT_MAX_BUF_SIZE,
+        };
+
+        (MemoryListener { inner }, connector)
+    }
+
+    /// Set waker of the accept loop notified of new connections.
+    pub(crate) fn attach(&self, waker: WakerQueue) {
+        self.lock().waker = Some(waker);
+    }
+
+    /// Set token of the listener while it is registered with the accept loop, or `None` after it
+    /// was deregistered. Connections opened meanwhile are reported once it is registered again.
+    pub(crate) fn set_registered(&self, token: Option<usize>) {
+        let waker = {
+            let mut inner = self.lock();
+            inner.token = token;
+            inner.waker()
+        };
+
+        wake(waker);
+    }
+
+    /// Take the next connection; fails with `WouldBlock` if there is none.
+    pub(crate) fn accept(&self) -> io::Result<DuplexStream> {
+        self.lock()
+            .pending
+            .pop_front()
+            .ok_or_else(|| io::ErrorKind::WouldBlock.into())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("Failed to lock MemoryListener")
+    }
+}
+
+impl Inner {
+    /// Returns waker and token of the listener if it is registered and has pending connections.
+    fn waker(&self) -> Option<(WakerQueue, usize)> {
+        match (&self.waker, self.token) {
+            (Some(waker), Some(token)) if !self.pending.is_empty() => {
+                Some((waker.clone(), token))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Let the accept loop accept pending connections.
+///
+/// Called without holding the lock of the listener, which the accept loop may take while it
+/// holds the lock of its waker queue.
+fn wake(waker: Option<(WakerQueue, usize)>) {
+    if let Some((waker, token)) = waker {
+        waker.wake(WakerInterest::Accept(token));
+    }
+}
+
+impl fmt::Debug for MemoryListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryListener")
+            .field("pending", &self.lock().pending.len())
+            .finish()
+    }
+}
+
+/// Opens in-memory connections to a [`MemoryListener`].
+#[derive(Clone)]
+pub struct MemoryConnector {
+    inner: Weak<Mutex<Inner>>,
+    max_buf_size: usize,
+}
+
+impl MemoryConnector {
+    /// Set capacity of the buffers of connections opened by this connector, in each direction.
+    ///
+    /// Writes wait once the buffer is full until the other side reads. Defaults to 64KiB.
+    pub fn max_buf_size(mut self, size: usize) -> Self {
+        self.max_buf_size = size;
+        self
+    }
+
+    /// Open connection to the listener.
+    ///
+    /// The connection is accepted once a worker is available, like a connection waiting in the
+    /// backlog of a socket. Returns a `ConnectionRefused` error if the listener was dropped
+    /// because the server stopped or the listener was removed.
+    pub fn connect(&self) -> io::Result<DuplexStream> {
+        let inner = self.inner.upgrade().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "In-memory listener is closed",
+            )
+        })?;
+
+        let (client, server) = duplex(self.max_buf_size);
+
+        let waker = {
+            let mut inner = inner.lock().expect("Failed to lock MemoryListener");
+            inner.pending.push_back(server);
+            inner.waker()
+        };
+
+        wake(waker);
+
+        Ok(client)
+    }
+}
+
+impl fmt::Debug for MemoryConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryConnector")
+            .field("max_buf_size", &self.max_buf_size)
+            .finish()
+    }
+}
+
+impl FromStream for DuplexStream {
+    fn from_mio(sock: MioStream) -> io::Result<Self> {
+        match sock {
+            MioStream::Memory(io) => Ok(io),
+            sock => Err(sock.unsupported("DuplexStream")),
+        }
+    }
+}
+
+impl IntoStream for DuplexStream {
+    fn into_mio(self) -> io::Result<MioStream> {
+        Ok(MioStream::Memory(self))
+    }
+}
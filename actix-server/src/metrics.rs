@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use crate::labels::ServiceLabels;
+
+/// Handler set with [`ServerBuilder::worker_metrics`](crate::ServerBuilder::worker_metrics).
+pub(crate) type WorkerMetrics = Arc<dyn WorkerMetricsHandler>;
+
+/// Receiver of [`WorkerEvent`]s from the server's workers.
+///
+/// Set with [`ServerBuilder::worker_metrics`](crate::ServerBuilder::worker_metrics) to push
+/// metrics to a monitoring system. The handler is shared by all workers and called on their
+/// threads, so it should return quickly.
+///
+/// Implemented for closures taking the index of the worker and the event.
+pub trait WorkerMetricsHandler: Send + Sync + 'static {
+    /// Called by the worker with index `worker` for each event.
+    fn event(&self, worker: usize, event: &WorkerEvent<'_>);
+}
+
+impl<F> WorkerMetricsHandler for F
+where
+    F: Fn(usize, &WorkerEvent<'_>) + Send + Sync + 'static,
+{
+    fn event(&self, worker: usize, event: &WorkerEvent<'_>) {
+        (self)(worker, event)
+    }
+}
+
+/// Event of a worker passed to a [`WorkerMetricsHandler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WorkerEvent<'a> {
+    /// A connection was passed to the service of listener `name`, with the listener's
+    /// [`labels`](crate::ServerBuilder::labels).
+    ConnectionAccepted {
+        name: &'a str,
+        labels: &'a ServiceLabels,
+    },
+
+    /// The service of listener `name` failed and was recreated.
+    ServiceRestarted {
+        name: &'a str,
+        labels: &'a ServiceLabels,
+    },
+
+    /// The service of listener `name` became ready or stopped being ready to handle connections.
+    ReadinessChanged {
+        name: &'a str,
+        labels: &'a ServiceLabels,
+        ready: bool,
+    },
+
+    /// The worker started shutting down with `connections` still open.
+    ShutdownStarted { graceful: bool, connections: usize },
+}
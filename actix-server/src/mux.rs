@@ -0,0 +1,333 @@
+//! Protocol detection; serving a listener with several services.
+
+use std::{
+    fmt, io,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::net::TcpStream;
+use actix_service::{
+    boxed::{self, BoxServiceFactory, RcService},
+    Service, ServiceFactory as BaseServiceFactory, ServiceFactoryExt,
+};
+use futures_core::future::LocalBoxFuture;
+
+use crate::client_hello::{self, ClientHello};
+use crate::service::ServiceFactory;
+
+/// Time a client has to send data after connecting.
+const PEEK_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Matcher = Rc<dyn Fn(&[u8]) -> bool>;
+type RouteFactory<E> = BoxServiceFactory<(), TcpStream, (), E, ()>;
+type RouteService<E> = RcService<TcpStream, (), E>;
+
+/// Services sharing one listener, picked for each connection by the first bytes the client sends.
+///
+/// Bound with [`ServerBuilder::bind_multi`](crate::ServerBuilder::bind_multi). On a new
+/// connection, up to `peek_len` bytes are peeked from the stream without consuming them and
+/// passed to the matchers in the order the services were added. The first service whose matcher
+/// returns true receives the stream, so it sees the data as sent by the client. Matchers should
+/// not rely on getting all `peek_len` bytes; they get what has arrived when the first data is
+/// read, which is empty if the client closed the connection without sending anything.
+///
+/// Services for TLS connections can be picked by the server name and ALPN protocols of the
+/// ClientHello with [`tls_service()`](Self::tls_service), without terminating TLS.
+///
+/// Connections matching no service and clients that do not send data within 10 seconds are
+/// closed with an error.
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{Mux, Server};
+/// use actix_service::fn_service;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mux = Mux::new(1)
+///     // TLS handshake record
+///     .service(
+///         |buf| buf.first() == Some(&0x16),
+///         || fn_service(|_io: TcpStream| async { Ok::<_, std::io::Error>(()) }),
+///     )
+///     // everything else
+///     .service(
+///         |_| true,
+///         || fn_service(|_io: TcpStream| async { Ok::<_, std::io::Error>(()) }),
+///     );
+///
+/// let builder = Server::build().bind_multi("mux", "127.0.0.1:0", mux)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Mux<E> {
+    peek_len: usize,
+    client_hello: bool,
+    routes: Vec<Box<dyn MuxRoute<E>>>,
+}
+
+impl<E: 'static> Mux<E> {
+    /// Create a service set without services, peeking up to `peek_len` bytes of each connection.
+    ///
+    /// # Panics
+    /// Panics if `peek_len` is 0.
+    pub fn new(peek_len: usize) -> Self {
+        assert_ne!(peek_len, 0, "peek length must be greater than 0");
+
+        Self {
+            peek_len,
+            client_hello: false,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Add a service receiving connections whose first bytes are accepted by `matcher`.
+    pub fn service<M, F, S>(mut self, matcher: M, factory: F) -> Self
+    where
+        M: Fn(&[u8]) -> bool + Send + Clone + 'static,
+        F: Fn() -> S + Send + Clone + 'static,
+        S: BaseServiceFactory<TcpStream, Config = (), Error = E> + 'static,
+        S::Service: 'static,
+        S::Future: 'static,
+        S::InitError: 'static,
+    {
+        self.routes.push(Box::new(Route { matcher, factory }));
+        self
+    }
+
+    /// Add a service receiving TLS connections whose [`ClientHello`] is accepted by `matcher`.
+    ///
+    /// Once a service is added with this method, connections starting with a TLS handshake are
+    /// only matched after the complete ClientHello has arrived, and the peek length is raised
+    /// to fit the largest ClientHello. Other matchers then see the whole ClientHello as well.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::net::TcpStream;
+    /// use actix_server::{Mux, Server};
+    /// use actix_service::fn_service;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mux = Mux::new(1)
+    ///     .tls_service(
+    ///         |hello| hello.server_name() == Some("api.example.com"),
+    ///         || fn_service(|_io: TcpStream| async { Ok::<_, std::io::Error>(()) }),
+    ///     )
+    ///     .tls_service(
+    ///         |_| true,
+    ///         || fn_service(|_io: TcpStream| async { Ok::<_, std::io::Error>(()) }),
+    ///     );
+    ///
+    /// let builder = Server::build().bind_multi("sni", "127.0.0.1:0", mux)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tls_service<M, F, S>(mut self, matcher: M, factory: F) -> Self
+    where
+        M: Fn(&ClientHello) -> bool + Send + Clone + 'static,
+        F: Fn() -> S + Send + Clone + 'static,
+        S: BaseServiceFactory<TcpStream, Config = (), Error = E> + 'static,
+        S::Service: 'static,
+        S::Future: 'static,
+        S::InitError: 'static,
+    {
+        self.client_hello = true;
+        self.peek_len = self.peek_len.max(client_hello::MAX_PEEK_LEN);
+
+        let matcher = move |buf: &[u8]| match ClientHello::parse(buf) {
+            Some(hello) => matcher(&hello),
+            None => false,
+        };
+
+        self.service(matcher, factory)
+    }
+}
+
+impl<E> Clone for Mux<E> {
+    fn clone(&self) -> Self {
+        Self {
+            peek_len: self.peek_len,
+            client_hello: self.client_hello,
+            routes: self
+                .routes
+                .iter()
+                .map(|route| route.clone_route())
+                .collect(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for Mux<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mux")
+            .field("peek_len", &self.peek_len)
+            .field("services", &self.routes.len())
+            .finish()
+    }
+}
+
+impl<E> ServiceFactory<TcpStream> for Mux<E>
+where
+    E: From<io::Error> + 'static,
+{
+    type Factory = MuxServiceFactory<E>;
+
+    fn create(&self) -> Self::Factory {
+        MuxServiceFactory {
+            peek_len: self.peek_len,
+            client_hello: self.client_hello,
+            routes: self
+                .routes
+                .iter()
+                .map(|route| (route.matcher(), route.create()))
+                .collect(),
+        }
+    }
+}
+
+trait MuxRoute<E>: Send {
+    fn clone_route(&self) -> Box<dyn MuxRoute<E>>;
+
+    fn matcher(&self) -> Matcher;
+
+    fn create(&self) -> RouteFactory<E>;
+}
+
+struct Route<M, F> {
+    matcher: M,
+    factory: F,
+}
+
+impl<M, F, S, E> MuxRoute<E> for Route<M, F>
+where
+    M: Fn(&[u8]) -> bool + Send + Clone + 'static,
+    F: Fn() -> S + Send + Clone + 'static,
+    S: BaseServiceFactory<TcpStream, Config = (), Error = E> + 'static,
+    S::Service: 'static,
+    S::Future: 'static,
+    S::InitError: 'static,
+    E: 'static,
+{
+    fn clone_route(&self) -> Box<dyn MuxRoute<E>> {
+        Box::new(Route {
+            matcher: self.matcher.clone(),
+            factory: self.factory.clone(),
+        })
+    }
+
+    fn matcher(&self) -> Matcher {
+        Rc::new(self.matcher.clone())
+    }
+
+    fn create(&self) -> RouteFactory<E> {
+        boxed::factory((self.factory)().map(|_| ()).map_init_err(|_| ()))
+    }
+}
+
+#[doc(hidden)]
+pub struct MuxServiceFactory<E> {
+    peek_len: usize,
+    client_hello: bool,
+    routes: Vec<(Matcher, RouteFactory<E>)>,
+}
+
+impl<E> BaseServiceFactory<TcpStream> for MuxServiceFactory<E>
+where
+    E: From<io::Error> + 'static,
+{
+    type Response = ();
+    type Error = E;
+    type Config = ();
+    type Service = MuxService<E>;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let peek_len = self.peek_len;
+        let client_hello = self.client_hello;
+        let futs = self
+            .routes
+            .iter()
+            .map(|(matcher, factory)| (matcher.clone(), factory.new_service(())))
+            .collect::<Vec<_>>();
+
+        Box::pin(async move {
+            let mut routes = Vec::with_capacity(futs.len());
+            for (matcher, fut) in futs {
+                routes.push((matcher, RouteService::from(fut.await?)));
+            }
+
+            Ok(MuxService {
+                peek_len,
+                client_hello,
+                routes: Rc::new(routes),
+            })
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct MuxService<E> {
+    peek_len: usize,
+    client_hello: bool,
+    routes: Rc<Vec<(Matcher, RouteService<E>)>>,
+}
+
+impl<E> Service<TcpStream> for MuxService<E>
+where
+    E: From<io::Error> + 'static,
+{
+    type Response = ();
+    type Error = E;
+    type Future = LocalBoxFuture<'static, Result<(), E>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), E>> {
+        let mut ready = true;
+
+        for (_, service) in self.routes.iter() {
+            ready &= service.poll_ready(cx)?.is_ready();
+        }
+
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, io: TcpStream) -> Self::Future {
+        let routes = self.routes.clone();
+        let client_hello = self.client_hello;
+        let mut buf = vec![0; self.peek_len];
+
+        Box::pin(async move {
+            let peek = async {
+                if client_hello {
+                    client_hello::peek(&io, &mut buf).await
+                } else {
+                    io.peek(&mut buf).await
+                }
+            };
+
+            let len = actix_rt::time::timeout(PEEK_TIMEOUT, peek)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "no data received from client",
+                    ))
+                })?;
+
+            match routes.iter().find(|(matcher, _)| matcher(&buf[..len])) {
+                Some((_, service)) => service.call(io).await,
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "no service matches connection",
+                )
+                .into()),
+            }
+        })
+    }
+}
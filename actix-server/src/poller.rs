@@ -0,0 +1,127 @@
+//! Readiness source driving the accept loop.
+
+use std::{io, time::Duration};
+
+use mio::{Events, Interest, Poll, Token as MioToken, Waker};
+
+use crate::socket::MioListener;
+
+/// Token reported by the [`Poller`] when its waker was woken.
+pub(crate) const WAKER_TOKEN: usize = usize::MAX;
+
+/// Source of listener readiness for the accept loop, built on `mio::Poll`.
+///
+/// The accept loop registers listeners with their tokens, blocks in [`poll`](Self::poll) until
+/// some of them are ready to accept and drains each ready listener until it would block. The
+/// poller only reports readiness; it never accepts connections itself.
+pub(crate) struct Poller {
+    poll: Poll,
+    events: Events,
+}
+
+impl Poller {
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(Self {
+            poll: Poll::new()?,
+            events: Events::with_capacity(128),
+        })
+    }
+
+    /// Create a waker that makes a blocked [`poll`](Self::poll) return [`WAKER_TOKEN`].
+    pub(crate) fn waker(&self) -> io::Result<Waker> {
+        Waker::new(self.poll.registry(), MioToken(WAKER_TOKEN))
+    }
+
+    /// Start reporting readiness of `lst` with the given token.
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) fn register(&self, lst: &mut MioListener, token: usize) -> io::Result<()> {
+        self.poll
+            .registry()
+            .register(lst, MioToken(token), Interest::READABLE)
+    }
+
+    /// Start reporting readiness of `lst` with the given token.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn register(&self, lst: &mut MioListener, token: usize) -> io::Result<()> {
+        // On windows, calling register without deregister cause an error.
+        // See https://github.com/actix/actix-web/issues/905
+        // Calling reregister seems to fix the issue.
+        let token = MioToken(token);
+        self.poll
+            .registry()
+            .register(lst, token, Interest::READABLE)
+            .or_else(|_| {
+                self.poll
+                    .registry()
+                    .reregister(lst, token, Interest::READABLE)
+            })
+    }
+
+    /// Stop reporting readiness of `lst`.
+    pub(crate) fn deregister(&self, lst: &mut MioListener) -> io::Result<()> {
+        self.poll.registry().deregister(lst)
+    }
+
+    /// Set maximum number of readiness events returned by one [`poll`](Self::poll).
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.events = Events::with_capacity(capacity);
+    }
+
+    /// Block until a registered listener is ready, the waker was woken or `timeout` elapsed,
+    /// replacing the contents of `tokens` with the ready tokens.
+    ///
+    /// Readiness may be reported spuriously and is edge triggered; a listener is only reported
+    /// again after it was drained.
+    pub(crate) fn poll(
+        &mut self,
+        tokens: &mut Vec<usize>,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        tokens.clear();
+        self.poll.poll(&mut self.events, timeout)?;
+        tokens.extend(self.events.iter().map(|event| usize::from(event.token())));
+        Ok(())
+    }
+
+    /// Returns file descriptor that is readable while [`poll`](Self::poll) would not block.
+    ///
+    /// Used to run the accept loop on an async runtime.
+    #[cfg(unix)]
+    pub(crate) fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        use std::os::unix::io::AsRawFd;
+
+        self.poll.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poller() {
+        let mut poller = Poller::new().unwrap();
+        let waker = poller.waker().unwrap();
+
+        let std_lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_lst.local_addr().unwrap();
+        std_lst.set_nonblocking(true).unwrap();
+        let mut lst = MioListener::from(std_lst);
+        poller.register(&mut lst, 7).unwrap();
+
+        let mut tokens = Vec::new();
+        poller
+            .poll(&mut tokens, Some(Duration::from_millis(0)))
+            .unwrap();
+        assert!(tokens.is_empty());
+
+        let _conn = std::net::TcpStream::connect(addr).unwrap();
+        poller.poll(&mut tokens, None).unwrap();
+        assert_eq!(tokens, [7]);
+
+        poller.deregister(&mut lst).unwrap();
+        waker.wake().unwrap();
+        poller.poll(&mut tokens, None).unwrap();
+        assert_eq!(tokens, [WAKER_TOKEN]);
+    }
+}
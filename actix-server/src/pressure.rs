@@ -0,0 +1,57 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use actix_rt::time::sleep;
+use futures_core::future::LocalBoxFuture;
+use log::info;
+
+use crate::waker_queue::{WakerInterest, WakerQueue};
+
+/// Check set with [`ServerBuilder::accept_pressure`](crate::ServerBuilder::accept_pressure).
+pub(crate) type PressureCheck = Box<dyn Fn() -> LocalBoxFuture<'static, bool> + Send>;
+
+/// Resource pressure reported by a [`PressureCheck`], shared between the task running the check
+/// and `Accept`.
+///
+/// `Accept` stops accepting while the pressure is high, leaving connections in the kernel
+/// backlogs, and is woken once it cleared.
+#[derive(Clone, Default)]
+pub(crate) struct AcceptPressure(Arc<AtomicBool>);
+
+impl AcceptPressure {
+    /// Returns true while accepting is held back.
+    pub(crate) fn is_high(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Run `check` every `interval` until all accept loops are gone.
+    pub(crate) async fn watch(
+        self,
+        interval: Duration,
+        check: PressureCheck,
+        wakers: Vec<WakerQueue>,
+    ) {
+        // other clones are held by the accept loops
+        while Arc::strong_count(&self.0) > 1 {
+            let high = check().await;
+
+            if self.0.swap(high, Ordering::AcqRel) != high {
+                if high {
+                    info!("Resource pressure is high, pausing accepting connections");
+                } else {
+                    info!("Resource pressure cleared, resuming accepting connections");
+                    for waker in &wakers {
+                        waker.wake(WakerInterest::ConnectionAvailable);
+                    }
+                }
+            }
+
+            sleep(interval).await;
+        }
+    }
+}
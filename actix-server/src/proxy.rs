@@ -0,0 +1,470 @@
+//! PROXY protocol support.
+//!
+//! Proxies and load balancers such as HAProxy or AWS NLB can prepend a header to each connection
+//! carrying the address of the client they accepted it from. Both the text (version 1) and the
+//! binary (version 2) format of the [PROXY protocol] are accepted.
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/2.4/doc/proxy-protocol.txt
+
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    str,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::net::{ActixStream, Ready};
+use actix_utils::future::poll_fn;
+use futures_core::future::LocalBoxFuture;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::socket::{FromStream, MioStream};
+
+/// Time a client has to send the complete header after connecting.
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum length of a version 1 header, including the terminating CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Length of the fixed part of a version 2 header.
+const V2_HEADER_LEN: usize = 16;
+
+/// Connection information sent by a proxy at the start of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    addrs: Option<(SocketAddr, SocketAddr)>,
+}
+
+impl ProxyHeader {
+    /// Returns PROXY protocol version used by the proxy; either 1 or 2.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns address of the client that connected to the proxy.
+    ///
+    /// Returns `None` if the proxy did not relay a TCP connection over IPv4 or IPv6, e.g. for
+    /// health checks the proxy makes on its own behalf.
+    pub fn source_addr(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(src, _)| src)
+    }
+
+    /// Returns address the client connected to on the proxy.
+    ///
+    /// Returns `None` in the same cases as [`source_addr`](Self::source_addr).
+    pub fn destination_addr(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(_, dst)| dst)
+    }
+}
+
+/// A stream accepted on a listener that expects a PROXY protocol header.
+///
+/// Use `ProxyStream<TcpStream>` or `ProxyStream<UnixStream>` as the stream type of a service
+/// factory to enable the PROXY protocol for a listener. The header is read before the stream is
+/// passed to the service; connections that do not start with a valid header within 10 seconds
+/// are closed.
+///
+/// Reads return data following the header.
+pub struct ProxyStream<Io> {
+    io: Io,
+    header: ProxyHeader,
+
+    /// Data received after the header in the same read.
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<Io> ProxyStream<Io> {
+    /// Returns header sent by the proxy.
+    pub fn header(&self) -> &ProxyHeader {
+        &self.header
+    }
+
+    /// Returns reference to the underlying stream.
+    pub fn get_ref(&self) -> &Io {
+        &self.io
+    }
+
+    /// Returns mutable reference to the underlying stream.
+    ///
+    /// Reading from the underlying stream directly skips data already buffered by this stream.
+    pub fn get_mut(&mut self) -> &mut Io {
+        &mut self.io
+    }
+
+    /// Consumes stream, returning the underlying stream, the header and data received after the
+    /// header that was not read yet.
+    pub fn into_parts(mut self) -> (Io, ProxyHeader, Vec<u8>) {
+        self.buf.drain(..self.pos);
+        (self.io, self.header, self.buf)
+    }
+}
+
+impl<Io: AsyncRead + Unpin> ProxyStream<Io> {
+    async fn read_header(mut self) -> io::Result<Self> {
+        loop {
+            if let Some((header, len)) = parse(&self.buf)? {
+                self.header = header;
+                self.pos = len;
+                return Ok(self);
+            }
+
+            let Self { io, buf, .. } = &mut self;
+            let n = poll_fn(|cx| read_buf(Pin::new(&mut *io), cx, buf)).await?;
+
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before PROXY protocol header was complete",
+                ));
+            }
+        }
+    }
+}
+
+/// Read into spare capacity of `buf`, growing it if full.
+fn read_buf<Io: AsyncRead>(
+    io: Pin<&mut Io>,
+    cx: &mut Context<'_>,
+    buf: &mut Vec<u8>,
+) -> Poll<io::Result<usize>> {
+    let len = buf.len();
+    if buf.capacity() == len {
+        buf.reserve(256);
+    }
+    buf.resize(buf.capacity(), 0);
+
+    let mut read = ReadBuf::new(&mut buf[len..]);
+    let res = io.poll_read(cx, &mut read);
+    let n = read.filled().len();
+    buf.truncate(len + n);
+
+    res.map_ok(|_| n)
+}
+
+impl<Io> FromStream for ProxyStream<Io>
+where
+    Io: FromStream + AsyncRead + Unpin + 'static,
+{
+    const HANDSHAKE: bool = true;
+
+    fn from_mio(sock: MioStream) -> io::Result<Self> {
+        Ok(Self {
+            io: Io::from_mio(sock)?,
+            // replaced by the received header before stream is passed to the service
+            header: ProxyHeader {
+                version: 0,
+                addrs: None,
+            },
+            buf: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    fn handshake(self) -> LocalBoxFuture<'static, io::Result<Self>> {
+        Box::pin(async move {
+            actix_rt::time::timeout(HEADER_TIMEOUT, self.read_header())
+                .await
+                .unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "timed out reading PROXY protocol header",
+                    ))
+                })
+        })
+    }
+}
+
+impl<Io: AsyncRead + Unpin> AsyncRead for ProxyStream<Io> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.pos < this.buf.len() {
+            let n = buf.remaining().min(this.buf.len() - this.pos);
+            buf.put_slice(&this.buf[this.pos..this.pos + n]);
+            this.pos += n;
+
+            if this.pos == this.buf.len() {
+                this.buf = Vec::new();
+                this.pos = 0;
+            }
+
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut this.io).poll_read(cx, buf)
+    }
+}
+
+impl<Io: AsyncWrite + Unpin> AsyncWrite for ProxyStream<Io> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl<Io: ActixStream> ActixStream for ProxyStream<Io> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        if self.pos < self.buf.len() {
+            Poll::Ready(Ok(Ready::READABLE))
+        } else {
+            self.io.poll_read_ready(cx)
+        }
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+impl<Io: fmt::Debug> fmt::Debug for ProxyStream<Io> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyStream")
+            .field("io", &self.io)
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parse header at the start of `buf`.
+///
+/// Returns the header and its length, or `None` if more data is needed.
+fn parse(buf: &[u8]) -> io::Result<Option<(ProxyHeader, usize)>> {
+    if buf.starts_with(V2_SIGNATURE) {
+        parse_v2(buf)
+    } else if buf.starts_with(V1_PREFIX) {
+        parse_v1(buf)
+    } else if V2_SIGNATURE.starts_with(buf) || V1_PREFIX.starts_with(buf) {
+        Ok(None)
+    } else {
+        Err(invalid(
+            "connection does not start with a PROXY protocol header",
+        ))
+    }
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Option<(ProxyHeader, usize)>> {
+    let end = match buf.iter().take(V1_MAX_LEN).position(|b| *b == b'\n') {
+        Some(end) => end,
+        None if buf.len() < V1_MAX_LEN => return Ok(None),
+        None => return Err(invalid("PROXY protocol v1 header too long")),
+    };
+
+    let line = match buf[..end].split_last() {
+        Some((b'\r', line)) => str::from_utf8(line).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| invalid("malformed PROXY protocol v1 header"))?;
+
+    let mut parts = line.split(' ').skip(1);
+
+    let addrs = match parts.next() {
+        Some("UNKNOWN") => None,
+        Some(proto @ "TCP4") | Some(proto @ "TCP6") => {
+            let mut next = || {
+                parts
+                    .next()
+                    .ok_or_else(|| invalid("truncated PROXY protocol v1 header"))
+            };
+            let (src, dst) = (next()?, next()?);
+            let (src_port, dst_port) = (next()?, next()?);
+
+            let ip = |addr: &str| -> io::Result<IpAddr> {
+                let ip = if proto == "TCP4" {
+                    addr.parse::<Ipv4Addr>().map(IpAddr::V4)
+                } else {
+                    addr.parse::<Ipv6Addr>().map(IpAddr::V6)
+                };
+                ip.map_err(|_| invalid("invalid address in PROXY protocol v1 header"))
+            };
+            let port = |port: &str| {
+                port.parse::<u16>()
+                    .map_err(|_| invalid("invalid port in PROXY protocol v1 header"))
+            };
+
+            let addrs = (
+                SocketAddr::new(ip(src)?, port(src_port)?),
+                SocketAddr::new(ip(dst)?, port(dst_port)?),
+            );
+
+            if parts.next().is_some() {
+                return Err(invalid("malformed PROXY protocol v1 header"));
+            }
+
+            Some(addrs)
+        }
+        _ => return Err(invalid("unsupported protocol in PROXY protocol v1 header")),
+    };
+
+    Ok(Some((ProxyHeader { version: 1, addrs }, end + 1)))
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Option<(ProxyHeader, usize)>> {
+    if buf.len() < V2_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let ver_cmd = buf[12];
+    let family = buf[13];
+    let len = V2_HEADER_LEN + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    if buf.len() < len {
+        return Ok(None);
+    }
+
+    let body = &buf[V2_HEADER_LEN..len];
+
+    let addrs = match (ver_cmd & 0x0F, family) {
+        // LOCAL; connection established by the proxy itself
+        (0x0, _) => None,
+
+        // PROXY over TCP/IPv4
+        (0x1, 0x11) => {
+            if body.len() < 12 {
+                return Err(invalid("truncated PROXY protocol v2 address"));
+            }
+            let ip = |b: &[u8]| IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]));
+            Some((
+                SocketAddr::new(ip(&body[0..4]), u16::from_be_bytes([body[8], body[9]])),
+                SocketAddr::new(ip(&body[4..8]), u16::from_be_bytes([body[10], body[11]])),
+            ))
+        }
+
+        // PROXY over TCP/IPv6
+        (0x1, 0x21) => {
+            if body.len() < 36 {
+                return Err(invalid("truncated PROXY protocol v2 address"));
+            }
+            let ip = |b: &[u8]| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(b);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            Some((
+                SocketAddr::new(ip(&body[0..16]), u16::from_be_bytes([body[32], body[33]])),
+                SocketAddr::new(ip(&body[16..32]), u16::from_be_bytes([body[34], body[35]])),
+            ))
+        }
+
+        // PROXY over other transports; addresses are not relayed
+        (0x1, _) => None,
+
+        _ => return Err(invalid("unsupported PROXY protocol v2 command")),
+    };
+
+    Ok(Some((ProxyHeader { version: 2, addrs }, len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Option<SocketAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn v1() {
+        let buf = b"PROXY TCP4 192.0.2.1 198.51.100.7 56324 443\r\nGET /";
+        let (header, len) = parse(buf).unwrap().unwrap();
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.source_addr(), addr("192.0.2.1:56324"));
+        assert_eq!(header.destination_addr(), addr("198.51.100.7:443"));
+        assert_eq!(&buf[len..], b"GET /");
+
+        let buf = b"PROXY TCP6 2001:db8::1 2001:db8::2 1000 80\r\n";
+        let (header, _) = parse(buf).unwrap().unwrap();
+        assert_eq!(header.source_addr(), addr("[2001:db8::1]:1000"));
+
+        let (header, len) = parse(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+        assert_eq!(header.source_addr(), None);
+        assert_eq!(len, 15);
+
+        assert!(parse(b"PRO").unwrap().is_none());
+        assert!(parse(b"PROXY TCP4 192.0.2.1").unwrap().is_none());
+
+        assert!(parse(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.0.2.1 198.51.100.7 56324\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 2001:db8::1 198.51.100.7 1 2\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.0.2.1 198.51.100.7 1 2\n").is_err());
+        assert!(parse(&[b'P'; 200][..]).is_err());
+        assert!(parse(&[&b"PROXY "[..], &[b' '; 200][..]].concat()).is_err());
+    }
+
+    #[test]
+    fn v2() {
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x21, 0x11, 0, 15]);
+        buf.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 7]);
+        buf.extend_from_slice(&56324u16.to_be_bytes());
+        buf.extend_from_slice(&443u16.to_be_bytes());
+        // TLV following the addresses
+        buf.extend_from_slice(&[0x04, 0, 0]);
+        buf.extend_from_slice(b"data");
+
+        for len in 0..buf.len() - 4 {
+            assert!(parse(&buf[..len]).unwrap().is_none());
+        }
+
+        let (header, len) = parse(&buf).unwrap().unwrap();
+        assert_eq!(header.version(), 2);
+        assert_eq!(header.source_addr(), addr("192.0.2.1:56324"));
+        assert_eq!(header.destination_addr(), addr("198.51.100.7:443"));
+        assert_eq!(&buf[len..], b"data");
+
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        let (header, _) = parse(&buf).unwrap().unwrap();
+        assert_eq!(header.source_addr(), None);
+
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x11, 0x11, 0, 0]);
+        assert!(parse(&buf).is_err());
+
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x21, 0x11, 0, 4, 0, 0, 0, 0]);
+        assert!(parse(&buf).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use actix_rt::time::Instant;
+
+/// Token bucket allowing `rate` events per second with bursts of up to `capacity` events.
+pub(crate) struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket.
+    pub(crate) fn new(rate: u32, capacity: u32) -> Self {
+        assert_ne!(rate, 0, "rate must be greater than 0");
+
+        Self {
+            rate: f64::from(rate),
+            capacity: f64::from(capacity.max(1)),
+            tokens: f64::from(capacity.max(1)),
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now > self.last {
+            let elapsed = (now - self.last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.last = now;
+        }
+    }
+
+    /// Check if a token is available, returning the time until one is if not.
+    ///
+    /// The token is not taken; call [`take`](Self::take) once the event happened.
+    pub(crate) fn check(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            Ok(())
+        } else {
+            let wait = (1.0 - self.tokens) / self.rate;
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+
+    /// Take a token. The bucket may go into debt if it is empty.
+    pub(crate) fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+
+    /// Returns true if no tokens were taken since the bucket was last refilled to capacity.
+    pub(crate) fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let mut bucket = TokenBucket::new(10, 2);
+        let start = bucket.last;
+        assert!(bucket.is_full(start));
+
+        assert!(bucket.check(start).is_ok());
+        bucket.take();
+        assert!(bucket.check(start).is_ok());
+        bucket.take();
+
+        let wait = bucket.check(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        let later = start + Duration::from_millis(100);
+        assert!(bucket.check(later).is_ok());
+        bucket.take();
+        assert!(bucket.check(later).is_err());
+
+        // refill is capped at capacity
+        let much_later = later + Duration::from_secs(10);
+        assert!(bucket.is_full(much_later));
+        bucket.take();
+        bucket.take();
+        assert!(bucket.check(much_later).is_err());
+    }
+}
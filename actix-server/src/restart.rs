@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use actix_rt::time::Instant;
+
+/// Callback set with [`ServerBuilder::on_worker_faulted`](crate::ServerBuilder::on_worker_faulted).
+pub(crate) type FaultCallback = Box<dyn Fn(&WorkerFault) + Send>;
+
+/// Policy for restarting workers that died, set with
+/// [`ServerBuilder::worker_restart`](crate::ServerBuilder::worker_restart).
+///
+/// A restarted worker takes over the index of the worker that died, so faults are counted per
+/// worker index across restarts. Once a worker faulted more often than the allowed number
+/// of restarts within the [window](Self::window), it is not restarted again and the server is
+/// stopped instead, letting a supervisor deal with the process rather than having the worker
+/// flap silently.
+///
+/// By default workers are restarted immediately and indefinitely.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_server::WorkerRestart;
+///
+/// // wait 100ms, 200ms, 400ms, ... before restarting and give up after 5 restarts in a minute
+/// let policy = WorkerRestart::new()
+///     .backoff(Duration::from_millis(100), Duration::from_secs(5))
+///     .max_restarts(5)
+///     .window(Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkerRestart {
+    max_restarts: Option<u32>,
+    window: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for WorkerRestart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkerRestart {
+    /// Create policy restarting workers immediately and indefinitely.
+    pub fn new() -> Self {
+        Self {
+            max_restarts: None,
+            window: Duration::from_secs(60),
+            initial_backoff: Duration::from_secs(0),
+            max_backoff: Duration::from_secs(0),
+        }
+    }
+
+    /// Set number of restarts of a worker within the window after which the server is stopped
+    /// on its next fault.
+    pub fn max_restarts(mut self, restarts: u32) -> Self {
+        self.max_restarts = Some(restarts);
+        self
+    }
+
+    /// Set time for which faults of a worker are remembered. Defaults to 60 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set delay before restarting a worker after its first fault within the window, and the
+    /// maximum delay. The delay doubles with each further fault.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Add a fault to the history of a worker and decide how to handle it.
+    pub(crate) fn fault(&self, worker: usize, history: &mut Vec<Instant>) -> WorkerFault {
+        let now = Instant::now();
+        history.retain(|at| now.duration_since(*at) < self.window);
+        history.push(now);
+
+        let faults = history.len() as u32;
+
+        let restart_delay = match self.max_restarts {
+            Some(max) if faults > max => None,
+            _ => Some(self.delay(faults)),
+        };
+
+        WorkerFault {
+            worker,
+            faults,
+            restart_delay,
+        }
+    }
+
+    fn delay(&self, faults: u32) -> Duration {
+        let factor = 1u32 << (faults - 1).min(31);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+/// Worker fault passed to the callback set with
+/// [`ServerBuilder::on_worker_faulted`](crate::ServerBuilder::on_worker_faulted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerFault {
+    worker: usize,
+    faults: u32,
+    restart_delay: Option<Duration>,
+}
+
+impl WorkerFault {
+    /// Returns index of the worker that died.
+    pub fn worker(&self) -> usize {
+        self.worker
+    }
+
+    /// Returns number of faults of this worker within the restart window, including this one.
+    pub fn faults(&self) -> u32 {
+        self.faults
+    }
+
+    /// Returns delay after which the worker is restarted, or `None` if restarts are exhausted
+    /// and the server is stopping.
+    pub fn restart_delay(&self) -> Option<Duration> {
+        self.restart_delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_and_max_restarts() {
+        let policy = WorkerRestart::new()
+            .backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .max_restarts(3);
+
+        let mut history = Vec::new();
+        let delays = (0..4)
+            .map(|_| policy.fault(1, &mut history).restart_delay())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(300)),
+                None
+            ]
+        );
+
+        let fault = WorkerRestart::new().fault(2, &mut Vec::new());
+        assert_eq!(fault.worker(), 2);
+        assert_eq!(fault.faults(), 1);
+        assert_eq!(fault.restart_delay(), Some(Duration::from_secs(0)));
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    io,
+    net::SocketAddr,
+    os::unix::io::{FromRawFd, IntoRawFd},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_rt::net::TcpStream;
+use mio::{event::Source, Interest, Registry, Token};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::addr::ListenerAddr;
+use crate::socket::{FromStream, IntoStream, Listener, MioStream, MioTcpListener};
+
+/// Association accepted by an SCTP listener.
+///
+/// SCTP listeners are added with [`ServerBuilder::bind_sctp`](crate::ServerBuilder::bind_sctp).
+/// They use one-to-one style sockets, so each association is accepted like a TCP connection and
+/// read and written as a byte stream. Message boundaries are not preserved and data is sent on
+/// the default stream of the association.
+#[derive(Debug)]
+pub struct SctpStream {
+    // one-to-one style SCTP sockets support all calls tokio makes on TCP streams
+    io: TcpStream,
+}
+
+impl SctpStream {
+    /// Returns primary address of the peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.peer_addr()
+    }
+
+    /// Returns local address of the association.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.local_addr()
+    }
+}
+
+impl AsyncRead for SctpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SctpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl FromStream for SctpStream {
+    fn from_mio(sock: MioStream) -> io::Result<Self> {
+        match sock.downcast::<SctpConn>() {
+            Ok(SctpConn(mio)) => {
+                let raw = IntoRawFd::into_raw_fd(mio);
+                // SAFETY: This is a in place conversion from mio stream to tokio stream.
+                let io = TcpStream::from_std(unsafe { FromRawFd::from_raw_fd(raw) })?;
+                Ok(Self { io })
+            }
+            Err(sock) => Err(sock.unsupported("SctpStream")),
+        }
+    }
+}
+
+impl IntoStream for SctpStream {
+    fn into_mio(self) -> io::Result<MioStream> {
+        let std = self.io.into_std()?;
+        let conn = SctpConn(mio::net::TcpStream::from_std(std));
+        Ok(MioStream::Custom(Box::new(conn)))
+    }
+}
+
+/// Association accepted by an [`SctpListener`], before it is converted to an [`SctpStream`].
+pub(crate) struct SctpConn(mio::net::TcpStream);
+
+/// One-to-one style SCTP socket, accepting associations like a TCP listener.
+pub(crate) struct SctpListener(MioTcpListener);
+
+impl SctpListener {
+    /// Create listener bound to `addr`.
+    pub(crate) fn bind(addr: SocketAddr, backlog: u32) -> io::Result<Self> {
+        let socket = Socket::new(
+            Domain::for_address(addr),
+            Type::STREAM,
+            Some(Protocol::from(libc::IPPROTO_SCTP)),
+        )?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(backlog as i32)?;
+        socket.set_nonblocking(true)?;
+
+        // SAFETY: the listener takes ownership of the socket, mio only uses it through its fd.
+        let lst = unsafe { MioTcpListener::from_raw_fd(socket.into_raw_fd()) };
+        Ok(Self(lst))
+    }
+}
+
+impl Listener for SctpListener {
+    type Stream = SctpConn;
+
+    fn accept(&mut self) -> io::Result<SctpConn> {
+        self.0.accept().map(|(stream, _)| SctpConn(stream))
+    }
+
+    fn local_addr(&self) -> ListenerAddr {
+        self.0
+            .local_addr()
+            .map_or(ListenerAddr::Other, ListenerAddr::Sctp)
+    }
+}
+
+impl Source for SctpListener {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.0.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.0.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.0.deregister(registry)
+    }
+}
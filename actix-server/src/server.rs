@@ -2,26 +2,89 @@ use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
+use actix_rt::net::TcpStream;
+use actix_utils::future::poll_fn;
 use tokio::sync::mpsc::UnboundedSender;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, watch};
 
 use crate::builder::ServerBuilder;
+use crate::close::CloseStats;
+use crate::latency::ServiceLatency;
+use crate::service::{ListenerFactory, ServiceFactory};
 use crate::signals::Signal;
+use crate::socket::{IntoStream, MioStream, StdSocketAddr, ToSocketAddrs};
+use crate::worker_status::WorkerStatus;
 
 #[derive(Debug)]
 pub(crate) enum ServerCommand {
     WorkerFaulted(usize),
+    /// Replace worker with the given index that stopped reporting heartbeats
+    WorkerUnresponsive(usize),
+    /// Start worker with the given index in place of one that died
+    StartWorker(usize),
+    /// Sample load and add or retire workers according to the autoscale policy
+    Autoscale,
     Pause(oneshot::Sender<()>),
     Resume(oneshot::Sender<()>),
     Signal(Signal),
     /// Whether to try and shut down gracefully
     Stop {
         graceful: bool,
+        /// Overrides the shutdown timeout of workers
+        timeout: Option<Duration>,
         completion: Option<oneshot::Sender<()>>,
     },
     /// Notify of server stop
     Notify(oneshot::Sender<()>),
+    /// Add a TCP listener to the running server
+    Bind {
+        name: String,
+        addrs: Vec<StdSocketAddr>,
+        factory: ListenerFactory,
+        tx: oneshot::Sender<io::Result<()>>,
+    },
+    /// Remove listeners with the given name from the running server
+    Unbind {
+        name: String,
+        tx: oneshot::Sender<io::Result<()>>,
+    },
+    /// Query connection close statistics
+    CloseStats(oneshot::Sender<CloseStats>),
+    /// Get receiver of the number of open connections
+    Connections(oneshot::Sender<watch::Receiver<usize>>),
+    /// Query service call latency of each worker
+    ServiceLatency(oneshot::Sender<Vec<ServiceLatency>>),
+    /// Query status of each worker
+    Workers(oneshot::Sender<Vec<WorkerStatus>>),
+    /// Change maximum number of concurrent connections per worker
+    MaxConn(usize, oneshot::Sender<()>),
+    /// Change graceful shutdown timeout of workers
+    ShutdownTimeout(Duration, oneshot::Sender<()>),
+    /// Replace worker with the given index with a new one
+    RestartWorker {
+        idx: usize,
+        graceful: bool,
+        tx: oneshot::Sender<io::Result<()>>,
+    },
+    /// Wait until the server is ready to serve connections
+    Ready(oneshot::Sender<io::Result<()>>),
+    /// All workers and the accept thread started, or some of them failed to
+    Started(bool),
+    /// Pause or resume accepting on listeners with the given name
+    PauseListener {
+        name: String,
+        paused: bool,
+        tx: oneshot::Sender<io::Result<()>>,
+    },
+    /// Dispatch a connection released by a service to listener with the given name
+    HandOff {
+        name: String,
+        io: MioStream,
+        worker: Option<usize>,
+        tx: oneshot::Sender<io::Result<()>>,
+    },
 }
 
 #[derive(Debug)]
@@ -48,6 +111,37 @@ impl Server {
         let _ = self.0.send(ServerCommand::WorkerFaulted(idx));
     }
 
+    pub(crate) fn worker_unresponsive(&self, idx: usize) {
+        let _ = self.0.send(ServerCommand::WorkerUnresponsive(idx));
+    }
+
+    pub(crate) fn start_worker(&self, idx: usize) {
+        let _ = self.0.send(ServerCommand::StartWorker(idx));
+    }
+
+    /// Returns false once the server is gone.
+    pub(crate) fn autoscale(&self) -> bool {
+        self.0.send(ServerCommand::Autoscale).is_ok()
+    }
+
+    pub(crate) fn started(&self, started: bool) {
+        let _ = self.0.send(ServerCommand::Started(started));
+    }
+
+    /// Wait until the server is ready to serve connections.
+    ///
+    /// Resolves once all listeners are registered with the accept loop and every worker created
+    /// its services and found them ready, so connections made afterwards are served without
+    /// delay. Resolves right away if that was already the case.
+    ///
+    /// Returns an error if a worker failed to start or the server stopped before it was ready.
+    pub fn ready(&self) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::Ready(tx));
+
+        async { rx.await.unwrap_or_else(|_| Err(not_running())) }
+    }
+
     /// Pause accepting incoming connections
     ///
     /// If socket contains some pending connection, they might be dropped.
@@ -69,13 +163,328 @@ impl Server {
         }
     }
 
+    /// Add new TCP service to the running server.
+    ///
+    /// Sockets are bound with the options configured on the builder (e.g.
+    /// [`backlog`](ServerBuilder::backlog)). The returned future resolves once the service has
+    /// been created on every worker and the accept loop has been notified of the new listeners.
+    ///
+    /// `name` must not be used by another listener of this server.
+    pub fn bind<F, U, N>(
+        &self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> impl Future<Output = io::Result<()>>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        let res = addr.to_socket_addrs().map(|addrs| {
+            let _ = self.0.send(ServerCommand::Bind {
+                name: name.as_ref().to_string(),
+                addrs: addrs.collect(),
+                factory: ListenerFactory::new(factory),
+                tx,
+            });
+        });
+
+        async move {
+            res?;
+            rx.await.unwrap_or_else(|_| Err(not_running()))
+        }
+    }
+
+    /// Remove all listeners with the given name from the running server.
+    ///
+    /// Listening sockets are closed; connections that were already accepted are left to
+    /// complete. Returns an error of kind [`NotFound`](io::ErrorKind::NotFound) if there is no
+    /// listener with this name.
+    pub fn unbind<N: AsRef<str>>(&self, name: N) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::Unbind {
+            name: name.as_ref().to_string(),
+            tx,
+        });
+
+        async { rx.await.unwrap_or_else(|_| Err(not_running())) }
+    }
+
+    /// Pause accepting on all listeners with the given name.
+    ///
+    /// Unlike [`pause()`](Self::pause), other listeners keep accepting, so e.g. a public port
+    /// can be drained while an admin port stays live. Connections already accepted are left to
+    /// complete and new ones wait in the kernel backlog. Returns an error of kind
+    /// [`NotFound`](io::ErrorKind::NotFound) if there is no listener with this name.
+    pub fn pause_listener<N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> impl Future<Output = io::Result<()>> {
+        self.set_listener_paused(name.as_ref(), true)
+    }
+
+    /// Resume accepting on all listeners with the given name after
+    /// [`pause_listener()`](Self::pause_listener).
+    ///
+    /// Listeners stay paused while the whole server is [paused](Self::pause).
+    pub fn resume_listener<N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> impl Future<Output = io::Result<()>> {
+        self.set_listener_paused(name.as_ref(), false)
+    }
+
+    fn set_listener_paused(
+        &self,
+        name: &str,
+        paused: bool,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::PauseListener {
+            name: name.to_string(),
+            paused,
+            tx,
+        });
+
+        async { rx.await.unwrap_or_else(|_| Err(not_running())) }
+    }
+
+    /// Returns number of closed connections by reason, aggregated over all workers.
+    pub fn close_stats(&self) -> impl Future<Output = io::Result<CloseStats>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::CloseStats(tx));
+
+        async { rx.await.map_err(|_| not_running()) }
+    }
+
+    /// Returns receiver of the number of connections currently served by all workers.
+    ///
+    /// Workers count connections as they take and close them. The total is published when the
+    /// receiver is returned and then every 100 milliseconds if it changed, so autoscalers or
+    /// load shedding can react to connection pressure without polling the server. Connections
+    /// waiting in the kernel backlog or in the channel of a worker are not counted.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn run(srv: actix_server::Server) -> std::io::Result<()> {
+    /// let mut conns = srv.connections().await?;
+    ///
+    /// while conns.changed().await.is_ok() {
+    ///     log::info!("{} open connections", *conns.borrow());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connections(&self) -> impl Future<Output = io::Result<watch::Receiver<usize>>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::Connections(tx));
+
+        async { rx.await.map_err(|_| not_running()) }
+    }
+
+    /// Returns latency histogram of each service on each worker, ordered by worker index.
+    ///
+    /// Latency is measured from calling the service, after handshakes such as PROXY protocol or
+    /// TLS, until the future it returned completed. Histograms are kept when services or workers
+    /// are restarted and dropped when their listener is unbound or their worker retired.
+    pub fn service_latency(&self) -> impl Future<Output = io::Result<Vec<ServiceLatency>>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::ServiceLatency(tx));
+
+        async { rx.await.map_err(|_| not_running()) }
+    }
+
+    /// Returns status of each running worker, ordered by worker index.
+    ///
+    /// Workers that died and were not started again yet are not listed.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn run(server: actix_server::Server) -> std::io::Result<()> {
+    /// for worker in server.workers().await? {
+    ///     println!(
+    ///         "worker {}: {} connections, restarted {} times",
+    ///         worker.idx(),
+    ///         worker.connections(),
+    ///         worker.restarts()
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn workers(&self) -> impl Future<Output = io::Result<Vec<WorkerStatus>>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::Workers(tx));
+
+        async { rx.await.map_err(|_| not_running()) }
+    }
+
+    /// Change the maximum per-worker number of concurrent connections.
+    ///
+    /// Applies to running workers and to workers started later. When lowered below the number
+    /// of connections a worker is serving, the worker is not sent new connections until enough
+    /// of them have completed; existing connections are not closed.
+    ///
+    /// See [`ServerBuilder::maxconn`].
+    pub fn set_maxconn(&self, num: usize) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.send(ServerCommand::MaxConn(num, tx));
+        async {
+            let _ = rx.await;
+        }
+    }
+
+    /// Change the timeout for graceful workers shutdown in seconds.
+    ///
+    /// Applies to a graceful shutdown already in progress as well.
+    ///
+    /// See [`ServerBuilder::shutdown_timeout`].
+    pub fn set_shutdown_timeout(&self, sec: u64) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .0
+            .send(ServerCommand::ShutdownTimeout(Duration::from_secs(sec), tx));
+        async {
+            let _ = rx.await;
+        }
+    }
+
+    /// Restart the worker with the given index, recreating its services.
+    ///
+    /// A new worker with fresh instances of all services is started and takes over new
+    /// connections right away; other workers are not affected. The old worker is stopped as with
+    /// [`stop`](Self::stop): if `graceful` is true, it finishes serving its connections within
+    /// the [shutdown timeout](ServerBuilder::shutdown_timeout). Connections dispatched to the old
+    /// worker that it has not started serving yet are handed to the other workers. The returned
+    /// future resolves once the old worker has stopped.
+    ///
+    /// Workers are indexed from 0 to the number of [workers](ServerBuilder::workers) minus one.
+    /// Returns an error of kind [`NotFound`](io::ErrorKind::NotFound) for other indexes.
+    pub fn restart_worker(
+        &self,
+        idx: usize,
+        graceful: bool,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .0
+            .send(ServerCommand::RestartWorker { idx, graceful, tx });
+
+        async { rx.await.unwrap_or_else(|_| Err(not_running())) }
+    }
+
+    /// Hand a connection over to the service of the listener with the given name.
+    ///
+    /// The connection is dispatched as if it was just accepted on that listener: a worker is
+    /// picked by the [dispatch strategy](ServerBuilder::dispatch_strategy) and it calls the
+    /// listener's service with the stream. Pass `worker` to send it to the worker with that index
+    /// instead; should that worker be unable to take it, the dispatch strategy is used after all.
+    /// This allows e.g. a service on a handshake listener to pass established sessions on to a
+    /// service bound under another name, possibly on a dedicated worker.
+    ///
+    /// The connection counts toward the connection limit of its new worker once dispatched and
+    /// stops counting toward the original worker's limit when the service call that handed it
+    /// off completes. Data already read from the stream by the original service is not replayed.
+    ///
+    /// Returns an error of kind [`NotFound`](io::ErrorKind::NotFound) if there is no listener
+    /// with the given name accepting this kind of stream or no worker with the given index.
+    pub fn hand_off<N, Io>(
+        &self,
+        name: N,
+        io: Io,
+        worker: Option<usize>,
+    ) -> impl Future<Output = io::Result<()>>
+    where
+        N: AsRef<str>,
+        Io: IntoStream,
+    {
+        let (tx, rx) = oneshot::channel();
+        let res = io.into_mio().map(|io| {
+            let _ = self.0.send(ServerCommand::HandOff {
+                name: name.as_ref().to_owned(),
+                io,
+                worker,
+                tx,
+            });
+        });
+
+        async move {
+            res?;
+            rx.await.unwrap_or_else(|_| Err(not_running()))
+        }
+    }
+
     /// Stop incoming connection processing, stop all workers and exit.
     ///
     /// If server starts with `spawn()` method, then spawned thread get terminated.
     pub fn stop(&self, graceful: bool) -> impl Future<Output = ()> {
+        self.send_stop(graceful, None)
+    }
+
+    /// Stop the server as with [`stop`](Self::stop), using `timeout` instead of the
+    /// [shutdown timeout](ServerBuilder::shutdown_timeout) for this shutdown.
+    ///
+    /// Lets a shutdown drain connections faster or slower than usual, e.g. for an urgent deploy
+    /// or for scheduled maintenance.
+    pub fn stop_with_timeout(
+        &self,
+        graceful: bool,
+        timeout: Duration,
+    ) -> impl Future<Output = ()> {
+        self.send_stop(graceful, Some(timeout))
+    }
+
+    /// Wait for the server to stop, stopping it gracefully once `shutdown` resolves.
+    ///
+    /// Ties the lifetime of the server to an external event, e.g. loss of a leader lease or a
+    /// command received by an admin endpoint, without spawning a task calling
+    /// [`stop`](Self::stop). Resolves like awaiting the server itself once it stopped, whether
+    /// because of `shutdown`, a signal or another call to `stop`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_server::Server;
+    /// use actix_service::fn_service;
+    /// use tokio::sync::oneshot;
+    ///
+    /// # async fn run(shutdown: oneshot::Receiver<()>) -> std::io::Result<()> {
+    /// Server::build()
+    ///     .bind("app", "127.0.0.1:0", || fn_service(|_| async { Ok::<_, ()>(()) }))?
+    ///     .run()
+    ///     .run_until(shutdown)
+    ///     .await
+    /// # }
+    /// ```
+    pub fn run_until<F: Future>(self, shutdown: F) -> impl Future<Output = io::Result<()>> {
+        let handle = self.clone();
+
+        async move {
+            let mut srv = self;
+            let mut shutdown = Box::pin(shutdown);
+
+            let stopped = poll_fn(|cx| match Pin::new(&mut srv).poll(cx) {
+                Poll::Ready(res) => Poll::Ready(Some(res)),
+                Poll::Pending => shutdown.as_mut().poll(cx).map(|_| None),
+            })
+            .await;
+
+            if let Some(res) = stopped {
+                return res;
+            }
+
+            handle.stop(true).await;
+            srv.await
+        }
+    }
+
+    fn send_stop(&self, graceful: bool, timeout: Option<Duration>) -> impl Future<Output = ()> {
         let (tx, rx) = oneshot::channel();
         let _ = self.0.send(ServerCommand::Stop {
             graceful,
+            timeout,
             completion: Some(tx),
         });
         async {
@@ -110,3 +519,7 @@ impl Future for Server {
         }
     }
 }
+
+fn not_running() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "server is not running")
+}
@@ -1,26 +1,102 @@
+use std::fmt;
+use std::future::Future;
 use std::marker::PhantomData;
-use std::net::SocketAddr;
+use std::rc::Rc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use actix_service::{Service, ServiceFactory as BaseServiceFactory};
 use actix_utils::future::{ready, Ready};
 use futures_core::future::LocalBoxFuture;
-use log::error;
+use log::{debug, error};
 
+use crate::addr::ListenerAddr;
+use crate::close::CloseReason;
+use crate::close_token::CloseToken;
+use crate::idle;
+use crate::labels::{LabeledName, ServiceLabels};
 use crate::socket::{FromStream, MioStream};
+use crate::trace;
 use crate::worker::WorkerCounterGuard;
 
 pub trait ServiceFactory<Stream: FromStream>: Send + Clone + 'static {
     type Factory: BaseServiceFactory<Stream, Config = ()>;
 
     fn create(&self) -> Self::Factory;
+
+    /// Returns hook run by each worker on graceful shutdown, once all its connections are closed
+    /// and before its services are dropped.
+    ///
+    /// Hooks of all services of a worker run concurrently and are bounded by the shutdown timeout.
+    /// They are not run on forced shutdown or if connections did not close in time. Hooks can
+    /// flush buffers or close connections to databases shared with the services created by this
+    /// factory, e.g. through an `Arc`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::net::TcpStream;
+    /// use actix_server::{Server, ServiceFactory};
+    /// use actix_service::boxed::{self, BoxServiceFactory};
+    /// use actix_service::fn_service;
+    /// use futures_core::future::LocalBoxFuture;
+    ///
+    /// #[derive(Clone)]
+    /// struct App;
+    ///
+    /// impl ServiceFactory<TcpStream> for App {
+    ///     type Factory = BoxServiceFactory<(), TcpStream, (), (), ()>;
+    ///
+    ///     fn create(&self) -> Self::Factory {
+    ///         boxed::factory(fn_service(|_: TcpStream| async { Ok(()) }))
+    ///     }
+    ///
+    ///     fn on_shutdown(&self) -> Option<LocalBoxFuture<'static, ()>> {
+    ///         Some(Box::pin(async {
+    ///             // flush buffers
+    ///         }))
+    ///     }
+    /// }
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let server = Server::build().bind("app", "0.0.0.0:8080", App)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn on_shutdown(&self) -> Option<LocalBoxFuture<'static, ()>> {
+        None
+    }
 }
 
 pub(crate) trait InternalServiceFactory: Send {
     fn name(&self, token: usize) -> &str;
 
+    /// Returns labels of the listener.
+    fn labels(&self, token: usize) -> &ServiceLabels;
+
+    /// Returns name and labels of the listener for log messages.
+    fn labeled_name(&self, token: usize) -> LabeledName<'_> {
+        LabeledName(self.name(token), self.labels(token))
+    }
+
+    fn token(&self) -> usize;
+
+    /// Returns address of the listener.
+    fn addr(&self) -> &ListenerAddr;
+
     fn clone_factory(&self) -> Box<dyn InternalServiceFactory>;
 
+    /// Set age after which services are signalled to close connections.
+    fn set_max_connection_age(&mut self, age: Duration);
+
+    /// Set labels of the listener.
+    fn set_labels(&mut self, labels: ServiceLabels);
+
+    /// Set time without activity after which connections are closed.
+    fn set_idle_timeout(&mut self, timeout: Duration);
+
+    /// Returns hook to run on graceful shutdown of the worker.
+    fn on_shutdown(&self) -> Option<LocalBoxFuture<'static, ()>>;
+
     fn create(&self) -> LocalBoxFuture<'static, Result<(usize, BoxedServerService), ()>>;
 }
 
@@ -34,14 +110,25 @@ pub(crate) type BoxedServerService = Box<
 >;
 
 pub(crate) struct StreamService<S, I> {
-    service: S,
+    service: Rc<S>,
+    addr: Rc<ListenerAddr>,
+    max_age: Option<Duration>,
+    idle_timeout: Option<Duration>,
     _phantom: PhantomData<I>,
 }
 
 impl<S, I> StreamService<S, I> {
-    pub(crate) fn new(service: S) -> Self {
+    pub(crate) fn new(
+        service: S,
+        addr: Rc<ListenerAddr>,
+        max_age: Option<Duration>,
+        idle_timeout: Option<Duration>,
+    ) -> Self {
         StreamService {
-            service,
+            service: Rc::new(service),
+            addr,
+            max_age,
+            idle_timeout,
             _phantom: PhantomData,
         }
     }
@@ -49,10 +136,10 @@ impl<S, I> StreamService<S, I> {
 
 impl<S, I> Service<(WorkerCounterGuard, MioStream)> for StreamService<S, I>
 where
-    S: Service<I>,
+    S: Service<I> + 'static,
     S::Future: 'static,
     S::Error: 'static,
-    I: FromStream,
+    I: FromStream + 'static,
 {
     type Response = ();
     type Error = ();
@@ -63,28 +150,85 @@ where
     }
 
     fn call(&self, (guard, req): (WorkerCounterGuard, MioStream)) -> Self::Future {
+        let token = CloseToken::connection(self.max_age);
+
+        let idle = self.idle_timeout;
+        let called = guard.call_start();
+
         ready(match FromStream::from_mio(req) {
+            Ok(stream) if !I::HANDSHAKE => {
+                called.now();
+                let f = ListenerAddr::sync_scope(self.addr.clone(), || match token {
+                    Some(ref token) => token.sync_scope(|| self.service.call(stream)),
+                    None => self.service.call(stream),
+                });
+                spawn_conn(guard, self.addr.clone(), token, idle, async move {
+                    CloseReason::from_result(&f.await)
+                });
+                Ok(())
+            }
             Ok(stream) => {
-                let f = self.service.call(stream);
-                actix_rt::spawn(async move {
-                    let _ = f.await;
-                    drop(guard);
+                let service = self.service.clone();
+                spawn_conn(guard, self.addr.clone(), token, idle, async move {
+                    match stream.handshake().await {
+                        Ok(stream) => {
+                            called.now();
+                            CloseReason::from_result(&service.call(stream).await)
+                        }
+                        Err(e) => {
+                            debug!("Connection handshake failed: {}", e);
+                            CloseReason::Rejected
+                        }
+                    }
                 });
                 Ok(())
             }
             Err(e) => {
                 error!("Can not convert to an async tcp stream: {}", e);
+                guard.close(CloseReason::Rejected);
                 Err(())
             }
         })
     }
 }
 
+/// Spawn task of a connection with its listener address and close token set, closed once it was
+/// idle for the `idle` timeout.
+fn spawn_conn<F>(
+    guard: WorkerCounterGuard,
+    addr: Rc<ListenerAddr>,
+    token: Option<CloseToken>,
+    idle: Option<Duration>,
+    fut: F,
+) where
+    F: Future<Output = CloseReason> + 'static,
+{
+    let fut = async move {
+        let reason = match idle {
+            Some(timeout) => idle::timeout(timeout, fut)
+                .await
+                .unwrap_or(CloseReason::IdleTimeout),
+            None => fut.await,
+        };
+        trace::closed(reason);
+        guard.close(reason);
+    };
+    let fut = trace::in_current_span(ListenerAddr::scope(addr, fut));
+
+    match token {
+        Some(token) => actix_rt::spawn(token.scope(fut)),
+        None => actix_rt::spawn(fut),
+    };
+}
+
 pub(crate) struct StreamNewService<F: ServiceFactory<Io>, Io: FromStream> {
     name: String,
     inner: F,
     token: usize,
-    addr: SocketAddr,
+    addr: ListenerAddr,
+    labels: ServiceLabels,
+    max_age: Option<Duration>,
+    idle_timeout: Option<Duration>,
     _t: PhantomData<Io>,
 }
 
@@ -97,13 +241,16 @@ where
         name: String,
         token: usize,
         inner: F,
-        addr: SocketAddr,
+        addr: ListenerAddr,
     ) -> Box<dyn InternalServiceFactory> {
         Box::new(Self {
             name,
             token,
             inner,
             addr,
+            labels: ServiceLabels::default(),
+            max_age: None,
+            idle_timeout: None,
             _t: PhantomData,
         })
     }
@@ -118,23 +265,58 @@ where
         &self.name
     }
 
+    fn labels(&self, _: usize) -> &ServiceLabels {
+        &self.labels
+    }
+
+    fn token(&self) -> usize {
+        self.token
+    }
+
+    fn addr(&self) -> &ListenerAddr {
+        &self.addr
+    }
+
     fn clone_factory(&self) -> Box<dyn InternalServiceFactory> {
         Box::new(Self {
             name: self.name.clone(),
             inner: self.inner.clone(),
             token: self.token,
-            addr: self.addr,
+            addr: self.addr.clone(),
+            labels: self.labels.clone(),
+            max_age: self.max_age,
+            idle_timeout: self.idle_timeout,
             _t: PhantomData,
         })
     }
 
+    fn set_max_connection_age(&mut self, age: Duration) {
+        self.max_age = Some(age);
+    }
+
+    fn set_labels(&mut self, labels: ServiceLabels) {
+        self.labels = labels;
+    }
+
+    fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = Some(timeout);
+    }
+
+    fn on_shutdown(&self) -> Option<LocalBoxFuture<'static, ()>> {
+        self.inner.on_shutdown()
+    }
+
     fn create(&self) -> LocalBoxFuture<'static, Result<(usize, BoxedServerService), ()>> {
         let token = self.token;
+        let addr = Rc::new(self.addr.clone());
+        let max_age = self.max_age;
+        let idle_timeout = self.idle_timeout;
         let fut = self.inner.create().new_service(());
         Box::pin(async move {
             match fut.await {
                 Ok(inner) => {
-                    let service = Box::new(StreamService::new(inner)) as _;
+                    let service =
+                        Box::new(StreamService::new(inner, addr, max_age, idle_timeout)) as _;
                     Ok((token, service))
                 }
                 Err(_) => Err(()),
@@ -143,6 +325,128 @@ where
     }
 }
 
+/// Type-erased constructor of [`StreamNewService`]s for listeners added to a running server.
+pub(crate) struct ListenerFactory(
+    Box<dyn Fn(String, usize, ListenerAddr) -> Box<dyn InternalServiceFactory> + Send>,
+);
+
+impl ListenerFactory {
+    pub(crate) fn new<F, Io>(factory: F) -> Self
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+    {
+        Self(Box::new(move |name, token, addr| {
+            StreamNewService::create(name, token, factory.clone(), addr)
+        }))
+    }
+
+    pub(crate) fn create(
+        &self,
+        name: String,
+        token: usize,
+        addr: ListenerAddr,
+    ) -> Box<dyn InternalServiceFactory> {
+        (self.0)(name, token, addr)
+    }
+}
+
+impl fmt::Debug for ListenerFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListenerFactory").finish()
+    }
+}
+
+/// Service factory passing data produced by a callback alongside each stream.
+///
+/// Created by [`ServerBuilder::bind_on_connect`](crate::ServerBuilder::bind_on_connect).
+#[derive(Clone)]
+pub(crate) struct OnConnect<F, C> {
+    factory: F,
+    on_connect: C,
+}
+
+impl<F, C> OnConnect<F, C> {
+    pub(crate) fn new(factory: F, on_connect: C) -> Self {
+        Self {
+            factory,
+            on_connect,
+        }
+    }
+}
+
+impl<F, C, S, T, Io> ServiceFactory<Io> for OnConnect<F, C>
+where
+    F: Fn() -> S + Send + Clone + 'static,
+    S: BaseServiceFactory<(Io, T), Config = ()>,
+    S::Future: 'static,
+    C: Fn(&Io) -> T + Send + Clone + 'static,
+    Io: FromStream,
+{
+    type Factory = OnConnectServiceFactory<S, C>;
+
+    fn create(&self) -> Self::Factory {
+        OnConnectServiceFactory {
+            factory: (self.factory)(),
+            on_connect: self.on_connect.clone(),
+        }
+    }
+}
+
+pub(crate) struct OnConnectServiceFactory<S, C> {
+    factory: S,
+    on_connect: C,
+}
+
+impl<S, C, T, Io> BaseServiceFactory<Io> for OnConnectServiceFactory<S, C>
+where
+    S: BaseServiceFactory<(Io, T), Config = ()>,
+    S::Future: 'static,
+    C: Fn(&Io) -> T + Clone + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Config = ();
+    type Service = OnConnectService<S::Service, C>;
+    type InitError = S::InitError;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let fut = self.factory.new_service(());
+        let on_connect = self.on_connect.clone();
+
+        Box::pin(async move {
+            let service = fut.await?;
+            Ok(OnConnectService {
+                service,
+                on_connect,
+            })
+        })
+    }
+}
+
+pub(crate) struct OnConnectService<S, C> {
+    service: S,
+    on_connect: C,
+}
+
+impl<S, C, T, Io> Service<Io> for OnConnectService<S, C>
+where
+    S: Service<(Io, T)>,
+    C: Fn(&Io) -> T,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, io: Io) -> Self::Future {
+        let data = (self.on_connect)(&io);
+        self.service.call((io, data))
+    }
+}
+
 impl<F, T, I> ServiceFactory<I> for F
 where
     F: Fn() -> T + Send + Clone + 'static,
@@ -4,6 +4,11 @@ use std::task::{Context, Poll};
 
 use crate::server::Server;
 
+/// Async callback registered with [`ServerBuilder::on_signal`](crate::ServerBuilder::on_signal).
+#[cfg(unix)]
+pub(crate) type SignalHandler =
+    Box<dyn Fn(Server) -> futures_core::future::LocalBoxFuture<'static, ()> + Send>;
+
 /// Different types of process signals
 #[allow(dead_code)]
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -16,61 +21,129 @@ pub(crate) enum Signal {
     Term,
     /// SIGQUIT
     Quit,
+    /// CTRL_BREAK_EVENT
+    Break,
+    /// CTRL_CLOSE_EVENT
+    Close,
 }
 
 pub(crate) struct Signals {
     srv: Server,
     #[cfg(not(unix))]
     signals: futures_core::future::LocalBoxFuture<'static, std::io::Result<()>>,
+    #[cfg(windows)]
+    ctrl_break: Option<actix_rt::signal::windows::CtrlBreak>,
+    #[cfg(windows)]
+    ctrl_close: Option<actix_rt::signal::windows::CtrlClose>,
     #[cfg(unix)]
     signals: Vec<(Signal, actix_rt::signal::unix::Signal)>,
+    #[cfg(unix)]
+    hooks: Vec<(SignalHandler, actix_rt::signal::unix::Signal)>,
 }
 
 impl Signals {
+    #[cfg(not(unix))]
     pub(crate) fn start(srv: Server) {
-        #[cfg(not(unix))]
-        {
+        actix_rt::spawn(Signals {
+            srv,
+            signals: Box::pin(actix_rt::signal::ctrl_c()),
+            #[cfg(windows)]
+            ctrl_break: console_event(Signal::Break, actix_rt::signal::windows::ctrl_break()),
+            #[cfg(windows)]
+            ctrl_close: console_event(Signal::Close, actix_rt::signal::windows::ctrl_close()),
+        });
+    }
+
+    /// Start listening for signals. Built-in handling of stop signals is skipped if `builtin`
+    /// is false; `hooks` are run either way.
+    #[cfg(unix)]
+    pub(crate) fn start(
+        srv: Server,
+        builtin: bool,
+        hooks: Vec<(actix_rt::signal::unix::SignalKind, SignalHandler)>,
+    ) {
+        use actix_rt::signal::unix;
+
+        let sig_map = [
+            (unix::SignalKind::interrupt(), Signal::Int),
+            (unix::SignalKind::hangup(), Signal::Hup),
+            (unix::SignalKind::terminate(), Signal::Term),
+            (unix::SignalKind::quit(), Signal::Quit),
+        ];
+
+        let signals = sig_map
+            .iter()
+            .filter(|_| builtin)
+            .filter_map(|(kind, sig)| {
+                unix::signal(*kind)
+                    .map(|tokio_sig| (*sig, tokio_sig))
+                    .map_err(|e| {
+                        log::error!(
+                            "Can not initialize stream handler for {:?} err: {}",
+                            sig,
+                            e
+                        )
+                    })
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        let hooks = hooks
+            .into_iter()
+            .filter_map(|(kind, handler)| {
+                unix::signal(kind)
+                    .map(|tokio_sig| (handler, tokio_sig))
+                    .map_err(|e| {
+                        log::error!(
+                            "Can not initialize stream handler for {:?} err: {}",
+                            kind,
+                            e
+                        )
+                    })
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        if !signals.is_empty() || !hooks.is_empty() {
             actix_rt::spawn(Signals {
                 srv,
-                signals: Box::pin(actix_rt::signal::ctrl_c()),
+                signals,
+                hooks,
             });
         }
-        #[cfg(unix)]
-        {
-            use actix_rt::signal::unix;
-
-            let sig_map = [
-                (unix::SignalKind::interrupt(), Signal::Int),
-                (unix::SignalKind::hangup(), Signal::Hup),
-                (unix::SignalKind::terminate(), Signal::Term),
-                (unix::SignalKind::quit(), Signal::Quit),
-            ];
-
-            let signals = sig_map
-                .iter()
-                .filter_map(|(kind, sig)| {
-                    unix::signal(*kind)
-                        .map(|tokio_sig| (*sig, tokio_sig))
-                        .map_err(|e| {
-                            log::error!(
-                                "Can not initialize stream handler for {:?} err: {}",
-                                sig,
-                                e
-                            )
-                        })
-                        .ok()
-                })
-                .collect::<Vec<_>>();
-
-            actix_rt::spawn(Signals { srv, signals });
-        }
     }
 }
 
+#[cfg(windows)]
+fn console_event<T>(sig: Signal, res: std::io::Result<T>) -> Option<T> {
+    res.map_err(|e| log::error!("Can not initialize stream handler for {:?} err: {}", sig, e))
+        .ok()
+}
+
 impl Future for Signals {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        #[cfg(windows)]
+        {
+            let this = self.as_mut().get_mut();
+
+            if let Some(Poll::Ready(Some(()))) =
+                this.ctrl_break.as_mut().map(|sig| sig.poll_recv(cx))
+            {
+                this.srv.signal(Signal::Break);
+                return Poll::Ready(());
+            }
+
+            // closing the console leaves the process a few seconds to stop before it is killed
+            if let Some(Poll::Ready(Some(()))) =
+                this.ctrl_close.as_mut().map(|sig| sig.poll_recv(cx))
+            {
+                this.srv.signal(Signal::Close);
+                return Poll::Ready(());
+            }
+        }
+
         #[cfg(not(unix))]
         match self.signals.as_mut().poll(cx) {
             Poll::Ready(_) => {
@@ -81,13 +154,25 @@ impl Future for Signals {
         }
         #[cfg(unix)]
         {
-            for (sig, fut) in self.signals.iter_mut() {
-                if Pin::new(fut).poll_recv(cx).is_ready() {
-                    let sig = *sig;
-                    self.srv.signal(sig);
-                    return Poll::Ready(());
+            let this = self.as_mut().get_mut();
+
+            for (handler, sig) in this.hooks.iter_mut() {
+                while let Poll::Ready(Some(())) = Pin::new(&mut *sig).poll_recv(cx) {
+                    actix_rt::spawn(handler(this.srv.clone()));
                 }
             }
+
+            for (sig, fut) in this.signals.iter_mut() {
+                // SIGHUP is received so it does not terminate the process but is otherwise
+                // left to hooks
+                while let Poll::Ready(Some(())) = Pin::new(&mut *fut).poll_recv(cx) {
+                    this.srv.signal(*sig);
+                    if *sig != Signal::Hup {
+                        return Poll::Ready(());
+                    }
+                }
+            }
+
             Poll::Pending
         }
     }
@@ -1,23 +1,43 @@
 pub(crate) use std::net::{
     SocketAddr as StdSocketAddr, TcpListener as StdTcpListener, ToSocketAddrs,
+    UdpSocket as StdUdpSocket,
 };
 
-pub(crate) use mio::net::{TcpListener as MioTcpListener, TcpSocket as MioTcpSocket};
+pub(crate) use mio::net::{
+    TcpListener as MioTcpListener, TcpSocket as MioTcpSocket, UdpSocket as MioUdpSocket,
+};
 #[cfg(unix)]
 pub(crate) use {
     mio::net::UnixListener as MioUnixListener,
     std::os::unix::net::UnixListener as StdUnixListener,
 };
 
-use std::{fmt, io};
+use std::{
+    any::{Any, TypeId},
+    convert::TryFrom,
+    fmt, io,
+    sync::Arc,
+};
 
 use actix_rt::net::TcpStream;
+use actix_utils::future::ready;
+use futures_core::future::LocalBoxFuture;
 use mio::{event::Source, Interest, Registry, Token};
 
+use crate::addr::ListenerAddr;
+use crate::memory::MemoryListener;
+use crate::udp::{Datagram, MAX_DATAGRAM_SIZE};
+
 pub(crate) enum MioListener {
     Tcp(MioTcpListener),
     #[cfg(unix)]
     Uds(MioUnixListener),
+    /// UDP socket registered with the poll, a handle to the same socket used for replies and
+    /// the buffer datagrams are received into.
+    Udp(MioUdpSocket, Arc<StdUdpSocket>, Box<[u8]>),
+    Memory(MemoryListener),
+    /// Listener of a custom transport.
+    Custom(Box<dyn AnyListener>),
 }
 
 impl MioListener {
@@ -32,14 +52,62 @@ impl MioListener {
                 .local_addr()
                 .map(SocketAddr::Uds)
                 .unwrap_or(SocketAddr::Unknown),
+            MioListener::Udp(ref sock, ..) => sock
+                .local_addr()
+                .map(SocketAddr::Udp)
+                .unwrap_or(SocketAddr::Unknown),
+            MioListener::Memory(_) => SocketAddr::Memory,
+            MioListener::Custom(ref lst) => SocketAddr::Custom(lst.local_addr()),
         }
     }
 
-    pub(crate) fn accept(&self) -> io::Result<MioStream> {
+    /// Create another handle to this listener for another accept loop.
+    ///
+    /// Returns `None` for listeners only one accept loop can accept on.
+    pub(crate) fn try_clone(&self) -> Option<io::Result<MioListener>> {
+        match *self {
+            MioListener::Tcp(ref lst) => Some(
+                socket2::SockRef::from(lst)
+                    .try_clone()
+                    .map(|sock| MioListener::Tcp(MioTcpListener::from_std(sock.into()))),
+            ),
+            #[cfg(unix)]
+            MioListener::Uds(ref lst) => Some(
+                socket2::SockRef::from(lst)
+                    .try_clone()
+                    .map(|sock| MioListener::Uds(MioUnixListener::from_std(sock.into()))),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Returns true if `stream` could have been accepted by this listener.
+    pub(crate) fn is_stream_kind(&self, stream: &MioStream) -> bool {
+        match (self, stream) {
+            (MioListener::Tcp(_), MioStream::Tcp(_)) => true,
+            #[cfg(unix)]
+            (MioListener::Uds(_), MioStream::Uds(_)) => true,
+            (MioListener::Memory(_), MioStream::Memory(_)) => true,
+            (MioListener::Custom(lst), MioStream::Custom(stream)) => {
+                lst.stream_type() == <dyn Any>::type_id(&**stream)
+            }
+            _ => false,
+        }
+    }
+
+    /// Accept a new connection or, for UDP listeners, receive the next datagram.
+    pub(crate) fn accept(&mut self) -> io::Result<MioStream> {
         match *self {
             MioListener::Tcp(ref lst) => lst.accept().map(|(stream, _)| MioStream::Tcp(stream)),
             #[cfg(unix)]
             MioListener::Uds(ref lst) => lst.accept().map(|(stream, _)| MioStream::Uds(stream)),
+            MioListener::Udp(ref sock, ref shared, ref mut buf) => {
+                let (len, peer) = sock.recv_from(buf)?;
+                let dgram = Datagram::new(buf[..len].to_vec(), peer, shared.clone());
+                Ok(MioStream::Udp(dgram))
+            }
+            MioListener::Memory(ref lst) => lst.accept().map(MioStream::Memory),
+            MioListener::Custom(ref mut lst) => lst.accept(),
         }
     }
 }
@@ -55,6 +123,14 @@ impl Source for MioListener {
             MioListener::Tcp(ref mut lst) => lst.register(registry, token, interests),
             #[cfg(unix)]
             MioListener::Uds(ref mut lst) => lst.register(registry, token, interests),
+            MioListener::Udp(ref mut sock, ..) => sock.register(registry, token, interests),
+            MioListener::Custom(ref mut lst) => {
+                lst.source().register(registry, token, interests)
+            }
+            MioListener::Memory(ref lst) => {
+                lst.set_registered(Some(token.0));
+                Ok(())
+            }
         }
     }
 
@@ -68,6 +144,14 @@ impl Source for MioListener {
             MioListener::Tcp(ref mut lst) => lst.reregister(registry, token, interests),
             #[cfg(unix)]
             MioListener::Uds(ref mut lst) => lst.reregister(registry, token, interests),
+            MioListener::Udp(ref mut sock, ..) => sock.reregister(registry, token, interests),
+            MioListener::Custom(ref mut lst) => {
+                lst.source().reregister(registry, token, interests)
+            }
+            MioListener::Memory(ref lst) => {
+                lst.set_registered(Some(token.0));
+                Ok(())
+            }
         }
     }
 
@@ -75,16 +159,12 @@ impl Source for MioListener {
         match *self {
             MioListener::Tcp(ref mut lst) => lst.deregister(registry),
             #[cfg(unix)]
-            MioListener::Uds(ref mut lst) => {
-                let res = lst.deregister(registry);
-
-                // cleanup file path
-                if let Ok(addr) = lst.local_addr() {
-                    if let Some(path) = addr.as_pathname() {
-                        let _ = std::fs::remove_file(path);
-                    }
-                }
-                res
+            MioListener::Uds(ref mut lst) => lst.deregister(registry),
+            MioListener::Udp(ref mut sock, ..) => sock.deregister(registry),
+            MioListener::Custom(ref mut lst) => lst.source().deregister(registry),
+            MioListener::Memory(ref lst) => {
+                lst.set_registered(None);
+                Ok(())
             }
         }
     }
@@ -96,6 +176,18 @@ impl From<StdTcpListener> for MioListener {
     }
 }
 
+impl TryFrom<StdUdpSocket> for MioListener {
+    type Error = io::Error;
+
+    fn try_from(sock: StdUdpSocket) -> io::Result<Self> {
+        // Both handles share the same underlying socket. The mio one is only used for reading
+        // and registration, the other is handed out with received datagrams.
+        let shared = Arc::new(sock.try_clone()?);
+        let buf = vec![0; MAX_DATAGRAM_SIZE].into_boxed_slice();
+        Ok(MioListener::Udp(MioUdpSocket::from_std(sock), shared, buf))
+    }
+}
+
 #[cfg(unix)]
 impl From<StdUnixListener> for MioListener {
     fn from(lst: StdUnixListener) -> Self {
@@ -109,6 +201,9 @@ impl fmt::Debug for MioListener {
             MioListener::Tcp(ref lst) => write!(f, "{:?}", lst),
             #[cfg(all(unix))]
             MioListener::Uds(ref lst) => write!(f, "{:?}", lst),
+            MioListener::Udp(ref sock, ..) => write!(f, "{:?}", sock),
+            MioListener::Memory(ref lst) => write!(f, "{:?}", lst),
+            MioListener::Custom(ref lst) => write!(f, "CustomListener({})", lst.local_addr()),
         }
     }
 }
@@ -118,7 +213,11 @@ impl fmt::Display for MioListener {
         match *self {
             MioListener::Tcp(ref lst) => write!(f, "{:?}", lst),
             #[cfg(unix)]
-            MioListener::Uds(ref lst) => write!(f, "{:?}", lst),
+            MioListener::Uds(_) => write!(f, "{}", self.local_addr()),
+            MioListener::Udp(ref sock, ..) => write!(f, "{:?}", sock),
+            MioListener::Memory(_) | MioListener::Custom(_) => {
+                write!(f, "{}", self.local_addr())
+            }
         }
     }
 }
@@ -128,6 +227,9 @@ pub(crate) enum SocketAddr {
     Tcp(StdSocketAddr),
     #[cfg(unix)]
     Uds(mio::net::SocketAddr),
+    Udp(StdSocketAddr),
+    Memory,
+    Custom(ListenerAddr),
 }
 
 impl fmt::Display for SocketAddr {
@@ -136,7 +238,13 @@ impl fmt::Display for SocketAddr {
             Self::Unknown => write!(f, "Unknown SocketAddr"),
             Self::Tcp(ref addr) => write!(f, "{}", addr),
             #[cfg(unix)]
-            Self::Uds(ref addr) => write!(f, "{:?}", addr),
+            Self::Uds(ref addr) => match addr.as_abstract_namespace() {
+                Some(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+                None => write!(f, "{:?}", addr),
+            },
+            Self::Udp(ref addr) => write!(f, "{} (udp)", addr),
+            Self::Memory => write!(f, "memory"),
+            Self::Custom(ref addr) => write!(f, "{}", addr),
         }
     }
 }
@@ -148,20 +256,284 @@ impl fmt::Debug for SocketAddr {
             Self::Tcp(ref addr) => write!(f, "{:?}", addr),
             #[cfg(unix)]
             Self::Uds(ref addr) => write!(f, "{:?}", addr),
+            Self::Udp(ref addr) => write!(f, "{:?}", addr),
+            Self::Memory => write!(f, "Memory"),
+            Self::Custom(ref addr) => write!(f, "{:?}", addr),
         }
     }
 }
 
+/// Stream accepted by a listener, before it is converted to the stream type of a service.
+///
+/// More variants may be added for new kinds of listeners.
+#[non_exhaustive]
 #[derive(Debug)]
 pub enum MioStream {
+    /// Stream accepted by a TCP listener.
     Tcp(mio::net::TcpStream),
+
+    /// Stream accepted by a unix domain socket listener.
     #[cfg(unix)]
     Uds(mio::net::UnixStream),
+
+    /// Datagram received by a UDP socket.
+    Udp(Datagram),
+
+    /// Connection accepted by a [`MemoryListener`](crate::MemoryListener).
+    Memory(tokio::io::DuplexStream),
+
+    /// Stream accepted by a custom [`Listener`], see [`downcast`](Self::downcast).
+    Custom(Box<dyn Any + Send>),
 }
 
-/// helper trait for converting mio stream to tokio stream.
+impl MioStream {
+    /// Returns stream accepted by a custom [`Listener`] if it is of type `T`.
+    ///
+    /// Returns the stream back if it was accepted by another kind of listener.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        match self {
+            MioStream::Custom(stream) => stream
+                .downcast()
+                .map(|stream| *stream)
+                .map_err(Self::Custom),
+            stream => Err(stream),
+        }
+    }
+
+    /// Error returned by [`FromStream::from_mio`] for streams of listeners it does not support.
+    pub(crate) fn unsupported(&self, ty: &str) -> io::Error {
+        let kind = match self {
+            MioStream::Tcp(_) => "TCP",
+            #[cfg(unix)]
+            MioStream::Uds(_) => "unix domain socket",
+            MioStream::Udp(_) => "UDP",
+            MioStream::Memory(_) => "memory",
+            MioStream::Custom(_) => "custom",
+        };
+
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("stream of {} listener can not be converted to {}", kind, ty),
+        )
+    }
+}
+
+/// Listener of a custom transport, added to the server with
+/// [`ServerBuilder::listen_custom`](crate::ServerBuilder::listen_custom).
+///
+/// The listener is registered with the `mio` poll of the accept loop through its [`Source`]
+/// implementation, which for transports based on file descriptors is usually one delegating to
+/// [`SourceFd`](mio::unix::SourceFd). Once it is readable, the accept loop calls
+/// [`accept`](Self::accept) until it returns an error of kind `WouldBlock`; readiness is edge
+/// triggered. Accepted streams are sent to workers as [`MioStream::Custom`] and converted to the
+/// stream type of the service with its [`FromStream`] implementation, which takes them back with
+/// [`MioStream::downcast`].
+///
+/// The listener has to be registered with the `mio` version this crate depends on.
+///
+/// # Examples
+/// ```
+/// use std::io;
+///
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{FromStream, Listener, ListenerAddr, MioStream, Server};
+/// use actix_service::fn_service;
+/// use mio::{event::Source, net::TcpListener, Interest, Registry, Token};
+///
+/// /// TCP listener of a tunnel, accepting connections of its clients.
+/// struct TunnelListener(TcpListener);
+///
+/// impl Source for TunnelListener {
+///     fn register(&mut self, reg: &Registry, token: Token, int: Interest) -> io::Result<()> {
+///         self.0.register(reg, token, int)
+///     }
+///
+///     fn reregister(&mut self, reg: &Registry, token: Token, int: Interest) -> io::Result<()> {
+///         self.0.reregister(reg, token, int)
+///     }
+///
+///     fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+///         self.0.deregister(registry)
+///     }
+/// }
+///
+/// impl Listener for TunnelListener {
+///     type Stream = mio::net::TcpStream;
+///
+///     fn accept(&mut self) -> io::Result<Self::Stream> {
+///         self.0.accept().map(|(stream, _)| stream)
+///     }
+///
+///     fn local_addr(&self) -> ListenerAddr {
+///         self.0.local_addr().map_or(ListenerAddr::Other, ListenerAddr::Tcp)
+///     }
+/// }
+///
+/// /// Connection of a tunnel client, converted on the worker thread.
+/// struct TunnelStream(TcpStream);
+///
+/// impl FromStream for TunnelStream {
+///     fn from_mio(sock: MioStream) -> io::Result<Self> {
+///         let stream = sock
+///             .downcast::<mio::net::TcpStream>()
+///             .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "not a tunnel stream"))?;
+///         TcpStream::from_mio(MioStream::Tcp(stream)).map(TunnelStream)
+///     }
+/// }
+///
+/// # fn run() -> io::Result<()> {
+/// let lst = TcpListener::bind("127.0.0.1:8080".parse().unwrap())?;
+///
+/// let server = Server::build()
+///     .listen_custom("tunnel", TunnelListener(lst), || {
+///         fn_service(|io: TunnelStream| async move {
+///             // serve connection
+///             Ok::<_, ()>(())
+///         })
+///     })
+///     .run();
+/// # Ok(())
+/// # }
+/// ```
+pub trait Listener: Source + Send + 'static {
+    /// Stream accepted by the listener.
+    type Stream: Send + 'static;
+
+    /// Accepts a new stream.
+    ///
+    /// Returns an error of kind `WouldBlock` once no more streams are pending.
+    fn accept(&mut self) -> io::Result<Self::Stream>;
+
+    /// Returns address of the listener, passed to services as [`ListenerAddr::current`] and
+    /// shown in log messages.
+    fn local_addr(&self) -> ListenerAddr {
+        ListenerAddr::Other
+    }
+}
+
+/// Object safe [`Listener`], accepting streams as [`MioStream::Custom`].
+pub(crate) trait AnyListener: Send {
+    fn accept(&mut self) -> io::Result<MioStream>;
+
+    fn local_addr(&self) -> ListenerAddr;
+
+    /// Returns type of accepted streams.
+    fn stream_type(&self) -> TypeId;
+
+    fn source(&mut self) -> &mut dyn Source;
+}
+
+impl<L: Listener> AnyListener for L {
+    fn accept(&mut self) -> io::Result<MioStream> {
+        Listener::accept(self).map(|stream| MioStream::Custom(Box::new(stream)))
+    }
+
+    fn local_addr(&self) -> ListenerAddr {
+        Listener::local_addr(self)
+    }
+
+    fn stream_type(&self) -> TypeId {
+        TypeId::of::<L::Stream>()
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+}
+
+/// Conversion of accepted streams to the stream type of a service.
+///
+/// Implemented by the stream types services receive, e.g. `TcpStream`, `UnixStream` or
+/// [`ProxyStream`](crate::ProxyStream). Implement it for a custom transport layered on top of
+/// them and add its service with [`ServerBuilder::bind_stream`](crate::ServerBuilder::bind_stream).
+///
+/// Conversion runs on the worker thread the connection was dispatched to. A transport that has to
+/// exchange data with the client first, e.g. to read a preface or negotiate a protocol, does so
+/// in [`handshake`](Self::handshake), which does not delay other connections of the worker.
+///
+/// # Examples
+/// ```
+/// use std::io;
+///
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{FromStream, MioStream, Server};
+/// use actix_service::fn_service;
+/// use futures_core::future::LocalBoxFuture;
+/// use tokio::io::AsyncReadExt;
+///
+/// /// TCP stream of a client that started with a `HELO` preface.
+/// struct Preface(TcpStream);
+///
+/// impl FromStream for Preface {
+///     const HANDSHAKE: bool = true;
+///
+///     fn from_mio(sock: MioStream) -> io::Result<Self> {
+///         TcpStream::from_mio(sock).map(Preface)
+///     }
+///
+///     fn handshake(self) -> LocalBoxFuture<'static, io::Result<Self>> {
+///         Box::pin(async move {
+///             let mut io = self.0;
+///             let mut buf = [0; 4];
+///             io.read_exact(&mut buf).await?;
+///
+///             if &buf != b"HELO" {
+///                 return Err(io::Error::new(io::ErrorKind::InvalidData, "missing preface"));
+///             }
+///
+///             Ok(Preface(io))
+///         })
+///     }
+/// }
+///
+/// # fn run() -> io::Result<()> {
+/// let server = Server::build()
+///     .bind_stream("app", "0.0.0.0:8080", || {
+///         fn_service(|io: Preface| async move {
+///             // serve connection
+///             Ok::<_, ()>(())
+///         })
+///     })?
+///     .run();
+/// # Ok(())
+/// # }
+/// ```
 pub trait FromStream: Sized {
+    /// Whether [`handshake`](Self::handshake) has to complete before the stream is passed to
+    /// the service.
+    const HANDSHAKE: bool = false;
+
+    /// Converts accepted stream.
+    ///
+    /// Returns an error of kind [`InvalidInput`](io::ErrorKind::InvalidInput) for streams of
+    /// listeners the implementation does not support, e.g. when its service was added with
+    /// [`ServerBuilder::listen_custom`](crate::ServerBuilder::listen_custom) to a listener
+    /// accepting another stream type. The connection is then rejected.
     fn from_mio(sock: MioStream) -> io::Result<Self>;
+
+    /// Performs protocol exchange needed on a new stream, e.g. reading a PROXY protocol header.
+    ///
+    /// Only called when [`HANDSHAKE`](Self::HANDSHAKE) is true.
+    fn handshake(self) -> LocalBoxFuture<'static, io::Result<Self>>
+    where
+        Self: 'static,
+    {
+        Box::pin(ready(Ok(self)))
+    }
+}
+
+/// Conversion of streams back to accepted streams, used by
+/// [`Server::hand_off`](crate::Server::hand_off) to pass them to another service.
+pub trait IntoStream {
+    /// Converts stream back to the stream accepted by its listener.
+    fn into_mio(self) -> io::Result<MioStream>;
+}
+
+impl IntoStream for TcpStream {
+    fn into_mio(self) -> io::Result<MioStream> {
+        let std = self.into_std()?;
+        Ok(MioStream::Tcp(mio::net::TcpStream::from_std(std)))
+    }
 }
 
 #[cfg(windows)]
@@ -179,6 +551,7 @@ mod win_impl {
                     // SAFETY: This is a in place conversion from mio stream to tokio stream.
                     TcpStream::from_std(unsafe { FromRawSocket::from_raw_socket(raw) })
                 }
+                sock => Err(sock.unsupported("TcpStream")),
             }
         }
     }
@@ -201,23 +574,28 @@ mod unix_impl {
                     // SAFETY: This is a in place conversion from mio stream to tokio stream.
                     TcpStream::from_std(unsafe { FromRawFd::from_raw_fd(raw) })
                 }
-                MioStream::Uds(_) => {
-                    panic!("Should not happen, bug in server impl");
-                }
+                sock => Err(sock.unsupported("TcpStream")),
             }
         }
     }
 
+    impl IntoStream for UnixStream {
+        fn into_mio(self) -> io::Result<MioStream> {
+            let std = self.into_std()?;
+            Ok(MioStream::Uds(mio::net::UnixStream::from_std(std)))
+        }
+    }
+
     // FIXME: This is a workaround and we need an efficient way to convert between mio and tokio stream
     impl FromStream for UnixStream {
         fn from_mio(sock: MioStream) -> io::Result<Self> {
             match sock {
-                MioStream::Tcp(_) => panic!("Should not happen, bug in server impl"),
                 MioStream::Uds(mio) => {
                     let raw = IntoRawFd::into_raw_fd(mio);
                     // SAFETY: This is a in place conversion from mio stream to tokio stream.
                     UnixStream::from_std(unsafe { FromRawFd::from_raw_fd(raw) })
                 }
+                sock => Err(sock.unsupported("UnixStream")),
             }
         }
     }
@@ -243,6 +621,32 @@ mod tests {
         assert!(format!("{}", lst).contains("127.0.0.1"));
     }
 
+    #[test]
+    fn udp() {
+        let sock = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+        sock.set_nonblocking(true).unwrap();
+        let addr = sock.local_addr().unwrap();
+        let mut lst = MioListener::try_from(sock).unwrap();
+
+        let peer = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to(b"hello", addr).unwrap();
+        peer.send_to(b"hi", addr).unwrap();
+
+        // received datagrams do not carry data of previous ones in the reused buffer
+        for data in [&b"hello"[..], &b"hi"[..]].iter() {
+            let dgram = loop {
+                match lst.accept() {
+                    Ok(MioStream::Udp(dgram)) => break dgram,
+                    Ok(_) => panic!("not a datagram"),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    Err(e) => panic!("{}", e),
+                }
+            };
+            assert_eq!(dgram.data(), *data);
+            assert_eq!(dgram.peer_addr(), peer.local_addr().unwrap());
+        }
+    }
+
     #[test]
     #[cfg(unix)]
     fn uds() {
@@ -258,4 +662,14 @@ mod tests {
             assert!(format!("{}", lst).contains("/tmp/sock.xxxxx"));
         }
     }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn uds_abstract() {
+        let name = format!("actix-server-test-{}", std::process::id());
+        let opts = crate::uds::UdsOptions::default();
+        let lst = crate::uds::bind_uds_listener(format!("@{}", name).as_ref(), &opts).unwrap();
+        let lst = MioListener::from(lst);
+        assert_eq!(format!("{}", lst), format!("@{}", name));
+    }
 }
@@ -0,0 +1,84 @@
+//! Socket activation; sockets passed by a service manager such as systemd.
+//!
+//! See `sd_listen_fds(3)` for the protocol.
+
+use std::{env, io, os::unix::io::RawFd};
+
+/// First file descriptor passed by the service manager.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Returns file descriptors passed to this process by the service manager with their names.
+///
+/// Names are `None` if the service manager did not set `LISTEN_FDNAMES`. Returns an empty list if
+/// no sockets were passed or they were meant for another process.
+pub(crate) fn listen_fds() -> io::Result<Vec<(RawFd, Option<String>)>> {
+    parse_listen_fds(|key| env::var(key).ok())
+}
+
+/// Parse the file descriptors passed by the service manager from variables looked up with `var`.
+fn parse_listen_fds<F>(var: F) -> io::Result<Vec<(RawFd, Option<String>)>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let pid = var("LISTEN_PID").and_then(|pid| pid.parse::<u32>().ok());
+
+    // variables may be inherited from a parent process that was socket activated
+    if pid != Some(std::process::id()) {
+        return Ok(Vec::new());
+    }
+
+    let count = var("LISTEN_FDS")
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid LISTEN_FDS"))?;
+
+    let names = var("LISTEN_FDNAMES");
+    let mut names = names.as_deref().map(|names| names.split(':'));
+
+    Ok((0..count)
+        .map(|idx| {
+            let name = names.as_mut().and_then(|names| names.next());
+            (LISTEN_FDS_START + idx, name.map(str::to_owned))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(vars: &[(&str, &str)]) -> io::Result<Vec<(RawFd, Option<String>)>> {
+        parse_listen_fds(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| (*v).to_owned())
+        })
+    }
+
+    #[test]
+    fn parse_env() {
+        let pid = std::process::id().to_string();
+
+        assert_eq!(
+            parse(&[
+                ("LISTEN_PID", &pid),
+                ("LISTEN_FDS", "2"),
+                ("LISTEN_FDNAMES", "http:admin")
+            ])
+            .unwrap(),
+            vec![(3, Some("http".to_owned())), (4, Some("admin".to_owned()))]
+        );
+
+        assert_eq!(
+            parse(&[("LISTEN_PID", &pid), ("LISTEN_FDS", "2")]).unwrap(),
+            vec![(3, None), (4, None)]
+        );
+
+        assert!(parse(&[("LISTEN_PID", &pid), ("LISTEN_FDS", "two")]).is_err());
+
+        assert!(parse(&[("LISTEN_PID", "1"), ("LISTEN_FDS", "2")])
+            .unwrap()
+            .is_empty());
+
+        assert!(parse(&[]).unwrap().is_empty());
+    }
+}
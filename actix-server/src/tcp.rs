@@ -0,0 +1,276 @@
+use std::{io, time::Duration};
+
+use socket2::{SockRef, TcpKeepalive};
+
+use crate::socket::MioTcpListener;
+
+/// Socket options for TCP listeners, set with
+/// [`ServerBuilder::tcp_options`](crate::ServerBuilder::tcp_options).
+///
+/// By default the options are set on every accepted stream before it is passed to the service.
+/// Options left unset keep the operating system defaults.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_server::TcpOptions;
+///
+/// let opts = TcpOptions::new()
+///     .nodelay(true)
+///     .keepalive(Duration::from_secs(60))
+///     .keepalive_interval(Duration::from_secs(10))
+///     .keepalive_retries(3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TcpOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    keepalive_retries: Option<u32>,
+    ttl: Option<u32>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    fast_open: Option<u32>,
+    on_listener: bool,
+}
+
+impl TcpOptions {
+    /// Create options leaving all socket options at their defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `TCP_NODELAY`, disabling Nagle's algorithm when enabled.
+    pub fn nodelay(mut self, enabled: bool) -> Self {
+        self.nodelay = Some(enabled);
+        self
+    }
+
+    /// Enable `SO_KEEPALIVE`, sending the first keepalive probe after the connection was idle
+    /// for `idle`.
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// Set time between keepalive probes and enable `SO_KEEPALIVE`.
+    ///
+    /// Only supported on Linux, Android, BSDs, illumos and Apple platforms; ignored elsewhere.
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = Some(interval);
+        self
+    }
+
+    /// Set number of unanswered keepalive probes after which the connection is dropped and
+    /// enable `SO_KEEPALIVE`.
+    ///
+    /// Only supported on Linux, Android, BSDs, illumos and Apple platforms; ignored elsewhere.
+    pub fn keepalive_retries(mut self, retries: u32) -> Self {
+        self.keepalive_retries = Some(retries);
+        self
+    }
+
+    /// Set `IP_TTL`, the time-to-live of outgoing packets.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set `SO_RCVBUF`, the size of the kernel receive buffer.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_SNDBUF`, the size of the kernel send buffer.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Enable TCP Fast Open (`TCP_FASTOPEN`) on the listener, with at most `queue_len` pending
+    /// Fast Open requests.
+    ///
+    /// Clients that connected before can send data with their `SYN`, saving a round trip before
+    /// the service sees the first request. Accepted streams are the same as without Fast Open, but
+    /// data sent with the `SYN` may be readable right away and, since a `SYN` can be duplicated
+    /// in the network, may be delivered twice; services should only enable this for protocols
+    /// whose first request is safe to replay, e.g. TLS handshakes.
+    ///
+    /// Always set on the listener, regardless of [`on_listener()`](Self::on_listener). Only
+    /// supported on Linux and Android, where the server side must be enabled in the
+    /// `net.ipv4.tcp_fastopen` sysctl; ignored elsewhere.
+    pub fn fast_open(mut self, queue_len: u32) -> Self {
+        self.fast_open = Some(queue_len);
+        self
+    }
+
+    /// Set options once on the listener instead of on every accepted stream.
+    ///
+    /// This saves system calls per connection but relies on accepted sockets inheriting the
+    /// options from their listener, which differs between platforms and options. Setting the
+    /// receive buffer size on the listener is also required for buffers larger than 64KiB to be
+    /// used from the start of a connection.
+    ///
+    /// Disabled by default.
+    pub fn on_listener(mut self, enabled: bool) -> Self {
+        self.on_listener = enabled;
+        self
+    }
+
+    pub(crate) fn is_on_listener(&self) -> bool {
+        self.on_listener
+    }
+
+    /// Set options on a listener.
+    pub(crate) fn apply_listener(&self, lst: &MioTcpListener) -> io::Result<()> {
+        self.apply(SockRef::from(lst))
+    }
+
+    /// Set options that only apply to listeners.
+    pub(crate) fn apply_listener_only(&self, lst: &MioTcpListener) -> io::Result<()> {
+        match self.fast_open {
+            Some(queue_len) => set_fast_open(lst, queue_len),
+            None => Ok(()),
+        }
+    }
+
+    /// Set options on an accepted stream.
+    pub(crate) fn apply_stream(&self, stream: &mio::net::TcpStream) -> io::Result<()> {
+        self.apply(SockRef::from(stream))
+    }
+
+    fn apply(&self, sock: SockRef<'_>) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            sock.set_nodelay(nodelay)?;
+        }
+
+        if let Some(keepalive) = self.tcp_keepalive() {
+            sock.set_tcp_keepalive(&keepalive)?;
+        }
+
+        if let Some(ttl) = self.ttl {
+            sock.set_ttl(ttl)?;
+        }
+
+        if let Some(size) = self.recv_buffer_size {
+            sock.set_recv_buffer_size(size)?;
+        }
+
+        if let Some(size) = self.send_buffer_size {
+            sock.set_send_buffer_size(size)?;
+        }
+
+        Ok(())
+    }
+
+    fn tcp_keepalive(&self) -> Option<TcpKeepalive> {
+        if self.keepalive.is_none()
+            && self.keepalive_interval.is_none()
+            && self.keepalive_retries.is_none()
+        {
+            return None;
+        }
+
+        let mut keepalive = TcpKeepalive::new();
+
+        if let Some(idle) = self.keepalive {
+            keepalive = keepalive.with_time(idle);
+        }
+
+        Some(self.keepalive_probes(keepalive))
+    }
+
+    #[cfg(any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "fuchsia",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_vendor = "apple",
+    ))]
+    fn keepalive_probes(&self, mut keepalive: TcpKeepalive) -> TcpKeepalive {
+        if let Some(interval) = self.keepalive_interval {
+            keepalive = keepalive.with_interval(interval);
+        }
+
+        if let Some(retries) = self.keepalive_retries {
+            keepalive = keepalive.with_retries(retries);
+        }
+
+        keepalive
+    }
+
+    #[cfg(not(any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "fuchsia",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_vendor = "apple",
+    )))]
+    fn keepalive_probes(&self, keepalive: TcpKeepalive) -> TcpKeepalive {
+        keepalive
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_fast_open(lst: &MioTcpListener, queue_len: u32) -> io::Result<()> {
+    use std::{convert::TryFrom as _, mem, os::unix::io::AsRawFd as _};
+
+    let queue_len = libc::c_int::try_from(queue_len).unwrap_or(libc::c_int::MAX);
+    let res = unsafe {
+        libc::setsockopt(
+            lst.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            &queue_len as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_fast_open(_: &MioTcpListener, _: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{mem, os::unix::io::AsRawFd as _};
+
+    use super::*;
+
+    #[test]
+    fn fast_open() {
+        let lst = MioTcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        TcpOptions::new()
+            .fast_open(16)
+            .apply_listener_only(&lst)
+            .unwrap();
+
+        let mut queue_len: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let res = unsafe {
+            libc::getsockopt(
+                lst.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_FASTOPEN,
+                &mut queue_len as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        assert_eq!(res, 0);
+        assert_eq!(queue_len, 16);
+    }
+}
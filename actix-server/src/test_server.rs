@@ -49,7 +49,8 @@ impl TestServer {
         // run server in separate thread
         thread::spawn(move || {
             let sys = System::new();
-            factory(Server::build()).workers(1).disable_signals().run();
+            let srv = factory(Server::build()).workers(1).disable_signals().run();
+            let _ = sys.block_on(srv.ready());
 
             tx.send(System::current()).unwrap();
             sys.run()
@@ -75,12 +76,13 @@ impl TestServer {
             let local_addr = tcp.local_addr().unwrap();
 
             sys.block_on(async {
-                Server::build()
+                let srv = Server::build()
                     .listen("test", tcp, factory)
                     .unwrap()
                     .workers(1)
                     .disable_signals()
                     .run();
+                let _ = srv.ready().await;
                 tx.send((System::current(), local_addr)).unwrap();
             });
             sys.run()
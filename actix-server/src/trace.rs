@@ -0,0 +1,95 @@
+//! Tracing of connections through the accept loop and workers, enabled with the `tracing`
+//! feature. Without it the types here are empty and their methods do nothing.
+
+use std::future::Future;
+#[cfg(feature = "tracing")]
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use crate::close::CloseReason;
+
+#[cfg(feature = "tracing")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Span of a connection, opened when it is accepted and entered by the worker running its
+/// service.
+///
+/// The span is named `connection` and records a process wide connection `id`, the name of the
+/// `listener` and the index of the `worker`, once known. Its events report the time the
+/// connection waited between being accepted and its service being called, and the reason it was
+/// closed.
+#[derive(Debug)]
+pub(crate) struct ConnSpan {
+    /// Span and time the connection was accepted; boxed to keep connections in channels small.
+    #[cfg(feature = "tracing")]
+    inner: Box<(tracing::Span, Instant)>,
+}
+
+impl ConnSpan {
+    /// Open span of a connection accepted from listener `name`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn accepted(name: &str) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let span = tracing::info_span!(
+                target: "actix_server",
+                "connection",
+                id,
+                listener = name,
+                worker = tracing::field::Empty,
+            );
+            tracing::trace!(target: "actix_server", parent: &span, "connection accepted");
+
+            Self {
+                inner: Box::new((span, Instant::now())),
+            }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        Self {}
+    }
+
+    /// Call `f` starting the service of the connection on worker `idx` in the span.
+    ///
+    /// The task of the connection runs in the span as well, see [`in_current_span`].
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn in_worker<R>(self, idx: usize, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "tracing")]
+        {
+            let (span, accepted) = *self.inner;
+            span.record("worker", idx);
+            let _enter = span.enter();
+            tracing::debug!(
+                target: "actix_server",
+                queue_time_us = accepted.elapsed().as_micros() as u64,
+                "connection dispatched",
+            );
+            f()
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+}
+
+/// Run `fut`, the task of a connection, in the span of the connection if it is the current one.
+#[cfg(feature = "tracing")]
+pub(crate) fn in_current_span<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    tracing::Instrument::in_current_span(fut)
+}
+
+/// Run `fut`, the task of a connection, in the span of the connection if it is the current one.
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn in_current_span<F: Future>(fut: F) -> F {
+    fut
+}
+
+/// Report that the current connection was closed.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn closed(reason: CloseReason) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "actix_server", ?reason, "connection closed");
+}
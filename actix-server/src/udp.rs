@@ -0,0 +1,75 @@
+use std::{fmt, io, net::SocketAddr, sync::Arc};
+
+use crate::socket::{FromStream, MioStream, StdUdpSocket};
+
+/// Maximum payload of a single UDP datagram.
+pub(crate) const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// A datagram received on a UDP listener.
+///
+/// UDP listeners are added with [`ServerBuilder::bind_udp`](crate::ServerBuilder::bind_udp).
+/// Each received datagram is dispatched to a worker as if it was a new connection, carrying its
+/// payload, the peer address and a handle to the listening socket that can be used to reply.
+///
+/// Replies are sent on the listening socket, which is in non-blocking mode; sending returns an
+/// [`io::ErrorKind::WouldBlock`] error when the socket send buffer is full.
+pub struct Datagram {
+    data: Vec<u8>,
+    peer: SocketAddr,
+    socket: Arc<StdUdpSocket>,
+}
+
+impl Datagram {
+    pub(crate) fn new(data: Vec<u8>, peer: SocketAddr, socket: Arc<StdUdpSocket>) -> Self {
+        Self { data, peer, socket }
+    }
+
+    /// Returns datagram payload.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes datagram, returning its payload.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Returns address of the peer that sent this datagram.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Returns local address of the listening socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Sends `buf` to the peer that sent this datagram.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.send_to(buf, self.peer)
+    }
+
+    /// Sends `buf` to the given address from the listening socket.
+    pub fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.socket.send_to(buf, addr)
+    }
+}
+
+impl fmt::Debug for Datagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Datagram")
+            .field("len", &self.data.len())
+            .field("peer", &self.peer)
+            .field("local", &self.socket.local_addr().ok())
+            .finish()
+    }
+}
+
+impl FromStream for Datagram {
+    fn from_mio(sock: MioStream) -> io::Result<Self> {
+        match sock {
+            MioStream::Udp(dgram) => Ok(dgram),
+            sock => Err(sock.unsupported("Datagram")),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    ffi::{CString, OsStr},
+    io,
+    os::unix::{ffi::OsStrExt as _, fs::PermissionsExt as _},
+    path::Path,
+};
+
+use crate::socket::StdUnixListener;
+
+/// Options for unix domain sockets bound with
+/// [`ServerBuilder::bind_uds_with_options`](crate::ServerBuilder::bind_uds_with_options).
+///
+/// Mode and ownership are applied to the socket file before the listener starts accepting, so
+/// clients never see it with the permissions resulting from the process umask. They are ignored
+/// for abstract sockets, which have no file.
+///
+/// # Examples
+/// ```
+/// use actix_server::UdsOptions;
+///
+/// // owner and group may connect, the socket file is kept when the server stops
+/// let opts = UdsOptions::new().mode(0o660).unlink_on_stop(false);
+/// ```
+#[derive(Debug, Clone)]
+pub struct UdsOptions {
+    mode: Option<u32>,
+    owner: Option<u32>,
+    group: Option<u32>,
+    unlink_on_stop: bool,
+}
+
+impl Default for UdsOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdsOptions {
+    /// Create options keeping the file mode and ownership resulting from the process and
+    /// removing the socket file when the server stops.
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            owner: None,
+            group: None,
+            unlink_on_stop: true,
+        }
+    }
+
+    /// Set permission bits of the socket file, e.g. `0o660`.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set owning user id of the socket file; changing it usually requires privileges.
+    pub fn owner(mut self, uid: u32) -> Self {
+        self.owner = Some(uid);
+        self
+    }
+
+    /// Set owning group id of the socket file.
+    pub fn group(mut self, gid: u32) -> Self {
+        self.group = Some(gid);
+        self
+    }
+
+    /// Set whether the socket file is removed when the server stops or its listener is removed
+    /// with [`Server::unbind`](crate::Server::unbind).
+    ///
+    /// Enabled by default.
+    pub fn unlink_on_stop(mut self, unlink: bool) -> Self {
+        self.unlink_on_stop = unlink;
+        self
+    }
+
+    pub(crate) fn unlinks_on_stop(&self) -> bool {
+        self.unlink_on_stop
+    }
+}
+
+/// Returns true if `path` names a socket in the Linux abstract namespace.
+pub(crate) fn is_abstract(path: &Path) -> bool {
+    cfg!(any(target_os = "linux", target_os = "android"))
+        && matches!(path.as_os_str().as_bytes().first(), Some(b'@') | Some(0))
+}
+
+/// Bind a unix domain listener to `path`, which may name an abstract socket on Linux.
+///
+/// A stale socket file at `path` is removed first.
+pub(crate) fn bind_uds_listener(path: &Path, opts: &UdsOptions) -> io::Result<StdUnixListener> {
+    use socket2::{Domain, SockAddr, Socket, Type};
+
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+
+    if is_abstract(path) {
+        let mut name = vec![0];
+        name.extend_from_slice(&path.as_os_str().as_bytes()[1..]);
+        socket.bind(&SockAddr::unix(OsStr::from_bytes(&name))?)?;
+    } else {
+        remove_uds_path(path)?;
+        socket.bind(&SockAddr::unix(path)?)?;
+
+        if let Err(e) = set_file_options(path, opts) {
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+    }
+
+    socket.listen(libc::SOMAXCONN)?;
+    Ok(socket.into())
+}
+
+fn set_file_options(path: &Path, opts: &UdsOptions) -> io::Result<()> {
+    if opts.owner.is_some() || opts.group.is_some() {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        // -1 keeps the current id
+        let uid = opts
+            .owner
+            .map_or(libc::uid_t::MAX, |uid| uid as libc::uid_t);
+        let gid = opts
+            .group
+            .map_or(libc::gid_t::MAX, |gid| gid as libc::gid_t);
+
+        if unsafe { libc::chown(path.as_ptr(), uid, gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    if let Some(mode) = opts.mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(())
+}
+
+/// Remove socket file at `path`; it must not exist when binding.
+pub(crate) fn remove_uds_path(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        // NotFound is expected and not an issue. Anything else is.
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -1,19 +1,28 @@
 use std::{
     collections::VecDeque,
+    io,
     ops::Deref,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
 };
 
-use mio::{Registry, Token as MioToken, Waker};
+use mio::Waker;
+use tokio::sync::oneshot;
 
-use crate::worker::WorkerHandleAccept;
+use crate::accept::ServerSocketInfo;
+use crate::poller::Poller;
+use crate::socket::MioStream;
+use crate::worker::{Conn, Stop, WorkerHandleAccept, WorkerHandleServer};
+use crate::worker_status::WorkerStatus;
 
-/// Waker token for `mio::Poll` instance.
-pub(crate) const WAKER_TOKEN: MioToken = MioToken(usize::MAX);
+/// Waker with a queue for waking up the `Accept`'s `Poller` and contains the
+/// `WakerInterest` the `Poller` would want to look into.
+pub(crate) struct WakerQueue(Arc<WakerQueueInner>);
 
-/// `mio::Waker` with a queue for waking up the `Accept`'s `Poll` and contains the `WakerInterest`
-/// the `Poll` would want to look into.
-pub(crate) struct WakerQueue(Arc<(Waker, Mutex<VecDeque<WakerInterest>>)>);
+/// Waker, queued interests and whether wakes are coalesced.
+type WakerQueueInner = (Waker, Mutex<VecDeque<WakerInterest>>, AtomicBool);
 
 impl Clone for WakerQueue {
     fn clone(&self) -> Self {
@@ -22,7 +31,7 @@ impl Clone for WakerQueue {
 }
 
 impl Deref for WakerQueue {
-    type Target = (Waker, Mutex<VecDeque<WakerInterest>>);
+    type Target = WakerQueueInner;
 
     fn deref(&self) -> &Self::Target {
         self.0.deref()
@@ -30,29 +39,54 @@ impl Deref for WakerQueue {
 }
 
 impl WakerQueue {
-    /// Construct a waker queue with given `Poll`'s `Registry` and capacity.
+    /// Construct a waker queue for given `Poller`.
     ///
-    /// A fixed `WAKER_TOKEN` is used to identify the wake interest and the `Poll` needs to match
-    /// event's token for it to properly handle `WakerInterest`.
-    pub(crate) fn new(registry: &Registry) -> std::io::Result<Self> {
-        let waker = Waker::new(registry, WAKER_TOKEN)?;
+    /// The poller reports a fixed `WAKER_TOKEN` when woken and `Accept` needs to match the token
+    /// for it to properly handle `WakerInterest`.
+    pub(crate) fn new(poller: &Poller) -> io::Result<Self> {
+        let waker = poller.waker()?;
         let queue = Mutex::new(VecDeque::with_capacity(16));
 
-        Ok(Self(Arc::new((waker, queue))))
+        Ok(Self(Arc::new((waker, queue, AtomicBool::new(false)))))
+    }
+
+    /// Coalesce wakes while interests are queued.
+    ///
+    /// `Accept` drains the whole queue once woken, so the poller is only woken for an interest
+    /// pushed to an empty queue. `WorkerAvailable` interests already queued for the same worker
+    /// are not queued again.
+    pub(crate) fn set_coalescing(&self, enabled: bool) {
+        self.deref().2.store(enabled, Ordering::Relaxed);
     }
 
     /// Push a new interest to the queue and wake up the accept poll afterwards.
     pub(crate) fn wake(&self, interest: WakerInterest) {
-        let (waker, queue) = self.deref();
+        let (waker, queue, coalesce) = self.deref();
+
+        let mut queue = queue.lock().expect("Failed to lock WakerQueue");
+
+        if coalesce.load(Ordering::Relaxed) && !queue.is_empty() {
+            let queued = match interest {
+                WakerInterest::WorkerAvailable(idx) => queue
+                    .iter()
+                    .any(|i| matches!(i, WakerInterest::WorkerAvailable(i) if *i == idx)),
+                _ => false,
+            };
+
+            if !queued {
+                queue.push_back(interest);
+            }
 
-        queue
-            .lock()
-            .expect("Failed to lock WakerQueue")
-            .push_back(interest);
+            // poller is woken already and has not drained the queue yet
+            return;
+        }
+
+        queue.push_back(interest);
+        drop(queue);
 
         waker
             .wake()
-            .unwrap_or_else(|e| panic!("can not wake up Accept Poll: {}", e));
+            .unwrap_or_else(|e| panic!("can not wake up Accept Poller: {}", e));
     }
 
     /// Get a MutexGuard of the waker queue.
@@ -82,8 +116,115 @@ pub(crate) enum WakerInterest {
     /// connection `Accept` would deregister socket listener temporary and wake up the poll and
     /// register them again after the delayed future resolve.
     Timer,
+    /// `FdCheck` is an interest sent as a delayed future while listeners are paused and resumed
+    /// depending on file descriptor usage. `Accept` checks the usage and schedules the next check.
+    FdCheck,
+    /// `Heartbeat` is an interest sent periodically by a worker to report it is responsive.
+    Heartbeat(usize),
+    /// `HeartbeatCheck` is an interest sent as a delayed future while workers report heartbeats.
+    /// `Accept` looks for workers that stopped reporting and schedules the next check.
+    HeartbeatCheck,
     /// `Worker` is an interest happen after a worker runs into faulted state(This is determined
     /// by if work can be sent to it successfully).`Accept` would be waked up and add the new
     /// `WorkerHandleAccept`.
     Worker(WorkerHandleAccept),
+    /// `ReplaceWorker` is an interest from `ServerBuilder` restarting a worker on request.
+    /// `Accept` swaps in the handle of the replacement worker with the same index and only then
+    /// sends `stop` to the old worker; the old worker sees the stop command before its
+    /// connection channel is closed and drains its connections as requested.
+    ReplaceWorker(Box<ReplaceWorker>),
+    /// `RetireWorker` is an interest from `ServerBuilder` removing a worker started by the
+    /// autoscaler. `Accept` drops the worker's handle after sending `stop` to it, so the worker
+    /// sees the stop command before its connection channel is closed.
+    RetireWorker {
+        old: WorkerHandleServer,
+        stop: Stop,
+    },
+    /// `Redispatch` is an interest from a worker stopped with `ReplaceWorker` or `RetireWorker`
+    /// handing back connections that were queued for it but not served yet.
+    Redispatch(Vec<Conn>),
+    /// `AddListener` is an interest from `ServerBuilder` for a listener bound on a running
+    /// server. All workers have created the listener's service at this point.
+    AddListener(Box<ServerSocketInfo>),
+    /// `RemoveListener` is an interest from `ServerBuilder` to stop accepting on, and close, the
+    /// listener with given token.
+    RemoveListener(usize),
+    /// `PauseListener` and `ResumeListener` are interests from `ServerBuilder` to stop and
+    /// restart accepting on the listener with given token, independent of the whole server
+    /// being paused.
+    PauseListener(usize),
+    ResumeListener(usize),
+    /// `ConnectionAvailable` is an interest from a connection closed while the number of open
+    /// connections was at the limit set with `ServerBuilder::max_total_connections`.
+    ConnectionAvailable,
+    /// `Accept` is an interest from a listener not driven by the `Poller`, such as an in-memory
+    /// listener, that has connections to accept on the listener with given token.
+    Accept(usize),
+    /// `HandOff` is an interest from `ServerBuilder` to dispatch a connection released by a
+    /// service to the first listener of `tokens` accepting this kind of stream, optionally on a
+    /// given worker.
+    HandOff(Box<HandOff>),
+    /// `Workers` is an interest from `ServerBuilder` querying status of the workers `Accept`
+    /// sends connections to.
+    Workers(oneshot::Sender<Vec<WorkerStatus>>),
+}
+
+/// Handles of the worker being replaced and its replacement, see
+/// [`WakerInterest::ReplaceWorker`].
+pub(crate) struct ReplaceWorker {
+    pub(crate) handle: WorkerHandleAccept,
+    pub(crate) old: WorkerHandleServer,
+    pub(crate) stop: Stop,
+}
+
+/// Connection handed off to other listeners, see [`WakerInterest::HandOff`].
+pub(crate) struct HandOff {
+    pub(crate) io: MioStream,
+    pub(crate) tokens: Vec<usize>,
+    pub(crate) worker: Option<usize>,
+    pub(crate) tx: oneshot::Sender<io::Result<()>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::poller::WAKER_TOKEN;
+
+    /// Whether the poller was woken since the last call.
+    fn woken(poller: &mut Poller) -> bool {
+        let mut tokens = Vec::new();
+        poller
+            .poll(&mut tokens, Some(Duration::from_millis(0)))
+            .unwrap();
+        tokens == [WAKER_TOKEN]
+    }
+
+    #[test]
+    fn coalescing() {
+        let mut poller = Poller::new().unwrap();
+        let queue = WakerQueue::new(&poller).unwrap();
+
+        queue.wake(WakerInterest::WorkerAvailable(0));
+        assert!(woken(&mut poller));
+        queue.wake(WakerInterest::WorkerAvailable(0));
+        assert!(woken(&mut poller));
+        assert_eq!(queue.guard().len(), 2);
+        queue.guard().clear();
+
+        queue.set_coalescing(true);
+        queue.wake(WakerInterest::WorkerAvailable(0));
+        assert!(woken(&mut poller));
+        queue.wake(WakerInterest::WorkerAvailable(1));
+        queue.wake(WakerInterest::WorkerAvailable(0));
+        queue.wake(WakerInterest::Timer);
+        assert!(!woken(&mut poller));
+        assert_eq!(queue.guard().len(), 3);
+
+        // drained queue is woken again
+        queue.guard().clear();
+        queue.wake(WakerInterest::WorkerAvailable(0));
+        assert!(woken(&mut poller));
+    }
 }
@@ -1,10 +1,13 @@
 use std::{
+    cell::Cell,
+    fmt,
     future::Future,
     mem,
+    ops::Index,
     pin::Pin,
     rc::Rc,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
     task::{Context, Poll},
@@ -13,44 +16,107 @@ use std::{
 
 use actix_rt::{
     spawn,
-    time::{sleep, Instant, Sleep},
+    time::{sleep, sleep_until, Instant, Sleep},
     Arbiter,
 };
 use futures_core::{future::LocalBoxFuture, ready};
 use log::{error, info, trace};
 use tokio::sync::{
-    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-    oneshot,
+    mpsc::{
+        channel, error::TrySendError, unbounded_channel, Receiver, Sender, UnboundedReceiver,
+        UnboundedSender,
+    },
+    oneshot, watch,
 };
 
+use crate::close::{CloseReason, CloseTracker, ServiceCounters};
+use crate::close_token::CloseToken;
+use crate::conn_limit::ConnectionPermit;
+use crate::drain::DrainCounts;
 use crate::join_all;
+use crate::metrics::{WorkerEvent, WorkerMetrics};
 use crate::service::{BoxedServerService, InternalServiceFactory};
 use crate::socket::MioStream;
+use crate::trace::ConnSpan;
 use crate::waker_queue::{WakerInterest, WakerQueue};
 
 /// Stop worker message. Returns `true` on successful graceful shutdown.
 /// and `false` if some connections still alive when shutdown execute.
+///
+/// A `timeout` overrides the shutdown timeout of the worker.
 pub(crate) struct Stop {
     graceful: bool,
+    timeout: Option<Duration>,
+    redispatch: bool,
     tx: oneshot::Sender<bool>,
 }
 
+impl Stop {
+    pub(crate) fn new(
+        graceful: bool,
+        timeout: Option<Duration>,
+    ) -> (Self, oneshot::Receiver<bool>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            Self {
+                graceful,
+                timeout,
+                redispatch: false,
+                tx,
+            },
+            rx,
+        )
+    }
+
+    /// Hand connections still queued for the worker back to `Accept` for dispatch to other
+    /// workers. Only for workers `Accept` no longer sends connections to.
+    pub(crate) fn redispatch(mut self) -> Self {
+        self.redispatch = true;
+        self
+    }
+}
+
+/// Messages sent from `ServerBuilder` to a worker.
+pub(crate) enum WorkerCommand {
+    Stop(Stop),
+    /// Create a service for a listener added at runtime. Returns `true` once the service is
+    /// created and `false` if the factory failed.
+    AddService {
+        factory: Box<dyn InternalServiceFactory>,
+        tx: oneshot::Sender<bool>,
+    },
+    /// Drop the service of a listener removed at runtime. Connections already dispatched to it
+    /// are left to complete.
+    RemoveService(usize),
+    /// Change maximum number of concurrent connections.
+    MaxConn(usize),
+    /// Change graceful shutdown timeout; applies to a shutdown in progress as well.
+    ShutdownTimeout(Duration),
+}
+
 #[derive(Debug)]
 pub(crate) struct Conn {
     pub io: MioStream,
     pub token: usize,
+    /// Counts the connection against the limit on connections of all workers until dropped.
+    pub permit: Option<ConnectionPermit>,
+    pub span: ConnSpan,
+    /// Time the connection was accepted; kept when it is handed back for dispatch.
+    pub accepted: Instant,
 }
 
 fn handle_pair(
     idx: usize,
-    tx1: UnboundedSender<Conn>,
-    tx2: UnboundedSender<Stop>,
+    tx1: Sender<Conn>,
+    tx2: UnboundedSender<WorkerCommand>,
     counter: Counter,
+    full: Arc<AtomicBool>,
 ) -> (WorkerHandleAccept, WorkerHandleServer) {
     let accept = WorkerHandleAccept {
         idx,
         tx: tx1,
         counter,
+        full,
     };
 
     let server = WorkerHandleServer { idx, tx: tx2 };
@@ -70,45 +136,60 @@ fn handle_pair(
 /// unable to accept any work.
 ///
 /// `ServerWorker` always decrement the counter when every work received from `Accept` is done.
-/// On reaching counter limit worker would use `mio::Waker` and `WakerQueue` to wake up `Accept`
+/// On reaching counter limit worker would use `WakerQueue` to wake up `Accept`
 /// and notify it to update cached `Availability` again to mark worker as able to accept work again.
 ///
 /// Hence, a wake up would only happen after `Accept` increment it to limit.
 /// And a decrement to limit always wake up `Accept`.
+///
+/// The limit can be changed at runtime. A lower limit takes effect with the next increment. After
+/// raising the limit `ServerWorker` wakes up `Accept` itself, as decrements would not cross it.
 #[derive(Clone)]
 pub(crate) struct Counter {
     counter: Arc<AtomicUsize>,
-    limit: usize,
+    limit: Arc<AtomicUsize>,
 }
 
 impl Counter {
     pub(crate) fn new(limit: usize) -> Self {
         Self {
             counter: Arc::new(AtomicUsize::new(1)),
-            limit,
+            limit: Arc::new(AtomicUsize::new(limit)),
         }
     }
 
     /// Increment counter by 1 and return true when hitting limit
     #[inline(always)]
     pub(crate) fn inc(&self) -> bool {
-        self.counter.fetch_add(1, Ordering::Relaxed) != self.limit
+        self.counter.fetch_add(1, Ordering::Relaxed) < self.limit.load(Ordering::Relaxed)
     }
 
     /// Decrement counter by 1 and return true if crossing limit.
     #[inline(always)]
     pub(crate) fn dec(&self) -> bool {
-        self.counter.fetch_sub(1, Ordering::Relaxed) == self.limit
+        self.counter.fetch_sub(1, Ordering::Relaxed) == self.limit.load(Ordering::Relaxed)
     }
 
     pub(crate) fn total(&self) -> usize {
         self.counter.load(Ordering::SeqCst) - 1
     }
+
+    /// Returns number of connections that can be added before hitting limit.
+    pub(crate) fn remaining(&self) -> usize {
+        self.limit
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.total())
+    }
+
+    /// Set limit and return true if it was raised.
+    pub(crate) fn set_limit(&self, limit: usize) -> bool {
+        self.limit.swap(limit, Ordering::Relaxed) < limit
+    }
 }
 
 pub(crate) struct WorkerCounter {
     idx: usize,
-    inner: Rc<(WakerQueue, Counter)>,
+    inner: Rc<(WakerQueue, Counter, Arc<CloseTracker>, Arc<AtomicUsize>)>,
 }
 
 impl Clone for WorkerCounter {
@@ -121,30 +202,125 @@ impl Clone for WorkerCounter {
 }
 
 impl WorkerCounter {
-    pub(crate) fn new(idx: usize, waker_queue: WakerQueue, counter: Counter) -> Self {
+    pub(crate) fn new(
+        idx: usize,
+        waker_queue: WakerQueue,
+        counter: Counter,
+        close: Arc<CloseTracker>,
+    ) -> Self {
+        let open = close.worker_connections(idx);
+
         Self {
             idx,
-            inner: Rc::new((waker_queue, counter)),
+            inner: Rc::new((waker_queue, counter, close, open)),
         }
     }
 
     #[inline(always)]
-    pub(crate) fn guard(&self) -> WorkerCounterGuard {
-        WorkerCounterGuard(self.clone())
+    fn guard(
+        &self,
+        conns: Option<Rc<ListenerConns>>,
+        permit: Option<ConnectionPermit>,
+    ) -> WorkerCounterGuard {
+        if let Some(ref conns) = conns {
+            conns.open.set(conns.open.get() + 1);
+            conns.counters.open.fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.3.fetch_add(1, Ordering::Relaxed);
+
+        WorkerCounterGuard {
+            counter: self.clone(),
+            conns,
+            _permit: permit,
+            reason: None,
+            called: CallStart::default(),
+        }
     }
 
     fn total(&self) -> usize {
         self.inner.1.total()
     }
+
+    fn close(&self) -> &CloseTracker {
+        &self.inner.2
+    }
+
+    /// Hand connections sent to this worker back to `Accept`, uncounting them.
+    fn redispatch(&self, conns: Vec<Conn>) {
+        let (waker_queue, counter, ..) = &*self.inner;
+
+        conns.iter().for_each(|_| {
+            counter.dec();
+        });
+        waker_queue.wake(WakerInterest::Redispatch(conns));
+    }
+
+    /// Notify `Accept` that this worker can take connections again.
+    fn wake_accept(&self) {
+        self.inner.0.wake(WakerInterest::WorkerAvailable(self.idx));
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let (waker_queue, counter, ..) = &*self.inner;
+
+        // accept may have marked the worker unavailable at the old limit
+        if counter.set_limit(limit) && counter.total() < limit {
+            waker_queue.wake(WakerInterest::WorkerAvailable(self.idx));
+        }
+    }
+}
+
+/// Connection guard; records why the connection ended when dropped.
+///
+/// A guard dropped without [`close`](Self::close) being called belongs to a connection whose
+/// task was dropped with its worker.
+pub(crate) struct WorkerCounterGuard {
+    counter: WorkerCounter,
+    conns: Option<Rc<ListenerConns>>,
+    _permit: Option<ConnectionPermit>,
+    reason: Option<CloseReason>,
+    called: CallStart,
+}
+
+impl WorkerCounterGuard {
+    /// Returns handle for marking when the service was called with the connection.
+    pub(crate) fn call_start(&self) -> CallStart {
+        self.called.clone()
+    }
+
+    /// Record why the connection ended and, if the service was called, the call latency.
+    pub(crate) fn close(mut self, reason: CloseReason) {
+        if let (Some(start), Some(conns)) = (self.called.0.get(), self.conns.as_ref()) {
+            conns.counters.latency.record(start.elapsed());
+        }
+
+        self.reason = Some(reason);
+    }
 }
 
-pub(crate) struct WorkerCounterGuard(WorkerCounter);
+/// Time a service was called with a connection, set once handshakes such as PROXY or TLS are
+/// done so they do not count towards the service latency.
+#[derive(Clone, Default)]
+pub(crate) struct CallStart(Rc<Cell<Option<Instant>>>);
+
+impl CallStart {
+    pub(crate) fn now(&self) {
+        self.0.set(Some(Instant::now()));
+    }
+}
 
 impl Drop for WorkerCounterGuard {
     fn drop(&mut self) {
-        let (waker_queue, counter) = &*self.0.inner;
+        let (waker_queue, counter, close, open) = &*self.counter.inner;
+        close.record(self.reason.unwrap_or(CloseReason::Shutdown));
+        open.fetch_sub(1, Ordering::Relaxed);
+        if let Some(ref conns) = self.conns {
+            conns.open.set(conns.open.get() - 1);
+            conns.closed.set(conns.closed.get() + 1);
+            conns.counters.open.fetch_sub(1, Ordering::Relaxed);
+        }
         if counter.dec() {
-            waker_queue.wake(WakerInterest::WorkerAvailable(self.0.idx));
+            waker_queue.wake(WakerInterest::WorkerAvailable(self.counter.idx));
         }
     }
 }
@@ -153,10 +329,15 @@ impl Drop for WorkerCounterGuard {
 /// availability of worker to other thread.
 ///
 /// Held by [Accept](crate::accept::Accept).
+///
+/// Connections are sent over a bounded channel. When it is full, `Accept` marks the worker
+/// unavailable and sets the `full` flag; `ServerWorker` wakes up `Accept` once it takes the next
+/// connection off the channel.
 pub(crate) struct WorkerHandleAccept {
     idx: usize,
-    tx: UnboundedSender<Conn>,
+    tx: Sender<Conn>,
     counter: Counter,
+    full: Arc<AtomicBool>,
 }
 
 impl WorkerHandleAccept {
@@ -166,52 +347,194 @@ impl WorkerHandleAccept {
     }
 
     #[inline(always)]
-    pub(crate) fn send(&self, msg: Conn) -> Result<(), Conn> {
-        self.tx.send(msg).map_err(|msg| msg.0)
+    pub(crate) fn send(&self, msg: Conn) -> Result<(), TrySendError<Conn>> {
+        match self.tx.try_send(msg) {
+            Err(TrySendError::Full(msg)) => {
+                self.full.store(true, Ordering::SeqCst);
+
+                // worker may have emptied the channel before it could see the flag
+                self.tx.try_send(msg)
+            }
+            res => res,
+        }
     }
 
     #[inline(always)]
     pub(crate) fn inc_counter(&self) -> bool {
         self.counter.inc()
     }
+
+    /// Returns number of connections currently handled by the worker.
+    #[inline(always)]
+    pub(crate) fn connections(&self) -> usize {
+        self.counter.total()
+    }
+
+    /// Returns number of connections the worker can take before hitting its limit.
+    #[inline(always)]
+    pub(crate) fn remaining(&self) -> usize {
+        self.counter.remaining()
+    }
+
+    /// Returns number of connections that can be sent before the worker's channel is full.
+    ///
+    /// A full channel is marked, so the worker wakes up `Accept` once it took a connection off.
+    pub(crate) fn capacity(&self) -> usize {
+        match self.tx.capacity() {
+            0 => {
+                self.full.store(true, Ordering::SeqCst);
+
+                // worker may have emptied the channel before it could see the flag
+                self.tx.capacity()
+            }
+            capacity => capacity,
+        }
+    }
 }
 
 /// Handle to worker than can send stop message to worker.
 ///
 /// Held by [ServerBuilder](crate::builder::ServerBuilder).
-#[derive(Debug)]
 pub(crate) struct WorkerHandleServer {
     idx: usize,
-    tx: UnboundedSender<Stop>,
+    tx: UnboundedSender<WorkerCommand>,
+}
+
+impl fmt::Debug for WorkerHandleServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerHandleServer")
+            .field("idx", &self.idx)
+            .finish()
+    }
 }
 
 impl WorkerHandleServer {
-    pub(crate) fn stop(&self, graceful: bool) -> oneshot::Receiver<bool> {
+    pub(crate) fn idx(&self) -> usize {
+        self.idx
+    }
+
+    pub(crate) fn stop(
+        &self,
+        graceful: bool,
+        timeout: Option<Duration>,
+    ) -> oneshot::Receiver<bool> {
+        let (stop, rx) = Stop::new(graceful, timeout);
+        self.send_stop(stop);
+        rx
+    }
+
+    pub(crate) fn send_stop(&self, stop: Stop) {
+        let _ = self.tx.send(WorkerCommand::Stop(stop));
+    }
+
+    pub(crate) fn add_service(
+        &self,
+        factory: Box<dyn InternalServiceFactory>,
+    ) -> oneshot::Receiver<bool> {
         let (tx, rx) = oneshot::channel();
-        let _ = self.tx.send(Stop { graceful, tx });
+        let _ = self.tx.send(WorkerCommand::AddService { factory, tx });
         rx
     }
+
+    pub(crate) fn remove_service(&self, token: usize) {
+        let _ = self.tx.send(WorkerCommand::RemoveService(token));
+    }
+
+    pub(crate) fn max_concurrent_connections(&self, num: usize) {
+        let _ = self.tx.send(WorkerCommand::MaxConn(num));
+    }
+
+    pub(crate) fn shutdown_timeout(&self, dur: Duration) {
+        let _ = self.tx.send(WorkerCommand::ShutdownTimeout(dur));
+    }
 }
 
 /// Service worker.
 ///
 /// Worker accepts Socket objects via unbounded channel and starts stream processing.
 pub(crate) struct ServerWorker {
-    // UnboundedReceiver<Conn> should always be the first field.
+    // Receiver<Conn> should always be the first field.
     // It must be dropped as soon as ServerWorker dropping.
-    rx: UnboundedReceiver<Conn>,
-    rx2: UnboundedReceiver<Stop>,
+    rx: Receiver<Conn>,
+    /// Set by `Accept` when the connection channel was found full.
+    full: Arc<AtomicBool>,
+    rx2: UnboundedReceiver<WorkerCommand>,
+    /// Command received while checking for one before exiting on a closed connection channel.
+    command: Option<WorkerCommand>,
     counter: WorkerCounter,
-    services: Box<[WorkerService]>,
-    factories: Box<[Box<dyn InternalServiceFactory>]>,
+    /// Services indexed by listener token. Tokens of removed listeners are `None`.
+    services: Vec<Option<WorkerService>>,
+    factories: Factories,
+    /// Services of listeners added at runtime that are being created.
+    pending: Vec<PendingService>,
     state: WorkerState,
     shutdown_timeout: Duration,
+    unavailable_timeout: Option<Duration>,
+    /// Expires once `held` waited for `unavailable_timeout`.
+    unavailable_timer: Option<Pin<Box<Sleep>>>,
+    /// Oldest connection taken off the channel while services were not ready that did not wait
+    /// for `unavailable_timeout` yet; served before the ones still on the channel.
+    held: Option<Conn>,
+    /// Notified once all services are available for the first time.
+    ready: Option<oneshot::Sender<()>>,
+    on_stop: Option<WorkerHook>,
+    metrics: Option<WorkerMetrics>,
+    /// Closes the `CloseToken`s of the worker and its connections.
+    shutdown: watch::Sender<bool>,
+    /// Outcome of a stop command, passed on to its caller once the stop hook finished.
+    stop_reply: Option<(oneshot::Receiver<bool>, oneshot::Sender<bool>)>,
+}
+
+/// Service factories indexed by factory id.
+///
+/// Factories of removed listeners are dropped and their slots reused by listeners added later.
+struct Factories(Vec<Option<Box<dyn InternalServiceFactory>>>);
+
+impl Factories {
+    fn insert(&mut self, factory: Box<dyn InternalServiceFactory>) -> usize {
+        match self.0.iter().position(Option::is_none) {
+            Some(id) => {
+                self.0[id] = Some(factory);
+                id
+            }
+            None => {
+                self.0.push(Some(factory));
+                self.0.len() - 1
+            }
+        }
+    }
+
+    fn remove(&mut self, id: usize) {
+        self.0[id] = None;
+    }
+}
+
+impl Index<usize> for Factories {
+    type Output = dyn InternalServiceFactory;
+
+    fn index(&self, id: usize) -> &Self::Output {
+        self.0[id].as_deref().expect("Service factory was removed")
+    }
+}
+
+struct PendingService {
+    factory_id: usize,
+    fut: LocalBoxFuture<'static, Result<(usize, BoxedServerService), ()>>,
+    tx: oneshot::Sender<bool>,
 }
 
 struct WorkerService {
     factory: usize,
     status: WorkerServiceStatus,
     service: BoxedServerService,
+    conns: Rc<ListenerConns>,
+}
+
+/// Connections of a listener handled by the worker.
+struct ListenerConns {
+    open: Cell<usize>,
+    closed: Cell<usize>,
+    counters: Arc<ServiceCounters>,
 }
 
 impl WorkerService {
@@ -231,12 +554,25 @@ enum WorkerServiceStatus {
     Stopped,
 }
 
+/// Hook set with [`ServerBuilder::on_worker_start`](crate::ServerBuilder::on_worker_start) or
+/// [`ServerBuilder::on_worker_stop`](crate::ServerBuilder::on_worker_stop).
+pub(crate) type WorkerHook = Arc<dyn Fn(usize) -> LocalBoxFuture<'static, ()> + Send + Sync>;
+
 /// Config for worker behavior passed down from server builder.
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub(crate) struct ServerWorkerConfig {
     shutdown_timeout: Duration,
     max_blocking_threads: usize,
     max_concurrent_connections: usize,
+    channel_capacity: usize,
+    affinity: Option<usize>,
+    on_start: Option<WorkerHook>,
+    on_stop: Option<WorkerHook>,
+    metrics: Option<WorkerMetrics>,
+    /// Interval of heartbeats reported to `Accept`.
+    heartbeat: Option<Duration>,
+    /// Time after which connections queued while services are not ready are closed.
+    unavailable_timeout: Option<Duration>,
 }
 
 impl Default for ServerWorkerConfig {
@@ -247,6 +583,13 @@ impl Default for ServerWorkerConfig {
             shutdown_timeout: Duration::from_secs(30),
             max_blocking_threads,
             max_concurrent_connections: 25600,
+            channel_capacity: 1024,
+            affinity: None,
+            on_start: None,
+            on_stop: None,
+            metrics: None,
+            heartbeat: None,
+            unavailable_timeout: None,
         }
     }
 }
@@ -260,64 +603,131 @@ impl ServerWorkerConfig {
         self.max_concurrent_connections = num;
     }
 
+    pub(crate) fn max_concurrent_connections_limit(&self) -> usize {
+        self.max_concurrent_connections
+    }
+
+    pub(crate) fn channel_capacity(&mut self, num: usize) {
+        self.channel_capacity = num;
+    }
+
     pub(crate) fn shutdown_timeout(&mut self, dur: Duration) {
         self.shutdown_timeout = dur;
     }
+
+    pub(crate) fn affinity(&mut self, core: Option<usize>) {
+        self.affinity = core;
+    }
+
+    pub(crate) fn on_start(&mut self, hook: WorkerHook) {
+        self.on_start = Some(hook);
+    }
+
+    pub(crate) fn on_stop(&mut self, hook: WorkerHook) {
+        self.on_stop = Some(hook);
+    }
+
+    pub(crate) fn metrics(&mut self, handler: WorkerMetrics) {
+        self.metrics = Some(handler);
+    }
+
+    pub(crate) fn heartbeat(&mut self, interval: Duration) {
+        self.heartbeat = Some(interval);
+    }
+
+    pub(crate) fn unavailable_timeout(&mut self, timeout: Duration) {
+        self.unavailable_timeout = Some(timeout);
+    }
 }
 
 impl ServerWorker {
     pub(crate) fn start(
         idx: usize,
+        name: Option<String>,
         factories: Vec<Box<dyn InternalServiceFactory>>,
         waker_queue: WakerQueue,
         config: ServerWorkerConfig,
+        close: Arc<CloseTracker>,
+        ready: Option<oneshot::Sender<()>>,
     ) -> (WorkerHandleAccept, WorkerHandleServer) {
-        let (tx1, rx) = unbounded_channel();
+        let (tx1, rx) = channel(config.channel_capacity);
         let (tx2, rx2) = unbounded_channel();
 
         let counter = Counter::new(config.max_concurrent_connections);
+        let full = Arc::new(AtomicBool::new(false));
+        let full_clone = full.clone();
 
         let counter_clone = counter.clone();
         // every worker runs in it's own arbiter.
         // use a custom tokio runtime builder to change the settings of runtime.
-        Arbiter::with_tokio_rt(move || {
+        let (affinity, max_blocking_threads) = (config.affinity, config.max_blocking_threads);
+        let runtime_factory = move || {
+            // runtime is created on the worker thread; threads it spawns inherit the affinity
+            if let Some(core) = affinity {
+                crate::affinity::pin_current_thread(core);
+            }
+
             tokio::runtime::Builder::new_current_thread()
                 .enable_all()
-                .max_blocking_threads(config.max_blocking_threads)
+                .max_blocking_threads(max_blocking_threads)
                 .build()
                 .unwrap()
-        })
-        .spawn(async move {
-            let fut = factories
-                .iter()
-                .enumerate()
-                .map(|(idx, factory)| {
-                    let fut = factory.create();
-                    async move { fut.await.map(|(t, s)| (idx, t, s)) }
-                })
-                .collect::<Vec<_>>();
+        };
+
+        let arbiter = match name {
+            Some(name) => Arbiter::with_tokio_rt_and_name(name, runtime_factory),
+            None => Arbiter::with_tokio_rt(runtime_factory),
+        };
+
+        arbiter.spawn(async move {
+            // reports keep coming as long as the worker thread is not blocked
+            if let Some(interval) = config.heartbeat {
+                let waker_queue = waker_queue.clone();
+                spawn(async move {
+                    loop {
+                        sleep(interval).await;
+                        waker_queue.wake(WakerInterest::Heartbeat(idx));
+                    }
+                });
+            }
+
+            let (shutdown, token) = CloseToken::new();
+            token.set_worker();
 
             // a second spawn to run !Send future tasks.
             spawn(async move {
+                // per worker setup runs before services are created
+                if let Some(ref on_start) = config.on_start {
+                    on_start(idx).await;
+                }
+
+                let fut = factories
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, factory)| {
+                        let fut = factory.create();
+                        async move { fut.await.map(|(t, s)| (idx, t, s)) }
+                    })
+                    .collect::<Vec<_>>();
+
                 let res = join_all(fut)
                     .await
                     .into_iter()
                     .collect::<Result<Vec<_>, _>>();
                 let services = match res {
-                    Ok(res) => res
-                        .into_iter()
-                        .fold(Vec::new(), |mut services, (factory, token, service)| {
-                            assert_eq!(token, services.len());
-                            services.push(WorkerService {
-                                factory,
-                                service,
-                                status: WorkerServiceStatus::Unavailable,
-                            });
+                    Ok(res) => res.into_iter().fold(
+                        Vec::new(),
+                        |mut services, (factory, token, service)| {
+                            let counters = close.counters(idx, token);
+                            insert_service(&mut services, factory, token, service, counters);
                             services
-                        })
-                        .into_boxed_slice(),
+                        },
+                    ),
                     Err(e) => {
                         error!("Can not start worker: {:?}", e);
+                        if let Some(ref on_stop) = config.on_stop {
+                            on_stop(idx).await;
+                        }
                         Arbiter::current().stop();
                         return;
                     }
@@ -326,23 +736,96 @@ impl ServerWorker {
                 // a third spawn to make sure ServerWorker runs as non boxed future.
                 spawn(ServerWorker {
                     rx,
+                    full: full_clone,
                     rx2,
+                    command: None,
                     services,
-                    counter: WorkerCounter::new(idx, waker_queue, counter_clone),
-                    factories: factories.into_boxed_slice(),
+                    counter: WorkerCounter::new(idx, waker_queue, counter_clone, close),
+                    factories: Factories(factories.into_iter().map(Some).collect()),
+                    pending: Vec::new(),
                     state: Default::default(),
                     shutdown_timeout: config.shutdown_timeout,
+                    unavailable_timeout: config.unavailable_timeout,
+                    unavailable_timer: None,
+                    held: None,
+                    ready,
+                    on_stop: config.on_stop,
+                    metrics: config.metrics,
+                    shutdown,
+                    stop_reply: None,
                 });
             });
         });
 
-        handle_pair(idx, tx1, tx2, counter)
+        handle_pair(idx, tx1, tx2, counter, full)
+    }
+
+    /// Close connections queued for the worker once its services were not ready for the
+    /// unavailable timeout, and connections sent to it afterwards until they are.
+    fn poll_unavailable(&mut self, cx: &mut Context<'_>) {
+        let timeout = match self.unavailable_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+
+        if let Some(ref mut timer) = self.unavailable_timer {
+            if timer.as_mut().poll(cx).is_pending() {
+                return;
+            }
+            self.unavailable_timer = None;
+        }
+
+        // connections arrive about in the order they were accepted, so the ones after a
+        // connection that did not wait long enough yet did not either
+        let now = Instant::now();
+        let mut num = 0;
+        loop {
+            let conn = match self.held.take() {
+                Some(conn) => conn,
+                None => match Pin::new(&mut self.rx).poll_recv(cx) {
+                    Poll::Ready(Some(conn)) => {
+                        if self.full.swap(false, Ordering::SeqCst) {
+                            self.counter.wake_accept();
+                        }
+                        conn
+                    }
+                    _ => break,
+                },
+            };
+
+            let deadline = conn.accepted + timeout;
+            if deadline > now {
+                let mut timer = Box::pin(sleep_until(deadline));
+                let _ = timer.as_mut().poll(cx);
+                self.unavailable_timer = Some(timer);
+                self.held = Some(conn);
+                break;
+            }
+
+            self.counter
+                .guard(None, conn.permit)
+                .close(CloseReason::Rejected);
+            num += 1;
+        }
+
+        if num > 0 {
+            error!(
+                "Services of worker {} not ready for {:?}, closed {} queued connections",
+                self.counter.idx, timeout, num
+            );
+        }
     }
 
     fn restart_service(&mut self, idx: usize, factory_id: usize) {
         let factory = &self.factories[factory_id];
-        trace!("Service {:?} failed, restarting", factory.name(idx));
-        self.services[idx].status = WorkerServiceStatus::Restarting;
+        trace!(
+            "Service {} on {} failed, restarting",
+            factory.labeled_name(idx),
+            factory.addr()
+        );
+        if let Some(srv) = self.services[idx].as_mut() {
+            srv.status = WorkerServiceStatus::Restarting;
+        }
         self.state = WorkerState::Restarting(Restart {
             factory_id,
             token: idx,
@@ -350,9 +833,98 @@ impl ServerWorker {
         });
     }
 
+    fn add_service(
+        &mut self,
+        factory: Box<dyn InternalServiceFactory>,
+        tx: oneshot::Sender<bool>,
+    ) {
+        let fut = factory.create();
+        self.pending.push(PendingService {
+            factory_id: self.factories.insert(factory),
+            fut,
+            tx,
+        });
+    }
+
+    fn remove_service(&mut self, token: usize) {
+        if let Some(srv) = self.services.get_mut(token).and_then(Option::take) {
+            let factory = &self.factories[srv.factory];
+            trace!(
+                "Service {} on {} removed",
+                factory.labeled_name(token),
+                factory.addr()
+            );
+            self.release_factory(srv.factory);
+        }
+    }
+
+    /// Drop factory once no service, pending service or restart refers to it anymore.
+    fn release_factory(&mut self, factory_id: usize) {
+        let used = self
+            .services
+            .iter()
+            .flatten()
+            .any(|srv| srv.factory == factory_id)
+            || self.pending.iter().any(|p| p.factory_id == factory_id)
+            || matches!(
+                self.state,
+                WorkerState::Restarting(ref restart) if restart.factory_id == factory_id
+            );
+
+        if !used {
+            self.factories.remove(factory_id);
+        }
+    }
+
+    /// Poll creation of services added at runtime.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) {
+        let mut idx = 0;
+        while idx < self.pending.len() {
+            let res = match self.pending[idx].fut.as_mut().poll(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => {
+                    idx += 1;
+                    continue;
+                }
+            };
+
+            let pending = self.pending.swap_remove(idx);
+            match res {
+                Ok((token, service)) => {
+                    let factory = &self.factories[pending.factory_id];
+                    trace!(
+                        "Service {} on {} has been added",
+                        factory.labeled_name(token),
+                        factory.addr()
+                    );
+                    let counters = self.counter.close().counters(self.counter.idx, token);
+                    insert_service(
+                        &mut self.services,
+                        pending.factory_id,
+                        token,
+                        service,
+                        counters,
+                    );
+                    let _ = pending.tx.send(true);
+                }
+                Err(_) => {
+                    let factory = &self.factories[pending.factory_id];
+                    error!(
+                        "Can not start {} service on {}",
+                        factory.labeled_name(0),
+                        factory.addr()
+                    );
+                    self.release_factory(pending.factory_id);
+                    let _ = pending.tx.send(false);
+                }
+            }
+        }
+    }
+
     fn shutdown(&mut self, force: bool) {
         self.services
             .iter_mut()
+            .flatten()
             .filter(|srv| srv.status == WorkerServiceStatus::Available)
             .for_each(|srv| {
                 srv.status = if force {
@@ -363,9 +935,65 @@ impl ServerWorker {
             });
     }
 
+    /// Close connection channel and hand connections not taken off it yet back to `Accept`.
+    fn redispatch(&mut self, cx: &mut Context<'_>) {
+        self.rx.close();
+
+        let mut conns = self.held.take().into_iter().collect::<Vec<_>>();
+        while let Poll::Ready(Some(conn)) = Pin::new(&mut self.rx).poll_recv(cx) {
+            conns.push(conn);
+        }
+
+        if !conns.is_empty() {
+            info!(
+                "Handing {} queued connections of worker {} back for dispatch",
+                conns.len(),
+                self.counter.idx
+            );
+            self.counter.redispatch(conns);
+        }
+    }
+
+    /// Start shutdown hooks of services, bounded by `deadline`.
+    ///
+    /// Returns `None` after reporting graceful shutdown if no service has a hook.
+    fn drain(&self, tx: oneshot::Sender<bool>, deadline: Instant) -> Option<Drain> {
+        let mut factories = self
+            .services
+            .iter()
+            .flatten()
+            .map(|srv| srv.factory)
+            .collect::<Vec<_>>();
+        factories.sort_unstable();
+        factories.dedup();
+
+        let hooks = factories
+            .into_iter()
+            .filter_map(|idx| self.factories[idx].on_shutdown())
+            .collect::<Vec<_>>();
+
+        if hooks.is_empty() {
+            let _ = tx.send(true);
+            return None;
+        }
+
+        trace!("Running {} shutdown hooks", hooks.len());
+
+        Some(Drain {
+            hooks: Box::pin(join_all(hooks)),
+            timer: Box::pin(sleep_until(deadline)),
+            tx,
+        })
+    }
+
     fn check_readiness(&mut self, cx: &mut Context<'_>) -> Result<bool, (usize, usize)> {
         let mut ready = true;
         for (idx, srv) in self.services.iter_mut().enumerate() {
+            let srv = match srv {
+                Some(srv) => srv,
+                None => continue,
+            };
+
             if srv.status == WorkerServiceStatus::Available
                 || srv.status == WorkerServiceStatus::Unavailable
             {
@@ -373,10 +1001,19 @@ impl ServerWorker {
                     Poll::Ready(Ok(_)) => {
                         if srv.status == WorkerServiceStatus::Unavailable {
                             trace!(
-                                "Service {:?} is available",
-                                self.factories[srv.factory].name(idx)
+                                "Service {} is available",
+                                self.factories[srv.factory].labeled_name(idx)
                             );
                             srv.status = WorkerServiceStatus::Available;
+                            if let Some(ref metrics) = self.metrics {
+                                let factory = &self.factories[srv.factory];
+                                let event = WorkerEvent::ReadinessChanged {
+                                    name: factory.name(idx),
+                                    labels: factory.labels(idx),
+                                    ready: true,
+                                };
+                                metrics.event(self.counter.idx, &event);
+                            }
                         }
                     }
                     Poll::Pending => {
@@ -384,16 +1021,27 @@ impl ServerWorker {
 
                         if srv.status == WorkerServiceStatus::Available {
                             trace!(
-                                "Service {:?} is unavailable",
-                                self.factories[srv.factory].name(idx)
+                                "Service {} is unavailable",
+                                self.factories[srv.factory].labeled_name(idx)
                             );
                             srv.status = WorkerServiceStatus::Unavailable;
+                            if let Some(ref metrics) = self.metrics {
+                                let factory = &self.factories[srv.factory];
+                                let event = WorkerEvent::ReadinessChanged {
+                                    name: factory.name(idx),
+                                    labels: factory.labels(idx),
+                                    ready: false,
+                                };
+                                metrics.event(self.counter.idx, &event);
+                            }
                         }
                     }
                     Poll::Ready(Err(_)) => {
+                        let factory = &self.factories[srv.factory];
                         error!(
-                            "Service {:?} readiness check returned error, restarting",
-                            self.factories[srv.factory].name(idx)
+                            "Service {} on {} readiness check returned error, restarting",
+                            factory.labeled_name(idx),
+                            factory.addr()
                         );
                         srv.status = WorkerServiceStatus::Failed;
                         return Err((idx, srv.factory));
@@ -406,11 +1054,35 @@ impl ServerWorker {
     }
 }
 
+fn insert_service(
+    services: &mut Vec<Option<WorkerService>>,
+    factory: usize,
+    token: usize,
+    service: BoxedServerService,
+    counters: Arc<ServiceCounters>,
+) {
+    if services.len() <= token {
+        services.resize_with(token + 1, || None);
+    }
+
+    services[token] = Some(WorkerService {
+        factory,
+        service,
+        status: WorkerServiceStatus::Unavailable,
+        conns: Rc::new(ListenerConns {
+            open: Cell::new(0),
+            closed: Cell::new(0),
+            counters,
+        }),
+    });
+}
+
 enum WorkerState {
     Available,
     Unavailable,
     Restarting(Restart),
     Shutdown(Shutdown),
+    Draining(Drain),
 }
 
 struct Restart {
@@ -427,6 +1099,32 @@ struct Shutdown {
     timer: Pin<Box<Sleep>>,
     start_from: Instant,
     tx: oneshot::Sender<bool>,
+    /// Open and closed connections of each listener when shutdown started.
+    conns: Vec<(usize, Rc<ListenerConns>, usize, usize)>,
+}
+
+impl Shutdown {
+    /// Report connections handled by each listener since shutdown started.
+    fn report(&self, close: &CloseTracker) {
+        for (token, conns, open, closed) in &self.conns {
+            close.record_drain(
+                *token,
+                DrainCounts {
+                    in_flight: *open,
+                    completed: conns.closed.get() - closed,
+                    abandoned: conns.open.get(),
+                },
+            );
+        }
+    }
+}
+
+/// Shutdown hooks of services run once connections are closed on graceful shutdown.
+struct Drain {
+    hooks: LocalBoxFuture<'static, Vec<()>>,
+    /// Fires once the shutdown timeout elapsed.
+    timer: Pin<Box<Sleep>>,
+    tx: oneshot::Sender<bool>,
 }
 
 impl Default for WorkerState {
@@ -437,8 +1135,24 @@ impl Default for WorkerState {
 
 impl Drop for ServerWorker {
     fn drop(&mut self) {
-        // Stop the Arbiter ServerWorker runs on on drop.
-        Arbiter::current().stop();
+        match self.on_stop.take() {
+            // Run stop hook and stop the Arbiter ServerWorker runs on once it finished.
+            Some(on_stop) => {
+                let fut = on_stop(self.counter.idx);
+                let reply = self.stop_reply.take();
+                spawn(async move {
+                    fut.await;
+                    if let Some((rx, tx)) = reply {
+                        let _ = tx.send(rx.await.unwrap_or(false));
+                    }
+                    Arbiter::current().stop();
+                });
+            }
+            // Stop the Arbiter ServerWorker runs on on drop.
+            None => {
+                Arbiter::current().stop();
+            }
+        }
     }
 }
 
@@ -448,11 +1162,75 @@ impl Future for ServerWorker {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.as_mut().get_mut();
 
-        // `StopWorker` message handler
-        if let Poll::Ready(Some(Stop { graceful, tx })) = Pin::new(&mut this.rx2).poll_recv(cx)
-        {
+        // worker command handler
+        loop {
+            let cmd = match this.command.take() {
+                Some(cmd) => cmd,
+                None => match Pin::new(&mut this.rx2).poll_recv(cx) {
+                    Poll::Ready(Some(cmd)) => cmd,
+                    _ => break,
+                },
+            };
+
+            let Stop {
+                graceful,
+                timeout,
+                redispatch,
+                mut tx,
+            } = match cmd {
+                WorkerCommand::Stop(stop) => stop,
+                WorkerCommand::AddService { factory, tx } => {
+                    this.add_service(factory, tx);
+                    continue;
+                }
+                WorkerCommand::RemoveService(token) => {
+                    this.remove_service(token);
+                    continue;
+                }
+                WorkerCommand::MaxConn(num) => {
+                    this.counter.set_limit(num);
+                    continue;
+                }
+                WorkerCommand::ShutdownTimeout(dur) => {
+                    this.shutdown_timeout = dur;
+                    continue;
+                }
+            };
+
+            if let Some(dur) = timeout {
+                this.shutdown_timeout = dur;
+            }
+
+            let _ = this.shutdown.send(true);
+
+            if redispatch {
+                this.redispatch(cx);
+            }
+
+            // stop outcome is reported once the stop hook finished
+            if this.on_stop.is_some() {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                this.stop_reply = Some((reply_rx, mem::replace(&mut tx, reply_tx)));
+            }
+
             let num = this.counter.total();
-            if num == 0 {
+            if let Some(ref metrics) = this.metrics {
+                let event = WorkerEvent::ShutdownStarted {
+                    graceful,
+                    connections: num,
+                };
+                metrics.event(this.counter.idx, &event);
+            }
+
+            if num == 0 && graceful {
+                info!("Shutting down worker, 0 connections");
+                this.shutdown(false);
+
+                match this.drain(tx, Instant::now() + this.shutdown_timeout) {
+                    Some(drain) => this.state = WorkerState::Draining(drain),
+                    None => return Poll::Ready(()),
+                }
+            } else if num == 0 {
                 info!("Shutting down worker, 0 connections");
                 let _ = tx.send(true);
                 return Poll::Ready(());
@@ -460,10 +1238,22 @@ impl Future for ServerWorker {
                 info!("Graceful worker shutdown, {} connections", num);
                 this.shutdown(false);
 
+                let conns = this
+                    .services
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(token, srv)| {
+                        let conns = srv.as_ref()?.conns.clone();
+                        let (open, closed) = (conns.open.get(), conns.closed.get());
+                        Some((token, conns, open, closed))
+                    })
+                    .collect();
+
                 this.state = WorkerState::Shutdown(Shutdown {
                     timer: Box::pin(sleep(Duration::from_secs(1))),
                     start_from: Instant::now(),
                     tx,
+                    conns,
                 });
             } else {
                 info!("Force shutdown worker, {} connections", num);
@@ -472,15 +1262,27 @@ impl Future for ServerWorker {
                 let _ = tx.send(false);
                 return Poll::Ready(());
             }
+
+            break;
+        }
+
+        if !this.pending.is_empty() {
+            this.poll_pending(cx);
         }
 
         match this.state {
             WorkerState::Unavailable => match this.check_readiness(cx) {
                 Ok(true) => {
+                    if let Some(tx) = this.ready.take() {
+                        let _ = tx.send(());
+                    }
                     this.state = WorkerState::Available;
                     self.poll(cx)
                 }
-                Ok(false) => Poll::Pending,
+                Ok(false) => {
+                    this.poll_unavailable(cx);
+                    Poll::Pending
+                }
                 Err((token, idx)) => {
                     this.restart_service(token, idx);
                     self.poll(cx)
@@ -490,23 +1292,45 @@ impl Future for ServerWorker {
                 let factory_id = restart.factory_id;
                 let token = restart.token;
 
-                let (token_new, service) = ready!(restart.fut.as_mut().poll(cx))
-                    .unwrap_or_else(|_| {
-                        panic!(
-                            "Can not restart {:?} service",
-                            this.factories[factory_id].name(token)
-                        )
-                    });
+                let res = match restart.fut.as_mut().poll(cx) {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => {
+                        this.poll_unavailable(cx);
+                        return Poll::Pending;
+                    }
+                };
+                let (token_new, service) = res.unwrap_or_else(|_| {
+                    panic!(
+                        "Can not restart {} service",
+                        this.factories[factory_id].labeled_name(token)
+                    )
+                });
 
                 assert_eq!(token, token_new);
 
                 trace!(
-                    "Service {:?} has been restarted",
-                    this.factories[factory_id].name(token)
+                    "Service {} has been restarted",
+                    this.factories[factory_id].labeled_name(token)
                 );
 
-                this.services[token].created(service);
+                if let Some(ref metrics) = this.metrics {
+                    let factory = &this.factories[factory_id];
+                    let event = WorkerEvent::ServiceRestarted {
+                        name: factory.name(token),
+                        labels: factory.labels(token),
+                    };
+                    metrics.event(this.counter.idx, &event);
+                }
+
                 this.state = WorkerState::Unavailable;
+                match this.services[token].as_mut() {
+                    Some(srv) => srv.created(service),
+                    // listener was removed while restarting
+                    None => {
+                        drop(service);
+                        this.release_factory(factory_id);
+                    }
+                }
 
                 self.poll(cx)
             }
@@ -517,12 +1341,19 @@ impl Future for ServerWorker {
                 if this.counter.total() == 0 {
                     // Graceful shutdown.
                     if let WorkerState::Shutdown(shutdown) = mem::take(&mut this.state) {
-                        let _ = shutdown.tx.send(true);
+                        shutdown.report(this.counter.close());
+
+                        let deadline = shutdown.start_from + this.shutdown_timeout;
+                        if let Some(drain) = this.drain(shutdown.tx, deadline) {
+                            this.state = WorkerState::Draining(drain);
+                            return self.poll(cx);
+                        }
                     }
                     Poll::Ready(())
                 } else if shutdown.start_from.elapsed() >= this.shutdown_timeout {
                     // Timeout forceful shutdown.
                     if let WorkerState::Shutdown(shutdown) = mem::take(&mut this.state) {
+                        shutdown.report(this.counter.close());
                         let _ = shutdown.tx.send(false);
                     }
                     Poll::Ready(())
@@ -533,8 +1364,25 @@ impl Future for ServerWorker {
                     shutdown.timer.as_mut().poll(cx)
                 }
             }
+            WorkerState::Draining(ref mut drain) => {
+                let done = match drain.hooks.as_mut().poll(cx) {
+                    Poll::Ready(_) => true,
+                    Poll::Pending => {
+                        ready!(drain.timer.as_mut().poll(cx));
+                        error!("Shutdown hooks did not finish before shutdown timeout");
+                        false
+                    }
+                };
+
+                if let WorkerState::Draining(drain) = mem::take(&mut this.state) {
+                    let _ = drain.tx.send(done);
+                }
+                Poll::Ready(())
+            }
             // actively poll stream and handle worker command
             WorkerState::Available => loop {
+                this.unavailable_timer = None;
+
                 match this.check_readiness(cx) {
                     Ok(true) => {}
                     Ok(false) => {
@@ -548,13 +1396,58 @@ impl Future for ServerWorker {
                     }
                 }
 
-                // handle incoming io stream
-                match ready!(Pin::new(&mut this.rx).poll_recv(cx)) {
+                // handle incoming io stream, starting with one held while services were not ready
+                let msg = match this.held.take() {
+                    Some(conn) => Some(conn),
+                    None => ready!(Pin::new(&mut this.rx).poll_recv(cx)),
+                };
+                match msg {
                     Some(msg) => {
-                        let guard = this.counter.guard();
-                        let _ = this.services[msg.token].service.call((guard, msg.io));
+                        if this.full.swap(false, Ordering::SeqCst) {
+                            this.counter.wake_accept();
+                        }
+
+                        let Conn {
+                            io,
+                            token,
+                            permit,
+                            span,
+                            ..
+                        } = msg;
+                        match this.services.get(token) {
+                            Some(Some(srv)) => {
+                                if let Some(ref metrics) = this.metrics {
+                                    let factory = &this.factories[srv.factory];
+                                    let event = WorkerEvent::ConnectionAccepted {
+                                        name: factory.name(token),
+                                        labels: factory.labels(token),
+                                    };
+                                    metrics.event(this.counter.idx, &event);
+                                }
+
+                                let guard = this.counter.guard(Some(srv.conns.clone()), permit);
+                                span.in_worker(this.counter.idx, || {
+                                    let _ = srv.service.call((guard, io));
+                                });
+                            }
+                            // listener was removed after connection was accepted
+                            _ => {
+                                trace!("Dropping connection for removed listener");
+                                this.counter
+                                    .guard(None, permit)
+                                    .close(CloseReason::Rejected);
+                            }
+                        }
+                    }
+                    None => {
+                        // `Accept` closes the channel right after the server sent a stop
+                        // command, which may have arrived since commands were handled
+                        if let Poll::Ready(Some(cmd)) = Pin::new(&mut this.rx2).poll_recv(cx) {
+                            this.command = Some(cmd);
+                            return self.poll(cx);
+                        }
+                        return Poll::Ready(());
                     }
-                    None => return Poll::Ready(()),
                 };
             },
         }
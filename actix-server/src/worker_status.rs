@@ -0,0 +1,110 @@
+use crate::{addr::ListenerAddr, labels::ServiceLabels};
+
+/// Status of a worker of a running server, returned by
+/// [`Server::workers`](crate::Server::workers).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerStatus {
+    idx: usize,
+    available: bool,
+    connections: usize,
+    restarts: usize,
+    services: Vec<ServiceStatus>,
+}
+
+impl WorkerStatus {
+    pub(crate) fn new(idx: usize, available: bool, connections: usize) -> Self {
+        Self {
+            idx,
+            available,
+            connections,
+            restarts: 0,
+            services: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_restarts(&mut self, restarts: usize) {
+        self.restarts = restarts;
+    }
+
+    pub(crate) fn set_services(&mut self, services: Vec<ServiceStatus>) {
+        self.services = services;
+    }
+
+    /// Returns index of the worker.
+    ///
+    /// A worker started in place of a restarted one keeps its index.
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// Returns true if the worker is sent new connections.
+    ///
+    /// Workers are not available while they serve their [maximum number of
+    /// connections](crate::ServerBuilder::maxconn), while their services are not ready, or while
+    /// they are considered [unresponsive](crate::WorkerHeartbeat).
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// Returns number of connections sent to the worker that are not closed yet.
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+
+    /// Returns number of times a worker with this index was restarted, on request, after it
+    /// died or because it was unresponsive.
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    /// Returns status of the services run by the worker, one for each listener.
+    pub fn services(&self) -> &[ServiceStatus] {
+        &self.services
+    }
+}
+
+/// Status of the service of a listener on a worker, part of a [`WorkerStatus`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceStatus {
+    name: String,
+    addr: ListenerAddr,
+    labels: ServiceLabels,
+    connections: usize,
+}
+
+impl ServiceStatus {
+    pub(crate) fn new(name: String, addr: ListenerAddr, labels: ServiceLabels) -> Self {
+        Self {
+            name,
+            addr,
+            labels,
+            connections: 0,
+        }
+    }
+
+    pub(crate) fn with_connections(mut self, connections: usize) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    /// Returns name of the listener.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns address of the listener.
+    pub fn addr(&self) -> &ListenerAddr {
+        &self.addr
+    }
+
+    /// Returns [labels](crate::ServerBuilder::labels) of the listener.
+    pub fn labels(&self) -> &ServiceLabels {
+        &self.labels
+    }
+
+    /// Returns number of connections of the listener handled by the worker that are not closed
+    /// yet.
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+}
@@ -1,9 +1,9 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc};
-use std::{net, thread, time::Duration};
+use std::{io, net, thread, time::Duration};
 
 use actix_rt::{net::TcpStream, time::sleep};
-use actix_server::Server;
+use actix_server::{CloseReason, Datagram, DispatchStrategy, Server};
 use actix_service::fn_service;
 use actix_utils::future::ok;
 use futures_util::future::lazy;
@@ -17,6 +17,26 @@ fn unused_addr() -> net::SocketAddr {
     tcp.local_addr().unwrap()
 }
 
+/// Block until `srv` accepts on all its listeners and its workers are ready.
+fn wait_ready(srv: &Server) {
+    actix_rt::Runtime::new()
+        .unwrap()
+        .block_on(srv.ready())
+        .unwrap();
+}
+
+/// Poll `cond` until it holds, failing the test after 5 seconds.
+fn wait_until(mut cond: impl FnMut() -> bool) {
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while !cond() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "condition not met in time"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
 #[test]
 fn test_bind() {
     let addr = unused_addr();
@@ -45,55 +65,130 @@ fn test_bind() {
 }
 
 #[test]
-fn test_listen() {
-    let addr = unused_addr();
+#[cfg(target_os = "linux")]
+fn test_bind_uds_abstract() {
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+
+    use actix_rt::net::UnixStream as RtUnixStream;
+    use tokio::io::AsyncWriteExt;
+
+    let name = format!("actix-server-test-{}", std::process::id());
+    let addr = format!("@{}", name);
     let (tx, rx) = mpsc::channel();
 
     let h = thread::spawn(move || {
         let sys = actix_rt::System::new();
-        let lst = net::TcpListener::bind(addr).unwrap();
-        sys.block_on(async {
+        let srv = sys.block_on(lazy(|_| {
             Server::build()
-                .disable_signals()
                 .workers(1)
-                .listen("test", lst, move || fn_service(|_| ok::<_, ()>(())))
+                .disable_signals()
+                .bind_uds("test", addr, move || {
+                    fn_service(|mut io: RtUnixStream| async move {
+                        io.write_all(b"hello").await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
                 .unwrap()
-                .run();
-            let _ = tx.send(actix_rt::System::current());
-        });
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
         let _ = sys.run();
     });
-    let sys = rx.recv().unwrap();
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    // no file is created for abstract sockets
+    assert!(!std::path::Path::new(&format!("@{}", name)).exists());
+
+    let mut sun_path = vec![0];
+    sun_path.extend_from_slice(name.as_bytes());
+    let addr = socket2::SockAddr::unix(std::str::from_utf8(&sun_path).unwrap()).unwrap();
+    let sock =
+        socket2::Socket::new(socket2::Domain::UNIX, socket2::Type::STREAM, None).unwrap();
+    sock.connect(&addr).unwrap();
+
+    let mut conn = UnixStream::from(sock);
+    let mut buf = Vec::new();
+    conn.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"hello");
 
-    thread::sleep(Duration::from_millis(500));
-    assert!(net::TcpStream::connect(addr).is_ok());
     sys.stop();
     let _ = h.join();
 }
 
 #[test]
-#[cfg(unix)]
-fn test_start() {
-    use std::io::Read;
+#[cfg(target_os = "linux")]
+fn test_bind_reuse_port() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
 
-    use actix_codec::{BytesCodec, Framed};
-    use bytes::Bytes;
-    use futures_util::sink::SinkExt;
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let num = num2.clone();
+            let num2 = num2.clone();
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .reuse_port(true)
+                .bind("test1", addr, move || {
+                    let num = num.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("test2", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    for _ in 0..4 {
+        assert!(net::TcpStream::connect(addr).is_ok());
+    }
+    wait_until(|| num.load(Ordering::SeqCst) == 4);
 
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_reuse_port_accept_loops() {
     let addr = unused_addr();
     let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
 
     let h = thread::spawn(move || {
         let sys = actix_rt::System::new();
         let srv = sys.block_on(lazy(|_| {
             Server::build()
-                .backlog(100)
+                .workers(2)
                 .disable_signals()
+                .reuse_port(true)
+                .accept_thread_name("reuse-accept")
                 .bind("test", addr, move || {
-                    fn_service(|io: TcpStream| async move {
-                        let mut f = Framed::new(io, BytesCodec);
-                        f.send(Bytes::from_static(b"test")).await.unwrap();
-                        Ok::<_, ()>(())
+                    let num = num2.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
                     })
                 })
                 .unwrap()
@@ -103,351 +198,3926 @@ fn test_start() {
         let _ = tx.send((srv, actix_rt::System::current()));
         let _ = sys.run();
     });
-
     let (srv, sys) = rx.recv().unwrap();
 
-    let mut buf = [1u8; 4];
-    let mut conn = net::TcpStream::connect(addr).unwrap();
-    let _ = conn.read_exact(&mut buf);
-    assert_eq!(buf, b"test"[..]);
-
-    // pause
-    let _ = srv.pause();
-    thread::sleep(Duration::from_millis(200));
-    let mut conn = net::TcpStream::connect(addr).unwrap();
-    conn.set_read_timeout(Some(Duration::from_millis(100)))
-        .unwrap();
-    let res = conn.read_exact(&mut buf);
-    assert!(res.is_err());
+    let rt = actix_rt::System::new();
+    rt.block_on(srv.ready()).unwrap();
 
-    // resume
-    let _ = srv.resume();
-    thread::sleep(Duration::from_millis(100));
-    assert!(net::TcpStream::connect(addr).is_ok());
-    assert!(net::TcpStream::connect(addr).is_ok());
-    assert!(net::TcpStream::connect(addr).is_ok());
+    // one accept loop per worker
+    let threads = std::fs::read_dir("/proc/self/task")
+        .unwrap()
+        .filter_map(|task| std::fs::read_to_string(task.unwrap().path().join("comm")).ok())
+        .filter(|name| name.starts_with("reuse-accept"))
+        .count();
+    assert_eq!(threads, 2);
 
-    let mut buf = [0u8; 4];
-    let mut conn = net::TcpStream::connect(addr).unwrap();
-    let _ = conn.read_exact(&mut buf);
-    assert_eq!(buf, b"test"[..]);
+    for _ in 0..32 {
+        assert!(net::TcpStream::connect(addr).is_ok());
+    }
+    wait_until(|| num.load(Ordering::SeqCst) == 32);
 
-    // stop
-    let _ = srv.stop(false);
-    thread::sleep(Duration::from_millis(100));
-    assert!(net::TcpStream::connect(addr).is_err());
+    let workers = rt.block_on(srv.workers()).unwrap();
+    assert_eq!(workers.iter().map(|w| w.idx()).collect::<Vec<_>>(), [0, 1]);
 
-    thread::sleep(Duration::from_millis(100));
     sys.stop();
     let _ = h.join();
 }
 
-#[actix_rt::test]
-async fn test_max_concurrent_connections() {
-    // Note:
-    // A tcp listener would accept connects based on it's backlog setting.
-    //
-    // The limit test on the other hand is only for concurrent tcp stream limiting a work
-    // thread accept.
+#[test]
+fn test_bind_dual_stack() {
+    let port = unused_addr().port();
+    let (tx, rx) = mpsc::channel();
 
-    use tokio::io::AsyncWriteExt;
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_dual_stack("test", port, move || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        }));
 
-    let addr = unused_addr();
-    let (tx, rx) = mpsc::channel();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    assert!(net::TcpStream::connect(("127.0.0.1", port)).is_ok());
+    if net::TcpListener::bind("[::1]:0").is_ok() {
+        assert!(net::TcpStream::connect(("::1", port)).is_ok());
+    }
 
-    let counter = Arc::new(AtomicUsize::new(0));
-    let counter_clone = counter.clone();
+    sys.stop();
+    let _ = h.join();
+}
 
-    let max_conn = 3;
+#[test]
+fn test_bind_ipv6_only() {
+    // skip on hosts without IPv6 support
+    if net::TcpListener::bind("[::1]:0").is_err() {
+        return;
+    }
+
+    let port = unused_addr().port();
+    let (tx, rx) = mpsc::channel();
 
     let h = thread::spawn(move || {
-        actix_rt::System::new().block_on(async {
-            let server = Server::build()
-                // Set a relative higher backlog.
-                .backlog(12)
-                // max connection for a worker is 3.
-                .maxconn(max_conn)
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
                 .workers(1)
                 .disable_signals()
-                .bind("test", addr, move || {
-                    let counter = counter.clone();
-                    fn_service(move |_io: TcpStream| {
-                        let counter = counter.clone();
-                        async move {
-                            counter.fetch_add(1, Ordering::SeqCst);
-                            sleep(Duration::from_secs(20)).await;
-                            counter.fetch_sub(1, Ordering::SeqCst);
-                            Ok::<(), ()>(())
-                        }
-                    })
-                })?
-                .run();
-
-            let _ = tx.send((server.clone(), actix_rt::System::current()));
+                .ipv6_only(true)
+                .bind("test", ("::", port), move || {
+                    fn_service(|_| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run()
+        }));
 
-            server.await
-        })
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
     });
-
     let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    assert!(net::TcpStream::connect(("::1", port)).is_ok());
+    assert!(net::TcpStream::connect(("127.0.0.1", port)).is_err());
 
-    let mut conns = vec![];
+    sys.stop();
+    let _ = h.join();
+}
 
-    for _ in 0..12 {
-        let conn = tokio::net::TcpStream::connect(addr).await.unwrap();
-        conns.push(conn);
+#[test]
+fn test_bind_policy() {
+    use actix_server::BindPolicy;
+
+    let err =
+        Server::build()
+            .bind_policy(BindPolicy::Ipv6Only)
+            .bind("test", "127.0.0.1:0", || fn_service(|_| ok::<_, ()>(())));
+    assert_eq!(err.err().unwrap().kind(), io::ErrorKind::AddrNotAvailable);
+
+    // skip on hosts without IPv6 support
+    if net::TcpListener::bind("[::1]:0").is_err() {
+        return;
     }
 
-    sleep(Duration::from_secs(5)).await;
+    let port1 = unused_addr().port();
+    let port2 = unused_addr().port();
+    let port3 = unused_addr().port();
+    let (tx, rx) = mpsc::channel();
 
-    // counter would remain at 3 even with 12 successful connection.
-    // and 9 of them remain in backlog.
-    assert_eq!(max_conn, counter_clone.load(Ordering::SeqCst));
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srvs = sys.block_on(lazy(|_| {
+            [
+                (BindPolicy::DualStack, port1, [0, 0, 0, 0], [0u16; 8]),
+                (BindPolicy::Ipv6Only, port2, [0, 0, 0, 0], [0u16; 8]),
+                // `[::1]` does not accept IPv4 connections, so `127.0.0.1` is bound as well
+                (
+                    BindPolicy::DualStack,
+                    port3,
+                    [127, 0, 0, 1],
+                    [0, 0, 0, 0, 0, 0, 0, 1],
+                ),
+            ]
+            .iter()
+            .map(|&(policy, port, v4, v6)| {
+                let addrs = [
+                    net::SocketAddr::from((v4, port)),
+                    net::SocketAddr::from((v6, port)),
+                ];
 
-    for mut conn in conns {
-        conn.shutdown().await.unwrap();
-    }
+                Server::build()
+                    .workers(1)
+                    .disable_signals()
+                    .bind_policy(policy)
+                    .bind("test", &addrs[..], move || fn_service(|_| ok::<_, ()>(())))
+                    .unwrap()
+                    .run()
+            })
+            .collect::<Vec<_>>()
+        }));
 
-    srv.stop(false).await;
+        let _ = tx.send((srvs, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srvs, sys) = rx.recv().unwrap();
+    srvs.iter().for_each(wait_ready);
+    assert!(net::TcpStream::connect(("::1", port1)).is_ok());
+    assert!(net::TcpStream::connect(("127.0.0.1", port1)).is_ok());
+    assert!(net::TcpStream::connect(("::1", port2)).is_ok());
+    assert!(net::TcpStream::connect(("127.0.0.1", port2)).is_err());
+    assert!(net::TcpStream::connect(("::1", port3)).is_ok());
+    assert!(net::TcpStream::connect(("127.0.0.1", port3)).is_ok());
 
     sys.stop();
-    let _ = h.join().unwrap();
+    let _ = h.join();
 }
 
-#[actix_rt::test]
-async fn test_service_restart() {
-    use std::task::{Context, Poll};
-
-    use actix_service::{fn_factory, Service};
-    use futures_core::future::LocalBoxFuture;
-    use tokio::io::AsyncWriteExt;
+#[test]
+fn test_dispatch_strategy() {
+    for strategy in &[
+        DispatchStrategy::RoundRobin,
+        DispatchStrategy::LeastConnections,
+        DispatchStrategy::RandomTwoChoices,
+    ] {
+        let strategy = *strategy;
+        let addr = unused_addr();
+        let (tx, rx) = mpsc::channel();
+        let num = Arc::new(AtomicUsize::new(0));
+        let num2 = num.clone();
 
-    struct TestService(Arc<AtomicUsize>);
+        let h = thread::spawn(move || {
+            let sys = actix_rt::System::new();
+            let srv = sys.block_on(lazy(|_| {
+                Server::build()
+                    .workers(2)
+                    .disable_signals()
+                    .dispatch_strategy(strategy)
+                    .bind("test", addr, move || {
+                        let num = num2.clone();
+                        fn_service(move |_| {
+                            num.fetch_add(1, Ordering::SeqCst);
+                            ok::<_, ()>(())
+                        })
+                    })
+                    .unwrap()
+                    .run()
+            }));
 
-    impl Service<TcpStream> for TestService {
-        type Response = ();
-        type Error = ();
-        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+            let _ = tx.send((srv, actix_rt::System::current()));
+            let _ = sys.run();
+        });
+        let (srv, sys) = rx.recv().unwrap();
+        wait_ready(&srv);
 
-        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            let TestService(ref counter) = self;
-            let c = counter.fetch_add(1, Ordering::SeqCst);
-            // Force the service to restart on first readiness check.
-            if c > 0 {
-                Poll::Ready(Ok(()))
-            } else {
-                Poll::Ready(Err(()))
-            }
+        for _ in 0..6 {
+            assert!(net::TcpStream::connect(addr).is_ok());
         }
+        wait_until(|| num.load(Ordering::SeqCst) == 6);
 
-        fn call(&self, _: TcpStream) -> Self::Future {
-            Box::pin(async { Ok(()) })
-        }
+        sys.stop();
+        let _ = h.join();
     }
+}
 
-    let addr1 = unused_addr();
-    let addr2 = unused_addr();
-    let (tx, rx) = mpsc::channel();
-    let num = Arc::new(AtomicUsize::new(0));
-    let num2 = Arc::new(AtomicUsize::new(0));
+#[test]
+#[cfg(target_os = "linux")]
+fn test_worker_affinity() {
+    fn cpus_allowed() -> String {
+        std::fs::read_to_string("/proc/thread-self/status")
+            .unwrap()
+            .lines()
+            .find(|line| line.starts_with("Cpus_allowed_list:"))
+            .unwrap()
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .to_owned()
+    }
 
-    let num_clone = num.clone();
-    let num2_clone = num2.clone();
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (cpu_tx, cpu_rx) = mpsc::channel();
 
     let h = thread::spawn(move || {
-        let num = num.clone();
-        actix_rt::System::new().block_on(async {
-            let server = Server::build()
-                .backlog(1)
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .worker_affinity(vec![0])
                 .disable_signals()
-                .bind("addr1", addr1, move || {
-                    let num = num.clone();
-                    fn_factory(move || {
-                        let num = num.clone();
-                        async move { Ok::<_, ()>(TestService(num)) }
+                .bind("test", addr, move || {
+                    let cpu_tx = cpu_tx.clone();
+                    fn_service(move |_| {
+                        let _ = cpu_tx.send(cpus_allowed());
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    assert!(net::TcpStream::connect(addr).is_ok());
+    let cpus = cpu_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(cpus, "0");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_close_stats() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (reason_tx, reason_rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let reason_tx = std::sync::Mutex::new(reason_tx);
+            let num = Arc::new(AtomicUsize::new(0));
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_connection_close(move |reason| {
+                    let _ = reason_tx.lock().unwrap().send(reason);
+                })
+                .bind("test", addr, move || {
+                    let num = num.clone();
+                    fn_service(move |_| {
+                        let res = match num.fetch_add(1, Ordering::SeqCst) {
+                            0 => Ok(()),
+                            1 => Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+                            _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
+                        };
+                        async move { res }
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    for _ in 0..3 {
+        let _conn = net::TcpStream::connect(addr).unwrap();
+        let reason = reason_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(reason, CloseReason::Shutdown);
+    }
+
+    let stats = actix_rt::Runtime::new()
+        .unwrap()
+        .block_on(srv.close_stats())
+        .unwrap();
+    assert_eq!(stats.get(CloseReason::Completed), 1);
+    assert_eq!(stats.get(CloseReason::PeerReset), 1);
+    assert_eq!(stats.get(CloseReason::ServiceError), 1);
+    assert_eq!(stats.total(), 3);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_service_latency() {
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (reason_tx, reason_rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let reason_tx = std::sync::Mutex::new(reason_tx);
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_connection_close(move |reason| {
+                    let _ = reason_tx.lock().unwrap().send(reason);
+                })
+                .bind("slow", addr1, || {
+                    fn_service(|_| async {
+                        sleep(Duration::from_millis(30)).await;
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("fast", addr2, || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    for addr in [addr1, addr1, addr2].iter() {
+        let _conn = net::TcpStream::connect(addr).unwrap();
+        reason_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    let latency = actix_rt::Runtime::new()
+        .unwrap()
+        .block_on(srv.service_latency())
+        .unwrap();
+    assert_eq!(latency.len(), 2);
+
+    assert_eq!(latency[0].name(), "slow");
+    assert_eq!(latency[0].worker(), 0);
+    assert_eq!(latency[0].histogram().count(), 2);
+    assert!(latency[0].histogram().mean().unwrap() >= Duration::from_millis(30));
+    assert!(latency[0].histogram().quantile(0.5).unwrap() > Duration::from_millis(30));
+
+    assert_eq!(latency[1].name(), "fast");
+    assert_eq!(latency[1].histogram().count(), 1);
+    assert!(latency[1].histogram().quantile(1.0).unwrap() <= Duration::from_millis(25));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_udp() {
+    let addr = net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind_udp("test", addr, move || {
+                    fn_service(|dgram: Datagram| {
+                        let mut res = dgram.data().to_vec();
+                        res.reverse();
+                        let _ = dgram.send(&res);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    let client = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    for msg in &[&b"hello"[..], &b"world"[..]] {
+        client.send_to(msg, addr).unwrap();
+
+        let mut buf = [0; 16];
+        let (len, from) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(from, addr);
+
+        let mut expected = msg.to_vec();
+        expected.reverse();
+        assert_eq!(&buf[..len], &expected[..]);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "sctp"))]
+fn test_bind_sctp() {
+    use std::io::{Read, Write};
+
+    use actix_server::SctpStream;
+    use socket2::{Domain, Protocol, Socket, Type};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let new_socket = || {
+        Socket::new(
+            Domain::IPV4,
+            Type::STREAM,
+            Some(Protocol::from(libc::IPPROTO_SCTP)),
+        )
+    };
+
+    // skip on hosts without SCTP support
+    if new_socket().is_err() {
+        return;
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_sctp("test", addr, move || {
+                    fn_service(|mut io: SctpStream| async move {
+                        let mut buf = [0; 5];
+                        io.read_exact(&mut buf).await?;
+                        buf.reverse();
+                        io.write_all(&buf).await
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    let client = new_socket().unwrap();
+    client.connect(&addr.into()).unwrap();
+    let mut client = net::TcpStream::from(client);
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0; 5];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"olleh");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_unbind_running() {
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr1, move || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+
+    rt.block_on(srv.bind("added", addr2, move || {
+        let num = num2.clone();
+        fn_service(move |_| {
+            num.fetch_add(1, Ordering::SeqCst);
+            ok::<_, ()>(())
+        })
+    }))
+    .unwrap();
+
+    assert!(net::TcpStream::connect(addr2).is_ok());
+    wait_until(|| num.load(Ordering::SeqCst) == 1);
+
+    // names are unique
+    let err = rt
+        .block_on(srv.bind("test", unused_addr(), || fn_service(|_| ok::<_, ()>(()))))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    rt.block_on(srv.unbind("added")).unwrap();
+    // accept loop handles requests in order, listener is closed once it replied
+    rt.block_on(srv.workers()).unwrap();
+    assert!(net::TcpStream::connect(addr2).is_err());
+
+    let err = rt.block_on(srv.unbind("added")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    // remaining listener is unaffected
+    assert!(net::TcpStream::connect(addr1).is_ok());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_accept_rate() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .max_accept_rate("test", 5)
+                .bind("test", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    let conns = (0..10)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+
+    // burst is accepted right away, the rest is spread out
+    thread::sleep(Duration::from_millis(300));
+    let accepted = num.load(Ordering::SeqCst);
+    assert!((5..10).contains(&accepted), "accepted {}", accepted);
+
+    wait_until(|| num.load(Ordering::SeqCst) == 10);
+
+    drop(conns);
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_connection_rate() {
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+    let num3 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .max_connection_rate(5)
+                .bind("test1", addr1, move || {
+                    let num = num2.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("test2", addr2, move || {
+                    let num = num3.clone();
+                    fn_service(move |_| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    // limit is shared by both listeners
+    let conns = (0..10)
+        .map(|i| net::TcpStream::connect(if i % 2 == 0 { addr1 } else { addr2 }).unwrap())
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(300));
+    let accepted = num.load(Ordering::SeqCst);
+    assert!((5..10).contains(&accepted), "accepted {}", accepted);
+
+    wait_until(|| num.load(Ordering::SeqCst) == 10);
+
+    drop(conns);
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_listen() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let lst = net::TcpListener::bind(addr).unwrap();
+        sys.block_on(async {
+            Server::build()
+                .disable_signals()
+                .workers(1)
+                .listen("test", lst, move || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run();
+            let _ = tx.send(actix_rt::System::current());
+        });
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_listen_tokio() {
+    use std::io::Read;
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        sys.block_on(async {
+            let lst = actix_rt::net::TcpListener::bind(addr).await.unwrap();
+            Server::build()
+                .disable_signals()
+                .workers(1)
+                .listen_tokio("test", lst, move || {
+                    fn_service(|mut io: TcpStream| async move { io.write_all(b"1").await })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send(actix_rt::System::current());
+        });
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    conn.read_exact(&mut [0; 1]).unwrap();
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_start() {
+    use std::io::Read;
+
+    use actix_codec::{BytesCodec, Framed};
+    use bytes::Bytes;
+    use futures_util::sink::SinkExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .backlog(100)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut buf = [1u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    // pause
+    let _ = srv.pause();
+    thread::sleep(Duration::from_millis(200));
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let res = conn.read_exact(&mut buf);
+    assert!(res.is_err());
+
+    // resume
+    let _ = srv.resume();
+    thread::sleep(Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    assert!(net::TcpStream::connect(addr).is_ok());
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    // stop
+    let _ = srv.stop(false);
+    thread::sleep(Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr).is_err());
+
+    thread::sleep(Duration::from_millis(100));
+    sys.stop();
+    let _ = h.join();
+}
+
+#[actix_rt::test]
+async fn test_max_concurrent_connections() {
+    // Note:
+    // A tcp listener would accept connects based on it's backlog setting.
+    //
+    // The limit test on the other hand is only for concurrent tcp stream limiting a work
+    // thread accept.
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter_clone = counter.clone();
+
+    let max_conn = 3;
+
+    let h = thread::spawn(move || {
+        actix_rt::System::new().block_on(async {
+            let server = Server::build()
+                // Set a relative higher backlog.
+                .backlog(12)
+                // max connection for a worker is 3.
+                .maxconn(max_conn)
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let counter = counter.clone();
+                    fn_service(move |_io: TcpStream| {
+                        let counter = counter.clone();
+                        async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            sleep(Duration::from_secs(20)).await;
+                            counter.fetch_sub(1, Ordering::SeqCst);
+                            Ok::<(), ()>(())
+                        }
+                    })
+                })?
+                .run();
+
+            let _ = tx.send((server.clone(), actix_rt::System::current()));
+
+            server.await
+        })
+    });
+
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut conns = vec![];
+
+    for _ in 0..12 {
+        let conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+        conns.push(conn);
+    }
+
+    sleep(Duration::from_secs(5)).await;
+
+    // counter would remain at 3 even with 12 successful connection.
+    // and 9 of them remain in backlog.
+    assert_eq!(max_conn, counter_clone.load(Ordering::SeqCst));
+
+    for mut conn in conns {
+        conn.shutdown().await.unwrap();
+    }
+
+    srv.stop(false).await;
+
+    sys.stop();
+    let _ = h.join().unwrap();
+}
+
+#[actix_rt::test]
+async fn test_service_restart() {
+    use std::task::{Context, Poll};
+
+    use actix_service::{fn_factory, Service};
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::AsyncWriteExt;
+
+    struct TestService(Arc<AtomicUsize>);
+
+    impl Service<TcpStream> for TestService {
+        type Response = ();
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            let TestService(ref counter) = self;
+            let c = counter.fetch_add(1, Ordering::SeqCst);
+            // Force the service to restart on first readiness check.
+            if c > 0 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Ready(Err(()))
+            }
+        }
+
+        fn call(&self, _: TcpStream) -> Self::Future {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = Arc::new(AtomicUsize::new(0));
+
+    let num_clone = num.clone();
+    let num2_clone = num2.clone();
+
+    let h = thread::spawn(move || {
+        let num = num.clone();
+        actix_rt::System::new().block_on(async {
+            let server = Server::build()
+                .backlog(1)
+                .disable_signals()
+                .bind("addr1", addr1, move || {
+                    let num = num.clone();
+                    fn_factory(move || {
+                        let num = num.clone();
+                        async move { Ok::<_, ()>(TestService(num)) }
+                    })
+                })
+                .unwrap()
+                .bind("addr2", addr2, move || {
+                    let num2 = num2.clone();
+                    fn_factory(move || {
+                        let num2 = num2.clone();
+                        async move { Ok::<_, ()>(TestService(num2)) }
+                    })
+                })
+                .unwrap()
+                .workers(1)
+                .run();
+
+            let _ = tx.send((server.clone(), actix_rt::System::current()));
+            server.await
+        })
+    });
+
+    let (server, sys) = rx.recv().unwrap();
+
+    for _ in 0..5 {
+        TcpStream::connect(addr1)
+            .await
+            .unwrap()
+            .shutdown()
+            .await
+            .unwrap();
+        TcpStream::connect(addr2)
+            .await
+            .unwrap()
+            .shutdown()
+            .await
+            .unwrap();
+    }
+
+    sleep(Duration::from_secs(3)).await;
+
+    assert!(num_clone.load(Ordering::SeqCst) > 5);
+    assert!(num2_clone.load(Ordering::SeqCst) > 5);
+
+    sys.stop();
+    let _ = server.stop(false);
+    let _ = h.join().unwrap();
+}
+
+#[ignore]
+#[actix_rt::test]
+async fn worker_restart() {
+    use actix_service::{Service, ServiceFactory};
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    struct TestServiceFactory(Arc<AtomicUsize>);
+
+    impl ServiceFactory<TcpStream> for TestServiceFactory {
+        type Response = ();
+        type Error = ();
+        type Config = ();
+        type Service = TestService;
+        type InitError = ();
+        type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+        fn new_service(&self, _: Self::Config) -> Self::Future {
+            let counter = self.0.fetch_add(1, Ordering::Relaxed);
+
+            Box::pin(async move { Ok(TestService(counter)) })
+        }
+    }
+
+    struct TestService(usize);
+
+    impl Service<TcpStream> for TestService {
+        type Response = ();
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        actix_service::always_ready!();
+
+        fn call(&self, stream: TcpStream) -> Self::Future {
+            let counter = self.0;
+
+            let mut stream = stream.into_std().unwrap();
+            use std::io::Write;
+            let str = counter.to_string();
+            let buf = str.as_bytes();
+
+            let mut written = 0;
+
+            while written < buf.len() {
+                if let Ok(n) = stream.write(&buf[written..]) {
+                    written += n;
+                }
+            }
+            stream.flush().unwrap();
+            stream.shutdown(net::Shutdown::Write).unwrap();
+
+            // force worker 2 to restart service once.
+            if counter == 2 {
+                panic!("panic on purpose")
+            } else {
+                Box::pin(async { Ok(()) })
+            }
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let counter = Arc::new(AtomicUsize::new(1));
+    let h = thread::spawn(move || {
+        let counter = counter.clone();
+        actix_rt::System::new().block_on(async {
+            let server = Server::build()
+                .disable_signals()
+                .bind("addr", addr, move || TestServiceFactory(counter.clone()))
+                .unwrap()
+                .workers(2)
+                .run();
+
+            let _ = tx.send((server.clone(), actix_rt::System::current()));
+            server.await
+        })
+    });
+
+    let (server, sys) = rx.recv().unwrap();
+
+    sleep(Duration::from_secs(3)).await;
+
+    let mut buf = [0; 8];
+
+    // worker 1 would not restart and return it's id consistently.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("1", id);
+    stream.shutdown().await.unwrap();
+
+    // worker 2 dead after return response.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("2", id);
+    stream.shutdown().await.unwrap();
+
+    // request to worker 1
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("1", id);
+    stream.shutdown().await.unwrap();
+
+    // TODO: Remove sleep if it can pass CI.
+    sleep(Duration::from_secs(3)).await;
+
+    // worker 2 restarting and work goes to worker 1.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("1", id);
+    stream.shutdown().await.unwrap();
+
+    // TODO: Remove sleep if it can pass CI.
+    sleep(Duration::from_secs(3)).await;
+
+    // worker 2 restarted but worker 1 was still the next to accept connection.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("1", id);
+    stream.shutdown().await.unwrap();
+
+    // TODO: Remove sleep if it can pass CI.
+    sleep(Duration::from_secs(3)).await;
+
+    // worker 2 accept connection again but it's id is 3.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let n = stream.read(&mut buf).await.unwrap();
+    let id = String::from_utf8_lossy(&buf[0..n]);
+    assert_eq!("3", id);
+    stream.shutdown().await.unwrap();
+
+    sys.stop();
+    let _ = server.stop(false);
+    let _ = h.join().unwrap();
+}
+
+#[test]
+fn test_bind_proxied() {
+    use std::io::{Read, Write};
+
+    use actix_server::ProxyStream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let closed = Arc::new(AtomicUsize::new(0));
+    let closed2 = closed.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_connection_close(move |reason| {
+                    if reason == CloseReason::Rejected {
+                        closed2.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+                .bind_proxied("test", addr, move || {
+                    fn_service(|mut io: ProxyStream<TcpStream>| async move {
+                        let mut buf = [0; 4];
+                        io.read_exact(&mut buf).await?;
+
+                        let src = io.header().source_addr().unwrap();
+                        io.write_all(format!("{} ", src).as_bytes()).await?;
+                        io.write_all(&buf).await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"PROXY TCP4 192.0.2.1 127.0.0.1 56324 443\r\nping")
+        .unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "192.0.2.1:56324 ping");
+
+    // connection without header is closed before reaching the service
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"ping\r\n").unwrap();
+    let mut res = Vec::new();
+    let _ = conn.read_to_end(&mut res);
+    assert!(res.is_empty());
+    wait_until(|| closed.load(Ordering::SeqCst) == 1);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_restart_worker() {
+    use std::io::{Read, Write};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let created = Arc::new(AtomicUsize::new(0));
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let gen = created.fetch_add(1, Ordering::SeqCst) as u8 + 1;
+
+                    // reply with generation of the service, then wait for client to close
+                    fn_service(move |mut io: TcpStream| async move {
+                        io.write_all(&[gen]).await?;
+                        io.read_to_end(&mut Vec::new()).await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut buf = [0; 1];
+    let mut conn1 = net::TcpStream::connect(addr).unwrap();
+    conn1.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [1]);
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let srv2 = srv.clone();
+    thread::spawn(move || {
+        let res = actix_rt::Runtime::new()
+            .unwrap()
+            .block_on(srv2.restart_worker(0, true));
+        let _ = done_tx.send(res);
+    });
+
+    // replacement serves new connections while old worker drains
+    let rt = actix_rt::Runtime::new().unwrap();
+    wait_until(|| rt.block_on(srv.workers()).unwrap()[0].restarts() == 1);
+    let mut conn2 = net::TcpStream::connect(addr).unwrap();
+    conn2.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [2]);
+    assert!(done_rx.try_recv().is_err());
+
+    // old worker stops once its last connection is closed
+    conn1.write_all(b"x").unwrap();
+    drop(conn1);
+    done_rx
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .unwrap();
+
+    let err = rt.block_on(srv.restart_worker(1, false)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[actix_rt::test]
+async fn test_reconfigure_running() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter_clone = counter.clone();
+
+    let h = thread::spawn(move || {
+        actix_rt::System::new().block_on(async {
+            let server = Server::build()
+                .backlog(12)
+                .maxconn(2)
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let counter = counter.clone();
+                    fn_service(move |_io: TcpStream| {
+                        let counter = counter.clone();
+                        async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            sleep(Duration::from_secs(60)).await;
+                            Ok::<(), ()>(())
+                        }
+                    })
+                })?
+                .run();
+
+            let _ = tx.send((server.clone(), actix_rt::System::current()));
+
+            server.await
+        })
+    });
+
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut conns = vec![];
+    for _ in 0..6 {
+        conns.push(tokio::net::TcpStream::connect(addr).await.unwrap());
+    }
+
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(counter_clone.load(Ordering::SeqCst), 2);
+
+    // raised limit lets worker take connections waiting in backlog
+    srv.set_maxconn(4).await;
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(counter_clone.load(Ordering::SeqCst), 4);
+
+    // graceful shutdown gives up on the still running services after new timeout
+    srv.set_shutdown_timeout(1).await;
+    let res = actix_rt::time::timeout(Duration::from_secs(5), srv.stop(true)).await;
+    assert!(res.is_ok());
+
+    sys.stop();
+    let _ = h.join().unwrap();
+}
+
+#[test]
+fn test_worker_channel_capacity() {
+    use std::io::Read;
+
+    use actix_service::fn_factory;
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .worker_channel_capacity(2)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    // worker does not take connections off its channel until service is created
+                    fn_factory(|| async {
+                        sleep(Duration::from_millis(500)).await;
+                        Ok::<_, ()>(fn_service(|mut io: TcpStream| async move {
+                            io.write_all(b"x").await
+                        }))
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let conns = (0..6)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+
+    // connections left in backlog are accepted once worker has caught up
+    for mut conn in conns {
+        conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buf = [0; 1];
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, *b"x");
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_hand_off() {
+    use std::io::{Read, Write};
+    use std::sync::Mutex;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let handle = Arc::new(Mutex::new(None::<Server>));
+            let handle2 = handle.clone();
+            let created = Arc::new(AtomicUsize::new(0));
+
+            let srv = Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("handshake", addr1, move || {
+                    let handle = handle2.clone();
+
+                    fn_service(move |mut io: TcpStream| {
+                        let srv = handle.lock().unwrap().clone().unwrap();
+                        async move {
+                            // client picks the worker
+                            let mut buf = [0; 1];
+                            io.read_exact(&mut buf).await?;
+                            srv.hand_off("data", io, Some(buf[0] as usize)).await
+                        }
+                    })
+                })
+                .unwrap()
+                .bind("data", addr2, move || {
+                    let id = created.fetch_add(1, Ordering::SeqCst) as u8;
+
+                    // reply with id of the service instance
+                    fn_service(move |mut io: TcpStream| async move {
+                        io.write_all(&[id]).await?;
+                        io.read_to_end(&mut Vec::new()).await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run();
+
+            *handle.lock().unwrap() = Some(srv.clone());
+            srv
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let ids = [0, 1, 0, 1]
+        .iter()
+        .map(|worker| {
+            let mut conn = net::TcpStream::connect(addr1).unwrap();
+            conn.write_all(&[*worker]).unwrap();
+            let mut buf = [0; 1];
+            conn.read_exact(&mut buf).unwrap();
+            buf[0]
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ids[0], ids[2], "ids {:?}", ids);
+    assert_eq!(ids[1], ids[3], "ids {:?}", ids);
+    assert_ne!(ids[0], ids[1], "ids {:?}", ids);
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(async {
+        let io = TcpStream::connect(addr2).await.unwrap();
+        let err = srv.hand_off("missing", io, None).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let io = TcpStream::connect(addr2).await.unwrap();
+        let err = srv.hand_off("data", io, Some(5)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    });
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_on_connect() {
+    use std::io::Read;
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_on_connect(
+                    "test",
+                    addr,
+                    |io: &TcpStream| io.peer_addr().unwrap(),
+                    || {
+                        // reply with peer address seen by on_connect
+                        fn_service(|(mut io, peer): (TcpStream, net::SocketAddr)| async move {
+                            io.write_all(peer.to_string().as_bytes()).await
+                        })
+                    },
+                )
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut peer = String::new();
+    conn.read_to_string(&mut peer).unwrap();
+    assert_eq!(peer, conn.local_addr().unwrap().to_string());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_multi() {
+    use std::io::{Read, Write};
+
+    use actix_server::Mux;
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            // echo first 4 bytes prefixed with the name of the service
+            fn reply(
+                name: &'static str,
+            ) -> impl Fn(TcpStream) -> LocalBoxFuture<'static, io::Result<()>> + Clone
+            {
+                move |mut io: TcpStream| {
+                    Box::pin(async move {
+                        let mut buf = [0; 4];
+                        io.read_exact(&mut buf).await?;
+                        io.write_all(name.as_bytes()).await?;
+                        io.write_all(&buf).await
+                    })
+                }
+            }
+
+            let mux = Mux::new(4)
+                .service(|buf| buf.starts_with(b"GET"), || fn_service(reply("http:")))
+                .service(|_| true, || fn_service(reply("other:")));
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_multi("test", addr, mux)
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    for (req, res) in [("GET ", "http:GET "), ("PING", "other:PING")].iter() {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.write_all(req.as_bytes()).unwrap();
+        let mut buf = String::new();
+        conn.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, *res);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_drain_report() {
+    use std::io::Write;
+
+    use actix_server::WorkerEvent;
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (report_tx, report_rx) = mpsc::channel();
+    let report_tx = std::sync::Mutex::new(report_tx);
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let stop_tx = std::sync::Mutex::new(stop_tx);
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .shutdown_timeout(1)
+                .worker_metrics(move |_, event: &WorkerEvent<'_>| {
+                    if let WorkerEvent::ShutdownStarted { .. } = event {
+                        let _ = stop_tx.lock().unwrap().send(());
+                    }
+                })
+                .on_drain(move |report| {
+                    let _ = report_tx.lock().unwrap().send(report.clone());
+                })
+                .bind("test", addr, move || {
+                    // serve until client closes connection
+                    fn_service(|mut io: TcpStream| async move {
+                        io.read_to_end(&mut Vec::new()).await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+
+    let mut conn1 = net::TcpStream::connect(addr).unwrap();
+    let _conn2 = net::TcpStream::connect(addr).unwrap();
+    // both connections reached the service, not just the channel of the worker
+    wait_until(|| *rt.block_on(srv.connections()).unwrap().borrow() == 2);
+
+    let srv2 = srv.clone();
+    let stop =
+        thread::spawn(move || actix_rt::Runtime::new().unwrap().block_on(srv2.stop(true)));
+
+    // first connection completes during drain, second is dropped at shutdown timeout
+    stop_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    conn1.write_all(b"x").unwrap();
+    drop(conn1);
+
+    let report = report_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    let _ = stop.join();
+
+    assert!(!report.is_clean());
+    assert!(report.duration() >= Duration::from_secs(1));
+
+    let lst = &report.listeners()[0];
+    assert_eq!(lst.name(), "test");
+    assert_eq!(lst.in_flight(), 2);
+    assert_eq!(lst.completed(), 1);
+    assert_eq!(lst.abandoned(), 1);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_listener_addr() {
+    use std::os::unix::net::UnixStream;
+
+    use actix_rt::net::UnixStream as RtUnixStream;
+    use actix_server::ListenerAddr;
+
+    let addr = unused_addr();
+    let path = std::env::temp_dir().join(format!(
+        "actix-server-test-{}-addr.sock",
+        std::process::id()
+    ));
+    let (tx, rx) = mpsc::channel();
+    let (addr_tx, addr_rx) = mpsc::channel();
+    let addr_tx = Arc::new(std::sync::Mutex::new(addr_tx));
+    let addr_tx2 = addr_tx.clone();
+
+    let p = path.clone();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("tcp", addr, move || {
+                    let addr_tx = addr_tx.clone();
+                    fn_service(move |_: TcpStream| {
+                        let _ = addr_tx.lock().unwrap().send(ListenerAddr::current());
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind_uds("uds", p, move || {
+                    let addr_tx = addr_tx2.clone();
+                    fn_service(move |_: RtUnixStream| {
+                        let addr_tx = addr_tx.clone();
+                        async move {
+                            let _ = addr_tx.lock().unwrap().send(ListenerAddr::current());
+                            Ok::<_, ()>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    let lst_addr = addr_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(lst_addr, Some(ListenerAddr::Tcp(addr)));
+
+    let _conn = UnixStream::connect(&path).unwrap();
+    let lst_addr = addr_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(lst_addr, Some(ListenerAddr::Uds(path.clone())));
+
+    assert_eq!(ListenerAddr::current(), None);
+
+    let _ = std::fs::remove_file(&path);
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_uds_with_options() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+
+    use actix_rt::net::UnixStream as RtUnixStream;
+    use actix_server::UdsOptions;
+
+    let dir = std::env::temp_dir();
+    let pid = std::process::id();
+    let path1 = dir.join(format!("actix-server-test-{}-1.sock", pid));
+    let path2 = dir.join(format!("actix-server-test-{}-2.sock", pid));
+    let (tx, rx) = mpsc::channel();
+
+    let (p1, p2) = (path1.clone(), path2.clone());
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_uds_with_options("test1", p1, UdsOptions::new().mode(0o600), || {
+                    fn_service(|_: RtUnixStream| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .bind_uds_with_options(
+                    "test2",
+                    p2,
+                    UdsOptions::new().unlink_on_stop(false),
+                    || fn_service(|_: RtUnixStream| ok::<_, ()>(())),
+                )
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mode = std::fs::metadata(&path1).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // pausing does not remove socket files
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.pause());
+    rt.block_on(srv.resume());
+    assert!(UnixStream::connect(&path1).is_ok());
+
+    rt.block_on(srv.stop(true));
+    assert!(!path1.exists());
+    assert!(path2.exists());
+
+    let _ = std::fs::remove_file(&path2);
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_tcp_options() {
+    use std::io::Read;
+
+    use actix_server::TcpOptions;
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .tcp_options("test", TcpOptions::new().nodelay(true).ttl(42))
+                .bind("test", addr, || {
+                    // reply with options seen by the service
+                    fn_service(|mut io: TcpStream| async move {
+                        let opts = format!("{} {}", io.nodelay()?, io.ttl()?);
+                        io.write_all(opts.as_bytes()).await
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut opts = String::new();
+    conn.read_to_string(&mut opts).unwrap();
+    assert_eq!(opts, "true 42");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_on_signal() {
+    use actix_rt::signal::unix::SignalKind;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (sig_tx, sig_rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_signal(SignalKind::hangup(), move |_srv| {
+                    let sig_tx = sig_tx.clone();
+                    async move {
+                        let _ = sig_tx.send(());
+                    }
+                })
+                .bind("test", addr, || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // hooks run on every signal
+    for _ in 0..2 {
+        unsafe { libc::kill(libc::getpid(), libc::SIGHUP) };
+        sig_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_restart_policy() {
+    use actix_server::WorkerRestart;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .system_exit()
+                .worker_restart(
+                    WorkerRestart::new()
+                        .backoff(Duration::from_millis(50), Duration::from_secs(1))
+                        .max_restarts(1),
+                )
+                .on_worker_faulted(move |fault| {
+                    let _ = tx.send(fault.clone());
+                })
+                .bind("test", addr, || {
+                    // panic in call, not in the returned future, takes down the worker
+                    fn_service(
+                        |_: TcpStream| -> actix_utils::future::Ready<Result<(), ()>> {
+                            panic!("panic on purpose")
+                        },
+                    )
+                })
+                .unwrap()
+                .run()
+        }));
+        let _ = sys.run();
+    });
+
+    // faults are noticed when the next connection is dispatched to the dead worker
+    let mut faults = Vec::new();
+    while faults.len() < 2 {
+        let _ = net::TcpStream::connect(addr);
+        if let Ok(fault) = rx.recv_timeout(Duration::from_millis(100)) {
+            faults.push(fault);
+        }
+    }
+
+    assert_eq!(faults[0].worker(), 0);
+    assert_eq!(faults[0].faults(), 1);
+    assert_eq!(faults[0].restart_delay(), Some(Duration::from_millis(50)));
+    assert_eq!(faults[1].faults(), 2);
+    assert_eq!(faults[1].restart_delay(), None);
+
+    // restarts are exhausted, server stops and exits the system
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_fd_headroom() {
+    let addr_low = unused_addr();
+    let addr_high = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (event_tx, event_rx) = mpsc::channel();
+    let low = Arc::new(AtomicUsize::new(0));
+    let high = Arc::new(AtomicUsize::new(0));
+    let (low2, high2) = (low.clone(), high.clone());
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                // more than the process can ever have free
+                .fd_headroom("low", usize::MAX)
+                .on_fd_pressure(move |event| {
+                    let _ = event_tx.send(event.clone());
+                })
+                .bind("low", addr_low, move || {
+                    let low = low2.clone();
+                    fn_service(move |_| {
+                        low.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("high", addr_high, move || {
+                    let high = high2.clone();
+                    fn_service(move |_| {
+                        high.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let event = event_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.name(), "low");
+    assert!(event.is_paused());
+    assert!(event.open_fds() > 0);
+
+    let _low_conn = net::TcpStream::connect(addr_low).unwrap();
+    let _high_conn = net::TcpStream::connect(addr_high).unwrap();
+    wait_until(|| high.load(Ordering::SeqCst) == 1);
+
+    // connection to the paused listener stays in the backlog
+    assert_eq!(low.load(Ordering::SeqCst), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_pause_listener() {
+    let public_addr = unused_addr();
+    let admin_addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let public = Arc::new(AtomicUsize::new(0));
+    let admin = Arc::new(AtomicUsize::new(0));
+    let (public2, admin2) = (public.clone(), admin.clone());
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("public", public_addr, move || {
+                    let public = public2.clone();
+                    fn_service(move |_| {
+                        public.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("admin", admin_addr, move || {
+                    let admin = admin2.clone();
+                    fn_service(move |_| {
+                        admin.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+
+    rt.block_on(srv.pause_listener("public")).unwrap();
+    // accept loop handles requests in order, listener is paused once it replied
+    rt.block_on(srv.workers()).unwrap();
+
+    let _public_conn = net::TcpStream::connect(public_addr).unwrap();
+    let _admin_conn = net::TcpStream::connect(admin_addr).unwrap();
+    wait_until(|| admin.load(Ordering::SeqCst) == 1);
+
+    // connection to the paused listener waits in the backlog
+    assert_eq!(public.load(Ordering::SeqCst), 0);
+
+    rt.block_on(srv.resume_listener("public")).unwrap();
+    wait_until(|| public.load(Ordering::SeqCst) == 1);
+
+    let err = rt.block_on(srv.pause_listener("missing")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_ready() {
+    use std::time::Instant;
+
+    use actix_service::fn_factory;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    // services are created as soon as the server starts
+    let start = Instant::now();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, || {
+                    fn_factory(|| async {
+                        // slow service creation
+                        sleep(Duration::from_millis(300)).await;
+                        Ok::<_, ()>(fn_service(|_| ok::<_, ()>(())))
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+
+    rt.block_on(srv.ready()).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    // resolves right away once ready
+    let start = Instant::now();
+    rt.block_on(srv.ready()).unwrap();
+    assert!(start.elapsed() < Duration::from_millis(300));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_health_check() {
+    use std::io::{Read, Write};
+
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let health_addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .maxconn(2)
+                .disable_signals()
+                .bind("test", addr, || {
+                    // hold connection until the client closes it
+                    fn_service(|mut io: TcpStream| async move {
+                        let _ = io.read(&mut [0; 1]).await;
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .health_check(health_addr)
+                .unwrap()
+                .health_check_payload("ok")
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let probe = || {
+        let mut conn = net::TcpStream::connect(health_addr)?;
+        let mut buf = String::new();
+        conn.read_to_string(&mut buf)?;
+        Ok::<_, io::Error>(buf)
+    };
+
+    assert_eq!(probe().unwrap(), "ok");
+
+    // the only worker reached its connection limit
+    let conns = (0..2)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    wait_until(|| probe().is_err());
+
+    for mut conn in conns {
+        conn.write_all(b"x").unwrap();
+    }
+    wait_until(|| probe().is_ok());
+    assert_eq!(probe().unwrap(), "ok");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_connection_age() {
+    use std::io::Read;
+    use std::time::Instant;
+
+    use actix_server::CloseToken;
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let token = CloseToken::current().unwrap();
+                        assert!(!token.is_closing());
+
+                        token.closing().await;
+                        io.write_all(b"bye").await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .max_connection_age("test", Duration::from_millis(300))
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let start = Instant::now();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut buf = String::new();
+    conn.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "bye");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[actix_rt::test]
+async fn test_listen_memory() {
+    use actix_server::MemoryListener;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    let (lst, connector) = MemoryListener::new();
+
+    // opened before the server runs, waits like in a backlog
+    let early = connector.connect().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .listen_memory("echo", lst, || {
+                    fn_service(|mut io: DuplexStream| async move {
+                        let mut buf = Vec::new();
+                        io.read_to_end(&mut buf).await?;
+                        io.write_all(&buf).await
+                    })
+                })
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let echo = |mut conn: DuplexStream, msg: &'static [u8]| async move {
+        conn.write_all(msg).await.unwrap();
+        conn.shutdown().await.unwrap();
+
+        let mut buf = Vec::new();
+        conn.read_to_end(&mut buf).await.unwrap();
+        buf
+    };
+
+    assert_eq!(echo(early, b"early").await, b"early");
+    for _ in 0..5 {
+        let conn = connector.connect().unwrap();
+        assert_eq!(echo(conn, b"hello").await, b"hello");
+    }
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+
+    let err = connector.connect().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
+
+#[test]
+fn test_from_config() {
+    use actix_server::{ListenerConfig, ServerBuilder, ServerConfig};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let config = ServerConfig {
+        workers: Some(1),
+        listeners: vec![ListenerConfig::new("test", addr.to_string())],
+        ..Default::default()
+    };
+
+    let err = ServerBuilder::from_config(config.clone())
+        .unwrap()
+        .bind_configured("missing", || fn_service(|_| ok::<_, ()>(())))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            ServerBuilder::from_config(config)
+                .unwrap()
+                .disable_signals()
+                .bind_configured("test", || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_from_config_nodelay() {
+    use std::io::Read;
+
+    use actix_server::{ListenerConfig, ServerBuilder, ServerConfig};
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let config = ServerConfig {
+        workers: Some(1),
+        listeners: vec![ListenerConfig::new("test", addr.to_string()).nodelay(true)],
+        ..Default::default()
+    };
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            ServerBuilder::from_config(config)
+                .unwrap()
+                .disable_signals()
+                .bind_configured("test", || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let nodelay = io.nodelay()?.to_string();
+                        io.write_all(nodelay.as_bytes()).await
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut nodelay = String::new();
+    conn.read_to_string(&mut nodelay).unwrap();
+    assert_eq!(nodelay, "true");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_on_shutdown() {
+    use std::io::Read;
+
+    use actix_server::ServiceFactory;
+    use actix_service::boxed::{self, BoxServiceFactory};
+    use futures_core::future::LocalBoxFuture;
+
+    #[derive(Clone)]
+    struct Factory(Arc<AtomicUsize>);
+
+    impl ServiceFactory<TcpStream> for Factory {
+        type Factory = BoxServiceFactory<(), TcpStream, (), (), ()>;
+
+        fn create(&self) -> Self::Factory {
+            boxed::factory(fn_service(|_| ok(())))
+        }
+
+        fn on_shutdown(&self) -> Option<LocalBoxFuture<'static, ()>> {
+            let num = self.0.clone();
+            Some(Box::pin(async move {
+                sleep(Duration::from_millis(50)).await;
+                num.fetch_add(1, Ordering::SeqCst);
+            }))
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, Factory(num2))
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    // connection is closed once served
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(conn.read(&mut [0; 1]).unwrap(), 0);
+    assert_eq!(num.load(Ordering::SeqCst), 0);
+
+    actix_rt::Runtime::new().unwrap().block_on(srv.stop(true));
+    assert_eq!(num.load(Ordering::SeqCst), 2);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_client_idle_timeout() {
+    use std::io::{Read, Write};
+    use std::time::Instant;
+
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (reason_tx, reason_rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let reason_tx = std::sync::Mutex::new(reason_tx);
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .client_idle_timeout(Duration::from_millis(300))
+                .on_connection_close(move |reason| {
+                    let _ = reason_tx.lock().unwrap().send(reason);
+                })
+                .bind("test", addr, || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let mut buf = [0; 16];
+                        while io.read(&mut buf).await? > 0 {}
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let start = Instant::now();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    // activity keeps the connection open
+    for _ in 0..4 {
+        thread::sleep(Duration::from_millis(150));
+        conn.write_all(b"ping").unwrap();
+    }
+    assert!(reason_rx.try_recv().is_err());
+
+    let mut buf = Vec::new();
+    assert_eq!(conn.read_to_end(&mut buf).unwrap(), 0);
+    assert!(start.elapsed() >= Duration::from_millis(900));
+
+    let reason = reason_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(reason, CloseReason::IdleTimeout);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_connections() {
+    use std::io::Read;
+
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let mut buf = [0; 16];
+                        while io.read(&mut buf).await? > 0 {}
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    let mut conns = rt.block_on(srv.connections()).unwrap();
+    assert_eq!(*conns.borrow(), 0);
+
+    let wait_for = |conns: &mut tokio::sync::watch::Receiver<usize>, num| {
+        rt.block_on(async {
+            actix_rt::time::timeout(Duration::from_secs(5), async {
+                while *conns.borrow() != num {
+                    conns.changed().await.unwrap();
+                }
+            })
+            .await
+            .unwrap()
+        })
+    };
+
+    let conn1 = net::TcpStream::connect(addr).unwrap();
+    let mut conn2 = net::TcpStream::connect(addr).unwrap();
+    wait_for(&mut conns, 2);
+
+    drop(conn1);
+    wait_for(&mut conns, 1);
+
+    conn2.shutdown(net::Shutdown::Write).unwrap();
+    let _ = conn2.read(&mut [0; 1]);
+    wait_for(&mut conns, 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_thread_name() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (name_tx, name_rx) = mpsc::channel();
+    let name_tx = Arc::new(std::sync::Mutex::new(name_tx));
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .worker_thread_name("srv-worker")
+                .accept_thread_name("srv-accept")
+                .bind("test", addr, move || {
+                    let name_tx = name_tx.clone();
+                    fn_service(move |_: TcpStream| {
+                        let name = thread::current().name().map(str::to_owned);
+                        let _ = name_tx.lock().unwrap().send(name);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    let name = name_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(name.as_deref(), Some("srv-worker-0"));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_accept_mode_runtime() {
+    use actix_server::AcceptMode;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .accept_mode(AcceptMode::Runtime)
+                .bind("test", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |_: TcpStream| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.ready()).unwrap();
+
+    for _ in 0..3 {
+        let _conn = net::TcpStream::connect(addr).unwrap();
+    }
+    wait_until(|| num.load(Ordering::SeqCst) == 3);
+
+    // accept loop stops with the server
+    rt.block_on(srv.stop(true));
+    assert!(net::TcpStream::connect(addr).is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_wake_coalescing() {
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .maxconn(2)
+                .disable_signals()
+                .accept_events(1)
+                .accept_wake_coalescing(true)
+                .bind("test", [addr1, addr2].as_ref(), move || {
+                    let num = num2.clone();
+                    fn_service(move |_: TcpStream| {
+                        let num = num.clone();
+                        async move {
+                            sleep(Duration::from_millis(10)).await;
+                            num.fetch_add(1, Ordering::SeqCst);
+                            Ok::<_, ()>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    let conns = (0..10)
+        .map(|i| net::TcpStream::connect(if i % 2 == 0 { addr1 } else { addr2 }).unwrap())
+        .collect::<Vec<_>>();
+    wait_until(|| num.load(Ordering::SeqCst) == 10);
+    drop(conns);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_heartbeat_restart() {
+    use std::io::Read;
+
+    use actix_server::WorkerHeartbeat;
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Arc::new(std::sync::Mutex::new(release_rx));
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .worker_heartbeat(
+                    WorkerHeartbeat::new(Duration::from_millis(50))
+                        .timeout(Duration::from_millis(200))
+                        .restart(true),
+                )
+                .bind("test", addr, move || {
+                    let release_rx = release_rx.clone();
+                    fn_service(move |mut io: TcpStream| {
+                        let release_rx = release_rx.clone();
+                        async move {
+                            // first connection blocks the worker thread until released
+                            let mut buf = [0; 1];
+                            io.peek(&mut buf).await?;
+                            if buf[0] == b'b' {
+                                let _ = release_rx.lock().unwrap().recv();
+                            }
+                            io.write_all(b"1").await
+                        }
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.ready()).unwrap();
+
+    let mut blocked = net::TcpStream::connect(addr).unwrap();
+    std::io::Write::write_all(&mut blocked, b"b").unwrap();
+    wait_until(|| rt.block_on(srv.workers()).unwrap()[0].restarts() == 1);
+
+    // replacement worker serves new connections
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    std::io::Write::write_all(&mut conn, b"a").unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    conn.read_exact(&mut [0; 1]).unwrap();
+
+    let _ = release_tx.send(());
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_close_token_shutdown() {
+    use std::io::Read;
+
+    use actix_server::CloseToken;
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, || {
+                    // token of the worker
+                    assert!(!CloseToken::current().unwrap().is_closing());
+
+                    fn_service(|mut io: TcpStream| async move {
+                        let token = CloseToken::current().unwrap();
+                        io.write_all(b"1").await?;
+                        token.closing().await;
+                        io.write_all(b"2").await
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    assert!(CloseToken::current().is_none());
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut buf = [0; 1];
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"1");
+
+    // connection finishes well within the default shutdown timeout of 30 seconds
+    let rt = actix_rt::Runtime::new().unwrap();
+    let res = rt.block_on(async {
+        actix_rt::time::timeout(Duration::from_secs(5), srv.stop(true)).await
+    });
+    assert!(res.is_ok());
+
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"2");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_hooks() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let (start, create, stop) = (events.clone(), events.clone(), events.clone());
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_worker_start(move |idx| {
+                    let start = start.clone();
+                    async move { start.lock().unwrap().push(format!("start {}", idx)) }
+                })
+                .on_worker_stop(move |idx| {
+                    let stop = stop.clone();
+                    async move {
+                        sleep(Duration::from_millis(50)).await;
+                        stop.lock().unwrap().push(format!("stop {}", idx));
+                    }
+                })
+                .bind("test", addr, move || {
+                    create.lock().unwrap().push("create".to_owned());
+                    fn_service(|_: TcpStream| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.ready()).unwrap();
+    assert_eq!(*events.lock().unwrap(), ["start 0", "create"]);
+
+    rt.block_on(srv.stop(true));
+    assert_eq!(*events.lock().unwrap(), ["start 0", "create", "stop 0"]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_metrics() {
+    use actix_server::WorkerEvent;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let events2 = events.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .worker_metrics(move |idx, event: &WorkerEvent<'_>| {
+                    let event = match event {
+                        WorkerEvent::ConnectionAccepted { name, .. } => {
+                            format!("accepted {}", name)
+                        }
+                        WorkerEvent::ShutdownStarted { graceful, .. } => {
+                            format!("shutdown {}", graceful)
+                        }
+                        _ => return,
+                    };
+                    events2.lock().unwrap().push(format!("{} {}", idx, event));
+                })
+                .bind("test", addr, move || {
+                    fn_service(|_: TcpStream| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.ready()).unwrap();
+
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    wait_until(|| !events.lock().unwrap().is_empty());
+    assert_eq!(*events.lock().unwrap(), ["0 accepted test"]);
+
+    rt.block_on(srv.stop(true));
+    assert_eq!(
+        *events.lock().unwrap(),
+        ["0 accepted test", "0 shutdown true"]
+    );
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_stream() {
+    use std::io::{Read, Write};
+
+    use actix_server::{FromStream, MioStream};
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    struct Preface(TcpStream);
+
+    impl FromStream for Preface {
+        const HANDSHAKE: bool = true;
+
+        fn from_mio(sock: MioStream) -> io::Result<Self> {
+            TcpStream::from_mio(sock).map(Preface)
+        }
+
+        fn handshake(self) -> LocalBoxFuture<'static, io::Result<Self>> {
+            Box::pin(async move {
+                let mut io = self.0;
+                let mut buf = [0; 4];
+                io.read_exact(&mut buf).await?;
+
+                if &buf != b"HELO" {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "no preface"));
+                }
+
+                Ok(Preface(io))
+            })
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_stream("test", addr, move || {
+                    fn_service(|io: Preface| async move {
+                        let mut io = io.0;
+                        io.write_all(b"welcome").await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"HELO").unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "welcome");
+
+    // connection without preface is closed before reaching the service
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"ping").unwrap();
+    let mut res = Vec::new();
+    let _ = conn.read_to_end(&mut res);
+    assert!(res.is_empty());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_listen_custom() {
+    use std::io::Read;
+
+    use actix_server::{FromStream, Listener, ListenerAddr, MioStream};
+    use mio::{event::Source, Interest, Registry, Token};
+    use tokio::io::AsyncWriteExt;
+
+    struct TunnelListener(mio::net::TcpListener);
+
+    struct TunnelConn(mio::net::TcpStream);
+
+    impl Source for TunnelListener {
+        fn register(&mut self, reg: &Registry, token: Token, int: Interest) -> io::Result<()> {
+            self.0.register(reg, token, int)
+        }
+
+        fn reregister(
+            &mut self,
+            reg: &Registry,
+            token: Token,
+            int: Interest,
+        ) -> io::Result<()> {
+            self.0.reregister(reg, token, int)
+        }
+
+        fn deregister(&mut self, reg: &Registry) -> io::Result<()> {
+            self.0.deregister(reg)
+        }
+    }
+
+    impl Listener for TunnelListener {
+        type Stream = TunnelConn;
+
+        fn accept(&mut self) -> io::Result<TunnelConn> {
+            self.0.accept().map(|(stream, _)| TunnelConn(stream))
+        }
+
+        fn local_addr(&self) -> ListenerAddr {
+            ListenerAddr::Tcp(self.0.local_addr().unwrap())
+        }
+    }
+
+    struct Tunnel(TcpStream);
+
+    impl FromStream for Tunnel {
+        fn from_mio(sock: MioStream) -> io::Result<Self> {
+            let conn = sock.downcast::<TunnelConn>().ok().unwrap();
+            TcpStream::from_mio(MioStream::Tcp(conn.0)).map(Tunnel)
+        }
+    }
+
+    let addr = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let lst = mio::net::TcpListener::bind(addr).unwrap();
+            let lst2 = mio::net::TcpListener::bind(addr2).unwrap();
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .listen_custom("test", TunnelListener(lst), || {
+                    fn_service(|io: Tunnel| async move {
+                        let mut io = io.0;
+                        let addr = ListenerAddr::current().unwrap();
+                        io.write_all(addr.to_string().as_bytes()).await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                // stream type not accepted by the listener
+                .listen_custom("mismatch", TunnelListener(lst2), || {
+                    fn_service(|_: TcpStream| ok::<_, ()>(()))
+                })
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // rejected without panicking the worker
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    let mut res = Vec::new();
+    conn.read_to_end(&mut res).unwrap();
+    assert!(res.is_empty());
+
+    for _ in 0..2 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let mut res = String::new();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, addr.to_string());
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_retry() {
+    let addr = unused_addr();
+    let lst = net::TcpListener::bind(addr).unwrap();
+
+    let err = Server::build().bind("test", addr, || fn_service(|_: TcpStream| ok::<_, ()>(())));
+    assert_eq!(err.err().unwrap().kind(), io::ErrorKind::AddrInUse);
+
+    let h = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        drop(lst);
+    });
+
+    let res = Server::build()
+        .bind_retry(50, Duration::from_millis(50))
+        .bind("test", addr, || fn_service(|_: TcpStream| ok::<_, ()>(())));
+    assert!(res.is_ok());
+
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_total_connections() {
+    use std::io::Read;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .max_total_connections(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |mut io: TcpStream| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        async move {
+                            io.write_all(b"1").await?;
+                            let mut buf = [0; 16];
+                            while io.read(&mut buf).await? > 0 {}
+                            Ok::<_, io::Error>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    let mut conn1 = net::TcpStream::connect(addr).unwrap();
+    conn1.read_exact(&mut [0; 1]).unwrap();
+
+    // second connection waits in the backlog while the first one is open
+    let mut conn2 = net::TcpStream::connect(addr).unwrap();
+    conn2
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    assert!(conn2.read_exact(&mut [0; 1]).is_err());
+    assert_eq!(num.load(Ordering::SeqCst), 1);
+
+    drop(conn1);
+    conn2
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    conn2.read_exact(&mut [0; 1]).unwrap();
+    assert_eq!(num.load(Ordering::SeqCst), 2);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_pressure() {
+    use std::io::Read;
+    use std::sync::atomic::AtomicBool;
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+    let pressure = Arc::new(AtomicBool::new(true));
+    let pressure2 = pressure.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .accept_pressure(Duration::from_millis(50), move || {
+                    let high = pressure2.load(Ordering::SeqCst);
+                    async move { high }
+                })
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |mut io: TcpStream| {
+                        num.fetch_add(1, Ordering::SeqCst);
+                        async move { io.write_all(b"1").await }
                     })
                 })
                 .unwrap()
-                .bind("addr2", addr2, move || {
-                    let num2 = num2.clone();
-                    fn_factory(move || {
-                        let num2 = num2.clone();
-                        async move { Ok::<_, ()>(TestService(num2)) }
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    // connection waits in the backlog while under pressure
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    assert!(conn.read_exact(&mut [0; 1]).is_err());
+    assert_eq!(num.load(Ordering::SeqCst), 0);
+
+    pressure.store(false, Ordering::SeqCst);
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    conn.read_exact(&mut [0; 1]).unwrap();
+    assert_eq!(num.load(Ordering::SeqCst), 1);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_burst() {
+    use std::io::Read;
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .accept_burst(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|mut io: TcpStream| async move {
+                        io.write_all(b"1").await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // connections pending at once are all accepted, one burst at a time
+    let mut conns = (0..8)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    for conn in &mut conns {
+        conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        conn.read_exact(&mut [0; 1]).unwrap();
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_mptcp() {
+    use std::io::Read;
+
+    use tokio::io::AsyncWriteExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .mptcp(true)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|mut io: TcpStream| async move {
+                        io.write_all(b"1").await?;
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // plain TCP clients are served by MPTCP listeners, as are all clients where the kernel does
+    // not support MPTCP
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    conn.read_exact(&mut [0; 1]).unwrap();
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_stop_with_timeout() {
+    use std::time::Instant;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .shutdown_timeout(60)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|_: TcpStream| async {
+                        sleep(Duration::from_secs(60)).await;
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    wait_until(|| rt.block_on(srv.workers()).unwrap()[0].connections() == 1);
+
+    // connection outlives the given timeout, but not the configured one
+    let start = Instant::now();
+    rt.block_on(srv.stop_with_timeout(true, Duration::from_millis(500)));
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_run_until() {
+    use tokio::sync::oneshot;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let res = sys.block_on(async {
+            let srv = Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move || fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run();
+
+            let _ = tx.send(srv.clone());
+            srv.run_until(stop_rx).await
+        });
+        assert!(res.is_ok());
+    });
+    let srv = rx.recv().unwrap();
+    wait_ready(&srv);
+
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    let _ = stop_tx.send(());
+    h.join().unwrap();
+
+    // listener is closed by the accept thread
+    wait_until(|| net::TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn test_workers_status() {
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let mut buf = [0; 16];
+                        while io.read(&mut buf).await? > 0 {}
+                        Ok::<_, io::Error>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+    rt.block_on(srv.ready()).unwrap();
+
+    let conn = net::TcpStream::connect(addr).unwrap();
+    wait_until(|| {
+        let workers = rt.block_on(srv.workers()).unwrap();
+        workers.iter().map(|w| w.connections()).sum::<usize>() == 1
+    });
+
+    let workers = rt.block_on(srv.workers()).unwrap();
+    assert_eq!(workers.iter().map(|w| w.idx()).collect::<Vec<_>>(), [0, 1]);
+    assert!(workers
+        .iter()
+        .all(|w| w.is_available() && w.restarts() == 0));
+    assert_eq!(workers.iter().map(|w| w.connections()).sum::<usize>(), 1);
+
+    drop(conn);
+    rt.block_on(srv.restart_worker(1, false)).unwrap();
+
+    let workers = rt.block_on(srv.workers()).unwrap();
+    assert_eq!(workers.len(), 2);
+    assert_eq!(workers[0].restarts(), 0);
+    assert_eq!(workers[1].restarts(), 1);
+
+    sys.stop();
+    let _ = h.join();
+
+    assert!(rt.block_on(srv.workers()).is_err());
+}
+
+#[test]
+fn test_labels() {
+    use actix_server::{ListenerAddr, ServiceLabels, WorkerEvent};
+    use tokio::io::AsyncReadExt;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let events2 = events.clone();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            let factory = || {
+                fn_service(|mut io: TcpStream| async move {
+                    let mut buf = [0; 16];
+                    while io.read(&mut buf).await? > 0 {}
+                    Ok::<_, io::Error>(())
+                })
+            };
+
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .worker_metrics(move |_, event: &WorkerEvent<'_>| {
+                    if let WorkerEvent::ConnectionAccepted { name, labels } = event {
+                        events2.lock().unwrap().push(format!("{} {}", name, labels));
+                    }
+                })
+                .bind("acme", addr1, factory)
+                .unwrap()
+                .labels(
+                    "acme",
+                    ServiceLabels::new()
+                        .label("tenant", "acme")
+                        .label("env", "prod"),
+                )
+                .bind("other", addr2, factory)
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::System::new();
+    rt.block_on(srv.ready()).unwrap();
+
+    let _conn = net::TcpStream::connect(addr1).unwrap();
+    wait_until(|| !events.lock().unwrap().is_empty());
+
+    assert_eq!(*events.lock().unwrap(), ["acme env=prod,tenant=acme"]);
+
+    let workers = rt.block_on(srv.workers()).unwrap();
+    let services = workers[0].services();
+    assert_eq!(services.len(), 2);
+
+    assert_eq!(services[0].name(), "acme");
+    assert_eq!(*services[0].addr(), ListenerAddr::Tcp(addr1));
+    assert_eq!(services[0].labels().get("tenant"), Some("acme"));
+    assert_eq!(services[0].connections(), 1);
+
+    assert_eq!(services[1].name(), "other");
+    assert!(services[1].labels().is_empty());
+    assert_eq!(services[1].connections(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_autoscale() {
+    use actix_server::WorkerAutoscale;
+    use tokio::io::AsyncReadExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let (start, stop) = (events.clone(), events.clone());
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .maxconn(2)
+                .worker_autoscale(
+                    WorkerAutoscale::new(2)
+                        .interval(Duration::from_millis(50))
+                        .sustain(Duration::from_millis(200))
+                        .cool_down(Duration::from_millis(200)),
+                )
+                .disable_signals()
+                .on_worker_start(move |idx| {
+                    let start = start.clone();
+                    async move { start.lock().unwrap().push(format!("start {}", idx)) }
+                })
+                .on_worker_stop(move |idx| {
+                    let stop = stop.clone();
+                    async move { stop.lock().unwrap().push(format!("stop {}", idx)) }
+                })
+                .bind("test", addr, move || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let mut buf = [0; 16];
+                        while io.read(&mut buf).await? > 0 {}
+                        Ok::<_, io::Error>(())
                     })
                 })
                 .unwrap()
-                .workers(1)
-                .run();
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let rt = actix_rt::Runtime::new().unwrap();
+    rt.block_on(srv.ready()).unwrap();
+    assert_eq!(*events.lock().unwrap(), ["start 0"]);
+
+    // both connections of the only worker are in use
+    let conns = (0..2)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    wait_until(|| events.lock().unwrap().len() == 2);
+    assert_eq!(*events.lock().unwrap(), ["start 0", "start 1"]);
+
+    // added worker is retired once connections are closed
+    drop(conns);
+    wait_until(|| events.lock().unwrap().len() == 3);
+    assert_eq!(*events.lock().unwrap(), ["start 0", "start 1", "stop 1"]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_restart_worker_redispatch() {
+    use std::{
+        io::Read,
+        task::{Context, Poll},
+    };
+
+    use actix_service::{fn_factory, Service};
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::AsyncWriteExt;
+
+    // service that is never ready on the first worker
+    struct TestService(bool);
+
+    impl Service<TcpStream> for TestService {
+        type Response = ();
+        type Error = io::Error;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, mut io: TcpStream) -> Self::Future {
+            Box::pin(async move { io.write_all(b"1").await })
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let created = Arc::new(AtomicUsize::new(0));
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let ready = created.fetch_add(1, Ordering::SeqCst) > 0;
+                    fn_factory(move || async move { Ok::<_, ()>(TestService(ready)) })
+                })
+                .unwrap()
+                .run()
+        }));
 
-            let _ = tx.send((server.clone(), actix_rt::System::current()));
-            server.await
-        })
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
     });
+    let (srv, sys) = rx.recv().unwrap();
 
-    let (server, sys) = rx.recv().unwrap();
+    let rt = actix_rt::Runtime::new().unwrap();
 
-    for _ in 0..5 {
-        TcpStream::connect(addr1)
-            .await
-            .unwrap()
-            .shutdown()
-            .await
-            .unwrap();
-        TcpStream::connect(addr2)
-            .await
-            .unwrap()
-            .shutdown()
-            .await
-            .unwrap();
-    }
+    let mut conns = (0..3)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    wait_until(|| rt.block_on(srv.workers()).unwrap()[0].connections() == 3);
 
-    sleep(Duration::from_secs(3)).await;
+    // connections queued for the old worker are served by its replacement rather than waiting
+    // for the shutdown timeout
+    rt.block_on(srv.restart_worker(0, true)).unwrap();
 
-    assert!(num_clone.load(Ordering::SeqCst) > 5);
-    assert!(num2_clone.load(Ordering::SeqCst) > 5);
+    for conn in &mut conns {
+        conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        conn.read_exact(&mut [0; 1]).unwrap();
+    }
 
     sys.stop();
-    let _ = server.stop(false);
-    let _ = h.join().unwrap();
+    let _ = h.join();
 }
 
-#[ignore]
-#[actix_rt::test]
-async fn worker_restart() {
-    use actix_service::{Service, ServiceFactory};
+#[test]
+fn test_bind_multi_tls() {
+    use std::io::{Read, Write};
+
+    use actix_server::Mux;
     use futures_core::future::LocalBoxFuture;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-    struct TestServiceFactory(Arc<AtomicUsize>);
+    // ClientHello record with server name extension
+    fn client_hello(name: &str) -> Vec<u8> {
+        fn vec16(data: &[u8]) -> Vec<u8> {
+            let mut buf = (data.len() as u16).to_be_bytes().to_vec();
+            buf.extend_from_slice(data);
+            buf
+        }
 
-    impl ServiceFactory<TcpStream> for TestServiceFactory {
-        type Response = ();
-        type Error = ();
-        type Config = ();
-        type Service = TestService;
-        type InitError = ();
-        type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+        let mut entry = vec![0];
+        entry.extend(vec16(name.as_bytes()));
+        let mut exts = vec![0, 0];
+        exts.extend(vec16(&vec16(&entry)));
 
-        fn new_service(&self, _: Self::Config) -> Self::Future {
-            let counter = self.0.fetch_add(1, Ordering::Relaxed);
+        let mut body = vec![3, 3];
+        body.extend(&[0; 32]);
+        body.push(0);
+        body.extend(vec16(&[0x13, 0x01]));
+        body.extend(&[1, 0]);
+        body.extend(vec16(&exts));
 
-            Box::pin(async move { Ok(TestService(counter)) })
-        }
+        let mut msg = vec![1, 0];
+        msg.extend(vec16(&body));
+
+        let mut record = vec![0x16, 3, 1];
+        record.extend(vec16(&msg));
+        record
     }
 
-    struct TestService(usize);
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
 
-    impl Service<TcpStream> for TestService {
-        type Response = ();
-        type Error = ();
-        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            // read whole ClientHello record and reply with the service name
+            fn reply(
+                name: &'static str,
+            ) -> impl Fn(TcpStream) -> LocalBoxFuture<'static, io::Result<()>> + Clone
+            {
+                move |mut io: TcpStream| {
+                    Box::pin(async move {
+                        let mut header = [0; 5];
+                        io.read_exact(&mut header).await?;
+                        let len = u16::from_be_bytes([header[3], header[4]]);
+                        io.read_exact(&mut vec![0; len as usize]).await?;
+                        io.write_all(name.as_bytes()).await
+                    })
+                }
+            }
 
-        actix_service::always_ready!();
+            let mux = Mux::new(1)
+                .tls_service(
+                    |hello| hello.server_name() == Some("a.example"),
+                    || fn_service(reply("a")),
+                )
+                .tls_service(|_| true, || fn_service(reply("other")));
 
-        fn call(&self, stream: TcpStream) -> Self::Future {
-            let counter = self.0;
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_multi("test", addr, mux)
+                .unwrap()
+                .run()
+        }));
 
-            let mut stream = stream.into_std().unwrap();
-            use std::io::Write;
-            let str = counter.to_string();
-            let buf = str.as_bytes();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
 
-            let mut written = 0;
+    for (name, res) in [("a.example", "a"), ("b.example", "other")].iter() {
+        let hello = client_hello(name);
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_nodelay(true).unwrap();
 
-            while written < buf.len() {
-                if let Ok(n) = stream.write(&buf[written..]) {
-                    written += n;
-                }
-            }
-            stream.flush().unwrap();
-            stream.shutdown(net::Shutdown::Write).unwrap();
+        // ClientHello arriving in parts is matched once complete
+        conn.write_all(&hello[..20]).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        conn.write_all(&hello[20..]).unwrap();
 
-            // force worker 2 to restart service once.
-            if counter == 2 {
-                panic!("panic on purpose")
-            } else {
-                Box::pin(async { Ok(()) })
-            }
+        let mut buf = String::new();
+        conn.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, *res);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_socket_options() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let addr = unused_addr();
+
+    let res = Server::build()
+        .socket_options(|socket| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            // not bound yet
+            assert_eq!(socket.local_addr()?.as_socket().map(|a| a.port()), Some(0));
+            socket.set_recv_buffer_size(1 << 16)
+        })
+        .bind("test", addr, || fn_service(|_: TcpStream| ok::<_, ()>(())));
+    assert!(res.is_ok());
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+    let err = Server::build()
+        .socket_options(|_| Err(io::Error::new(io::ErrorKind::PermissionDenied, "hook")))
+        .bind("test", unused_addr(), || {
+            fn_service(|_: TcpStream| ok::<_, ()>(()))
+        });
+    let err = err.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(err.to_string(), "hook");
+}
+
+#[test]
+#[cfg(feature = "tracing")]
+fn test_connection_tracing() {
+    use std::fmt;
+    use std::io::Read;
+    use std::sync::Mutex;
+
+    use tokio::io::AsyncWriteExt;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Records spans and events of the server as lines of their fields.
+    #[derive(Default)]
+    struct Recorder {
+        next: AtomicUsize,
+        lines: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl Recorder {
+        fn push(&self, fields: Fields) {
+            self.lines.lock().unwrap().push(fields.0);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "actix_server"
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields(span.metadata().name().to_owned());
+            span.record(&mut fields);
+            self.push(fields);
+            Id::from_u64(self.next.fetch_add(1, Ordering::SeqCst) as u64 + 1)
+        }
+
+        fn record(&self, _: &Id, values: &Record<'_>) {
+            let mut fields = Fields("record".to_owned());
+            values.record(&mut fields);
+            self.push(fields);
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields("event".to_owned());
+            event.record(&mut fields);
+            self.push(fields);
         }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
     }
 
+    let recorder = Recorder::default();
+    let lines = recorder.lines.clone();
+    tracing::subscriber::set_global_default(recorder).unwrap();
+
     let addr = unused_addr();
     let (tx, rx) = mpsc::channel();
 
-    let counter = Arc::new(AtomicUsize::new(1));
     let h = thread::spawn(move || {
-        let counter = counter.clone();
-        actix_rt::System::new().block_on(async {
-            let server = Server::build()
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
                 .disable_signals()
-                .bind("addr", addr, move || TestServiceFactory(counter.clone()))
+                .bind("test", addr, move || {
+                    fn_service(|mut io: TcpStream| async move { io.write_all(b"1").await })
+                })
                 .unwrap()
-                .workers(2)
-                .run();
+                .run()
+        }));
 
-            let _ = tx.send((server.clone(), actix_rt::System::current()));
-            server.await
-        })
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
     });
+    let (_, sys) = rx.recv().unwrap();
 
-    let (server, sys) = rx.recv().unwrap();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    conn.read_exact(&mut [0; 1]).unwrap();
 
-    sleep(Duration::from_secs(3)).await;
+    let closed = "event message=connection closed reason=Completed";
+    wait_until(|| {
+        lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.starts_with(closed))
+    });
 
-    let mut buf = [0; 8];
+    let lines = lines.lock().unwrap().clone();
+    let has = |prefix: &str| lines.iter().any(|line| line.starts_with(prefix));
+    // other tests may run connections concurrently, so ids are not known
+    assert!(has("connection id="), "{:?}", lines);
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.ends_with(" listener=\"test\"")),
+        "{:?}",
+        lines
+    );
+    assert!(has("event message=connection accepted"), "{:?}", lines);
+    assert!(has("record worker=0"), "{:?}", lines);
+    assert!(
+        has("event message=connection dispatched queue_time_us="),
+        "{:?}",
+        lines
+    );
+    assert!(has(closed), "{:?}", lines);
 
-    // worker 1 would not restart and return it's id consistently.
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("1", id);
-    stream.shutdown().await.unwrap();
+    sys.stop();
+    let _ = h.join();
+}
 
-    // worker 2 dead after return response.
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("2", id);
-    stream.shutdown().await.unwrap();
+#[test]
+fn test_unavailable_timeout() {
+    use std::{
+        io::Read,
+        task::{Context, Poll},
+        time::Instant,
+    };
 
-    // request to worker 1
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("1", id);
-    stream.shutdown().await.unwrap();
+    use actix_service::{fn_factory, Service};
+    use futures_core::future::LocalBoxFuture;
 
-    // TODO: Remove sleep if it can pass CI.
-    sleep(Duration::from_secs(3)).await;
+    // service that is never ready
+    struct TestService;
 
-    // worker 2 restarting and work goes to worker 1.
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("1", id);
-    stream.shutdown().await.unwrap();
+    impl Service<TcpStream> for TestService {
+        type Response = ();
+        type Error = io::Error;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    // TODO: Remove sleep if it can pass CI.
-    sleep(Duration::from_secs(3)).await;
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
 
-    // worker 2 restarted but worker 1 was still the next to accept connection.
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("1", id);
-    stream.shutdown().await.unwrap();
+        fn call(&self, _: TcpStream) -> Self::Future {
+            unreachable!()
+        }
+    }
 
-    // TODO: Remove sleep if it can pass CI.
-    sleep(Duration::from_secs(3)).await;
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
 
-    // worker 2 accept connection again but it's id is 3.
-    let mut stream = TcpStream::connect(addr).await.unwrap();
-    let n = stream.read(&mut buf).await.unwrap();
-    let id = String::from_utf8_lossy(&buf[0..n]);
-    assert_eq!("3", id);
-    stream.shutdown().await.unwrap();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new();
+        let srv = sys.block_on(lazy(|_| {
+            Server::build()
+                .workers(1)
+                .unavailable_timeout(Duration::from_millis(300))
+                .disable_signals()
+                .bind("test", addr, || {
+                    fn_factory(|| async { Ok::<_, ()>(TestService) })
+                })
+                .unwrap()
+                .run()
+        }));
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    // connections are closed once they waited for the timeout
+    let mut conns = (0..2)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    for conn in &mut conns {
+        conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(conn.read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    // worker was not ready for longer than the timeout, but the connection waits all of it
+    let start = Instant::now();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(conn.read(&mut [0; 1]).unwrap(), 0);
+    assert!(start.elapsed() >= Duration::from_millis(300));
+
+    let rt = actix_rt::System::new();
+    let stats = rt.block_on(srv.close_stats()).unwrap();
+    assert_eq!(stats.get(CloseReason::Rejected), 3);
 
     sys.stop();
-    let _ = server.stop(false);
-    let _ = h.join().unwrap();
+    let _ = h.join();
 }
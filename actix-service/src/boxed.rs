@@ -1,15 +1,25 @@
 //! Trait object forms of services and service factories.
 
 use alloc::{boxed::Box, rc::Rc};
-use core::{future::Future, pin::Pin};
+use core::{
+    any::{type_name, Any},
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+};
 
+use futures_core::Stream;
 use paste::paste;
 
-use crate::{Service, ServiceFactory};
+use crate::{map::Map, Service, ServiceFactory};
 
 /// A boxed future with no send bound or lifetime parameters.
 pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
 
+/// A boxed stream with no send bound or lifetime parameters.
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = T>>>;
+
 macro_rules! service_object {
     ($name: ident, $type: tt, $fn_name: ident) => {
         paste! {
@@ -34,6 +44,24 @@ macro_rules! service_object {
 service_object!(BoxService, Box, service);
 service_object!(RcService, Rc, rc_service);
 
+/// Type alias for service trait object responding with a [`BoxStream`] of `Item`s.
+pub type BoxStreamService<Req, Item, Err> = BoxService<Req, BoxStream<Item>, Err>;
+
+/// Wraps a service responding with a stream as a trait object using [`BoxStreamService`].
+pub fn stream_service<S, Req>(
+    service: S,
+) -> BoxStreamService<Req, <S::Response as Stream>::Item, S::Error>
+where
+    S: Service<Req> + 'static,
+    S::Response: Stream + 'static,
+    S::Future: 'static,
+    Req: 'static,
+{
+    let boxed: fn(S::Response) -> BoxStream<<S::Response as Stream>::Item> =
+        |stream| Box::pin(stream);
+    self::service(Map::new(service, boxed))
+}
+
 struct ServiceWrapper<S> {
     inner: S,
 }
@@ -137,3 +165,165 @@ where
         Box::pin(async { f.await.map(|s| Box::new(ServiceWrapper::new(s)) as _) })
     }
 }
+
+/// Type alias for a boxed service factory whose config type has been erased.
+///
+/// Config is passed as a `Box<dyn Any>` and downcast to the wrapped factory's config type when
+/// [`new_service`](ServiceFactory::new_service) is called. See [`any_config_factory`].
+pub type AnyConfigServiceFactory<Req, Res, Err, InitErr> =
+    BoxServiceFactory<Box<dyn Any>, Req, Res, Err, AnyConfigError<InitErr>>;
+
+/// Wraps a service factory as a trait object that accepts any config as `Box<dyn Any>`.
+///
+/// This allows factories with differing config types to be stored in one collection. Passing a
+/// config of the wrong type to `new_service` resolves to [`AnyConfigError::ConfigMismatch`].
+///
+/// # Examples
+/// ```
+/// use actix_service::{boxed, fn_factory_with_config, fn_service, ServiceFactory};
+///
+/// # async fn run() {
+/// let factories = vec![
+///     boxed::any_config_factory(fn_factory_with_config(|num: u32| async move {
+///         Ok::<_, ()>(fn_service(move |_: ()| async move { Ok::<_, ()>(num.to_string()) }))
+///     })),
+///     boxed::any_config_factory(fn_factory_with_config(|name: &'static str| async move {
+///         Ok::<_, ()>(fn_service(move |_: ()| async move { Ok::<_, ()>(name.to_owned()) }))
+///     })),
+/// ];
+///
+/// assert!(factories[0].new_service(Box::new(42u32)).await.is_ok());
+/// assert!(factories[1].new_service(Box::new(42u32)).await.is_err());
+/// # }
+/// ```
+pub fn any_config_factory<SF, Req>(
+    factory: SF,
+) -> AnyConfigServiceFactory<Req, SF::Response, SF::Error, SF::InitError>
+where
+    SF: ServiceFactory<Req> + 'static,
+    Req: 'static,
+    SF::Config: 'static,
+    SF::Response: 'static,
+    SF::Service: 'static,
+    SF::Future: 'static,
+    SF::Error: 'static,
+    SF::InitError: 'static,
+{
+    self::factory(AnyConfigWrapper(factory, PhantomData))
+}
+
+/// Error returned by service factories created with [`any_config_factory`].
+pub enum AnyConfigError<E> {
+    /// The provided config could not be downcast to the type expected by the factory.
+    ConfigMismatch {
+        /// Name of the config type the factory expected.
+        expected: &'static str,
+    },
+
+    /// The wrapped factory failed to build a service.
+    Init(E),
+}
+
+impl<E: fmt::Debug> fmt::Debug for AnyConfigError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyConfigError::ConfigMismatch { expected } => f
+                .debug_struct("ConfigMismatch")
+                .field("expected", expected)
+                .finish(),
+            AnyConfigError::Init(err) => f.debug_tuple("Init").field(err).finish(),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for AnyConfigError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyConfigError::ConfigMismatch { expected } => {
+                write!(
+                    f,
+                    "service factory config type mismatch; expected `{}`",
+                    expected
+                )
+            }
+            AnyConfigError::Init(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+struct AnyConfigWrapper<SF, Req>(SF, PhantomData<Req>);
+
+impl<SF, Req> ServiceFactory<Req> for AnyConfigWrapper<SF, Req>
+where
+    SF: ServiceFactory<Req>,
+    SF::Config: 'static,
+    SF::Future: 'static,
+    SF::InitError: 'static,
+{
+    type Response = SF::Response;
+    type Error = SF::Error;
+    type Config = Box<dyn Any>;
+    type Service = SF::Service;
+    type InitError = AnyConfigError<SF::InitError>;
+    type Future = BoxFuture<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, cfg: Box<dyn Any>) -> Self::Future {
+        match cfg.downcast::<SF::Config>() {
+            Ok(cfg) => {
+                let fut = self.0.new_service(*cfg);
+                Box::pin(async { fut.await.map_err(AnyConfigError::Init) })
+            }
+            Err(_) => {
+                let expected = type_name::<SF::Config>();
+                Box::pin(async move { Err(AnyConfigError::ConfigMismatch { expected }) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+
+    use super::*;
+    use crate::{fn_factory_with_config, fn_service, ok};
+
+    #[actix_rt::test]
+    async fn test_any_config_factory() {
+        let factories: Vec<AnyConfigServiceFactory<u32, u32, (), ()>> = alloc::vec![
+            any_config_factory(fn_factory_with_config(|add: u32| {
+                ok::<_, ()>(fn_service(move |req: u32| ok::<_, ()>(req + add)))
+            })),
+            any_config_factory(fn_factory_with_config(|mul: u64| {
+                ok::<_, ()>(fn_service(move |req: u32| ok::<_, ()>(req * mul as u32)))
+            })),
+        ];
+
+        let srv = factories[0].new_service(Box::new(1u32)).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(3));
+
+        let srv = factories[1].new_service(Box::new(3u64)).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(6));
+    }
+
+    #[actix_rt::test]
+    async fn test_any_config_mismatch() {
+        let factory = any_config_factory(fn_factory_with_config(|add: u32| {
+            ok::<_, &'static str>(fn_service(move |req: u32| ok::<_, ()>(req + add)))
+        }));
+
+        let err = match factory.new_service(Box::new("wrong")).await {
+            Err(err) => err,
+            Ok(_) => panic!("config of wrong type should not produce a service"),
+        };
+
+        assert!(matches!(
+            err,
+            AnyConfigError::ConfigMismatch { expected: "u32" }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "service factory config type mismatch; expected `u32`"
+        );
+    }
+}
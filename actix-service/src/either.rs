@@ -0,0 +1,258 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+use super::{Service, ServiceFactory};
+
+/// Service that dispatches to one of two services that share a response and error type.
+///
+/// This is useful for conditional pipelines (e.g. TLS vs plaintext handlers) where the choice of
+/// service is made once, at construction time, and boxing is undesirable.
+///
+/// This is created by [`EitherServiceFactory`] or can be constructed directly.
+#[derive(Debug, Clone)]
+pub enum EitherService<A, B> {
+    /// A service of type `A`.
+    Left(A),
+
+    /// A service of type `B`.
+    Right(B),
+}
+
+impl<A, B, Req> Service<Req> for EitherService<A, B>
+where
+    A: Service<Req>,
+    B: Service<Req, Response = A::Response, Error = A::Error>,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = EitherFuture<A, B, Req>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            EitherService::Left(svc) => svc.poll_ready(cx),
+            EitherService::Right(svc) => svc.poll_ready(cx),
+        }
+    }
+
+    fn call(&self, req: Req) -> Self::Future {
+        match self {
+            EitherService::Left(svc) => EitherFuture::Left { fut: svc.call(req) },
+            EitherService::Right(svc) => EitherFuture::Right { fut: svc.call(req) },
+        }
+    }
+}
+
+pin_project! {
+    #[project = EitherFutureProj]
+    pub enum EitherFuture<A, B, Req>
+    where
+        A: Service<Req>,
+        B: Service<Req>,
+    {
+        Left { #[pin] fut: A::Future },
+        Right { #[pin] fut: B::Future },
+    }
+}
+
+impl<A, B, Req> Future for EitherFuture<A, B, Req>
+where
+    A: Service<Req>,
+    B: Service<Req, Response = A::Response, Error = A::Error>,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherFutureProj::Left { fut } => fut.poll(cx),
+            EitherFutureProj::Right { fut } => fut.poll(cx),
+        }
+    }
+}
+
+/// Service factory that selects one of two factories based on the service config.
+///
+/// Both factories must share the same config, response, error and init error types. The selector
+/// is called with a reference to the config on each [`new_service`](ServiceFactory::new_service)
+/// call; returning `true` constructs the left service and `false` the right one.
+///
+/// # Examples
+/// ```
+/// use actix_service::{fn_service, EitherServiceFactory, Service, ServiceFactory};
+///
+/// # async fn run() {
+/// let factory = EitherServiceFactory::new(
+///     fn_service(|req: u32| async move { Ok::<_, ()>(req * 2) }),
+///     fn_service(|req: u32| async move { Ok::<_, ()>(req) }),
+///     |double: &bool| *double,
+/// );
+///
+/// let svc = factory.new_service(true).await.unwrap();
+/// assert_eq!(svc.call(21).await, Ok(42));
+///
+/// let svc = factory.new_service(false).await.unwrap();
+/// assert_eq!(svc.call(21).await, Ok(21));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EitherServiceFactory<A, B, F> {
+    left: A,
+    right: B,
+    select: F,
+}
+
+impl<A, B, F> EitherServiceFactory<A, B, F> {
+    /// Create new `EitherServiceFactory` from two factories and a config-based selector.
+    pub fn new(left: A, right: B, select: F) -> Self {
+        Self {
+            left,
+            right,
+            select,
+        }
+    }
+}
+
+impl<A, B, F, Req> ServiceFactory<Req> for EitherServiceFactory<A, B, F>
+where
+    A: ServiceFactory<Req>,
+    B: ServiceFactory<
+        Req,
+        Config = A::Config,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+    F: Fn(&A::Config) -> bool,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = A::Config;
+    type Service = EitherService<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = EitherServiceFactoryFuture<A, B, Req>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        if (self.select)(&cfg) {
+            EitherServiceFactoryFuture::Left {
+                fut: self.left.new_service(cfg),
+            }
+        } else {
+            EitherServiceFactoryFuture::Right {
+                fut: self.right.new_service(cfg),
+            }
+        }
+    }
+}
+
+pin_project! {
+    #[project = EitherServiceFactoryFutureProj]
+    pub enum EitherServiceFactoryFuture<A, B, Req>
+    where
+        A: ServiceFactory<Req>,
+        B: ServiceFactory<Req>,
+    {
+        Left { #[pin] fut: A::Future },
+        Right { #[pin] fut: B::Future },
+    }
+}
+
+impl<A, B, Req> Future for EitherServiceFactoryFuture<A, B, Req>
+where
+    A: ServiceFactory<Req>,
+    B: ServiceFactory<Req, InitError = A::InitError>,
+{
+    type Output = Result<EitherService<A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherServiceFactoryFutureProj::Left { fut } => {
+                fut.poll(cx).map(|res| res.map(EitherService::Left))
+            }
+            EitherServiceFactoryFutureProj::Right { fut } => {
+                fut.poll(cx).map(|res| res.map(EitherService::Right))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use alloc::rc::Rc;
+    use futures_util::future::lazy;
+
+    use super::*;
+    use crate::{fn_factory_with_config, fn_service, ok, Ready};
+
+    struct Srv(&'static str, Rc<Cell<usize>>);
+
+    impl Service<()> for Srv {
+        type Response = &'static str;
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.1.set(self.1.get() + 1);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            ok(self.0)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_service() {
+        let cnt = Rc::new(Cell::new(0));
+
+        let srv = EitherService::<Srv, Srv>::Left(Srv("left", cnt.clone()));
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        assert_eq!(cnt.get(), 1);
+        assert_eq!(srv.call(()).await, Ok("left"));
+
+        let srv = EitherService::<Srv, Srv>::Right(Srv("right", cnt.clone()));
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        assert_eq!(cnt.get(), 2);
+        assert_eq!(srv.call(()).await, Ok("right"));
+    }
+
+    #[actix_rt::test]
+    async fn test_factory() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+
+        let left = fn_factory_with_config(move |_: bool| ok::<_, ()>(Srv("left", cnt.clone())));
+        let right =
+            fn_factory_with_config(move |_: bool| ok::<_, ()>(Srv("right", cnt2.clone())));
+        let factory = EitherServiceFactory::new(left, right, |cfg: &bool| *cfg);
+
+        let srv = factory.new_service(true).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok("left"));
+
+        let srv = factory.new_service(false).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok("right"));
+    }
+
+    #[actix_rt::test]
+    async fn test_factory_fn_service() {
+        let factory = EitherServiceFactory::new(
+            fn_service(|req: u32| ok::<_, ()>(req + 1)),
+            fn_service(|req: u32| ok::<_, ()>(req - 1)),
+            |cfg: &u32| *cfg > 0,
+        );
+
+        let srv = factory.new_service(1).await.unwrap();
+        assert_eq!(srv.call(10).await, Ok(11));
+
+        let srv = factory.new_service(0).await.unwrap();
+        assert_eq!(srv.call(10).await, Ok(9));
+    }
+}
@@ -20,6 +20,7 @@ mod and_then;
 mod apply;
 mod apply_cfg;
 pub mod boxed;
+mod either;
 mod ext;
 mod fn_service;
 mod macros;
@@ -29,16 +30,22 @@ mod map_err;
 mod map_init_err;
 mod pipeline;
 mod ready;
+mod stream;
+pub mod test;
 mod then;
 mod transform;
 mod transform_err;
 
 pub use self::apply::{apply_fn, apply_fn_factory};
 pub use self::apply_cfg::{apply_cfg, apply_cfg_factory};
+pub use self::either::{EitherService, EitherServiceFactory};
 pub use self::ext::{ServiceExt, ServiceFactoryExt, TransformExt};
 pub use self::fn_service::{fn_factory, fn_factory_with_config, fn_service};
 pub use self::map_config::{map_config, unit_config};
-pub use self::transform::{apply, ApplyTransform, Transform};
+pub use self::stream::{
+    StreamService, StreamServiceExt, StreamServiceFactory, StreamServiceFactoryExt,
+};
+pub use self::transform::{apply, apply_send, ApplyTransform, Transform};
 
 #[allow(unused_imports)]
 use self::ready::{err, ok, ready, Ready};
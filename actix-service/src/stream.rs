@@ -0,0 +1,594 @@
+//! Combinators for services responding with a stream.
+
+use core::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+
+use crate::{Service, ServiceFactory};
+
+/// A [`Service`] whose response is a [`Stream`].
+///
+/// Implemented for every service with a stream response; it exists to name the item type and to
+/// provide the [`StreamServiceExt`] combinators.
+pub trait StreamService<Req>: Service<Req> {
+    /// Items yielded by the response stream.
+    type Item;
+}
+
+impl<S, Req> StreamService<Req> for S
+where
+    S: Service<Req>,
+    S::Response: Stream,
+{
+    type Item = <S::Response as Stream>::Item;
+}
+
+/// A [`ServiceFactory`] whose services respond with a [`Stream`].
+pub trait StreamServiceFactory<Req>: ServiceFactory<Req> {
+    /// Items yielded by the response stream.
+    type Item;
+}
+
+impl<SF, Req> StreamServiceFactory<Req> for SF
+where
+    SF: ServiceFactory<Req>,
+    SF::Response: Stream,
+{
+    type Item = <SF::Response as Stream>::Item;
+}
+
+/// An extension trait for [`StreamService`]s that provides item-level adapters.
+pub trait StreamServiceExt<Req>: StreamService<Req> {
+    /// Map each item of the response stream to a different type.
+    ///
+    /// This is the stream counterpart of [`ServiceExt::map`](crate::ServiceExt::map); the
+    /// mapping function is cloned for every call.
+    fn map_stream<F, R>(self, f: F) -> MapStream<Self, F, Req, R>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item) -> R + Clone,
+    {
+        MapStream::new(self, f)
+    }
+
+    /// Map each item of the response stream to a stream and flatten the results.
+    ///
+    /// Items of a returned stream are yielded before the next item of the response stream is
+    /// polled.
+    fn flat_map<F, U>(self, f: F) -> FlatMap<Self, F, Req, U>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item) -> U + Clone,
+        U: Stream,
+    {
+        FlatMap::new(self, f)
+    }
+}
+
+impl<S, Req> StreamServiceExt<Req> for S where S: StreamService<Req> {}
+
+/// An extension trait for [`StreamServiceFactory`]s that provides item-level adapters.
+pub trait StreamServiceFactoryExt<Req>: StreamServiceFactory<Req> {
+    /// Map each item of the response streams of created services to a different type.
+    fn map_stream<F, R>(self, f: F) -> MapStreamServiceFactory<Self, F, Req, R>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item) -> R + Clone,
+    {
+        MapStreamServiceFactory::new(self, f)
+    }
+
+    /// Map each item of the response streams of created services to a stream and flatten the
+    /// results.
+    fn flat_map<F, U>(self, f: F) -> FlatMapServiceFactory<Self, F, Req, U>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item) -> U + Clone,
+        U: Stream,
+    {
+        FlatMapServiceFactory::new(self, f)
+    }
+}
+
+impl<SF, Req> StreamServiceFactoryExt<Req> for SF where SF: StreamServiceFactory<Req> {}
+
+/// Service for the `map_stream` combinator, changing the item type of a response stream.
+///
+/// This is created by the `StreamServiceExt::map_stream` method.
+pub struct MapStream<S, F, Req, R> {
+    service: S,
+    f: F,
+    _t: PhantomData<(Req, R)>,
+}
+
+impl<S, F, Req, R> MapStream<S, F, Req, R> {
+    pub(crate) fn new(service: S, f: F) -> Self
+    where
+        S: StreamService<Req>,
+        F: FnMut(S::Item) -> R,
+    {
+        Self {
+            service,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, Req, R> Clone for MapStream<S, F, Req, R>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, Req, R> Service<Req> for MapStream<S, F, Req, R>
+where
+    S: Service<Req>,
+    S::Response: Stream,
+    F: FnMut(<S::Response as Stream>::Item) -> R + Clone,
+{
+    type Response = MapStreamItems<S::Response, F>;
+    type Error = S::Error;
+    type Future = MapStreamFuture<S::Future, F>;
+
+    crate::forward_ready!(service);
+
+    fn call(&self, req: Req) -> Self::Future {
+        MapStreamFuture {
+            fut: self.service.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// Future of the [`MapStream`] service.
+    pub struct MapStreamFuture<Fut, F> {
+        #[pin]
+        fut: Fut,
+        f: Option<F>,
+    }
+}
+
+impl<Fut, F, St, E> Future for MapStreamFuture<Fut, F>
+where
+    Fut: Future<Output = Result<St, E>>,
+{
+    type Output = Result<MapStreamItems<St, F>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let stream = ready!(this.fut.poll(cx))?;
+        let f = this
+            .f
+            .take()
+            .expect("MapStreamFuture polled after completion");
+        Poll::Ready(Ok(MapStreamItems { stream, f }))
+    }
+}
+
+pin_project! {
+    /// Response stream of the [`MapStream`] service.
+    pub struct MapStreamItems<St, F> {
+        #[pin]
+        stream: St,
+        f: F,
+    }
+}
+
+impl<St, F, R> Stream for MapStreamItems<St, F>
+where
+    St: Stream,
+    F: FnMut(St::Item) -> R,
+{
+    type Item = R;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.stream.poll_next(cx));
+        Poll::Ready(item.map(this.f))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Service for the `flat_map` combinator, flattening a stream of streams.
+///
+/// This is created by the `StreamServiceExt::flat_map` method.
+pub struct FlatMap<S, F, Req, U> {
+    service: S,
+    f: F,
+    _t: PhantomData<(Req, U)>,
+}
+
+impl<S, F, Req, U> FlatMap<S, F, Req, U> {
+    pub(crate) fn new(service: S, f: F) -> Self
+    where
+        S: StreamService<Req>,
+        F: FnMut(S::Item) -> U,
+        U: Stream,
+    {
+        Self {
+            service,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, Req, U> Clone for FlatMap<S, F, Req, U>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, Req, U> Service<Req> for FlatMap<S, F, Req, U>
+where
+    S: Service<Req>,
+    S::Response: Stream,
+    F: FnMut(<S::Response as Stream>::Item) -> U + Clone,
+    U: Stream,
+{
+    type Response = FlatMapItems<S::Response, F, U>;
+    type Error = S::Error;
+    type Future = FlatMapFuture<S::Future, F, U>;
+
+    crate::forward_ready!(service);
+
+    fn call(&self, req: Req) -> Self::Future {
+        FlatMapFuture {
+            fut: self.service.call(req),
+            f: Some(self.f.clone()),
+            _t: PhantomData,
+        }
+    }
+}
+
+pin_project! {
+    /// Future of the [`FlatMap`] service.
+    pub struct FlatMapFuture<Fut, F, U> {
+        #[pin]
+        fut: Fut,
+        f: Option<F>,
+        _t: PhantomData<U>,
+    }
+}
+
+impl<Fut, F, U, St, E> Future for FlatMapFuture<Fut, F, U>
+where
+    Fut: Future<Output = Result<St, E>>,
+{
+    type Output = Result<FlatMapItems<St, F, U>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let stream = ready!(this.fut.poll(cx))?;
+        let f = this
+            .f
+            .take()
+            .expect("FlatMapFuture polled after completion");
+        Poll::Ready(Ok(FlatMapItems {
+            stream,
+            f,
+            inner: None,
+        }))
+    }
+}
+
+pin_project! {
+    /// Response stream of the [`FlatMap`] service.
+    pub struct FlatMapItems<St, F, U> {
+        #[pin]
+        stream: St,
+        f: F,
+        #[pin]
+        inner: Option<U>,
+    }
+}
+
+impl<St, F, U> Stream for FlatMapItems<St, F, U>
+where
+    St: Stream,
+    F: FnMut(St::Item) -> U,
+    U: Stream,
+{
+    type Item = U::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(inner) = this.inner.as_mut().as_pin_mut() {
+                match ready!(inner.poll_next(cx)) {
+                    Some(item) => return Poll::Ready(Some(item)),
+                    None => this.inner.set(None),
+                }
+            }
+
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(item) => this.inner.set(Some((this.f)(item))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Service factory for the `map_stream` combinator.
+///
+/// This is created by the `StreamServiceFactoryExt::map_stream` method.
+pub struct MapStreamServiceFactory<SF, F, Req, R> {
+    factory: SF,
+    f: F,
+    _t: PhantomData<(Req, R)>,
+}
+
+impl<SF, F, Req, R> MapStreamServiceFactory<SF, F, Req, R> {
+    pub(crate) fn new(factory: SF, f: F) -> Self
+    where
+        SF: StreamServiceFactory<Req>,
+        F: FnMut(SF::Item) -> R,
+    {
+        Self {
+            factory,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<SF, F, Req, R> Clone for MapStreamServiceFactory<SF, F, Req, R>
+where
+    SF: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<SF, F, Req, R> ServiceFactory<Req> for MapStreamServiceFactory<SF, F, Req, R>
+where
+    SF: ServiceFactory<Req>,
+    SF::Response: Stream,
+    F: FnMut(<SF::Response as Stream>::Item) -> R + Clone,
+{
+    type Response = MapStreamItems<SF::Response, F>;
+    type Error = SF::Error;
+
+    type Config = SF::Config;
+    type Service = MapStream<SF::Service, F, Req, R>;
+    type InitError = SF::InitError;
+    type Future = MapStreamServiceFuture<SF, F, Req, R>;
+
+    fn new_service(&self, cfg: SF::Config) -> Self::Future {
+        MapStreamServiceFuture {
+            fut: self.factory.new_service(cfg),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// Future of the [`MapStreamServiceFactory`].
+    pub struct MapStreamServiceFuture<SF, F, Req, R>
+    where
+        SF: ServiceFactory<Req>,
+        SF::Response: Stream,
+        F: FnMut(<SF::Response as Stream>::Item) -> R,
+    {
+        #[pin]
+        fut: SF::Future,
+        f: Option<F>,
+    }
+}
+
+impl<SF, F, Req, R> Future for MapStreamServiceFuture<SF, F, Req, R>
+where
+    SF: ServiceFactory<Req>,
+    SF::Response: Stream,
+    F: FnMut(<SF::Response as Stream>::Item) -> R,
+{
+    type Output = Result<MapStream<SF::Service, F, Req, R>, SF::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let service = ready!(this.fut.poll(cx))?;
+        let f = this
+            .f
+            .take()
+            .expect("MapStreamServiceFuture polled after completion");
+        Poll::Ready(Ok(MapStream::new(service, f)))
+    }
+}
+
+/// Service factory for the `flat_map` combinator.
+///
+/// This is created by the `StreamServiceFactoryExt::flat_map` method.
+pub struct FlatMapServiceFactory<SF, F, Req, U> {
+    factory: SF,
+    f: F,
+    _t: PhantomData<(Req, U)>,
+}
+
+impl<SF, F, Req, U> FlatMapServiceFactory<SF, F, Req, U> {
+    pub(crate) fn new(factory: SF, f: F) -> Self
+    where
+        SF: StreamServiceFactory<Req>,
+        F: FnMut(SF::Item) -> U,
+        U: Stream,
+    {
+        Self {
+            factory,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<SF, F, Req, U> Clone for FlatMapServiceFactory<SF, F, Req, U>
+where
+    SF: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<SF, F, Req, U> ServiceFactory<Req> for FlatMapServiceFactory<SF, F, Req, U>
+where
+    SF: ServiceFactory<Req>,
+    SF::Response: Stream,
+    F: FnMut(<SF::Response as Stream>::Item) -> U + Clone,
+    U: Stream,
+{
+    type Response = FlatMapItems<SF::Response, F, U>;
+    type Error = SF::Error;
+
+    type Config = SF::Config;
+    type Service = FlatMap<SF::Service, F, Req, U>;
+    type InitError = SF::InitError;
+    type Future = FlatMapServiceFuture<SF, F, Req, U>;
+
+    fn new_service(&self, cfg: SF::Config) -> Self::Future {
+        FlatMapServiceFuture {
+            fut: self.factory.new_service(cfg),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// Future of the [`FlatMapServiceFactory`].
+    pub struct FlatMapServiceFuture<SF, F, Req, U>
+    where
+        SF: ServiceFactory<Req>,
+        SF::Response: Stream,
+        F: FnMut(<SF::Response as Stream>::Item) -> U,
+    {
+        #[pin]
+        fut: SF::Future,
+        f: Option<F>,
+    }
+}
+
+impl<SF, F, Req, U> Future for FlatMapServiceFuture<SF, F, Req, U>
+where
+    SF: ServiceFactory<Req>,
+    SF::Response: Stream,
+    F: FnMut(<SF::Response as Stream>::Item) -> U,
+    U: Stream,
+{
+    type Output = Result<FlatMap<SF::Service, F, Req, U>, SF::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let service = ready!(this.fut.poll(cx))?;
+        let f = this
+            .f
+            .take()
+            .expect("FlatMapServiceFuture polled after completion");
+        Poll::Ready(Ok(FlatMap::new(service, f)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use futures_util::{
+        future::lazy,
+        stream::{self, Iter, StreamExt as _},
+    };
+
+    use super::*;
+    use crate::{fn_factory, fn_service, ok, Ready};
+
+    struct Numbers;
+
+    impl Service<usize> for Numbers {
+        type Response = Iter<core::ops::Range<usize>>;
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        crate::always_ready!();
+
+        fn call(&self, n: usize) -> Self::Future {
+            ok(stream::iter(0..n))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_map_stream() {
+        let srv = Numbers.map_stream(|n| n * 10);
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+
+        let items = srv.call(3).await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(items, vec![0, 10, 20]);
+    }
+
+    #[actix_rt::test]
+    async fn test_flat_map() {
+        let srv = Numbers.flat_map(|n| stream::iter(vec![n; n]));
+
+        let items = srv.call(4).await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(items, vec![1, 2, 2, 3, 3, 3]);
+    }
+
+    #[actix_rt::test]
+    async fn test_factory() {
+        let factory = fn_factory(|| ok::<_, ()>(Numbers))
+            .map_stream(|n| n + 1)
+            .flat_map(|n| stream::iter(0..n));
+
+        let srv = factory.new_service(()).await.unwrap();
+        let items = srv.call(3).await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(items, vec![0, 0, 1, 0, 1, 2]);
+
+        // fn_service is both a service and a service factory
+        let srv = fn_service(|n: usize| ok::<_, ()>(stream::iter(0..n)));
+        let srv = StreamServiceExt::map_stream(srv, |n| n * 2);
+        let items = srv.call(2).await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(items, vec![0, 2]);
+    }
+
+    #[actix_rt::test]
+    async fn test_boxed() {
+        let srv = crate::boxed::stream_service(Numbers.map_stream(|n| n * 3));
+        let items = srv.call(3).await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(items, vec![0, 3, 6]);
+    }
+}
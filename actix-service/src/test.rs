@@ -0,0 +1,420 @@
+//! Utilities for unit testing services and transforms.
+//!
+//! [`MockService`] plays the inner service of a transform with readiness and responses scripted
+//! by the test, and [`Spy`] wraps a service to record what reaches it. Both are cheap to clone;
+//! clones share their script and records, so the test keeps a handle after moving the service
+//! into the code under test.
+//!
+//! # Examples
+//! ```
+//! use actix_service::test::{MockService, Readiness, Spy};
+//! use actix_service::Service;
+//! use futures_util::future::lazy;
+//!
+//! # actix_rt::System::new().block_on(async {
+//! let mock = MockService::<u32, u32, ()>::new();
+//! mock.push_readiness(Readiness::Pending);
+//! mock.push_response(Ok(2));
+//!
+//! let spy = Spy::new();
+//! let srv = spy.wrap(mock.clone());
+//!
+//! assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+//! assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+//! assert_eq!(srv.call(1).await, Ok(2));
+//!
+//! assert_eq!(spy.requests(), [1]);
+//! assert_eq!(spy.successes(), 1);
+//! assert_eq!(mock.take_requests(), [1]);
+//! # });
+//! ```
+
+use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
+use core::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use futures_core::ready;
+use pin_project_lite::pin_project;
+
+use crate::{ok, Ready, Service, Transform};
+
+/// Scripted outcome of one [`MockService`] readiness check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Readiness<E> {
+    /// Service is ready.
+    Ready,
+
+    /// Service is not ready. The caller is woken once more readiness is scripted.
+    Pending,
+
+    /// Readiness check fails with the given error.
+    Failed(E),
+}
+
+/// Service with readiness and responses scripted by the test.
+///
+/// Each `poll_ready` call consumes the next entry pushed with
+/// [`push_readiness`](Self::push_readiness) and reports ready when none is left. Each response
+/// future resolves to the next result pushed with [`push_response`](Self::push_response); futures
+/// take results in the order they are polled and stay pending until a result is available.
+pub struct MockService<Req, Res, Err> {
+    inner: Rc<MockInner<Req, Res, Err>>,
+}
+
+struct MockInner<Req, Res, Err> {
+    readiness: RefCell<VecDeque<Readiness<Err>>>,
+    responses: RefCell<VecDeque<Result<Res, Err>>>,
+    requests: RefCell<Vec<Req>>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl<Req, Res, Err> MockInner<Req, Res, Err> {
+    fn wake(&self) {
+        for waker in self.wakers.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn register(&self, cx: &Context<'_>) {
+        let mut wakers = self.wakers.borrow_mut();
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+    }
+}
+
+impl<Req, Res, Err> MockService<Req, Res, Err> {
+    /// Create mock service without scripted readiness or responses.
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(MockInner {
+                readiness: RefCell::new(VecDeque::new()),
+                responses: RefCell::new(VecDeque::new()),
+                requests: RefCell::new(Vec::new()),
+                wakers: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Append outcome of a future readiness check.
+    pub fn push_readiness(&self, readiness: Readiness<Err>) {
+        self.inner.readiness.borrow_mut().push_back(readiness);
+        self.inner.wake();
+    }
+
+    /// Append result of a future response.
+    pub fn push_response(&self, res: Result<Res, Err>) {
+        self.inner.responses.borrow_mut().push_back(res);
+        self.inner.wake();
+    }
+
+    /// Take requests received since the last call, in the order they were received.
+    pub fn take_requests(&self) -> Vec<Req> {
+        core::mem::take(&mut *self.inner.requests.borrow_mut())
+    }
+}
+
+impl<Req, Res, Err> Default for MockService<Req, Res, Err> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Req, Res, Err> Clone for MockService<Req, Res, Err> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Req, Res, Err> fmt::Debug for MockService<Req, Res, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockService")
+            .field("readiness", &self.inner.readiness.borrow().len())
+            .field("responses", &self.inner.responses.borrow().len())
+            .field("requests", &self.inner.requests.borrow().len())
+            .finish()
+    }
+}
+
+impl<Req, Res, Err> Service<Req> for MockService<Req, Res, Err> {
+    type Response = Res;
+    type Error = Err;
+    type Future = MockResponse<Req, Res, Err>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Err>> {
+        let next = self.inner.readiness.borrow_mut().pop_front();
+
+        match next {
+            None | Some(Readiness::Ready) => Poll::Ready(Ok(())),
+            Some(Readiness::Failed(err)) => Poll::Ready(Err(err)),
+            Some(Readiness::Pending) => {
+                if self.inner.readiness.borrow().is_empty() {
+                    self.inner.register(cx);
+                } else {
+                    cx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    fn call(&self, req: Req) -> Self::Future {
+        self.inner.requests.borrow_mut().push(req);
+
+        MockResponse {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Response future of [`MockService`].
+pub struct MockResponse<Req, Res, Err> {
+    inner: Rc<MockInner<Req, Res, Err>>,
+}
+
+impl<Req, Res, Err> Future for MockResponse<Req, Res, Err> {
+    type Output = Result<Res, Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let next = self.inner.responses.borrow_mut().pop_front();
+
+        match next {
+            Some(res) => Poll::Ready(res),
+            None => {
+                self.inner.register(cx);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Transform recording readiness checks, requests and outcomes of the service it wraps.
+pub struct Spy<Req> {
+    log: Rc<RefCell<SpyLog<Req>>>,
+}
+
+struct SpyLog<Req> {
+    ready_polls: usize,
+    requests: Vec<Req>,
+    successes: usize,
+    failures: usize,
+}
+
+impl<Req> Spy<Req> {
+    /// Create spy without records.
+    pub fn new() -> Self {
+        Self {
+            log: Rc::new(RefCell::new(SpyLog {
+                ready_polls: 0,
+                requests: Vec::new(),
+                successes: 0,
+                failures: 0,
+            })),
+        }
+    }
+
+    /// Wrap `service` directly, without going through [`Transform::new_transform`].
+    pub fn wrap<S>(&self, service: S) -> SpyService<S, Req> {
+        SpyService {
+            service,
+            log: self.log.clone(),
+        }
+    }
+
+    /// Returns number of `poll_ready` calls.
+    pub fn ready_polls(&self) -> usize {
+        self.log.borrow().ready_polls
+    }
+
+    /// Returns number of `call` calls.
+    pub fn calls(&self) -> usize {
+        self.log.borrow().requests.len()
+    }
+
+    /// Returns number of response futures that resolved successfully.
+    pub fn successes(&self) -> usize {
+        self.log.borrow().successes
+    }
+
+    /// Returns number of response futures that resolved with an error.
+    pub fn failures(&self) -> usize {
+        self.log.borrow().failures
+    }
+}
+
+impl<Req: Clone> Spy<Req> {
+    /// Returns requests passed to the service, in the order they were received.
+    pub fn requests(&self) -> Vec<Req> {
+        self.log.borrow().requests.clone()
+    }
+}
+
+impl<Req> Default for Spy<Req> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Req> Clone for Spy<Req> {
+    fn clone(&self) -> Self {
+        Self {
+            log: self.log.clone(),
+        }
+    }
+}
+
+impl<Req> fmt::Debug for Spy<Req> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let log = self.log.borrow();
+        f.debug_struct("Spy")
+            .field("ready_polls", &log.ready_polls)
+            .field("calls", &log.requests.len())
+            .field("successes", &log.successes)
+            .field("failures", &log.failures)
+            .finish()
+    }
+}
+
+impl<S, Req> Transform<S, Req> for Spy<Req>
+where
+    S: Service<Req>,
+    Req: Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = SpyService<S, Req>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(self.wrap(service))
+    }
+}
+
+/// Service created by [`Spy`].
+pub struct SpyService<S, Req> {
+    service: S,
+    log: Rc<RefCell<SpyLog<Req>>>,
+}
+
+impl<S, Req> Service<Req> for SpyService<S, Req>
+where
+    S: Service<Req>,
+    Req: Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SpyResponse<S::Future, Req>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.log.borrow_mut().ready_polls += 1;
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: Req) -> Self::Future {
+        self.log.borrow_mut().requests.push(req.clone());
+
+        SpyResponse {
+            fut: self.service.call(req),
+            log: self.log.clone(),
+            _req: PhantomData,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future of [`SpyService`].
+    pub struct SpyResponse<F, Req> {
+        #[pin]
+        fut: F,
+        log: Rc<RefCell<SpyLog<Req>>>,
+        _req: PhantomData<Req>,
+    }
+}
+
+impl<F, Req, Res, Err> Future for SpyResponse<F, Req>
+where
+    F: Future<Output = Result<Res, Err>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.fut.poll(cx));
+
+        let mut log = this.log.borrow_mut();
+        match res {
+            Ok(_) => log.successes += 1,
+            Err(_) => log.failures += 1,
+        }
+
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use futures_util::future::lazy;
+
+    use super::*;
+    use crate::{apply, fn_factory, ServiceFactory};
+
+    #[actix_rt::test]
+    async fn test_mock_readiness() {
+        let mock = MockService::<(), (), &str>::new();
+        mock.push_readiness(Readiness::Pending);
+        mock.push_readiness(Readiness::Failed("err"));
+
+        assert_eq!(lazy(|cx| mock.poll_ready(cx)).await, Poll::Pending);
+        assert_eq!(
+            lazy(|cx| mock.poll_ready(cx)).await,
+            Poll::Ready(Err("err"))
+        );
+        assert_eq!(lazy(|cx| mock.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_mock_response_pending() {
+        let mock = MockService::<u8, u8, ()>::new();
+
+        let mut fut = Box::pin(mock.call(1));
+        assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
+
+        mock.push_response(Ok(2));
+        assert_eq!(fut.await, Ok(2));
+        assert_eq!(mock.take_requests(), [1]);
+        assert!(mock.take_requests().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_spy_transform() {
+        let mock = MockService::<u8, u8, ()>::new();
+        mock.push_response(Ok(1));
+        mock.push_response(Err(()));
+
+        let spy = Spy::new();
+        let mock2 = mock.clone();
+        let factory = apply(spy.clone(), fn_factory(move || ok::<_, ()>(mock2.clone())));
+        let srv = factory.new_service(()).await.unwrap();
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(3).await, Ok(1));
+        assert_eq!(srv.call(4).await, Err(()));
+
+        assert_eq!(spy.ready_polls(), 1);
+        assert_eq!(spy.calls(), 2);
+        assert_eq!(spy.requests(), [3, 4]);
+        assert_eq!(spy.successes(), 1);
+        assert_eq!(spy.failures(), 1);
+    }
+}
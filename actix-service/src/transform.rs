@@ -2,6 +2,7 @@ use alloc::{rc::Rc, sync::Arc};
 use core::{
     future::Future,
     marker::PhantomData,
+    ops::Deref,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -21,6 +22,19 @@ where
     ApplyTransform::new(t, factory.into_factory())
 }
 
+/// Apply a [`Transform`] to a [`Service`], keeping the composed factory `Send`.
+///
+/// Unlike [`apply`], the transform and service factory are shared with `Arc`, so the factory can
+/// be created on one thread and moved to another, e.g. to worker arbiters.
+pub fn apply_send<T, S, I, Req>(t: T, factory: I) -> ApplyTransform<T, S, Req, Arc<(T, S)>>
+where
+    I: IntoServiceFactory<S, Req>,
+    S: ServiceFactory<Req> + Send + Sync,
+    T: Transform<S::Service, Req, InitError = S::InitError> + Send + Sync,
+{
+    ApplyTransform(Arc::new((t, factory.into_factory())), PhantomData)
+}
+
 /// Defines the interface of a service factory that wraps inner service during construction.
 ///
 /// Transformers wrap an inner service and runs during inbound and/or outbound processing in the
@@ -131,7 +145,10 @@ where
 }
 
 /// Apply a [`Transform`] to a [`Service`].
-pub struct ApplyTransform<T, S, Req>(Rc<(T, S)>, PhantomData<Req>);
+///
+/// The transform and service factory are shared between clones and construction futures with
+/// `Rc`, or with `Arc` when created by [`apply_send`].
+pub struct ApplyTransform<T, S, Req, P = Rc<(T, S)>>(P, PhantomData<(T, S, Req)>);
 
 impl<T, S, Req> ApplyTransform<T, S, Req>
 where
@@ -144,16 +161,17 @@ where
     }
 }
 
-impl<T, S, Req> Clone for ApplyTransform<T, S, Req> {
+impl<T, S, Req, P: Clone> Clone for ApplyTransform<T, S, Req, P> {
     fn clone(&self) -> Self {
         ApplyTransform(self.0.clone(), PhantomData)
     }
 }
 
-impl<T, S, Req> ServiceFactory<Req> for ApplyTransform<T, S, Req>
+impl<T, S, Req, P> ServiceFactory<Req> for ApplyTransform<T, S, Req, P>
 where
     S: ServiceFactory<Req>,
     T: Transform<S::Service, Req, InitError = S::InitError>,
+    P: Deref<Target = (T, S)> + Clone,
 {
     type Response = T::Response;
     type Error = T::Error;
@@ -161,25 +179,25 @@ where
     type Config = S::Config;
     type Service = T::Transform;
     type InitError = T::InitError;
-    type Future = ApplyTransformFuture<T, S, Req>;
+    type Future = ApplyTransformFuture<T, S, Req, P>;
 
     fn new_service(&self, cfg: S::Config) -> Self::Future {
         ApplyTransformFuture {
             store: self.0.clone(),
             state: ApplyTransformFutureState::A {
-                fut: self.0.as_ref().1.new_service(cfg),
+                fut: self.0.deref().1.new_service(cfg),
             },
         }
     }
 }
 
 pin_project! {
-    pub struct ApplyTransformFuture<T, S, Req>
+    pub struct ApplyTransformFuture<T, S, Req, P = Rc<(T, S)>>
     where
         S: ServiceFactory<Req>,
         T: Transform<S::Service, Req, InitError = S::InitError>,
     {
-        store: Rc<(T, S)>,
+        store: P,
         #[pin]
         state: ApplyTransformFutureState<T, S, Req>,
     }
@@ -197,10 +215,11 @@ pin_project! {
     }
 }
 
-impl<T, S, Req> Future for ApplyTransformFuture<T, S, Req>
+impl<T, S, Req, P> Future for ApplyTransformFuture<T, S, Req, P>
 where
     S: ServiceFactory<Req>,
     T: Transform<S::Service, Req, InitError = S::InitError>,
+    P: Deref<Target = (T, S)>,
 {
     type Output = Result<T::Transform, T::InitError>;
 
@@ -226,7 +245,7 @@ mod tests {
     use actix_utils::future::{ready, Ready};
 
     use super::*;
-    use crate::Service;
+    use crate::{fn_factory, fn_service, Service};
 
     // pseudo-doctest for Transform trait
     pub struct TimeoutTransform {
@@ -267,4 +286,20 @@ mod tests {
             self.service.call(req)
         }
     }
+
+    #[actix_rt::test]
+    async fn test_apply_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let factory = apply_send(
+            TimeoutTransform {
+                timeout: Duration::from_secs(1),
+            },
+            fn_factory(|| ready(Ok::<_, ()>(fn_service(|req: u8| ready(Ok::<_, ()>(req)))))),
+        );
+        assert_send(&factory);
+
+        let srv = factory.clone().new_service(()).await.unwrap();
+        assert_eq!(srv.call(7).await, Ok(7));
+    }
 }
@@ -11,9 +11,11 @@ use actix_rt::net::{ActixStream, Ready};
 use actix_service::{Service, ServiceFactory};
 use actix_utils::counter::{Counter, CounterGuard};
 use futures_core::{future::LocalBoxFuture, ready};
+use log::error;
 
 pub use openssl::ssl::{
     AlpnError, Error as SslError, HandshakeError, Ssl, SslAcceptor, SslAcceptorBuilder,
+    SslMethod,
 };
 
 use super::MAX_CONN_COUNTER;
@@ -91,6 +93,45 @@ impl<T: ActixStream> ActixStream for TlsStream<T> {
     }
 }
 
+/// Configure pre-shared key (TLS-PSK) authentication on an acceptor builder.
+///
+/// `lookup` is called during the handshake with the identity sent by the client and should
+/// return the matching key, or `None` to reject the client. PSK cipher suites must be enabled
+/// separately, e.g. with `builder.set_cipher_list("PSK-AES128-GCM-SHA256")`.
+///
+/// # Examples
+/// ```no_run
+/// use actix_tls::accept::openssl::{set_psk, Acceptor, SslAcceptor, SslMethod};
+///
+/// let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+/// builder.set_cipher_list("PSK-AES128-GCM-SHA256").unwrap();
+/// set_psk(&mut builder, |identity| match identity {
+///     b"device-1" => Some(b"secret key".to_vec()),
+///     _ => None,
+/// });
+///
+/// let acceptor = Acceptor::new(builder.build());
+/// ```
+pub fn set_psk<F>(builder: &mut SslAcceptorBuilder, lookup: F)
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+{
+    builder.set_psk_server_callback(move |_, identity, psk_buf| {
+        // returning a zero length key makes OpenSSL abort the handshake
+        match lookup(identity.unwrap_or(&[])) {
+            Some(psk) if psk.len() <= psk_buf.len() => {
+                psk_buf[..psk.len()].copy_from_slice(&psk);
+                Ok(psk.len())
+            }
+            Some(_) => {
+                error!("Pre-shared key exceeds maximum length supported by OpenSSL");
+                Ok(0)
+            }
+            None => Ok(0),
+        }
+    });
+}
+
 /// Accept TLS connections via `openssl` package.
 ///
 /// `openssl` feature enables this `Acceptor` type.
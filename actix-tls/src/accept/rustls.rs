@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     future::Future,
     io::{self, IoSlice},
     ops::{Deref, DerefMut},
@@ -12,7 +13,11 @@ use actix_rt::net::{ActixStream, Ready};
 use actix_service::{Service, ServiceFactory};
 use actix_utils::counter::{Counter, CounterGuard};
 use futures_core::future::LocalBoxFuture;
-use tokio_rustls::{Accept, TlsAcceptor};
+use tokio_rustls::rustls::{
+    sign::{self, CertifiedKey},
+    Certificate, ClientHello, PrivateKey, ResolvesServerCert,
+};
+use tokio_rustls::{webpki::DNSNameRef, Accept, TlsAcceptor};
 
 pub use tokio_rustls::rustls::{ServerConfig, Session};
 
@@ -177,3 +182,145 @@ impl<T: ActixStream> Future for AcceptorServiceFut<T> {
         Pin::new(&mut this.fut).poll(cx).map_ok(TlsStream)
     }
 }
+
+/// Certificate resolver choosing the certificate by the server name (SNI) sent by the client.
+///
+/// Lets one acceptor serve several domains. Server names are matched case-insensitively. Clients
+/// that send no server name or one without a certificate get the default certificate, if set;
+/// otherwise the handshake fails.
+///
+/// # Examples
+/// ```no_run
+/// # use std::sync::Arc;
+/// use actix_tls::accept::rustls::{Acceptor, ServerConfig, SniResolver};
+/// use tokio_rustls::rustls::{Certificate, NoClientAuth, PrivateKey};
+///
+/// # fn load(_: &str) -> (Vec<Certificate>, PrivateKey) { unimplemented!() }
+/// # fn run() -> std::io::Result<()> {
+/// let mut resolver = SniResolver::new();
+///
+/// let (certs, key) = load("example.com");
+/// resolver.add("example.com", certs, &key)?;
+///
+/// let (certs, key) = load("example.org");
+/// resolver.add("example.org", certs, &key)?;
+///
+/// let mut config = ServerConfig::new(NoClientAuth::new());
+/// config.cert_resolver = Arc::new(resolver);
+///
+/// let acceptor = Acceptor::new(config);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct SniResolver {
+    by_name: HashMap<String, CertifiedKey>,
+    default: Option<CertifiedKey>,
+}
+
+impl SniResolver {
+    /// Create resolver without any certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add certificate chain and its private key for server `name`.
+    ///
+    /// Fails if `name` is not a valid DNS name, the certificate is not valid for `name` or the
+    /// key type is not supported.
+    pub fn add(
+        &mut self,
+        name: &str,
+        cert_chain: Vec<Certificate>,
+        key: &PrivateKey,
+    ) -> io::Result<()> {
+        let dns_name = DNSNameRef::try_from_ascii_str(name).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid server name: {}", name),
+            )
+        })?;
+
+        let certified = certified_key(cert_chain, key)?;
+        certified
+            .cross_check_end_entity_cert(Some(dns_name))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        self.by_name.insert(name.to_ascii_lowercase(), certified);
+        Ok(())
+    }
+
+    /// Set certificate chain and its private key used when no certificate matches the server name
+    /// sent by the client.
+    pub fn set_default(
+        &mut self,
+        cert_chain: Vec<Certificate>,
+        key: &PrivateKey,
+    ) -> io::Result<()> {
+        let certified = certified_key(cert_chain, key)?;
+        certified
+            .cross_check_end_entity_cert(None)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        self.default = Some(certified);
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<CertifiedKey> {
+        client_hello
+            .server_name()
+            .and_then(|name| {
+                let name: &str = name.into();
+                self.by_name.get(&name.to_ascii_lowercase())
+            })
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+fn certified_key(cert_chain: Vec<Certificate>, key: &PrivateKey) -> io::Result<CertifiedKey> {
+    let key = sign::any_supported_type(key).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Unsupported private key type")
+    })?;
+
+    Ok(CertifiedKey::new(cert_chain, Arc::new(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_rustls::rustls::internal::pemfile::{certs, rsa_private_keys};
+
+    use super::*;
+
+    fn cert_and_key() -> (Vec<Certificate>, PrivateKey) {
+        let certs = certs(&mut &include_bytes!("../../examples/cert.pem")[..]).unwrap();
+        let mut keys =
+            rsa_private_keys(&mut &include_bytes!("../../examples/key.pem")[..]).unwrap();
+        (certs, keys.remove(0))
+    }
+
+    #[test]
+    fn sni_resolver_add() {
+        let (certs, key) = cert_and_key();
+        let mut resolver = SniResolver::new();
+
+        resolver.add("localhost", certs.clone(), &key).unwrap();
+        resolver.set_default(certs.clone(), &key).unwrap();
+
+        // certificate is only valid for localhost
+        let err = resolver
+            .add("example.com", certs.clone(), &key)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = resolver.add("not a name", certs, &key).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = resolver
+            .set_default(Vec::new(), &PrivateKey(vec![0; 8]))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
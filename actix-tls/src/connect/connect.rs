@@ -29,7 +29,7 @@ impl Address for &'static str {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub(crate) enum ConnectAddrs {
     None,
     One(SocketAddr),
@@ -52,6 +52,19 @@ impl Default for ConnectAddrs {
     }
 }
 
+impl ConnectAddrs {
+    /// Move addresses of the preferred family to the front, keeping the resolver's order
+    /// otherwise.
+    fn prefer(&mut self, family: IpFamily) {
+        if let ConnectAddrs::Multi(ref mut addrs) = self {
+            let (mut preferred, other): (VecDeque<_>, VecDeque<_>) =
+                addrs.drain(..).partition(|addr| family.matches(addr));
+            preferred.extend(other);
+            *addrs = preferred;
+        }
+    }
+}
+
 impl From<Option<SocketAddr>> for ConnectAddrs {
     fn from(addr: Option<SocketAddr>) -> Self {
         match addr {
@@ -61,13 +74,30 @@ impl From<Option<SocketAddr>> for ConnectAddrs {
     }
 }
 
+/// IP address family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    fn matches(self, addr: &SocketAddr) -> bool {
+        match self {
+            IpFamily::V4 => addr.is_ipv4(),
+            IpFamily::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
 /// Connection info.
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Connect<T> {
     pub(crate) req: T,
     pub(crate) port: u16,
     pub(crate) addr: ConnectAddrs,
     pub(crate) local_addr: Option<IpAddr>,
+    pub(crate) family: Option<IpFamily>,
 }
 
 impl<T: Address> Connect<T> {
@@ -80,6 +110,7 @@ impl<T: Address> Connect<T> {
             port: port.unwrap_or(0),
             addr: ConnectAddrs::None,
             local_addr: None,
+            family: None,
         }
     }
 
@@ -91,6 +122,7 @@ impl<T: Address> Connect<T> {
             port: 0,
             addr: ConnectAddrs::One(addr),
             local_addr: None,
+            family: None,
         }
     }
 
@@ -119,6 +151,9 @@ impl<T: Address> Connect<T> {
         } else {
             ConnectAddrs::Multi(addrs)
         };
+        if let Some(family) = self.family {
+            self.addr.prefer(family);
+        }
         self
     }
 
@@ -128,6 +163,15 @@ impl<T: Address> Connect<T> {
         self
     }
 
+    /// Try addresses of the given family first when a host resolves to several addresses.
+    ///
+    /// Addresses of the other family are still tried after all preferred ones failed.
+    pub fn set_family_preference(mut self, family: IpFamily) -> Self {
+        self.family = Some(family);
+        self.addr.prefer(family);
+        self
+    }
+
     /// Get hostname.
     pub fn hostname(&self) -> &str {
         self.req.hostname()
@@ -339,6 +383,22 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn test_family_preference() {
+        let v4 = SocketAddr::from(([127, 0, 0, 1], 53));
+        let v6 = SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, 53));
+
+        let conn = Connect::new("hello")
+            .set_family_preference(IpFamily::V6)
+            .set_addrs(vec![v4, v6, v4]);
+        assert_eq!(conn.addrs().collect::<Vec<_>>(), [v6, v4, v4]);
+
+        let conn = Connect::new("hello")
+            .set_addrs(vec![v6, v4])
+            .set_family_preference(IpFamily::V4);
+        assert_eq!(conn.addrs().collect::<Vec<_>>(), [v4, v6]);
+    }
+
     #[test]
     fn test_local_addr() {
         let conn = Connect::new("hello").set_local_addr([127, 0, 0, 1]);
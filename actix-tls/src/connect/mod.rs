@@ -4,6 +4,10 @@
 //! - Resolve [`Address`] with given [`Resolver`] and collect list of socket addresses.
 //! - Establish TCP connection and return [`TcpStream`].
 //!
+//! # Stages of the UDP connector service:
+//! - Resolve [`Address`] with given [`Resolver`] and collect list of socket addresses.
+//! - Bind a local socket and connect it to the first usable address, returning [`UdpSocket`].
+//!
 //! # Stages of TLS connector services:
 //! - Establish [`TcpStream`] with connector service.
 //! - Wrap the stream and perform connect handshake with remote peer.
@@ -14,25 +18,33 @@
 //! * `rustls` - enables TLS support via `rustls` crate
 //!
 //! [`TcpStream`]: actix_rt::net::TcpStream
+//! [`UdpSocket`]: actix_rt::net::UdpSocket
 
 #[allow(clippy::module_inception)]
 mod connect;
 mod connector;
 mod error;
+mod reconnect;
+mod resolv_conf;
 mod resolve;
 mod service;
 pub mod ssl;
+mod udp;
 #[cfg(feature = "uri")]
 mod uri;
 
-use actix_rt::net::TcpStream;
+use actix_rt::net::{TcpStream, UdpSocket};
 use actix_service::{Service, ServiceFactory};
 
-pub use self::connect::{Address, Connect, Connection};
+pub use self::connect::{Address, Connect, Connection, IpFamily};
 pub use self::connector::{TcpConnector, TcpConnectorFactory};
 pub use self::error::ConnectError;
+pub use self::reconnect::Reconnect;
 pub use self::resolve::{Resolve, Resolver, ResolverFactory};
 pub use self::service::{ConnectService, ConnectServiceFactory};
+pub use self::udp::{
+    UdpConnectService, UdpConnectServiceFactory, UdpConnector, UdpConnectorFactory,
+};
 
 /// Create TCP connector service.
 pub fn new_connector<T: Address + 'static>(
@@ -72,3 +84,18 @@ pub fn default_connector_factory<T: Address + 'static>() -> impl ServiceFactory<
 > + Clone {
     new_connector_factory(Resolver::Default)
 }
+
+/// Create UDP connector service.
+pub fn new_udp_connector<T: Address + 'static>(
+    resolver: Resolver,
+) -> impl Service<Connect<T>, Response = Connection<T, UdpSocket>, Error = ConnectError> + Clone
+{
+    UdpConnectServiceFactory::new(resolver).service()
+}
+
+/// Create UDP connector service with default parameters.
+pub fn default_udp_connector<T: Address + 'static>(
+) -> impl Service<Connect<T>, Response = Connection<T, UdpSocket>, Error = ConnectError> + Clone
+{
+    new_udp_connector(Resolver::Default)
+}
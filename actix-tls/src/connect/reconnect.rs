@@ -0,0 +1,314 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep, Sleep};
+use actix_service::Service;
+use futures_core::{future::LocalBoxFuture, ready};
+use log::{trace, warn};
+
+/// Service keeping a session on a connection and re-establishing it when it fails.
+///
+/// The connection is opened with `connector` for a clone of `req` and passed to `on_connected`,
+/// which performs any handshake or authentication and returns the session: a service handling
+/// requests over that connection. Requests are passed to the current session.
+///
+/// A session fails when its `poll_ready` returns an error. It is then dropped and a new
+/// connection is opened. Failed connection attempts, and sessions failing before they were ever
+/// ready, are retried with exponential backoff. The error is only returned from `poll_ready`
+/// once the maximum number of attempts is reached, if one is set.
+///
+/// The first connection is opened when the service is first polled for readiness.
+///
+/// # Examples
+/// ```no_run
+/// use std::{net::SocketAddr, time::Duration};
+///
+/// use actix_service::{fn_service, Service};
+/// use actix_tls::connect::{default_connector, Connect, ConnectError, Reconnect};
+/// use actix_utils::future::poll_fn;
+///
+/// # async fn run() -> Result<(), ConnectError> {
+/// let client = Reconnect::new(
+///     default_connector(),
+///     Connect::new("localhost:6379".to_owned()),
+///     |conn| async move {
+///         // authenticate on the new connection here
+///         let peer = conn.peer_addr().map_err(ConnectError::Io)?;
+///         Ok(fn_service(move |_: ()| async move {
+///             Ok::<SocketAddr, ConnectError>(peer)
+///         }))
+///     },
+/// )
+/// .backoff(Duration::from_millis(50), Duration::from_secs(5));
+///
+/// poll_fn(|cx| client.poll_ready(cx)).await?;
+/// let peer = client.call(()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Reconnect<C, R, F, S, E> {
+    connector: C,
+    req: R,
+    on_connected: Rc<F>,
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<u32>,
+    attempts: Cell<u32>,
+    state: RefCell<State<S, E>>,
+}
+
+enum State<S, E> {
+    Idle,
+    Connecting(LocalBoxFuture<'static, Result<S, E>>),
+    Backoff(Pin<Box<Sleep>>),
+    /// Established session and whether it was ready before.
+    Connected(S, bool),
+}
+
+impl<C, R, F, Fut, S, E> Reconnect<C, R, F, S, E>
+where
+    C: Service<R>,
+    F: Fn(C::Response) -> Fut,
+    Fut: Future<Output = Result<S, E>>,
+{
+    /// Create service connecting with `connector` to `req` and creating sessions with
+    /// `on_connected`.
+    pub fn new(connector: C, req: R, on_connected: F) -> Self {
+        Reconnect {
+            connector,
+            req,
+            on_connected: Rc::new(on_connected),
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+            attempts: Cell::new(0),
+            state: RefCell::new(State::Idle),
+        }
+    }
+
+    /// Set delay before the first retry and the maximum delay between retries.
+    ///
+    /// The delay doubles with each failed attempt. Defaults to 100ms and 10s.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_delay = initial;
+        self.max_delay = max.max(initial);
+        self
+    }
+
+    /// Set number of consecutive failed attempts after which the error is returned from
+    /// `poll_ready`. The next readiness check starts over.
+    ///
+    /// By default connections are retried indefinitely.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        assert_ne!(attempts, 0, "max attempts must be greater than 0");
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Check if a session is currently established.
+    pub fn is_connected(&self) -> bool {
+        matches!(*self.state.borrow(), State::Connected(..))
+    }
+
+    fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32 << (attempts - 1).min(31);
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Schedule retry after a failed attempt, or return the error once attempts are exhausted.
+    fn retry(&self, state: &mut State<S, E>, err: E) -> Result<(), E> {
+        let attempts = self.attempts.get() + 1;
+
+        if matches!(self.max_attempts, Some(max) if attempts >= max) {
+            warn!("Reconnect: giving up after {} attempts", attempts);
+            self.attempts.set(0);
+            *state = State::Idle;
+            return Err(err);
+        }
+
+        let delay = self.delay(attempts);
+        warn!(
+            "Reconnect: attempt {} failed, retrying in {:?}",
+            attempts, delay
+        );
+        self.attempts.set(attempts);
+        *state = State::Backoff(Box::pin(sleep(delay)));
+        Ok(())
+    }
+}
+
+impl<C, R, F, Fut, S, E, Req> Service<Req> for Reconnect<C, R, F, S, E>
+where
+    C: Service<R>,
+    C::Future: 'static,
+    R: Clone,
+    F: Fn(C::Response) -> Fut + 'static,
+    Fut: Future<Output = Result<S, E>> + 'static,
+    S: Service<Req, Error = E> + 'static,
+    E: From<C::Error> + 'static,
+{
+    type Response = S::Response;
+    type Error = E;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut state = self.state.borrow_mut();
+
+        loop {
+            match *state {
+                State::Idle => match ready!(self.connector.poll_ready(cx)) {
+                    Ok(()) => {
+                        trace!("Reconnect: opening connection");
+                        let conn = self.connector.call(self.req.clone());
+                        let on_connected = self.on_connected.clone();
+
+                        *state = State::Connecting(Box::pin(async move {
+                            let conn = conn.await?;
+                            on_connected(conn).await
+                        }));
+                    }
+                    Err(err) => self.retry(&mut *state, err.into())?,
+                },
+
+                State::Connecting(ref mut fut) => match ready!(fut.as_mut().poll(cx)) {
+                    Ok(session) => {
+                        trace!("Reconnect: session established");
+                        *state = State::Connected(session, false);
+                    }
+                    Err(err) => self.retry(&mut *state, err)?,
+                },
+
+                State::Backoff(ref mut delay) => {
+                    ready!(delay.as_mut().poll(cx));
+                    *state = State::Idle;
+                }
+
+                State::Connected(ref session, ref mut was_ready) => {
+                    match ready!(session.poll_ready(cx)) {
+                        Ok(()) => {
+                            *was_ready = true;
+                            self.attempts.set(0);
+                            return Poll::Ready(Ok(()));
+                        }
+                        // session that was ready before is replaced right away
+                        Err(_) if *was_ready => {
+                            warn!("Reconnect: session failed, reconnecting");
+                            *state = State::Idle;
+                        }
+                        Err(err) => self.retry(&mut *state, err)?,
+                    }
+                }
+            }
+        }
+    }
+
+    fn call(&self, req: Req) -> Self::Future {
+        match *self.state.borrow() {
+            State::Connected(ref session, _) => session.call(req),
+            _ => panic!("Reconnect service called before it was ready"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::fn_service;
+    use actix_utils::future::{poll_fn, ready, Ready};
+
+    use super::*;
+    use crate::connect::ConnectError;
+
+    struct Session {
+        conn: usize,
+        broken: Rc<Cell<bool>>,
+    }
+
+    impl Service<()> for Session {
+        type Response = usize;
+        type Error = ConnectError;
+        type Future = Ready<Result<usize, ConnectError>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ConnectError>> {
+            if self.broken.get() {
+                Poll::Ready(Err(ConnectError::InvalidInput))
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            ready(Ok(self.conn))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reconnect_after_failure() {
+        let calls = Rc::new(Cell::new(0));
+        let broken = Rc::new(Cell::new(false));
+
+        let connector = fn_service({
+            let calls = calls.clone();
+            move |_: ()| {
+                calls.set(calls.get() + 1);
+                // first two attempts fail
+                let res = match calls.get() {
+                    1 | 2 => Err(ConnectError::Unresolved),
+                    conn => Ok(conn),
+                };
+                ready(res)
+            }
+        });
+
+        let client = Reconnect::new(connector, (), {
+            let broken = broken.clone();
+            move |conn| {
+                broken.set(false);
+                ready(Ok(Session {
+                    conn,
+                    broken: broken.clone(),
+                }))
+            }
+        })
+        .backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+        assert!(!client.is_connected());
+        poll_fn(|cx| client.poll_ready(cx)).await.unwrap();
+        assert!(client.is_connected());
+        assert_eq!(client.call(()).await.unwrap(), 3);
+
+        broken.set(true);
+        poll_fn(|cx| client.poll_ready(cx)).await.unwrap();
+        assert_eq!(client.call(()).await.unwrap(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn max_attempts() {
+        let calls = Rc::new(Cell::new(0));
+
+        let connector = fn_service({
+            let calls = calls.clone();
+            move |_: ()| {
+                calls.set(calls.get() + 1);
+                ready(Err::<(), _>(ConnectError::Unresolved))
+            }
+        });
+
+        let client = Reconnect::new(connector, (), |_| async {
+            Ok(fn_service(|_: ()| ready(Ok::<_, ConnectError>(()))))
+        })
+        .backoff(Duration::from_millis(1), Duration::from_millis(1))
+        .max_attempts(3);
+
+        let res = poll_fn(|cx| client.poll_ready(cx)).await;
+        assert!(matches!(res, Err(ConnectError::Unresolved)));
+        assert_eq!(calls.get(), 3);
+    }
+}
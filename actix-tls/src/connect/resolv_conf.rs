@@ -0,0 +1,82 @@
+//! Reload of system resolver configuration for the default resolver.
+//!
+//! The default resolver performs lookups with `getaddrinfo` on blocking threads. Depending on
+//! the C library, nameserver configuration is read once per thread and not refreshed when
+//! `/etc/resolv.conf` changes (e.g. on VPN connect or DHCP renew). Before each lookup the
+//! blocking thread checks the file's modification time, at most once per second, and
+//! re-initializes its resolver state when it changed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable reloading.
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Re-initialize resolver state of the current thread if the configuration file changed.
+///
+/// Must be called on the thread that performs the lookup.
+#[cfg(target_os = "linux")]
+pub(crate) fn refresh() {
+    use std::{
+        cell::RefCell,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    use log::debug;
+
+    const PATH: &str = "/etc/resolv.conf";
+    const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    struct State {
+        checked: Instant,
+        modified: Option<SystemTime>,
+    }
+
+    thread_local!(
+        // `const` initializers are only stable since Rust 1.59 (MSRV is 1.46)
+        #[allow(clippy::missing_const_for_thread_local)]
+        static STATE: RefCell<Option<State>> = RefCell::new(None);
+    );
+
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let now = Instant::now();
+
+        if let Some(ref state) = *state {
+            if now.duration_since(state.checked) < CHECK_INTERVAL {
+                return;
+            }
+        }
+
+        // a missing file is tracked as well; resolver falls back to defaults without it
+        let modified = std::fs::metadata(PATH).and_then(|m| m.modified()).ok();
+
+        match state.replace(State {
+            checked: now,
+            modified,
+        }) {
+            // first lookup on this thread reads the current configuration anyway
+            None => {}
+            Some(prev) if prev.modified == modified => {}
+            Some(_) => {
+                debug!("DNS resolver: {} changed; reloading configuration", PATH);
+
+                // SAFETY: res_init only touches resolver state of the calling thread.
+                unsafe {
+                    libc::res_init();
+                }
+            }
+        }
+    });
+}
+
+/// Resolver configuration is tracked by the platform on this target.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn refresh() {}
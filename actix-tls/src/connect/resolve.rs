@@ -15,6 +15,7 @@ use log::trace;
 
 use super::connect::{Address, Connect};
 use super::error::ConnectError;
+use super::resolv_conf;
 
 /// DNS Resolver Service Factory
 #[derive(Clone)]
@@ -118,6 +119,18 @@ impl Resolver {
         Self::Custom(Rc::new(resolver))
     }
 
+    /// Enable or disable reloading of system resolver configuration by the default resolver.
+    ///
+    /// When enabled, changes to `/etc/resolv.conf` (e.g. after VPN connect or DHCP renew) are
+    /// picked up by subsequent lookups of [`Resolver::Default`] without restarting the process.
+    /// The file is checked at most once per second per lookup thread. This only has an effect
+    /// on Linux; other platforms track resolver configuration themselves.
+    ///
+    /// The setting is process-wide and enabled by default.
+    pub fn reload_system_conf(enabled: bool) {
+        resolv_conf::set_enabled(enabled);
+    }
+
     // look up with default resolver variant.
     fn look_up<T: Address>(req: &Connect<T>) -> JoinHandle<io::Result<IntoIter<SocketAddr>>> {
         let host = req.hostname();
@@ -136,7 +149,10 @@ impl Resolver {
         };
 
         // run blocking DNS lookup in thread pool
-        spawn_blocking(move || std::net::ToSocketAddrs::to_socket_addrs(&host))
+        spawn_blocking(move || {
+            resolv_conf::refresh();
+            std::net::ToSocketAddrs::to_socket_addrs(&host)
+        })
     }
 }
 
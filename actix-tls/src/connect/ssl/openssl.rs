@@ -8,13 +8,61 @@ use std::{
 use actix_rt::net::ActixStream;
 use actix_service::{Service, ServiceFactory};
 use futures_core::{future::LocalBoxFuture, ready};
-use log::trace;
+use log::{error, trace};
 
-pub use openssl::ssl::{Error as SslError, HandshakeError, SslConnector, SslMethod};
+pub use openssl::ssl::{
+    Error as SslError, HandshakeError, SslConnector, SslConnectorBuilder, SslMethod,
+};
 pub use tokio_openssl::SslStream;
 
 use crate::connect::{Address, Connection};
 
+/// Configure pre-shared key (TLS-PSK) authentication on a connector builder.
+///
+/// `callback` is called during the handshake with the identity hint sent by the server, if any,
+/// and should return the client identity and key to use, or `None` to abort the handshake. PSK
+/// cipher suites must be enabled separately, e.g. with
+/// `builder.set_cipher_list("PSK-AES128-GCM-SHA256")`.
+///
+/// # Examples
+/// ```no_run
+/// use actix_tls::connect::ssl::openssl::{set_psk, OpensslConnector, SslConnector, SslMethod};
+///
+/// let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+/// builder.set_cipher_list("PSK-AES128-GCM-SHA256").unwrap();
+/// set_psk(&mut builder, |_hint| {
+///     Some((b"device-1".to_vec(), b"secret key".to_vec()))
+/// });
+///
+/// let connector = OpensslConnector::new(builder.build());
+/// ```
+pub fn set_psk<F>(builder: &mut SslConnectorBuilder, callback: F)
+where
+    F: Fn(Option<&[u8]>) -> Option<(Vec<u8>, Vec<u8>)> + Send + Sync + 'static,
+{
+    builder.set_psk_client_callback(move |_, hint, identity_buf, psk_buf| {
+        // returning a zero length key makes OpenSSL abort the handshake
+        match callback(hint) {
+            // identity is written as a nul terminated string
+            Some((identity, psk))
+                if identity.len() < identity_buf.len() && psk.len() <= psk_buf.len() =>
+            {
+                identity_buf[..identity.len()].copy_from_slice(&identity);
+                identity_buf[identity.len()] = 0;
+                psk_buf[..psk.len()].copy_from_slice(&psk);
+                Ok(psk.len())
+            }
+            Some(_) => {
+                error!(
+                    "Pre-shared key identity or key exceeds maximum length supported by OpenSSL"
+                );
+                Ok(0)
+            }
+            None => Ok(0),
+        }
+    });
+}
+
 /// OpenSSL connector factory
 pub struct OpensslConnector {
     connector: SslConnector,
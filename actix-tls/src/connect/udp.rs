@@ -0,0 +1,175 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use actix_rt::net::UdpSocket;
+use actix_service::{Service, ServiceFactory};
+use futures_core::future::LocalBoxFuture;
+use log::trace;
+
+use super::connect::{Address, Connect, ConnectAddrsIter, Connection};
+use super::error::ConnectError;
+use super::resolve::{Resolver, ResolverFactory};
+
+/// UDP connector service factory
+#[derive(Debug, Copy, Clone)]
+pub struct UdpConnectorFactory;
+
+impl UdpConnectorFactory {
+    /// Create UDP connector service
+    pub fn service(&self) -> UdpConnector {
+        UdpConnector
+    }
+}
+
+impl<T: Address> ServiceFactory<Connect<T>> for UdpConnectorFactory {
+    type Response = Connection<T, UdpSocket>;
+    type Error = ConnectError;
+    type Config = ();
+    type Service = UdpConnector;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let service = self.service();
+        Box::pin(async move { Ok(service) })
+    }
+}
+
+/// UDP connector service
+///
+/// Binds a socket to the requested local address, or to the unspecified address of the remote
+/// address' family, and connects it to the first resolved address that accepts it. Connecting a
+/// UDP socket sends nothing; it only fixes the peer for `send`/`recv` and filters datagrams
+/// from other peers.
+#[derive(Debug, Copy, Clone)]
+pub struct UdpConnector;
+
+impl<T: Address> Service<Connect<T>> for UdpConnector {
+    type Response = Connection<T, UdpSocket>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, mut req: Connect<T>) -> Self::Future {
+        let addrs = req.take_addrs();
+        let Connect {
+            req, local_addr, ..
+        } = req;
+
+        Box::pin(async move {
+            let socket = connect_first(req.hostname(), addrs, local_addr).await?;
+            Ok(Connection::new(socket, req))
+        })
+    }
+}
+
+async fn connect_first(
+    hostname: &str,
+    addrs: ConnectAddrsIter<'static>,
+    local_addr: Option<IpAddr>,
+) -> Result<UdpSocket, ConnectError> {
+    let mut last_err = None;
+
+    for addr in addrs {
+        trace!("UDP connector: connecting to {:?} - {:?}", hostname, addr);
+
+        match connect(addr, local_addr).await {
+            Ok(socket) => return Ok(socket),
+            Err(err) => {
+                trace!("UDP connector: failed to connect to {:?}: {}", addr, err);
+                last_err = Some(err);
+            }
+        }
+    }
+
+    Err(match last_err {
+        Some(err) => ConnectError::Io(err),
+        None => ConnectError::Unresolved,
+    })
+}
+
+async fn connect(addr: SocketAddr, local_addr: Option<IpAddr>) -> io::Result<UdpSocket> {
+    let local_ip = local_addr.unwrap_or(match addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+
+    let socket = UdpSocket::bind(SocketAddr::new(local_ip, 0)).await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+/// UDP connect service factory; resolves the address and connects a UDP socket.
+pub struct UdpConnectServiceFactory {
+    udp: UdpConnectorFactory,
+    resolver: ResolverFactory,
+}
+
+impl UdpConnectServiceFactory {
+    /// Construct new UdpConnectService factory
+    pub fn new(resolver: Resolver) -> Self {
+        UdpConnectServiceFactory {
+            udp: UdpConnectorFactory,
+            resolver: ResolverFactory::new(resolver),
+        }
+    }
+
+    /// Construct new service
+    pub fn service(&self) -> UdpConnectService {
+        UdpConnectService {
+            udp: self.udp.service(),
+            resolver: self.resolver.service(),
+        }
+    }
+}
+
+impl Clone for UdpConnectServiceFactory {
+    fn clone(&self) -> Self {
+        UdpConnectServiceFactory {
+            udp: self.udp,
+            resolver: self.resolver.clone(),
+        }
+    }
+}
+
+impl<T: Address> ServiceFactory<Connect<T>> for UdpConnectServiceFactory {
+    type Response = Connection<T, UdpSocket>;
+    type Error = ConnectError;
+    type Config = ();
+    type Service = UdpConnectService;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let service = self.service();
+        Box::pin(async { Ok(service) })
+    }
+}
+
+/// UDP connect service; resolves the address and connects a UDP socket.
+#[derive(Clone)]
+pub struct UdpConnectService {
+    udp: UdpConnector,
+    resolver: Resolver,
+}
+
+impl<T: Address> Service<Connect<T>> for UdpConnectService {
+    type Response = Connection<T, UdpSocket>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: Connect<T>) -> Self::Future {
+        let resolve = self.resolver.call(req);
+        let udp = self.udp;
+
+        Box::pin(async move {
+            let req = resolve.await?;
+            udp.call(req).await
+        })
+    }
+}
@@ -0,0 +1,121 @@
+//! Keying material exporters (RFC 5705).
+
+use std::io;
+
+/// TLS streams that can export keying material from their session, as described in [RFC 5705].
+///
+/// Exported material is bound to the TLS session; protocols use it to tie application-level
+/// tokens or authentication exchanges to the underlying channel. Both ends of a session derive
+/// the same bytes given the same label and context.
+///
+/// Implemented for client and server streams of the `openssl` and `rustls` backends. The
+/// `native-tls` backend does not expose this functionality.
+///
+/// [RFC 5705]: https://tools.ietf.org/html/rfc5705
+pub trait ExportKeyingMaterial {
+    /// Derive `len` bytes of keying material using the given `label` and optional `context`.
+    ///
+    /// Fails if the handshake is not complete or the backend rejects the parameters.
+    fn export_keying_material(
+        &self,
+        label: &str,
+        context: Option<&[u8]>,
+        len: usize,
+    ) -> io::Result<Vec<u8>>;
+}
+
+#[cfg(feature = "openssl")]
+mod openssl_impl {
+    use super::*;
+
+    use openssl::ssl::SslRef;
+    use tokio_openssl::SslStream;
+
+    fn export(
+        ssl: &SslRef,
+        label: &str,
+        context: Option<&[u8]>,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mut out = vec![0; len];
+        ssl.export_keying_material(&mut out, label, context)?;
+        Ok(out)
+    }
+
+    impl<T> ExportKeyingMaterial for SslStream<T> {
+        fn export_keying_material(
+            &self,
+            label: &str,
+            context: Option<&[u8]>,
+            len: usize,
+        ) -> io::Result<Vec<u8>> {
+            export(self.ssl(), label, context, len)
+        }
+    }
+
+    #[cfg(feature = "accept")]
+    impl<T> ExportKeyingMaterial for crate::accept::openssl::TlsStream<T> {
+        fn export_keying_material(
+            &self,
+            label: &str,
+            context: Option<&[u8]>,
+            len: usize,
+        ) -> io::Result<Vec<u8>> {
+            export(self.ssl(), label, context, len)
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+mod rustls_impl {
+    use super::*;
+
+    use tokio_rustls::rustls::Session;
+
+    fn export(
+        session: &dyn Session,
+        label: &str,
+        context: Option<&[u8]>,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mut out = vec![0; len];
+        session
+            .export_keying_material(&mut out, label.as_bytes(), context)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(out)
+    }
+
+    impl<T> ExportKeyingMaterial for tokio_rustls::client::TlsStream<T> {
+        fn export_keying_material(
+            &self,
+            label: &str,
+            context: Option<&[u8]>,
+            len: usize,
+        ) -> io::Result<Vec<u8>> {
+            export(self.get_ref().1, label, context, len)
+        }
+    }
+
+    impl<T> ExportKeyingMaterial for tokio_rustls::server::TlsStream<T> {
+        fn export_keying_material(
+            &self,
+            label: &str,
+            context: Option<&[u8]>,
+            len: usize,
+        ) -> io::Result<Vec<u8>> {
+            export(self.get_ref().1, label, context, len)
+        }
+    }
+
+    #[cfg(feature = "accept")]
+    impl<T> ExportKeyingMaterial for crate::accept::rustls::TlsStream<T> {
+        fn export_keying_material(
+            &self,
+            label: &str,
+            context: Option<&[u8]>,
+            len: usize,
+        ) -> io::Result<Vec<u8>> {
+            export(self.get_ref().1, label, context, len)
+        }
+    }
+}
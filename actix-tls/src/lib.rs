@@ -11,3 +11,7 @@ extern crate tls_openssl as openssl;
 pub mod accept;
 #[cfg(feature = "connect")]
 pub mod connect;
+
+mod keying_material;
+
+pub use self::keying_material::ExportKeyingMaterial;
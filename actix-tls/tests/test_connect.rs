@@ -31,6 +31,143 @@ async fn test_string() {
     assert_eq!(con.peer_addr().unwrap(), srv.addr());
 }
 
+#[cfg(all(feature = "accept", feature = "openssl"))]
+#[actix_rt::test]
+async fn test_openssl_psk() {
+    use actix_service::ServiceFactoryExt;
+    use actix_tls::{
+        accept::openssl::{self as accept_openssl, Acceptor, SslAcceptor},
+        connect::{
+            ssl::openssl::{
+                self as connect_openssl, OpensslConnector, SslConnector, SslMethod,
+            },
+            Connection,
+        },
+    };
+    use futures_util::stream::StreamExt;
+    use tls_openssl::ssl::{SslVerifyMode, SslVersion};
+
+    const PSK_CIPHER: &str = "PSK-AES128-GCM-SHA256";
+
+    let srv = TestServer::with(|| {
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        builder
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        builder.set_cipher_list(PSK_CIPHER).unwrap();
+        accept_openssl::set_psk(&mut builder, |identity| match identity {
+            b"client" => Some(b"0123456789abcdef".to_vec()),
+            _ => None,
+        });
+
+        Acceptor::new(builder.build())
+            .map_err(|_| ())
+            .and_then(fn_service(|io| async {
+                let mut framed = Framed::new(io, BytesCodec);
+                framed
+                    .send(Bytes::from_static(b"test"))
+                    .await
+                    .map_err(|_| ())
+            }))
+    });
+
+    let connector = |identity: &'static [u8]| {
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        builder.set_cipher_list(PSK_CIPHER).unwrap();
+        connect_openssl::set_psk(&mut builder, move |_| {
+            Some((identity.to_vec(), b"0123456789abcdef".to_vec()))
+        });
+        OpensslConnector::service(builder.build())
+    };
+
+    let io = TcpStream::connect(srv.addr()).await.unwrap();
+    let con = connector(b"client")
+        .call(Connection::new(io, "localhost"))
+        .await
+        .unwrap();
+    let mut framed = Framed::new(con.into_parts().0, BytesCodec);
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(&item[..], b"test");
+
+    let io = TcpStream::connect(srv.addr()).await.unwrap();
+    let res = connector(b"unknown")
+        .call(Connection::new(io, "localhost"))
+        .await;
+    assert!(res.is_err());
+}
+
+#[cfg(all(feature = "accept", feature = "openssl"))]
+#[actix_rt::test]
+async fn test_openssl_export_keying_material() {
+    use actix_service::ServiceFactoryExt;
+    use actix_tls::{
+        accept::openssl::{self as accept_openssl, Acceptor, SslAcceptor},
+        connect::{
+            ssl::openssl::{
+                self as connect_openssl, OpensslConnector, SslConnector, SslMethod,
+            },
+            Connection,
+        },
+        ExportKeyingMaterial,
+    };
+    use futures_util::stream::StreamExt;
+    use tls_openssl::ssl::{SslVerifyMode, SslVersion};
+
+    const PSK_CIPHER: &str = "PSK-AES128-GCM-SHA256";
+    const LABEL: &str = "EXPORTER-actix-test";
+
+    let srv = TestServer::with(|| {
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        builder
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        builder.set_cipher_list(PSK_CIPHER).unwrap();
+        accept_openssl::set_psk(&mut builder, |_| Some(b"0123456789abcdef".to_vec()));
+
+        Acceptor::new(builder.build())
+            .map_err(|_| ())
+            .and_then(fn_service(
+                |io: accept_openssl::TlsStream<TcpStream>| async move {
+                    let material = io
+                        .export_keying_material(LABEL, Some(b"ctx"), 32)
+                        .map_err(|_| ())?;
+                    let mut framed = Framed::new(io, BytesCodec);
+                    framed.send(Bytes::from(material)).await.map_err(|_| ())
+                },
+            ))
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    builder.set_cipher_list(PSK_CIPHER).unwrap();
+    connect_openssl::set_psk(&mut builder, |_| {
+        Some((b"client".to_vec(), b"0123456789abcdef".to_vec()))
+    });
+
+    let io = TcpStream::connect(srv.addr()).await.unwrap();
+    let con = OpensslConnector::service(builder.build())
+        .call(Connection::new(io, "localhost"))
+        .await
+        .unwrap();
+    let (io, _) = con.into_parts();
+
+    let material = io.export_keying_material(LABEL, Some(b"ctx"), 32).unwrap();
+    assert_eq!(material.len(), 32);
+    let other = io.export_keying_material(LABEL, None, 32).unwrap();
+    assert_ne!(material, other);
+
+    let mut framed = Framed::new(io, BytesCodec);
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(&item[..], &material[..]);
+}
+
 #[cfg(feature = "rustls")]
 #[actix_rt::test]
 async fn test_rustls_string() {
@@ -152,3 +289,29 @@ async fn test_local_addr() {
 
     assert_eq!(con.local_addr().unwrap().ip(), local)
 }
+
+#[actix_rt::test]
+async fn test_udp() {
+    use actix_rt::net::UdpSocket;
+
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let peer_addr = peer.local_addr().unwrap();
+
+    let conn = actix_connect::default_udp_connector();
+    let local = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3));
+
+    let (sock, _) = conn
+        .call(Connect::new(format!("localhost:{}", peer_addr.port())).set_local_addr(local))
+        .await
+        .unwrap()
+        .into_parts();
+
+    assert_eq!(sock.peer_addr().unwrap(), peer_addr);
+    assert_eq!(sock.local_addr().unwrap().ip(), local);
+
+    sock.send(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    let (len, from) = peer.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..len], b"ping");
+    assert_eq!(from, sock.local_addr().unwrap());
+}
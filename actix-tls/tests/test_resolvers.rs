@@ -78,3 +78,18 @@ async fn custom_resolver_connect() {
         .unwrap();
     assert_eq!(con.peer_addr().unwrap(), srv.addr());
 }
+
+#[actix_rt::test]
+async fn default_resolver_reload_system_conf() {
+    let resolver = Resolver::Default;
+
+    for &enabled in &[true, false] {
+        Resolver::reload_system_conf(enabled);
+
+        let req = resolver.call(Connect::new("localhost:8080")).await.unwrap();
+        assert!(req.addrs().all(|addr| addr.ip().is_loopback()));
+        assert!(req.addrs().any(|addr| addr.port() == 8080));
+    }
+
+    Resolver::reload_system_conf(true);
+}
@@ -1,6 +1,9 @@
 //! Task-notifying counter.
 
-use core::{cell::Cell, fmt, task};
+use core::{
+    cell::{Cell, RefCell},
+    fmt, task,
+};
 use std::rc::Rc;
 
 use local_waker::LocalWaker;
@@ -18,6 +21,7 @@ impl Counter {
             capacity,
             count: Cell::new(0),
             task: LocalWaker::new(),
+            watermarks: RefCell::new(Vec::new()),
         }))
     }
 
@@ -35,17 +39,93 @@ impl Counter {
     pub fn total(&self) -> usize {
         self.0.count.get()
     }
+
+    /// Register a callback that is called when the counter crosses the given watermarks.
+    ///
+    /// The callback receives [`Watermark::High`] when the count rises to `high` and
+    /// [`Watermark::Low`] when it afterwards falls back to `low`. The gap between the two
+    /// watermarks keeps the callback from firing on every guard while the count hovers around a
+    /// single value. If the count is already at or above `high`, the first call happens once
+    /// it falls to `low`.
+    ///
+    /// Callbacks run synchronously when a guard is created or dropped and must not register
+    /// further watermarks on this counter.
+    ///
+    /// # Panics
+    /// Panics if `low` is not less than `high`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    /// use actix_utils::counter::{Counter, Watermark};
+    ///
+    /// let counter = Counter::new(10);
+    /// let shedding = Rc::new(Cell::new(false));
+    ///
+    /// let shedding2 = shedding.clone();
+    /// counter.on_watermark(9, 5, move |mark| shedding2.set(mark == Watermark::High));
+    ///
+    /// let mut guards = (0..9).map(|_| counter.get()).collect::<Vec<_>>();
+    /// assert!(shedding.get());
+    ///
+    /// guards.truncate(5);
+    /// assert!(!shedding.get());
+    /// ```
+    pub fn on_watermark<F>(&self, high: usize, low: usize, callback: F)
+    where
+        F: Fn(Watermark) + 'static,
+    {
+        assert!(low < high, "low watermark must be less than high watermark");
+
+        self.0.watermarks.borrow_mut().push(WatermarkCallback {
+            high,
+            low,
+            above: Cell::new(self.total() >= high),
+            callback: Box::new(callback),
+        });
+    }
+}
+
+/// Watermark crossed by a [`Counter`].
+///
+/// See [`Counter::on_watermark`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watermark {
+    /// Count rose to the high watermark.
+    High,
+
+    /// Count fell back to the low watermark.
+    Low,
+}
+
+struct WatermarkCallback {
+    high: usize,
+    low: usize,
+
+    /// Whether the high watermark was reached and the low one not yet since.
+    above: Cell<bool>,
+
+    callback: Box<dyn Fn(Watermark)>,
 }
 
 struct CounterInner {
     count: Cell<usize>,
     capacity: usize,
     task: LocalWaker,
+    watermarks: RefCell<Vec<WatermarkCallback>>,
 }
 
 impl CounterInner {
     fn inc(&self) {
-        self.count.set(self.count.get() + 1);
+        let num = self.count.get() + 1;
+        self.count.set(num);
+
+        for mark in self.watermarks.borrow().iter() {
+            if !mark.above.get() && num >= mark.high {
+                mark.above.set(true);
+                (mark.callback)(Watermark::High);
+            }
+        }
     }
 
     fn dec(&self) {
@@ -54,6 +134,13 @@ impl CounterInner {
         if num == self.capacity {
             self.task.wake();
         }
+
+        for mark in self.watermarks.borrow().iter() {
+            if mark.above.get() && num - 1 <= mark.low {
+                mark.above.set(false);
+                (mark.callback)(Watermark::Low);
+            }
+        }
     }
 
     fn available(&self, cx: &mut task::Context<'_>) -> bool {
@@ -72,6 +159,7 @@ impl fmt::Debug for CounterInner {
             .field("count", &self.count.get())
             .field("capacity", &self.capacity)
             .field("task", &self.task)
+            .field("watermarks", &self.watermarks.borrow().len())
             .finish()
     }
 }
@@ -94,3 +182,55 @@ impl Drop for CounterGuard {
         self.0.dec();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermarks() {
+        let counter = Counter::new(10);
+        let marks = Rc::new(RefCell::new(Vec::new()));
+
+        let marks2 = marks.clone();
+        counter.on_watermark(3, 1, move |mark| marks2.borrow_mut().push(mark));
+
+        let mut guards = (0..2).map(|_| counter.get()).collect::<Vec<_>>();
+        assert!(marks.borrow().is_empty());
+
+        guards.push(counter.get());
+        guards.push(counter.get());
+        assert_eq!(*marks.borrow(), [Watermark::High]);
+
+        // hovering between watermarks does not fire
+        guards.truncate(2);
+        guards.push(counter.get());
+        assert_eq!(*marks.borrow(), [Watermark::High]);
+
+        guards.truncate(1);
+        assert_eq!(*marks.borrow(), [Watermark::High, Watermark::Low]);
+
+        guards.push(counter.get());
+        guards.push(counter.get());
+        assert_eq!(
+            *marks.borrow(),
+            [Watermark::High, Watermark::Low, Watermark::High]
+        );
+    }
+
+    #[test]
+    fn watermark_registered_above_high() {
+        let counter = Counter::new(10);
+        let mut guards = (0..5).map(|_| counter.get()).collect::<Vec<_>>();
+
+        let marks = Rc::new(RefCell::new(Vec::new()));
+        let marks2 = marks.clone();
+        counter.on_watermark(4, 2, move |mark| marks2.borrow_mut().push(mark));
+
+        guards.push(counter.get());
+        assert!(marks.borrow().is_empty());
+
+        guards.truncate(2);
+        assert_eq!(*marks.borrow(), [Watermark::Low]);
+    }
+}
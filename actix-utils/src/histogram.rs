@@ -0,0 +1,304 @@
+//! Fixed-bucket latency histogram.
+
+use core::{fmt, time::Duration};
+
+/// Number of linear sub-buckets within each power-of-two range.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// Highest power-of-two range tracked; larger values are clamped into the last bucket.
+///
+/// `2^36` microseconds is a little over 19 hours.
+const MAX_EXP: u32 = 36;
+
+/// One linear group below `SUB_BUCKETS`, one group per range up to `MAX_EXP` and a clamp bucket.
+const BUCKETS: usize = ((MAX_EXP - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS) as usize + 1;
+
+/// Latency histogram with fixed, logarithmically spaced buckets.
+///
+/// Durations are recorded with microsecond resolution into power-of-two ranges that are each split
+/// into 8 linear sub-buckets, bounding the relative error of reported values to 12.5%. The bucket
+/// layout is the same for every instance so histograms collected independently (e.g. one per
+/// worker thread) can be combined with [`merge`](Self::merge).
+///
+/// The type uses no atomics or locks; it is intended to be owned by a single thread and merged
+/// into a snapshot when reporting.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use actix_utils::histogram::Histogram;
+///
+/// let mut worker1 = Histogram::new();
+/// worker1.record(Duration::from_millis(2));
+///
+/// let mut worker2 = Histogram::new();
+/// worker2.record(Duration::from_millis(10));
+///
+/// let mut total = Histogram::new();
+/// total.merge(&worker1);
+/// total.merge(&worker2);
+///
+/// assert_eq!(total.count(), 2);
+/// assert_eq!(total.max(), Some(Duration::from_millis(10)));
+/// ```
+#[derive(Clone)]
+pub struct Histogram {
+    buckets: Box<[u64]>,
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS].into_boxed_slice(),
+            count: 0,
+            sum: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    /// Record a single duration.
+    pub fn record(&mut self, dur: Duration) {
+        let val = micros(dur);
+
+        self.buckets[bucket_index(val)] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(val);
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+    }
+
+    /// Add all values recorded in `other` to this histogram.
+    pub fn merge(&mut self, other: &Histogram) {
+        for (this, other) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *this += *other;
+        }
+
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Remove all recorded values.
+    pub fn reset(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket = 0;
+        }
+
+        self.count = 0;
+        self.sum = 0;
+        self.min = u64::MAX;
+        self.max = 0;
+    }
+
+    /// Returns number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns true if no values have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns smallest recorded value.
+    pub fn min(&self) -> Option<Duration> {
+        self.non_empty(self.min)
+    }
+
+    /// Returns largest recorded value.
+    pub fn max(&self) -> Option<Duration> {
+        self.non_empty(self.max)
+    }
+
+    /// Returns arithmetic mean of recorded values.
+    pub fn mean(&self) -> Option<Duration> {
+        self.non_empty(self.sum / self.count.max(1))
+    }
+
+    /// Returns an upper bound on the value below which `percentile` percent of recorded values
+    /// fall.
+    ///
+    /// `percentile` is clamped to the range `0.0..=100.0`.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        // float to int casts saturate so out of range percentiles end up clamped here
+        let rank = ((percentile / 100.0) * self.count as f64).ceil() as u64;
+        let rank = rank.max(1).min(self.count);
+
+        let mut seen = 0;
+        for (idx, count) in self.buckets.iter().enumerate() {
+            seen += *count;
+
+            if seen >= rank {
+                let val = bucket_high(idx).min(self.max).max(self.min);
+                return Some(Duration::from_micros(val));
+            }
+        }
+
+        unreachable!("bucket counts should sum to total count")
+    }
+
+    /// Returns iterator over non-empty buckets as pairs of bucket upper bound and value count.
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(idx, count)| (Duration::from_micros(bucket_high(idx)), *count))
+    }
+
+    fn non_empty(&self, val: u64) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(Duration::from_micros(val))
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Histogram")
+            .field("count", &self.count)
+            .field("min", &self.min())
+            .field("max", &self.max())
+            .field("mean", &self.mean())
+            .finish()
+    }
+}
+
+fn micros(dur: Duration) -> u64 {
+    let micros = dur.as_micros();
+
+    if micros > u64::MAX as u128 {
+        u64::MAX
+    } else {
+        micros as u64
+    }
+}
+
+fn bucket_index(val: u64) -> usize {
+    if val < SUB_BUCKETS {
+        return val as usize;
+    }
+
+    let exp = (63 - val.leading_zeros()).min(MAX_EXP);
+    let shift = exp - SUB_BUCKET_BITS;
+
+    if exp == MAX_EXP {
+        // clamp anything in or above the top range into the last bucket
+        return BUCKETS - 1;
+    }
+
+    let group = u64::from(shift + 1);
+    let sub = (val >> shift) - SUB_BUCKETS;
+    (group * SUB_BUCKETS + sub) as usize
+}
+
+fn bucket_high(idx: usize) -> u64 {
+    let idx = idx as u64;
+
+    if idx < SUB_BUCKETS {
+        return idx;
+    }
+
+    if idx as usize == BUCKETS - 1 {
+        return u64::MAX;
+    }
+
+    let shift = idx / SUB_BUCKETS - 1;
+    let sub = idx % SUB_BUCKETS;
+    let low = (SUB_BUCKETS + sub) << shift;
+    low + (1 << shift) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_bounds() {
+        for val in 0..100_000 {
+            let idx = bucket_index(val);
+            assert!(val <= bucket_high(idx), "{} above bucket {}", val, idx);
+
+            if idx > 0 {
+                assert!(val > bucket_high(idx - 1), "{} below bucket {}", val, idx);
+            }
+        }
+
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_index(1 << MAX_EXP), BUCKETS - 1);
+        assert_eq!(bucket_index((1 << MAX_EXP) - 1), BUCKETS - 2);
+    }
+
+    #[test]
+    fn stats() {
+        let mut hist = Histogram::new();
+        assert!(hist.is_empty());
+        assert_eq!(hist.min(), None);
+        assert_eq!(hist.percentile(50.0), None);
+
+        for ms in 1..=100 {
+            hist.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(hist.count(), 100);
+        assert_eq!(hist.min(), Some(Duration::from_millis(1)));
+        assert_eq!(hist.max(), Some(Duration::from_millis(100)));
+        assert_eq!(hist.mean(), Some(Duration::from_micros(50_500)));
+
+        let p50 = hist.percentile(50.0).unwrap();
+        assert!(p50 >= Duration::from_millis(50));
+        assert!(p50 <= Duration::from_micros(50_000 * 9 / 8));
+
+        assert_eq!(hist.percentile(100.0), Some(Duration::from_millis(100)));
+        let p0 = hist.percentile(0.0).unwrap();
+        assert!(p0 >= Duration::from_millis(1));
+        assert!(p0 <= Duration::from_micros(1_000 * 9 / 8));
+
+        hist.reset();
+        assert!(hist.is_empty());
+        assert_eq!(hist.buckets().count(), 0);
+    }
+
+    #[test]
+    fn merge() {
+        let mut a = Histogram::new();
+        a.record(Duration::from_micros(3));
+        a.record(Duration::from_secs(2));
+
+        let mut b = Histogram::new();
+        b.record(Duration::from_micros(3));
+        b.record(Duration::from_millis(7));
+
+        let mut total = Histogram::new();
+        total.merge(&a);
+        total.merge(&b);
+
+        assert_eq!(total.count(), 4);
+        assert_eq!(total.min(), Some(Duration::from_micros(3)));
+        assert_eq!(total.max(), Some(Duration::from_secs(2)));
+
+        let buckets = total.buckets().collect::<Vec<_>>();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0], (Duration::from_micros(3), 2));
+    }
+}
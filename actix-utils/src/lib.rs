@@ -7,3 +7,5 @@
 
 pub mod counter;
 pub mod future;
+pub mod histogram;
+pub mod single_flight;
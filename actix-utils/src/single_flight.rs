@@ -0,0 +1,234 @@
+//! Deduplication of concurrent async operations.
+
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{collections::HashMap, rc::Rc};
+
+/// Coalesces concurrent async operations with the same key.
+///
+/// The first caller of [`run`](Self::run) for a key runs its future; callers arriving while it is
+/// in flight wait for it and receive a clone of its output. Once the output is delivered the key
+/// is free again and the next caller starts a new operation, so results are not cached.
+///
+/// If the running caller is dropped before its future completes, one of the waiting callers runs
+/// its own future instead.
+///
+/// Useful for DNS lookups, token refreshes or configuration fetches that many tasks may need at
+/// the same time. `SingleFlight` is not thread-safe; use one per worker. Clones share in-flight
+/// operations.
+///
+/// # Examples
+/// ```
+/// use actix_utils::single_flight::SingleFlight;
+/// use futures_util::future::join;
+///
+/// # actix_rt::System::new().block_on(async {
+/// let flights = SingleFlight::new();
+///
+/// let (a, b) = join(
+///     flights.run("token", || async {
+///         actix_rt::task::yield_now().await;
+///         "fetched once".to_owned()
+///     }),
+///     flights.run("token", || async { unreachable!("coalesced with the first call") }),
+/// )
+/// .await;
+///
+/// assert_eq!(a, b);
+/// # });
+/// ```
+pub struct SingleFlight<K, V>(Rc<RefCell<HashMap<K, Rc<Flight<V>>>>>);
+
+struct Flight<V> {
+    /// Output of the operation; unset if it was abandoned.
+    output: RefCell<Option<V>>,
+    done: Cell<bool>,
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /// Create `SingleFlight` without operations in flight.
+    pub fn new() -> Self {
+        SingleFlight(Rc::new(RefCell::new(HashMap::new())))
+    }
+
+    /// Run future returned by `f` for `key`, or wait for the output of the one already in
+    /// flight for it.
+    pub async fn run<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        // wait until output of another caller is received or its operation is abandoned
+        loop {
+            let flight = self.0.borrow().get(&key).cloned();
+            match flight {
+                Some(flight) => {
+                    if let Some(output) = (Wait { flight }).await {
+                        return output;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let flight = Rc::new(Flight {
+            output: RefCell::new(None),
+            done: Cell::new(false),
+            waiters: RefCell::new(Vec::new()),
+        });
+        self.0.borrow_mut().insert(key.clone(), flight.clone());
+
+        let _guard = Leader {
+            flights: &self.0,
+            key: &key,
+            flight: &flight,
+        };
+
+        let output = f().await;
+        *flight.output.borrow_mut() = Some(output.clone());
+        output
+    }
+
+    /// Check if an operation is in flight for `key`.
+    pub fn is_in_flight(&self, key: &K) -> bool {
+        self.0.borrow().contains_key(key)
+    }
+}
+
+impl<K, V> Default for SingleFlight<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Clone for SingleFlight<K, V> {
+    fn clone(&self) -> Self {
+        SingleFlight(self.0.clone())
+    }
+}
+
+impl<K, V> fmt::Debug for SingleFlight<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleFlight")
+            .field("in_flight", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+/// Completes the flight of the running caller, also when it is dropped before its output is set.
+struct Leader<'a, K: Hash + Eq, V> {
+    flights: &'a RefCell<HashMap<K, Rc<Flight<V>>>>,
+    key: &'a K,
+    flight: &'a Rc<Flight<V>>,
+}
+
+impl<K: Hash + Eq, V> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        let mut flights = self.flights.borrow_mut();
+        if matches!(flights.get(self.key), Some(flight) if Rc::ptr_eq(flight, self.flight)) {
+            flights.remove(self.key);
+        }
+        drop(flights);
+
+        self.flight.done.set(true);
+        for waker in self.flight.waiters.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves to output of a flight, or `None` if it was abandoned.
+struct Wait<V> {
+    flight: Rc<Flight<V>>,
+}
+
+impl<V: Clone> Future for Wait<V> {
+    type Output = Option<V>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let flight = &self.flight;
+
+        if flight.done.get() {
+            return Poll::Ready(flight.output.borrow().clone());
+        }
+
+        let mut waiters = flight.waiters.borrow_mut();
+        if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+            waiters.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{join3, lazy};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn coalesce_concurrent_calls() {
+        let flights = SingleFlight::new();
+        let calls = Cell::new(0);
+
+        let fetch = || async {
+            let call = calls.get() + 1;
+            calls.set(call);
+            actix_rt::task::yield_now().await;
+            call
+        };
+
+        let (a, b, c) = join3(
+            flights.run(1, fetch),
+            flights.run(1, fetch),
+            flights.run(2, fetch),
+        )
+        .await;
+
+        assert_eq!((a, b), (1, 1));
+        assert_eq!(c, 2);
+        assert!(!flights.is_in_flight(&1));
+
+        // key is free again once the output was delivered
+        assert_eq!(flights.run(1, fetch).await, 3);
+    }
+
+    #[actix_rt::test]
+    async fn waiter_takes_over_abandoned_flight() {
+        let flights = SingleFlight::new();
+
+        let mut leader = Box::pin(flights.run("key", || async {
+            actix_rt::task::yield_now().await;
+            "leader"
+        }));
+        let mut waiter = Box::pin(flights.run("key", || async { "waiter" }));
+
+        lazy(|cx| {
+            assert!(leader.as_mut().poll(cx).is_pending());
+            assert!(waiter.as_mut().poll(cx).is_pending());
+        })
+        .await;
+        assert!(flights.is_in_flight(&"key"));
+
+        drop(leader);
+        assert!(!flights.is_in_flight(&"key"));
+
+        assert_eq!(waiter.await, "waiter");
+    }
+}